@@ -0,0 +1,173 @@
+/// Max number of breakpoints in executor.
+pub const MAX_BREAKPOINTS: usize = 32;
+
+/// Max number of watchpoints in executor.
+pub const MAX_WATCHPOINTS: usize = 8;
+
+/// Access to trigger watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+/// Watched memory range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub kind: WatchKind,
+    pub addr: u64,
+    pub len: u64,
+}
+
+impl Watchpoint {
+    /// Whether access of `len` bytes at `addr` triggers this watchpoint.
+    pub fn matches(&self, write: bool, addr: u64, len: u64) -> bool {
+        let kind = match self.kind {
+            WatchKind::Write => write,
+            WatchKind::Read => !write,
+            WatchKind::Access => true,
+        };
+
+        kind && addr < self.addr.saturating_add(self.len) && self.addr < addr.saturating_add(len)
+    }
+}
+
+/// Why executor stops without error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// One instruction is executed.
+    Stepped,
+    /// pc reaches breakpoint, instruction at pc isn't executed.
+    Breakpoint(u64),
+    /// Instruction at pc accesses watched `addr`, it isn't executed.
+    ///
+    /// Next `step` or `run` executes it without checking watchpoints.
+    Watchpoint { kind: WatchKind, addr: u64 },
+}
+
+/// Insert into first free slot, return false if full.
+pub(crate) fn insert<T: PartialEq>(slots: &mut [Option<T>], v: T) -> bool {
+    if slots.iter().flatten().any(|s| *s == v) {
+        return true;
+    }
+
+    match slots.iter_mut().find(|s| s.is_none()) {
+        Some(slot) => {
+            *slot = Some(v);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Remove from slots, return false if not found.
+pub(crate) fn remove<T: PartialEq>(slots: &mut [Option<T>], v: T) -> bool {
+    match slots.iter_mut().find(|s| s.as_ref() == Some(&v)) {
+        Some(slot) => {
+            *slot = None;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::riscv32i::{assemble, RV32iBaseInst};
+
+    use crate::{Error, Executor, Outcome, WatchKind};
+
+    const PROGRAM: &str = "
+        li a0, 5
+        sw a0, 64(zero)
+        lw a1, 64(zero)
+        addi a0, a0, 1
+        ecall
+    ";
+
+    fn executor() -> Executor<32, RV32iBaseInst<()>, [u8; 128], [u8; 128], ()> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        Executor::new(code, [0u8; 128], ())
+    }
+
+    fn is_ecall(r: Result<Outcome, Error<tangram_instruction::Error>>) -> bool {
+        matches!(
+            r,
+            Err(Error::InstructionError(
+                tangram_instruction::Error::EnvironmentCall
+            ))
+        )
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut executor = executor();
+
+        assert!(executor.add_breakpoint(8));
+        assert!(executor.add_breakpoint(12));
+
+        assert_eq!(executor.run(4).unwrap(), Outcome::Breakpoint(8));
+        assert_eq!(executor.regs()[10], 5);
+        assert_eq!(executor.regs()[11], 0);
+
+        assert_eq!(executor.run(4).unwrap(), Outcome::Breakpoint(12));
+        assert_eq!(executor.regs()[11], 5);
+        assert_eq!(executor.regs()[10], 5);
+
+        assert!(executor.remove_breakpoint(8));
+        assert!(!executor.remove_breakpoint(8));
+        assert!(is_ecall(executor.run(4)));
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut executor = executor();
+
+        assert!(executor.add_watchpoint(WatchKind::Read, 64, 4));
+        assert!(executor.add_watchpoint(WatchKind::Write, 68, 4));
+
+        assert_eq!(
+            executor.run(4).unwrap(),
+            Outcome::Watchpoint {
+                kind: WatchKind::Read,
+                addr: 64
+            }
+        );
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(executor.regs()[11], 0);
+
+        assert!(executor.remove_watchpoint(WatchKind::Read, 64, 4));
+        assert!(executor.add_watchpoint(WatchKind::Access, 60, 4));
+        assert!(is_ecall(executor.run(4)));
+        assert_eq!(executor.regs()[11], 5);
+
+        let mut executor = self::executor();
+        assert!(executor.add_watchpoint(WatchKind::Access, 66, 1));
+
+        assert_eq!(executor.step(4).unwrap(), Outcome::Stepped);
+        assert_eq!(
+            executor.step(4).unwrap(),
+            Outcome::Watchpoint {
+                kind: WatchKind::Access,
+                addr: 66
+            }
+        );
+        assert_eq!(executor.memory()[64], 0);
+
+        // Resume with watchpoint still set, it stops again on next access.
+        assert_eq!(
+            executor.run(4).unwrap(),
+            Outcome::Watchpoint {
+                kind: WatchKind::Access,
+                addr: 66
+            }
+        );
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(executor.memory()[64], 5);
+        assert!(is_ecall(executor.run(4)));
+        assert_eq!(executor.regs()[11], 5);
+    }
+}
@@ -0,0 +1,305 @@
+use core::fmt;
+
+use tangram_instruction::{
+    riscv32i::{RV32iBaseInst, RV32I_NAMES},
+    Instruction, MemoryMut, Reg32,
+};
+
+use crate::{Control, Monitor};
+
+pub const CSR_MCYCLE: u16 = 0xB00;
+pub const CSR_MINSTRET: u16 = 0xB02;
+pub const CSR_MCYCLEH: u16 = 0xB80;
+pub const CSR_MINSTRETH: u16 = 0xB82;
+pub const CSR_CYCLE: u16 = 0xC00;
+pub const CSR_TIME: u16 = 0xC01;
+pub const CSR_INSTRET: u16 = 0xC02;
+pub const CSR_CYCLEH: u16 = 0xC80;
+pub const CSR_TIMEH: u16 = 0xC81;
+pub const CSR_INSTRETH: u16 = 0xC82;
+
+/// Statistics collected by [`CounterMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counters {
+    /// Retired instructions, backing `minstret`.
+    pub instret: u64,
+    /// Started instructions, including trapped ones, backing `mcycle`.
+    pub cycles: u64,
+    /// Retired instructions indexed by [`RV32iBaseInst::index`].
+    pub opcodes: [u64; RV32I_NAMES.len()],
+    pub branches_taken: u64,
+    pub branches_not_taken: u64,
+    /// Retired loads of byte, half word and word.
+    pub loads: [u64; 3],
+    /// Retired stores of byte, half word and word.
+    pub stores: [u64; 3],
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            instret: 0,
+            cycles: 0,
+            opcodes: [0; RV32I_NAMES.len()],
+            branches_taken: 0,
+            branches_not_taken: 0,
+            loads: [0; 3],
+            stores: [0; 3],
+        }
+    }
+}
+
+impl Counters {
+    /// Read counter CSR of RV32, low and high halves are separate CSRs.
+    ///
+    /// `time` ticks once per cycle instead of following host clock.
+    pub fn read_csr(&self, csr: u16) -> Option<u64> {
+        let v = match csr {
+            CSR_MCYCLE | CSR_CYCLE | CSR_TIME => self.cycles,
+            CSR_MINSTRET | CSR_INSTRET => self.instret,
+            CSR_MCYCLEH | CSR_CYCLEH | CSR_TIMEH => self.cycles >> 32,
+            CSR_MINSTRETH | CSR_INSTRETH => self.instret >> 32,
+            _ => return None,
+        };
+
+        Some(v & 0xFFFF_FFFF)
+    }
+
+    /// Write low 32 bits of `value` to half of machine counter named by `csr`,
+    /// return false if `csr` isn't writable.
+    pub fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+        let (counter, shift) = match csr {
+            CSR_MCYCLE => (&mut self.cycles, 0),
+            CSR_MINSTRET => (&mut self.instret, 0),
+            CSR_MCYCLEH => (&mut self.cycles, 32),
+            CSR_MINSTRETH => (&mut self.instret, 32),
+            _ => return false,
+        };
+
+        let mask = 0xFFFF_FFFF << shift;
+        *counter = (*counter & !mask) | ((value << shift) & mask);
+
+        true
+    }
+}
+
+impl fmt::Display for Counters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [lb, lh, lw] = self.loads;
+        let [sb, sh, sw] = self.stores;
+
+        writeln!(f, "instret: {}", self.instret)?;
+        writeln!(f, "cycles: {}", self.cycles)?;
+        writeln!(
+            f,
+            "branches: {} taken, {} not taken",
+            self.branches_taken, self.branches_not_taken
+        )?;
+        writeln!(f, "loads: {} b, {} h, {} w", lb, lh, lw)?;
+        writeln!(f, "stores: {} b, {} h, {} w", sb, sh, sw)?;
+
+        for (name, count) in RV32I_NAMES.iter().zip(self.opcodes) {
+            if count != 0 {
+                writeln!(f, "{}: {}", name, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Monitor counting instructions, branches and memory accesses.
+#[derive(Debug, Default)]
+pub struct CounterMonitor {
+    counters: Counters,
+    /// Whether branch being executed is taken.
+    taken: Option<bool>,
+    /// Whether instruction being executed wrote `minstret`, its value isn't
+    /// incremented then.
+    instret_written: bool,
+}
+
+impl CounterMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// Mutable counters, for writes to `mcycle` and `minstret`.
+    pub fn counters_mut(&mut self) -> &mut Counters {
+        &mut self.counters
+    }
+}
+
+impl<I, R> Monitor<RV32iBaseInst<I>> for CounterMonitor
+where
+    I: Instruction<Register = R>,
+    R: Reg32 + Clone,
+{
+    fn before_execute(&mut self, inst: &RV32iBaseInst<I>, _pc: &R, regs: &[R]) -> Control {
+        self.counters.cycles += 1;
+        self.taken = inst.branch_taken(regs);
+        self.instret_written = false;
+
+        Control::Continue
+    }
+
+    fn monitor<M>(&mut self, inst: &RV32iBaseInst<I>, _pc: &R, _regs: &[R], _memory: &M) -> Control
+    where
+        M: MemoryMut<Register = R>,
+    {
+        let c = &mut self.counters;
+        if !self.instret_written {
+            c.instret += 1;
+        }
+        c.opcodes[inst.index()] += 1;
+
+        match self.taken {
+            Some(true) => c.branches_taken += 1,
+            Some(false) => c.branches_not_taken += 1,
+            None => {}
+        }
+
+        match inst {
+            RV32iBaseInst::Lb(_) | RV32iBaseInst::Lbu(_) => c.loads[0] += 1,
+            RV32iBaseInst::Lh(_) | RV32iBaseInst::Lhu(_) => c.loads[1] += 1,
+            RV32iBaseInst::Lw(_) | RV32iBaseInst::Lwu(_) => c.loads[2] += 1,
+            RV32iBaseInst::Sb(_) => c.stores[0] += 1,
+            RV32iBaseInst::Sh(_) => c.stores[1] += 1,
+            RV32iBaseInst::Sw(_) => c.stores[2] += 1,
+            _ => {}
+        }
+
+        Control::Continue
+    }
+
+    fn read_csr(&self, csr: u16) -> Option<u64> {
+        self.counters.read_csr(csr)
+    }
+
+    /// Written `minstret` holds value of write after instruction retires, like
+    /// on hardware.
+    fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+        if matches!(csr, CSR_MINSTRET | CSR_MINSTRETH) {
+            self.instret_written = true;
+        }
+        self.counters.write_csr(csr, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use tangram_instruction::{
+        riscv::{Illegal, Inst},
+        riscv32i::{assemble, RV32iBaseInst, RV32I_NAMES},
+        riscvzicsr::RVCsrInst,
+    };
+
+    use crate::{
+        CounterMonitor, Executor, CSR_MCYCLE, CSR_MCYCLEH, CSR_MINSTRET, CSR_MINSTRETH, CSR_TIME,
+    };
+
+    const PROGRAM: &str = "
+        li a0, 3
+        li a1, 64
+    loop:
+        sb a0, 0(a1)
+        sh a0, 2(a1)
+        lw a2, 0(a1)
+        addi a0, a0, -1
+        bnez a0, loop
+        beq zero, zero, done
+    done:
+        ecall
+    ";
+
+    fn count(name: &str) -> usize {
+        RV32I_NAMES.iter().position(|n| *n == name).unwrap()
+    }
+
+    #[test]
+    fn test_counters() {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], CounterMonitor::new());
+        assert!(executor.run(4).is_err());
+
+        let c = executor.monitor().counters();
+        assert_eq!(c.instret, 18);
+        assert_eq!(c.cycles, 19);
+        assert_eq!(c.opcodes[count("addi")], 5);
+        assert_eq!(c.opcodes[count("bne")], 3);
+        assert_eq!(c.opcodes[count("ecall")], 0);
+        // Branch to next instruction is still taken.
+        assert_eq!((c.branches_taken, c.branches_not_taken), (3, 1));
+        assert_eq!(c.loads, [0, 0, 3]);
+        assert_eq!(c.stores, [3, 3, 0]);
+
+        assert_eq!(
+            c.to_string(),
+            "instret: 18\ncycles: 19\nbranches: 3 taken, 1 not taken\n\
+             loads: 0 b, 0 h, 3 w\nstores: 3 b, 3 h, 0 w\n\
+             beq: 1\nbne: 3\nlw: 3\nsb: 3\nsh: 3\naddi: 5\n"
+        );
+    }
+
+    #[test]
+    fn test_counter_csr() {
+        let mut monitor = CounterMonitor::new();
+        let c = monitor.counters_mut();
+
+        // Each CSR is one 32-bit half.
+        assert!(c.write_csr(CSR_MINSTRET, 0x1234_5678_9ABC_DEF0));
+        assert_eq!(c.instret, 0x9ABC_DEF0);
+        assert!(c.write_csr(CSR_MINSTRETH, 1));
+        assert!(c.write_csr(CSR_MINSTRET, 0x11));
+        assert!(!c.write_csr(0xC02, 0));
+        assert_eq!(c.instret, 0x1_0000_0011);
+        assert_eq!(c.read_csr(CSR_MINSTRET), Some(0x11));
+        assert_eq!(c.read_csr(CSR_MINSTRETH), Some(1));
+        assert_eq!(c.read_csr(CSR_MCYCLEH), Some(0));
+        assert!(c.write_csr(CSR_MCYCLE, 42));
+        assert_eq!(c.read_csr(CSR_TIME), Some(42));
+        assert_eq!(c.read_csr(0x300), None);
+    }
+
+    #[test]
+    fn test_write_minstret() {
+        // `csrw minstret, a0`, `csrw mcycle, a0` then `csrr a1, minstret`
+        let mut code = [0u8; 16];
+        for (i, (funct3, rd, rs1, csr)) in [
+            (0b001, 0, 10, CSR_MINSTRET),
+            (0b001, 0, 10, CSR_MCYCLE),
+            (0b010, 11, 0, CSR_MINSTRET),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let inst = Inst::build_i(0b1110011, rd, funct3, rs1, csr as i32);
+            code[i * 4..][..4].copy_from_slice(&inst.raw().to_le_bytes());
+        }
+
+        let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+            Executor::new(code, [0u8; 16], CounterMonitor::new());
+        executor.regs_mut()[10] = 100;
+
+        // Written value isn't incremented by the writing instruction.
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MINSTRET), Some(100));
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MINSTRET), Some(101));
+        assert_eq!(executor.read_csr(CSR_MCYCLE), Some(100));
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[11], 101);
+        assert_eq!(executor.read_csr(CSR_MINSTRET), Some(102));
+    }
+}
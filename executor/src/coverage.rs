@@ -0,0 +1,146 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use tangram_instruction::{riscv32i::RV32iBaseInst, Instruction, MemoryMut, Reg32};
+
+use crate::{Control, LineTable, Monitor};
+
+/// Monitor recording executed pcs and directions of branches.
+#[derive(Debug, Default)]
+pub struct CoverageMonitor {
+    pcs: BTreeMap<u64, u64>,
+    /// Taken and not taken count of each branch.
+    branches: BTreeMap<u64, [u64; 2]>,
+    pc: u64,
+    /// Whether branch being executed is taken.
+    taken: Option<bool>,
+}
+
+impl CoverageMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times instruction at `pc` is started, trapped ones included.
+    pub fn hits(&self, pc: u64) -> u64 {
+        self.pcs.get(&pc).copied().unwrap_or_default()
+    }
+
+    /// Executed pcs with hits, in order.
+    pub fn pcs(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.pcs.iter().map(|(pc, n)| (*pc, *n))
+    }
+
+    /// Taken and not taken count of branch at `pc`, if it is executed.
+    pub fn branch(&self, pc: u64) -> Option<(u64, u64)> {
+        self.branches.get(&pc).map(|[t, n]| (*t, *n))
+    }
+
+    /// Write coverage in lcov tracefile format.
+    ///
+    /// Lines are resolved with `lines`. Without it, one record named `[pc]` uses
+    /// addresses as line numbers.
+    pub fn write_lcov<W: fmt::Write>(&self, out: &mut W, lines: Option<&LineTable>) -> fmt::Result {
+        let lines = match lines {
+            Some(lines) => lines,
+            None => {
+                let hits = self.pcs.iter().map(|(pc, n)| (*pc, *n));
+                let branches = self.branches.iter().map(|(pc, b)| (*pc, *b));
+                return write_record(out, "[pc]", hits, branches);
+            }
+        };
+
+        // Line of instructions is hit as many times as its most executed one.
+        let mut files = BTreeMap::<usize, FileCoverage>::new();
+        for (addr, file, line) in lines.rows() {
+            let end = lines.row_end(addr);
+            let hits = self.pcs.range(addr..end).map(|(_, n)| *n).max();
+
+            let n = files.entry(file).or_default().lines.entry(line as u64);
+            let n = n.or_default();
+            *n = (*n).max(hits.unwrap_or_default());
+        }
+
+        for (pc, b) in self.branches.iter() {
+            if let Some((file, line)) = lines.lookup(*pc) {
+                let branches = &mut files.entry(file).or_default().branches;
+                branches.insert((line as u64, *pc), *b);
+            }
+        }
+
+        for (file, c) in files {
+            let branches = c.branches.into_iter().map(|((line, _), b)| (line, b));
+            write_record(out, &lines.files()[file], c.lines.into_iter(), branches)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct FileCoverage {
+    lines: BTreeMap<u64, u64>,
+    /// Branches keyed by line and pc.
+    branches: BTreeMap<(u64, u64), [u64; 2]>,
+}
+
+/// Write record of one source file, `hits` and `branches` are sorted by line.
+fn write_record<W: fmt::Write>(
+    out: &mut W,
+    name: &str,
+    hits: impl Iterator<Item = (u64, u64)>,
+    branches: impl Iterator<Item = (u64, [u64; 2])>,
+) -> fmt::Result {
+    writeln!(out, "SF:{}", name)?;
+
+    let (mut found, mut hit) = (0, 0);
+    let mut block = 0;
+    let mut last = None;
+    for (line, [taken, not_taken]) in branches {
+        block = if last == Some(line) { block + 1 } else { 0 };
+        last = Some(line);
+
+        writeln!(out, "BRDA:{},{},0,{}", line, block, taken)?;
+        writeln!(out, "BRDA:{},{},1,{}", line, block, not_taken)?;
+        found += 2;
+        hit += (taken != 0) as u64 + (not_taken != 0) as u64;
+    }
+    writeln!(out, "BRF:{}", found)?;
+    writeln!(out, "BRH:{}", hit)?;
+
+    let (mut found, mut hit) = (0, 0);
+    for (line, n) in hits {
+        writeln!(out, "DA:{},{}", line, n)?;
+        found += 1;
+        hit += (n != 0) as u64;
+    }
+    writeln!(out, "LF:{}", found)?;
+    writeln!(out, "LH:{}", hit)?;
+
+    writeln!(out, "end_of_record")
+}
+
+impl<I, R> Monitor<RV32iBaseInst<I>> for CoverageMonitor
+where
+    I: Instruction<Register = R>,
+    R: Reg32 + Clone,
+{
+    fn before_execute(&mut self, inst: &RV32iBaseInst<I>, pc: &R, regs: &[R]) -> Control {
+        self.pc = pc.reg32() as u64;
+        self.taken = inst.branch_taken(regs);
+        *self.pcs.entry(self.pc).or_default() += 1;
+
+        Control::Continue
+    }
+
+    fn monitor<M>(&mut self, _inst: &RV32iBaseInst<I>, _pc: &R, _regs: &[R], _memory: &M) -> Control
+    where
+        M: MemoryMut<Register = R>,
+    {
+        if let Some(taken) = self.taken {
+            self.branches.entry(self.pc).or_default()[!taken as usize] += 1;
+        }
+
+        Control::Continue
+    }
+}
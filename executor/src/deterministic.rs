@@ -0,0 +1,394 @@
+use alloc::vec::Vec;
+
+use core::fmt::Debug;
+
+use tangram_instruction::{
+    riscv::Illegal,
+    riscv32i::RV32iBaseInst,
+    riscvb::RVBitInst,
+    riscvk::RVCryptoInst,
+    riscvv::{RVVectorInst, VectorState},
+    riscvzicsr::RVCsrInst,
+    Aligned, Instruction, MemoryMut,
+};
+
+use crate::{
+    BytecodeReader, CounterMonitor, CoverageMonitor, Error, Executor, Extension, Hasher,
+    MerkleMemory, Monitor, Outcome, PagedMemory, ProfileMonitor, TraceMonitor, TraceSink,
+    ZkTraceMonitor, CSR_MCYCLE, CSR_MCYCLEH, CSR_MINSTRET, CSR_MINSTRETH, PAGE_SIZE,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Instruction set, monitor or extension state whose effects depend only on
+/// guest state.
+///
+/// It is sealed and implemented for layers and monitors of this crate. None of
+/// them has floating point, whose NaN payloads differ between hosts, and
+/// counters like `time` are derived from executed instructions, never from host
+/// clock.
+pub trait Deterministic: sealed::Sealed {}
+
+/// Memory with defined contents before first store.
+pub trait DeterministicMemory: MemoryMut + sealed::Sealed {
+    /// Merkle root of contents, same as [`PagedMemory::root`] of equal memory.
+    fn root<H: Hasher>(&self) -> H::Digest;
+}
+
+impl sealed::Sealed for () {}
+impl Deterministic for () {}
+impl<R> sealed::Sealed for Illegal<R> {}
+impl<R> Deterministic for Illegal<R> {}
+
+impl<I: Deterministic> sealed::Sealed for RV32iBaseInst<I> {}
+impl<I: Deterministic> Deterministic for RV32iBaseInst<I> {}
+impl<I: Deterministic> sealed::Sealed for RVBitInst<I> {}
+impl<I: Deterministic> Deterministic for RVBitInst<I> {}
+impl<I: Deterministic> sealed::Sealed for RVCryptoInst<I> {}
+impl<I: Deterministic> Deterministic for RVCryptoInst<I> {}
+impl<I: Deterministic, const VLEN: usize> sealed::Sealed for RVVectorInst<I, VLEN> {}
+impl<I: Deterministic, const VLEN: usize> Deterministic for RVVectorInst<I, VLEN> {}
+impl<I: Deterministic> sealed::Sealed for RVCsrInst<I> {}
+impl<I: Deterministic> Deterministic for RVCsrInst<I> {}
+
+impl<const VLENB: usize> sealed::Sealed for VectorState<VLENB> {}
+impl<const VLENB: usize> Deterministic for VectorState<VLENB> {}
+
+impl sealed::Sealed for CounterMonitor {}
+impl Deterministic for CounterMonitor {}
+impl sealed::Sealed for CoverageMonitor {}
+impl Deterministic for CoverageMonitor {}
+impl sealed::Sealed for ProfileMonitor {}
+impl Deterministic for ProfileMonitor {}
+impl sealed::Sealed for ZkTraceMonitor {}
+impl Deterministic for ZkTraceMonitor {}
+impl<S: TraceSink> sealed::Sealed for TraceMonitor<S> {}
+impl<S: TraceSink> Deterministic for TraceMonitor<S> {}
+
+/// Whether `raw` is a floating point instruction, scalar or vector.
+///
+/// Loads and stores of vectors share opcodes with scalar ones, only widths of
+/// scalar ones are matched.
+fn is_float(raw: u32) -> bool {
+    let funct3 = (raw >> 12) & 0b111;
+
+    match raw & 0x7F {
+        0b0000111 | 0b0100111 => (1..=4).contains(&funct3),
+        0b1000011 | 0b1000111 | 0b1001011 | 0b1001111 | 0b1010011 => true,
+        0b1010111 => funct3 == 0b001 || funct3 == 0b101,
+        _ => false,
+    }
+}
+
+impl<const N: usize> sealed::Sealed for [u8; N] {}
+impl<const N: usize> DeterministicMemory for [u8; N] {
+    fn root<H: Hasher>(&self) -> H::Digest {
+        let mut memory = PagedMemory::<u32>::new(N as u64);
+        for (i, page) in self.chunks(PAGE_SIZE).enumerate() {
+            if page.iter().any(|b| *b != 0) {
+                tangram_instruction::MemoryMut::store(&mut memory, (i * PAGE_SIZE) as u32, page);
+            }
+        }
+
+        memory.root::<H>()
+    }
+}
+
+/// Alignment rules are part of the profile, not of state.
+impl<M: DeterministicMemory> sealed::Sealed for Aligned<M> {}
+impl<M: DeterministicMemory> DeterministicMemory for Aligned<M> {
+    fn root<H: Hasher>(&self) -> H::Digest {
+        self.memory.root::<H>()
+    }
+}
+
+macro_rules! impl_deterministic_memory {
+    ($reg:ty) => {
+        impl sealed::Sealed for PagedMemory<$reg> {}
+        impl DeterministicMemory for PagedMemory<$reg> {
+            fn root<H: Hasher>(&self) -> H::Digest {
+                PagedMemory::root::<H>(self)
+            }
+        }
+
+        impl<T: Hasher> sealed::Sealed for MerkleMemory<T, $reg> {}
+        impl<T: Hasher> DeterministicMemory for MerkleMemory<T, $reg> {
+            fn root<H: Hasher>(&self) -> H::Digest {
+                self.memory().root::<H>()
+            }
+        }
+    };
+}
+
+impl_deterministic_memory!(u32);
+impl_deterministic_memory!(u64);
+
+impl<const RS: usize, I, R, M, MM, X> Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction + Deterministic,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    M: DeterministicMemory<Register = I::Register>,
+    MM: Monitor<I> + Deterministic,
+    X: Extension + Deterministic,
+{
+    /// Commitment to pc, registers, CSRs and memory, identical on every host.
+    ///
+    /// Only available when instruction set, memory and monitor are deterministic.
+    /// Registers are hashed as one leaf of little-endian `u64`, pc first. It is
+    /// paired with leaf of CSRs, machine CSRs of hart then halves of `mcycle`
+    /// and `minstret` read from monitor, missing ones as 0. State of
+    /// extensions, if any, is another leaf paired with them, encoded by
+    /// [`Extension::encode`].
+    pub fn state_root<H: Hasher>(&self) -> H::Digest {
+        let mut regs = Vec::with_capacity((RS + 1) * 8);
+        for r in core::iter::once(self.pc()).chain(self.regs().iter()) {
+            regs.extend_from_slice(&(*r).into().to_le_bytes());
+        }
+
+        let mut csrs = Vec::new();
+        self.csrs().encode(&mut csrs);
+        for csr in COUNTER_CSRS {
+            let value = self.monitor().read_csr(csr).unwrap_or(0);
+            csrs.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut regs = H::node(&H::leaf(&regs), &H::leaf(&csrs));
+
+        let mut ext = Vec::new();
+        self.ext().encode(&mut ext);
+        if !ext.is_empty() {
+            regs = H::node(&regs, &H::leaf(&ext));
+        }
+
+        H::node(&regs, &self.memory().root::<H>())
+    }
+}
+
+/// Counter CSRs committed by [`Executor::state_root`].
+const COUNTER_CSRS: [u16; 4] = [CSR_MCYCLE, CSR_MCYCLEH, CSR_MINSTRET, CSR_MINSTRETH];
+
+/// Executor of deterministic profile, for nodes which must agree bit for bit.
+///
+/// Instruction set, memory and monitor must be [`Deterministic`], checked when
+/// it is built, and floating point encodings are illegal instructions, so guest
+/// traps on them instead of depending on host NaN handling. Same guest from same
+/// state reaches same [`DeterministicExecutor::state_root`] on every node.
+pub struct DeterministicExecutor<const RS: usize, I, R, M, MM, X = ()>(
+    Executor<RS, I, R, M, MM, X>,
+)
+where
+    I: Instruction;
+
+impl<const RS: usize, I, R, M, MM, X> DeterministicExecutor<RS, I, R, M, MM, X>
+where
+    I: Instruction + Deterministic,
+    I::Register: Default + Clone + Copy,
+    M: DeterministicMemory<Register = I::Register>,
+    MM: Deterministic,
+    X: Extension + Deterministic,
+{
+    pub fn new(reader: R, memory: M, monitor: MM) -> Self {
+        let mut executor = Executor::new(reader, memory, monitor);
+        executor.set_reject(is_float);
+
+        Self(executor)
+    }
+
+    pub fn executor(&self) -> &Executor<RS, I, R, M, MM, X> {
+        &self.0
+    }
+
+    /// Mutable executor, to set up guest before running it, the profile
+    /// can't be changed through it.
+    pub fn executor_mut(&mut self) -> &mut Executor<RS, I, R, M, MM, X> {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> Executor<RS, I, R, M, MM, X> {
+        self.0
+    }
+}
+
+impl<const RS: usize, I, R, M, MM, E, X> DeterministicExecutor<RS, I, R, M, MM, X>
+where
+    I: Instruction + Deterministic,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    R: BytecodeReader<Register = I::Register, Error = E>,
+    E: Debug,
+    M: DeterministicMemory<Register = I::Register>,
+    MM: Monitor<I> + Deterministic,
+    X: Extension + Deterministic,
+{
+    /// See [`Executor::step`].
+    pub fn step(&mut self, bytes_len: u8) -> Result<Outcome, Error<E>> {
+        self.0.step(bytes_len)
+    }
+
+    /// See [`Executor::run`].
+    pub fn run(&mut self, bytes_len: u8) -> Result<Outcome, Error<E>> {
+        self.0.run(bytes_len)
+    }
+
+    /// See [`Executor::state_root`].
+    pub fn state_root<H: Hasher>(&self) -> H::Digest {
+        self.0.state_root::<H>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::{
+        riscv::{Illegal, Inst},
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvv::{RVVectorInst, VectorState},
+        riscvzicsr::RVCsrInst,
+    };
+
+    use super::{is_float, DeterministicExecutor};
+    use crate::{
+        CounterMonitor, DeterministicMemory, Error, Executor, PagedMemory, Sha256,
+        CAUSE_STORE_ACCESS, CSR_MCAUSE, CSR_MSCRATCH, CSR_MTVAL, CSR_TIME,
+    };
+
+    type Inst32 = RV32iBaseInst<RVCsrInst<Illegal>>;
+
+    const PROGRAM: &str = "
+        li a0, 0
+        li a1, 0x1000
+        li t1, 20
+    loop:
+        addi a0, a0, 3
+        sw a0, 0(a1)
+        addi a1, a1, 0x100
+        addi t1, t1, -1
+        bnez t1, loop
+        ecall
+    ";
+
+    /// State root of `PROGRAM`, computed once, any host must reach it.
+    const ROOT: [u8; 32] = [
+        0xd3, 0x27, 0x6f, 0xe8, 0xd0, 0xdf, 0xe1, 0xea, 0x65, 0x70, 0x4d, 0xc4, 0x83, 0xd1, 0xa3,
+        0x13, 0xe6, 0xb3, 0x2a, 0xbc, 0x84, 0x47, 0x49, 0x09, 0x53, 0xae, 0x57, 0xb7, 0x74, 0x89,
+        0x7d, 0x1d,
+    ];
+
+    fn profile() -> DeterministicExecutor<32, Inst32, [u8; 128], PagedMemory<u32>, CounterMonitor> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        DeterministicExecutor::new(
+            code,
+            PagedMemory::<u32>::new(1 << 16),
+            CounterMonitor::new(),
+        )
+    }
+
+    #[test]
+    fn test_state_root() {
+        let mut executor = profile();
+        assert!(executor.run(4).is_err());
+        assert_eq!(executor.state_root::<Sha256>(), ROOT);
+
+        let mut executor = profile();
+        executor.executor_mut().regs_mut()[5] = 7;
+        assert!(executor.run(4).is_err());
+        assert_ne!(executor.state_root::<Sha256>(), ROOT);
+
+        // Vector state is committed too.
+        let mut executor: DeterministicExecutor<
+            32,
+            RV32iBaseInst<RVVectorInst<Illegal>>,
+            _,
+            _,
+            _,
+            VectorState,
+        > = DeterministicExecutor::new([0u8; 16], [0u8; 16], ());
+        let root = executor.state_root::<Sha256>();
+        executor.executor_mut().ext_mut().v[0][0] = 1;
+        assert_ne!(executor.state_root::<Sha256>(), root);
+
+        // Array and paged memory with equal contents have equal roots.
+        let mut bytes = [0u8; 3 * 4096];
+        bytes[0x2001] = 5;
+        let mut paged = PagedMemory::<u32>::new(3 * 4096);
+        tangram_instruction::MemoryMut::store(&mut paged, 0x2001, &[5]);
+        assert_eq!(bytes.root::<Sha256>(), paged.root::<Sha256>());
+    }
+
+    #[test]
+    fn test_state_root_csrs() {
+        let mut code = [0u8; 128];
+        assemble("lui a1, 0x20\nsw a1, 0(a1)", 0, &mut code).unwrap();
+        let csrw = Inst::build_i(0b1110011, 0, 0b001, 11, CSR_MSCRATCH as i32);
+        code[16..20].copy_from_slice(&csrw.raw().to_le_bytes());
+
+        let mut executor: DeterministicExecutor<32, Inst32, _, _, _> = DeterministicExecutor::new(
+            code,
+            PagedMemory::<u32>::new(1 << 16),
+            CounterMonitor::new(),
+        );
+        executor.executor_mut().set_trap_vector(Some(16));
+        let root = executor.state_root::<Sha256>();
+
+        // Store beyond memory traps, and the trap is committed.
+        executor.step(4).unwrap();
+        let before = executor.state_root::<Sha256>();
+        executor.step(4).unwrap();
+        assert_eq!(*executor.executor().pc(), 16);
+        assert_eq!(
+            executor.executor().read_csr(CSR_MCAUSE),
+            Some(CAUSE_STORE_ACCESS)
+        );
+        let trapped = executor.state_root::<Sha256>();
+        assert_ne!(trapped, before);
+        assert_ne!(trapped, root);
+
+        // Handler writing `mscratch` changes root too.
+        executor.step(4).unwrap();
+        assert_eq!(executor.executor().read_csr(CSR_MSCRATCH), Some(0x20000));
+        let written = executor.state_root::<Sha256>();
+        assert_ne!(written, trapped);
+
+        // So do counters of monitor.
+        let counters = executor.executor_mut().monitor_mut().counters_mut();
+        counters.instret += 1;
+        assert_ne!(executor.state_root::<Sha256>(), written);
+    }
+
+    #[test]
+    fn test_reject_float() {
+        // fadd.s ft0, ft1, ft2
+        const FADD: u32 = 0x0020_8053;
+
+        let mut code = [0u8; 128];
+        code[..4].copy_from_slice(&FADD.to_le_bytes());
+        let csrr = Inst::build_i(0b1110011, 10, 0b010, 0, CSR_TIME as i32);
+        code[16..20].copy_from_slice(&csrr.raw().to_le_bytes());
+
+        let mut executor: DeterministicExecutor<32, Inst32, _, _, _> =
+            DeterministicExecutor::new(code, [0u8; 64], CounterMonitor::new());
+        let illegal = tangram_instruction::Error::IllegalInstruction { raw: FADD, pc: 0 };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == illegal));
+
+        // Guest handles it, and reads time counted in instructions.
+        executor.executor_mut().set_trap_vector(Some(16));
+        executor.step(4).unwrap();
+        assert_eq!(executor.executor().read_csr(CSR_MCAUSE), Some(2));
+        assert_eq!(executor.executor().read_csr(CSR_MTVAL), Some(FADD as u64));
+        executor.step(4).unwrap();
+        assert_eq!(executor.executor().regs()[10], 1);
+
+        // Plain executor has no such profile.
+        let mut executor: Executor<32, Inst32, _, _, _> =
+            Executor::new(code, [0u8; 64], CounterMonitor::new());
+        executor.set_trap_vector(Some(16));
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(2));
+
+        assert!(is_float(0x0000_2007));
+        assert!(is_float(0x0220_1057));
+        assert!(!is_float(0x0200_0007));
+        assert!(!is_float(0x0220_0057));
+    }
+}
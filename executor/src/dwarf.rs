@@ -0,0 +1,401 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::Elf;
+
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_CONST_ADD_PC: u8 = 8;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 9;
+
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+
+const DW_LNCT_PATH: u64 = 1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 2;
+
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+
+/// Cursor over little-endian DWARF data.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let b = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(b)
+    }
+
+    fn uint(&mut self, len: usize) -> Option<u64> {
+        let mut v = [0u8; 8];
+        v[..len].copy_from_slice(self.bytes(len)?);
+        Some(u64::from_le_bytes(v))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            v |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+
+        None
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut v = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            v |= ((b & 0x7F) as i64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    v |= -1 << shift;
+                }
+                return Some(v);
+            }
+            if shift >= 64 {
+                return None;
+            }
+        }
+    }
+
+    fn c_str(&mut self) -> Option<&'a str> {
+        let s = &self.data[self.pos.min(self.data.len())..];
+        let end = s.iter().position(|b| *b == 0)?;
+        self.pos += end + 1;
+
+        core::str::from_utf8(&s[..end]).ok()
+    }
+}
+
+/// Read NUL terminated string at `off` of string section.
+fn str_at(section: &[u8], off: u64) -> Option<&str> {
+    let mut r = Reader::new(section);
+    r.pos = usize::try_from(off).ok()?;
+    r.c_str()
+}
+
+/// Row of line table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Row {
+    addr: u64,
+    file: usize,
+    line: u32,
+    /// First address after a sequence.
+    end: bool,
+}
+
+/// Source lines of addresses, from `.debug_line` of ELF file.
+///
+/// DWARF 2 to 5 are supported. Paths are joined with directories in line tables,
+/// compilation directory isn't known for DWARF 4 and older.
+#[derive(Debug, Clone, Default)]
+pub struct LineTable {
+    files: Vec<String>,
+    rows: Vec<Row>,
+}
+
+impl LineTable {
+    /// Parse line table, return `None` if ELF file has no `.debug_line` or it is invalid.
+    pub fn from_elf(elf: &Elf) -> Option<Self> {
+        let data = elf.section(".debug_line")?.data;
+        let line_str = elf.section(".debug_line_str").map_or(&[][..], |s| s.data);
+        let debug_str = elf.section(".debug_str").map_or(&[][..], |s| s.data);
+
+        let mut table = Self::default();
+        let mut r = Reader::new(data);
+
+        while !r.is_empty() {
+            let mut len = r.uint(4)?;
+            let offset_size = if len == 0xFFFF_FFFF {
+                len = r.uint(8)?;
+                8
+            } else {
+                4
+            };
+
+            let unit = r.bytes(usize::try_from(len).ok()?)?;
+            let strings = Strings {
+                line_str,
+                debug_str,
+                offset_size,
+            };
+            table.parse_unit(unit, &strings)?;
+        }
+
+        table.rows.sort_by_key(|r| (r.addr, !r.end));
+
+        Some(table)
+    }
+
+    fn parse_unit(&mut self, unit: &[u8], strings: &Strings) -> Option<()> {
+        let mut r = Reader::new(unit);
+
+        let version = r.uint(2)?;
+        if !(2..=5).contains(&version) {
+            return None;
+        }
+
+        let mut address_size = 0;
+        if version >= 5 {
+            address_size = r.u8()? as usize;
+            r.u8()?;
+        }
+
+        let header_len = usize::try_from(r.uint(strings.offset_size)?).ok()?;
+        let program = r.pos.checked_add(header_len)?;
+
+        let min_inst_len = r.u8()? as u64;
+        if version >= 4 {
+            r.u8()?;
+        }
+        // default_is_stmt
+        r.u8()?;
+        let line_base = r.u8()? as i8 as i64;
+        let line_range = r.u8()?;
+        let opcode_base = r.u8()?;
+        let opcode_lengths = r.bytes((opcode_base as usize).checked_sub(1)?)?;
+
+        if line_range == 0 {
+            return None;
+        }
+
+        // Files are numbered from 1 before DWARF 5.
+        let mut files = Vec::new();
+        if version >= 5 {
+            let dirs = self.entries(&mut r, strings)?;
+            for (name, dir) in self.entries(&mut r, strings)? {
+                files.push(self.file(&dirs, name.unwrap_or(""), dir));
+            }
+        } else {
+            let mut dirs = alloc::vec![(None, 0)];
+            while let Some(dir) = r.c_str().filter(|s| !s.is_empty()) {
+                dirs.push((Some(dir), 0));
+            }
+
+            files.push(usize::MAX);
+            while let Some(name) = r.c_str().filter(|s| !s.is_empty()) {
+                let dir = r.uleb()?;
+                r.uleb()?;
+                r.uleb()?;
+                files.push(self.file(&dirs, name, dir));
+            }
+        }
+
+        r.pos = program;
+
+        let mut addr = 0u64;
+        let mut file = 1u64;
+        let mut line = 1i64;
+        let row = |table: &mut Self, addr: u64, file: u64, line: i64, end: bool| {
+            table.rows.push(Row {
+                addr,
+                file: files.get(file as usize).copied().unwrap_or(usize::MAX),
+                line: line as u32,
+                end,
+            });
+        };
+
+        while !r.is_empty() {
+            let opcode = r.u8()?;
+
+            if opcode >= opcode_base {
+                let adjusted = (opcode - opcode_base) as u64;
+                addr = addr.wrapping_add(adjusted / line_range as u64 * min_inst_len);
+                line += line_base + (adjusted % line_range as u64) as i64;
+                row(self, addr, file, line, false);
+                continue;
+            }
+
+            match opcode {
+                0 => {
+                    let len = usize::try_from(r.uleb()?).ok()?;
+                    let mut ext = Reader::new(r.bytes(len)?);
+
+                    match ext.u8()? {
+                        DW_LNE_END_SEQUENCE => {
+                            row(self, addr, file, line, true);
+                            (addr, file, line) = (0, 1, 1);
+                        }
+                        DW_LNE_SET_ADDRESS => {
+                            let size = if address_size != 0 {
+                                address_size
+                            } else {
+                                len - 1
+                            };
+                            addr = ext.uint(size.min(8))?;
+                        }
+                        // Files defined by `DW_LNE_define_file` stay unknown.
+                        _ => {}
+                    }
+                }
+                DW_LNS_COPY => row(self, addr, file, line, false),
+                DW_LNS_ADVANCE_PC => {
+                    addr = addr.wrapping_add(r.uleb()?.wrapping_mul(min_inst_len));
+                }
+                DW_LNS_ADVANCE_LINE => line += r.sleb()?,
+                DW_LNS_SET_FILE => file = r.uleb()?,
+                DW_LNS_CONST_ADD_PC => {
+                    let adjusted = (255 - opcode_base) as u64;
+                    addr = addr.wrapping_add(adjusted / line_range as u64 * min_inst_len);
+                }
+                DW_LNS_FIXED_ADVANCE_PC => addr = addr.wrapping_add(r.uint(2)?),
+                _ => {
+                    for _ in 0..opcode_lengths[opcode as usize - 1] {
+                        r.uleb()?;
+                    }
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Read DWARF 5 directory or file entries, as path and directory index.
+    fn entries<'a>(
+        &self,
+        r: &mut Reader<'a>,
+        strings: &Strings<'a>,
+    ) -> Option<Vec<(Option<&'a str>, u64)>> {
+        let format_count = r.u8()?;
+        let mut format = Vec::new();
+        for _ in 0..format_count {
+            format.push((r.uleb()?, r.uleb()?));
+        }
+
+        let count = r.uleb()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let (mut path, mut dir) = (None, 0);
+
+            for (content, form) in format.iter() {
+                let mut s = None;
+                let mut v = 0;
+
+                match *form {
+                    DW_FORM_STRING => s = Some(r.c_str()?),
+                    DW_FORM_LINE_STRP => {
+                        s = Some(str_at(strings.line_str, r.uint(strings.offset_size)?)?)
+                    }
+                    DW_FORM_STRP => {
+                        s = Some(str_at(strings.debug_str, r.uint(strings.offset_size)?)?)
+                    }
+                    DW_FORM_UDATA => v = r.uleb()?,
+                    DW_FORM_DATA1 => v = r.uint(1)?,
+                    DW_FORM_DATA2 => v = r.uint(2)?,
+                    DW_FORM_DATA4 => v = r.uint(4)?,
+                    DW_FORM_DATA8 => v = r.uint(8)?,
+                    DW_FORM_DATA16 => {
+                        r.bytes(16)?;
+                    }
+                    DW_FORM_BLOCK => {
+                        let len = usize::try_from(r.uleb()?).ok()?;
+                        r.bytes(len)?;
+                    }
+                    _ => return None,
+                }
+
+                match *content {
+                    DW_LNCT_PATH => path = s,
+                    DW_LNCT_DIRECTORY_INDEX => dir = v,
+                    _ => {}
+                }
+            }
+
+            entries.push((path, dir));
+        }
+
+        Some(entries)
+    }
+
+    /// Index of file with `name` in directory `dir`, added if new.
+    fn file(&mut self, dirs: &[(Option<&str>, u64)], name: &str, dir: u64) -> usize {
+        let dir = dirs.get(dir as usize).and_then(|d| d.0).unwrap_or("");
+
+        let path = if name.starts_with('/') || dir.is_empty() || dir == "." {
+            name.to_string()
+        } else {
+            let mut path = dir.to_string();
+            if !path.ends_with('/') {
+                path.push('/');
+            }
+            path.push_str(name);
+            path
+        };
+
+        match self.files.iter().position(|f| *f == path) {
+            Some(i) => i,
+            None => {
+                self.files.push(path);
+                self.files.len() - 1
+            }
+        }
+    }
+
+    /// Paths of source files.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// File index and line of instruction at `pc`.
+    pub fn lookup(&self, pc: u64) -> Option<(usize, u32)> {
+        let i = self.rows.partition_point(|r| r.addr <= pc).checked_sub(1)?;
+        let row = self.rows[i];
+
+        (!row.end && row.file != usize::MAX).then_some((row.file, row.line))
+    }
+
+    /// Every `(address, file, line)` starting instructions of a line, in order of address.
+    pub fn rows(&self) -> impl Iterator<Item = (u64, usize, u32)> + '_ {
+        self.rows
+            .iter()
+            .filter(|r| !r.end && r.file != usize::MAX)
+            .map(|r| (r.addr, r.file, r.line))
+    }
+
+    /// Address after instructions of row starting at `addr`.
+    pub(crate) fn row_end(&self, addr: u64) -> u64 {
+        let i = self.rows.partition_point(|r| r.addr <= addr);
+        self.rows.get(i).map_or(u64::MAX, |r| r.addr)
+    }
+}
+
+/// String sections referenced by DWARF 5 line tables.
+struct Strings<'a> {
+    line_str: &'a [u8],
+    debug_str: &'a [u8],
+    offset_size: usize,
+}
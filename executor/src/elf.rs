@@ -0,0 +1,265 @@
+use crate::ElfError;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const STT_FUNC: u8 = 2;
+
+/// Loadable segment of ELF file
+pub struct Segment<'a> {
+    /// Virtual address to load
+    pub vaddr: u64,
+    /// Bytes in file, shorter than `mem_size` when segment has bss
+    pub data: &'a [u8],
+    /// Size in memory
+    pub mem_size: u64,
+}
+
+/// Symbol of ELF file
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub value: u64,
+    pub size: u64,
+    pub kind: u8,
+}
+
+impl<'a> Symbol<'a> {
+    pub fn is_function(&self) -> bool {
+        self.kind == STT_FUNC
+    }
+}
+
+/// Section of ELF file
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub kind: u32,
+    pub addr: u64,
+    pub data: &'a [u8],
+    link: u32,
+}
+
+/// Little-endian ELF32/ELF64 file, parsed without copy.
+pub struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < 52 {
+            return Err(ElfError::ErrTruncated);
+        }
+
+        if data[..4] != [0x7F, b'E', b'L', b'F'] {
+            return Err(ElfError::ErrBadMagic);
+        }
+
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return Err(ElfError::ErrUnsupportedClass),
+        };
+
+        if data[5] != 1 {
+            return Err(ElfError::ErrUnsupportedEndian);
+        }
+
+        if is_64 && data.len() < 64 {
+            return Err(ElfError::ErrTruncated);
+        }
+
+        Ok(Self { data, is_64 })
+    }
+
+    fn u16(&self, off: usize) -> u16 {
+        self.data
+            .get(off..off + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .unwrap_or_default()
+    }
+
+    fn u32(&self, off: usize) -> u32 {
+        self.data
+            .get(off..off + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .unwrap_or_default()
+    }
+
+    fn u64(&self, off: usize) -> u64 {
+        self.data
+            .get(off..off + 8)
+            .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .unwrap_or_default()
+    }
+
+    /// Read address-sized field.
+    fn addr(&self, off32: usize, off64: usize) -> u64 {
+        if self.is_64 {
+            self.u64(off64)
+        } else {
+            self.u32(off32) as u64
+        }
+    }
+
+    fn bytes(&self, off: u64, size: u64) -> &'a [u8] {
+        let (off, size) = (off as usize, size as usize);
+        self.data.get(off..off.saturating_add(size)).unwrap_or(&[])
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    pub fn machine(&self) -> u16 {
+        self.u16(18)
+    }
+
+    pub fn entry(&self) -> u64 {
+        self.addr(24, 24)
+    }
+
+    /// Loadable segments.
+    pub fn segments(&self) -> impl Iterator<Item = Segment<'a>> + '_ {
+        let phoff = self.addr(28, 32) as usize;
+        let (entsize, num) = if self.is_64 {
+            (self.u16(54) as usize, self.u16(56) as usize)
+        } else {
+            (self.u16(42) as usize, self.u16(44) as usize)
+        };
+
+        (0..num).filter_map(move |i| {
+            let p = phoff + i * entsize;
+            if self.u32(p) != PT_LOAD {
+                return None;
+            }
+
+            let (offset, vaddr, filesz, memsz) = if self.is_64 {
+                (
+                    self.u64(p + 8),
+                    self.u64(p + 16),
+                    self.u64(p + 32),
+                    self.u64(p + 40),
+                )
+            } else {
+                (
+                    self.u32(p + 4) as u64,
+                    self.u32(p + 8) as u64,
+                    self.u32(p + 16) as u64,
+                    self.u32(p + 20) as u64,
+                )
+            };
+
+            Some(Segment {
+                vaddr,
+                data: self.bytes(offset, filesz),
+                mem_size: memsz,
+            })
+        })
+    }
+
+    fn section_at(&self, index: usize, names: &'a [u8]) -> Section<'a> {
+        let shoff = self.addr(32, 40) as usize;
+        let entsize = if self.is_64 {
+            self.u16(58)
+        } else {
+            self.u16(46)
+        } as usize;
+        let s = shoff + index * entsize;
+
+        let (addr, offset, size, link) = if self.is_64 {
+            (
+                self.u64(s + 16),
+                self.u64(s + 24),
+                self.u64(s + 32),
+                self.u32(s + 40),
+            )
+        } else {
+            (
+                self.u32(s + 12) as u64,
+                self.u32(s + 16) as u64,
+                self.u32(s + 20) as u64,
+                self.u32(s + 24),
+            )
+        };
+
+        Section {
+            name: c_str(names, self.u32(s) as usize),
+            kind: self.u32(s + 4),
+            addr,
+            data: self.bytes(offset, size),
+            link,
+        }
+    }
+
+    /// All sections, named by section header string table.
+    pub fn sections(&self) -> impl Iterator<Item = Section<'a>> + '_ {
+        let (num, shstrndx) = if self.is_64 {
+            (self.u16(60) as usize, self.u16(62) as usize)
+        } else {
+            (self.u16(48) as usize, self.u16(50) as usize)
+        };
+
+        let names = if shstrndx < num {
+            self.section_at(shstrndx, &[]).data
+        } else {
+            &[]
+        };
+
+        (0..num).map(move |i| self.section_at(i, names))
+    }
+
+    /// Find section by name.
+    pub fn section(&self, name: &str) -> Option<Section<'a>> {
+        self.sections().find(|s| s.name == name)
+    }
+
+    /// Symbols in `.symtab`.
+    pub fn symbols(&self) -> impl Iterator<Item = Symbol<'a>> + '_ {
+        let symtab = self.sections().find(|s| s.kind == SHT_SYMTAB);
+        let strtab = symtab
+            .as_ref()
+            .and_then(|s| self.sections().nth(s.link as usize))
+            .map(|s| s.data)
+            .unwrap_or(&[]);
+        let data = symtab.map(|s| s.data).unwrap_or(&[]);
+
+        let entsize = if self.is_64 { 24 } else { 16 };
+
+        data.chunks_exact(entsize).map(move |e| {
+            let name = u32::from_le_bytes([e[0], e[1], e[2], e[3]]) as usize;
+
+            let (value, size, info) = if self.is_64 {
+                (
+                    u64::from_le_bytes([e[8], e[9], e[10], e[11], e[12], e[13], e[14], e[15]]),
+                    u64::from_le_bytes([e[16], e[17], e[18], e[19], e[20], e[21], e[22], e[23]]),
+                    e[4],
+                )
+            } else {
+                (
+                    u32::from_le_bytes([e[4], e[5], e[6], e[7]]) as u64,
+                    u32::from_le_bytes([e[8], e[9], e[10], e[11]]) as u64,
+                    e[12],
+                )
+            };
+
+            Symbol {
+                name: c_str(strtab, name),
+                value,
+                size,
+                kind: info & 0xF,
+            }
+        })
+    }
+
+    /// Find symbol by name.
+    pub fn symbol(&self, name: &str) -> Option<Symbol<'a>> {
+        self.symbols().find(|s| s.name == name)
+    }
+}
+
+/// Read NUL terminated string at `off` of string table.
+fn c_str(table: &[u8], off: usize) -> &str {
+    let s = table.get(off..).unwrap_or(&[]);
+    let end = s.iter().position(|b| *b == 0).unwrap_or(s.len());
+
+    core::str::from_utf8(&s[..end]).unwrap_or("")
+}
@@ -4,6 +4,8 @@ use core::fmt::Debug;
 pub enum Error<E: Debug> {
     AppError(E),
     InstructionError(tangram_instruction::Error),
+    /// Execution is halted by [`Monitor`](crate::Monitor).
+    Halted,
 }
 
 impl<E: Debug> From<tangram_instruction::Error> for Error<E> {
@@ -11,3 +13,12 @@ impl<E: Debug> From<tangram_instruction::Error> for Error<E> {
         Self::InstructionError(value)
     }
 }
+
+/// Error of parsing ELF file
+#[derive(Debug, PartialEq, Eq)]
+pub enum ElfError {
+    ErrBadMagic,
+    ErrUnsupportedClass,
+    ErrUnsupportedEndian,
+    ErrTruncated,
+}
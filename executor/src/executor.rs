@@ -1,11 +1,25 @@
-use core::fmt::Debug;
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+use core::{cell::RefCell, fmt::Debug};
 
 use tangram_instruction::{Instruction, MemoryMut};
 
-use crate::{AsyncBytecodeReader, BytecodeReader, Error, Monitor};
+#[cfg(feature = "alloc")]
+use crate::History;
+use crate::{
+    breakpoint, AsyncBytecodeReader, BytecodeReader, Control, Error, Extension, GuestTrap,
+    HartContext, HartCsrs, HookedMemory, Monitor, Outcome, TraceRecord, WatchKind, Watchpoint,
+    CAUSE_ILLEGAL_INSTRUCTION, CAUSE_INSTRUCTION_MISALIGNED, CAUSE_LOAD_ACCESS,
+    CAUSE_LOAD_MISALIGNED, CAUSE_MACHINE_ECALL, CAUSE_STORE_ACCESS, CAUSE_STORE_MISALIGNED,
+    MAX_BREAKPOINTS, MAX_WATCHPOINTS,
+};
 
 /// VM Executor
-pub struct Executor<const RS: usize, I, R, M, MM>
+///
+/// `RS` is length of register file, 32 for RV32I and 16 for RV32E, instructions
+/// naming registers beyond it are illegal. `X` is state of extensions, like
+/// [`VectorState`](tangram_instruction::riscvv::VectorState) for RVV.
+pub struct Executor<const RS: usize, I, R, M, MM, X = ()>
 where
     I: Instruction,
 {
@@ -14,12 +28,30 @@ where
     reader: R,
     memory: M,
     monitor: MM,
+    ext: X,
+    breakpoints: [Option<u64>; MAX_BREAKPOINTS],
+    watchpoints: [Option<Watchpoint>; MAX_WATCHPOINTS],
+    /// pc of instruction stopped by watchpoint, it ignores watchpoints once
+    /// when resumed.
+    watch_hit: Option<u64>,
+    /// Guest trap handler and trap it is handling.
+    csrs: HartCsrs,
+    /// Raw encodings which are illegal even if instruction set decodes them.
+    reject: fn(u32) -> bool,
+    /// Whether `ecall` jumps to trap vector instead of returning error.
+    trap_ecall: bool,
+    #[cfg(feature = "alloc")]
+    history: History<I::Register, X>,
+    /// Decoded instructions and raw encoding by pc, if caching is enabled.
+    #[cfg(feature = "alloc")]
+    decoded: Option<BTreeMap<u64, (I, u32)>>,
 }
 
-impl<const RS: usize, I, R, M, MM> Executor<RS, I, R, M, MM>
+impl<const RS: usize, I, R, M, MM, X> Executor<RS, I, R, M, MM, X>
 where
     I: Instruction,
     I::Register: Default + Clone + Copy,
+    X: Extension,
 {
     pub fn new(reader: R, memory: M, monitor: MM) -> Self {
         let pc = I::Register::default();
@@ -31,57 +63,975 @@ where
             memory,
             reader,
             monitor,
+            ext: X::default(),
+            breakpoints: [None; MAX_BREAKPOINTS],
+            watchpoints: [None; MAX_WATCHPOINTS],
+            watch_hit: None,
+            csrs: HartCsrs::default(),
+            reject: |_| false,
+            trap_ecall: false,
+            #[cfg(feature = "alloc")]
+            history: History::new(),
+            #[cfg(feature = "alloc")]
+            decoded: None,
         }
     }
+
+    /// Program counter
+    pub fn pc(&self) -> &I::Register {
+        &self.pc
+    }
+
+    /// Set program counter, usually to the entry of program.
+    ///
+    /// Recorded history is dropped, like for [`Executor::regs_mut`] and
+    /// [`Executor::memory_mut`], as steps can't be undone on changed state.
+    pub fn set_pc(&mut self, pc: I::Register) {
+        self.clear_history();
+        self.pc = pc;
+    }
+
+    /// Registers
+    pub fn regs(&self) -> &[I::Register; RS] {
+        &self.regs
+    }
+
+    /// Mutable registers, recorded history is dropped.
+    pub fn regs_mut(&mut self) -> &mut [I::Register; RS] {
+        self.clear_history();
+        &mut self.regs
+    }
+
+    /// Bytecode reader
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    /// Mutable bytecode reader, cached instructions stay until `fence.i` runs.
+    pub fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Memory
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    /// Mutable memory, recorded history and decoded instructions are dropped,
+    /// as code may be changed.
+    pub fn memory_mut(&mut self) -> &mut M {
+        self.clear_history();
+        #[cfg(feature = "alloc")]
+        self.invalidate_decoded();
+        &mut self.memory
+    }
+
+    /// State of extensions
+    pub fn ext(&self) -> &X {
+        &self.ext
+    }
+
+    /// Mutable state of extensions, recorded history is dropped.
+    pub fn ext_mut(&mut self) -> &mut X {
+        self.clear_history();
+        &mut self.ext
+    }
+
+    /// Monitor
+    pub fn monitor(&self) -> &MM {
+        &self.monitor
+    }
+
+    /// Mutable monitor
+    pub fn monitor_mut(&mut self) -> &mut MM {
+        &mut self.monitor
+    }
+
+    /// Make encodings matching `reject` illegal, for profiles forbidding part
+    /// of instruction set.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn set_reject(&mut self, reject: fn(u32) -> bool) {
+        self.reject = reject;
+    }
+
+    /// Swap pc, registers, machine CSRs and extensions of running hart with
+    /// those of another one. Recorded steps are of swapped out hart, so they are
+    /// dropped.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn swap_hart(
+        &mut self,
+        pc: &mut I::Register,
+        regs: &mut [I::Register; RS],
+        csrs: &mut HartCsrs,
+        ext: &mut X,
+    ) {
+        self.clear_history();
+        core::mem::swap(&mut self.pc, pc);
+        core::mem::swap(&mut self.regs, regs);
+        core::mem::swap(&mut self.csrs, csrs);
+        core::mem::swap(&mut self.ext, ext);
+    }
+
+    /// Keep decoded instructions by pc instead of reading and decoding them again.
+    ///
+    /// Cache is cleared when `fence.i` is executed, so guest code written to the
+    /// reader, like JIT output stored through [`MemoryReader`](crate::MemoryReader),
+    /// must be followed by it as on real harts.
+    #[cfg(feature = "alloc")]
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded = enabled.then(BTreeMap::new);
+    }
+
+    /// Drop all decoded instructions, like `fence.i`.
+    #[cfg(feature = "alloc")]
+    pub fn invalidate_decoded(&mut self) {
+        if let Some(decoded) = &mut self.decoded {
+            decoded.clear();
+        }
+    }
+
+    /// Number of decoded instructions in cache.
+    #[cfg(feature = "alloc")]
+    pub fn decoded_len(&self) -> usize {
+        self.decoded.as_ref().map_or(0, BTreeMap::len)
+    }
+
+    fn clear_history(&mut self) {
+        #[cfg(feature = "alloc")]
+        self.history.clear();
+    }
+
+    /// Values of `mepc`, `mcause` and `mtval`, set by last trap taken by guest
+    /// or by its handler, which returns to `epc` with `mret`.
+    pub fn last_trap(&self) -> Option<GuestTrap> {
+        self.csrs.trap
+    }
+
+    pub(crate) fn csrs(&self) -> &HartCsrs {
+        &self.csrs
+    }
+
+    /// Mutable machine CSRs, recorded history is dropped.
+    pub(crate) fn csrs_mut(&mut self) -> &mut HartCsrs {
+        self.clear_history();
+        &mut self.csrs
+    }
 }
 
-impl<const RS: usize, I, R, M, MM, E> Executor<RS, I, R, M, MM>
+impl<const RS: usize, I, R, M, MM, X> Executor<RS, I, R, M, MM, X>
 where
     I: Instruction,
-    I::Register: Default + Clone + Copy,
+    I::Register: Copy + Into<u64> + TryFrom<u64>,
+    X: Extension,
+{
+    /// Trap illegal instructions, misaligned addresses and accesses beyond memory
+    /// into guest handler at `vector` instead of returning the error, like `mtvec`
+    /// in direct mode. `None` returns errors until guest writes `mtvec`.
+    ///
+    /// Trap is reported to [`Monitor::on_trap`](crate::Monitor::on_trap) first,
+    /// and halting there still stops executor. Recorded history is dropped.
+    pub fn set_trap_vector(&mut self, vector: Option<I::Register>) {
+        #[cfg(feature = "alloc")]
+        self.history.clear();
+        self.csrs.vector = vector.map(Into::into);
+    }
+
+    /// Trap `ecall` into guest handler like other exceptions, with `mcause` of
+    /// [`CAUSE_MACHINE_ECALL`], instead of returning
+    /// [`EnvironmentCall`](tangram_instruction::Error::EnvironmentCall) to host.
+    ///
+    /// For guests handling their own environment calls, like test environments
+    /// of `riscv-tests`. Halting in [`Monitor::on_syscall`](crate::Monitor::on_syscall)
+    /// still stops executor.
+    pub fn set_trap_ecall(&mut self, enabled: bool) {
+        self.trap_ecall = enabled;
+    }
+
+    /// Move pc over instruction of `len` bytes without executing it, like
+    /// returning from `ecall` handler.
+    pub(crate) fn skip_inst(&mut self, len: u8) {
+        let pc = self.pc.into().wrapping_add(len as u64);
+        if let Ok(pc) = I::Register::try_from(pc) {
+            self.pc = pc;
+        }
+    }
+}
+
+/// First 4 bytes of instruction as little-endian integer.
+fn raw_inst(bytes: &[u8]) -> u32 {
+    let mut b = [0u8; 4];
+    let len = bytes.len().min(4);
+    b[..len].copy_from_slice(&bytes[..len]);
+
+    u32::from_le_bytes(b)
+}
+
+/// Decode instruction of `raw` encoding, unless `reject` makes it illegal.
+fn decode<I: Instruction>(
+    bytes: &[u8],
+    raw: u32,
+    reject: fn(u32) -> bool,
+) -> tangram_instruction::Result<I> {
+    match reject(raw) {
+        true => Err(tangram_instruction::Error::illegal(raw)),
+        false => I::new(bytes),
+    }
+}
+
+impl<const RS: usize, I, R, M, MM, X> Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    M: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    /// Stop `run` before executing instruction at `pc`, return false if there are
+    /// already [`MAX_BREAKPOINTS`].
+    pub fn add_breakpoint(&mut self, pc: I::Register) -> bool {
+        breakpoint::insert(&mut self.breakpoints, pc.into())
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: I::Register) -> bool {
+        breakpoint::remove(&mut self.breakpoints, pc.into())
+    }
+
+    /// Stop before instruction accessing `len` bytes at `addr`, return false if there
+    /// are already [`MAX_WATCHPOINTS`].
+    pub fn add_watchpoint(&mut self, kind: WatchKind, addr: I::Register, len: u64) -> bool {
+        let addr = addr.into();
+        breakpoint::insert(&mut self.watchpoints, Watchpoint { kind, addr, len })
+    }
+
+    pub fn remove_watchpoint(&mut self, kind: WatchKind, addr: I::Register, len: u64) -> bool {
+        let addr = addr.into();
+        breakpoint::remove(&mut self.watchpoints, Watchpoint { kind, addr, len })
+    }
+
+    pub(crate) fn is_breakpoint(&self) -> bool {
+        let pc = self.pc.into();
+        self.breakpoints.contains(&Some(pc))
+    }
+
+    /// Read CSR as guest sees it, machine CSRs of hart, those of extensions or
+    /// those of monitor, `None` if it doesn't exist.
+    ///
+    /// `mepc`, `mcause` and `mtval` are 0 until guest takes a trap.
+    pub fn read_csr(&self, csr: u16) -> Option<u64> {
+        self.csrs
+            .read(csr)
+            .or_else(|| self.ext.read_csr(csr))
+            .or_else(|| self.monitor.read_csr(csr))
+    }
+
+    /// Report error of instruction `raw` to `on_trap` or `on_syscall`, illegal
+    /// instructions, misaligned addresses and access faults jump to trap vector
+    /// if it is set, like `ecall` if it is trapped.
+    fn trap<E: Debug>(
+        &mut self,
+        e: tangram_instruction::Error,
+        raw: u32,
+    ) -> Result<Outcome, Error<E>> {
+        let e = e.with_pc(self.pc.into());
+        let control = match e {
+            tangram_instruction::Error::EnvironmentCall => {
+                self.monitor.on_syscall(&self.pc, &self.regs)
+            }
+            _ => self.monitor.on_trap(&self.pc, &e),
+        };
+
+        if control == Control::Halt {
+            return Err(Error::Halted);
+        }
+
+        let (cause, tval) = match e {
+            tangram_instruction::Error::IllegalInstruction { .. }
+            | tangram_instruction::Error::ErrFailedDeocdeInstructon => {
+                (CAUSE_ILLEGAL_INSTRUCTION, raw as u64)
+            }
+            tangram_instruction::Error::InstructionMisaligned { target, .. } => {
+                (CAUSE_INSTRUCTION_MISALIGNED, target)
+            }
+            tangram_instruction::Error::LoadMisaligned { addr, .. } => {
+                (CAUSE_LOAD_MISALIGNED, addr)
+            }
+            tangram_instruction::Error::StoreMisaligned { addr, .. } => {
+                (CAUSE_STORE_MISALIGNED, addr)
+            }
+            tangram_instruction::Error::LoadAccessFault { addr, .. } => (CAUSE_LOAD_ACCESS, addr),
+            tangram_instruction::Error::StoreAccessFault { addr, .. } => (CAUSE_STORE_ACCESS, addr),
+            tangram_instruction::Error::EnvironmentCall if self.trap_ecall => {
+                (CAUSE_MACHINE_ECALL, 0)
+            }
+            _ => return Err(Error::InstructionError(e)),
+        };
+
+        let vector = match self.csrs.vector.map(I::Register::try_from) {
+            Some(Ok(vector)) => vector,
+            _ => return Err(Error::InstructionError(e)),
+        };
+
+        #[cfg(feature = "alloc")]
+        let mark = self.history.begin(self.pc);
+        #[cfg(feature = "alloc")]
+        let csrs = self.csrs;
+
+        self.csrs.trap = Some(GuestTrap {
+            epc: self.pc.into(),
+            cause,
+            tval,
+        });
+        self.pc = vector;
+
+        #[cfg(feature = "alloc")]
+        if let Some(mark) = mark {
+            self.history.save_csrs(csrs, &self.csrs);
+            self.history.end(mark, self.pc);
+        }
+
+        Ok(Outcome::Stepped)
+    }
+
+    /// Take instruction at pc out of decode cache.
+    fn take_decoded(&mut self) -> Option<(I, u32)> {
+        #[cfg(feature = "alloc")]
+        if let Some(decoded) = &mut self.decoded {
+            return decoded.remove(&self.pc.into());
+        }
+
+        None
+    }
+
+    /// Execute `inst` and keep it in decode cache, `fence.i` clears the cache.
+    fn execute_decoded<E: Debug>(&mut self, mut inst: I, raw: u32) -> Result<Outcome, Error<E>> {
+        #[cfg(feature = "alloc")]
+        let pc = self.pc.into();
+        let r = self.execute(&mut inst, raw);
+
+        #[cfg(feature = "alloc")]
+        if let Some(decoded) = &mut self.decoded {
+            if inst.is_fence_i() {
+                decoded.clear();
+            } else {
+                decoded.insert(pc, (inst, raw));
+            }
+        }
+
+        r
+    }
+
+    fn execute<E: Debug>(&mut self, inst: &mut I, raw: u32) -> Result<Outcome, Error<E>> {
+        if self.monitor.before_execute(inst, &self.pc, &self.regs) == Control::Halt {
+            return Err(Error::Halted);
+        }
+
+        let pc = self.pc;
+        let regs = self.regs;
+        let ext = self.ext.clone();
+        let csrs = self.csrs;
+
+        let watchpoints = match self.watch_hit.take() {
+            Some(hit) if hit == pc.into() => &[][..],
+            _ => &self.watchpoints[..],
+        };
+        let monitor = RefCell::new(&mut self.monitor);
+        let mut memory: HookedMemory<I, M, MM, X> =
+            HookedMemory::new(&mut self.memory, &monitor, watchpoints);
+        let mut context = HartContext::new(&mut self.csrs, &mut self.ext, &monitor);
+
+        #[cfg(feature = "alloc")]
+        let mark = self.history.begin(pc);
+        #[cfg(feature = "alloc")]
+        if mark.is_some() {
+            memory = memory.with_history(&mut self.history);
+        }
+
+        let r = inst.execute(&mut self.pc, &mut self.regs, &mut memory, &mut context);
+        let mem = memory.access();
+        let hit = memory.hit();
+        let halt = memory.control() == Control::Halt;
+
+        if hit.is_some() || halt {
+            self.pc = pc;
+            self.regs = regs;
+            self.ext = ext.clone();
+            self.csrs = csrs;
+        }
+
+        #[cfg(feature = "alloc")]
+        if let Some(mark) = mark {
+            if hit.is_some() || halt {
+                self.history.cancel(mark);
+            } else {
+                self.history.save_regs(&regs, &self.regs);
+                self.history.save_ext(ext, &self.ext);
+                self.history.save_csrs(csrs, &self.csrs);
+                self.history.end(mark, self.pc);
+            }
+        }
+
+        if let Some((kind, addr)) = hit {
+            self.watch_hit = Some(pc.into());
+            return Ok(Outcome::Watchpoint { kind, addr });
+        }
+
+        if halt {
+            return Err(Error::Halted);
+        }
+
+        let mut control = Control::Continue;
+
+        if MM::RECORD {
+            let rd = match r {
+                Ok(_) => inst.rd(),
+                Err(_) => None,
+            };
+            let rd = rd.and_then(|i| Some((i as u8, (*self.regs.get(i)?).into())));
+
+            control = self.monitor.record(&TraceRecord {
+                pc: pc.into(),
+                inst: raw,
+                rd,
+                mem,
+            });
+        }
+
+        if let Err(e) = r {
+            let r = self.trap(e, raw);
+            return match control {
+                Control::Continue => r,
+                Control::Halt => Err(Error::Halted),
+            };
+        }
+
+        if self
+            .monitor
+            .monitor(inst, &self.pc, &self.regs, &self.memory)
+            == Control::Halt
+        {
+            control = Control::Halt;
+        }
+
+        match control {
+            Control::Continue => Ok(Outcome::Stepped),
+            Control::Halt => Err(Error::Halted),
+        }
+    }
+}
+
+impl<const RS: usize, I, R, M, MM, E, X> Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
     R: BytecodeReader<Register = I::Register, Error = E>,
     E: Debug,
     M: MemoryMut<Register = I::Register>,
     MM: Monitor<I>,
+    X: Extension,
 {
-    pub fn run(&mut self, bytes_len: u8) -> Result<(), Error<E>> {
-        loop {
-            let bytes = self
-                .reader
-                .read(&self.pc, bytes_len)
-                .map_err(Error::AppError)?;
+    /// Execute one instruction, breakpoints are ignored.
+    pub fn step(&mut self, bytes_len: u8) -> Result<Outcome, Error<E>> {
+        if let Some((inst, raw)) = self.take_decoded() {
+            return self.execute_decoded(inst, raw);
+        }
 
-            let mut inst = I::new(bytes)?;
+        let bytes = self
+            .reader
+            .fetch(&self.memory, &self.pc, bytes_len)
+            .map_err(Error::AppError)?;
 
-            inst.execute(&mut self.pc, &mut self.regs, &mut self.memory)?;
-            self.monitor
-                .monitor(&inst, &self.pc, &self.regs, &self.memory);
+        let raw = raw_inst(bytes);
+        match decode(bytes, raw, self.reject) {
+            Ok(inst) => self.execute_decoded(inst, raw),
+            Err(e) => self.trap(e, raw),
+        }
+    }
+
+    /// Execute until breakpoint, watchpoint or error.
+    ///
+    /// Breakpoint at current pc is skipped, and instruction stopped by watchpoint
+    /// runs without it, so `run` can resume from both.
+    pub fn run(&mut self, bytes_len: u8) -> Result<Outcome, Error<E>> {
+        loop {
+            let outcome = self.step(bytes_len)?;
+            if outcome != Outcome::Stepped {
+                return Ok(outcome);
+            }
+
+            if self.is_breakpoint() {
+                return Ok(Outcome::Breakpoint(self.pc.into()));
+            }
         }
     }
 }
 
-impl<const RS: usize, I, R, M, MM, E> Executor<RS, I, R, M, MM>
+impl<const RS: usize, I, R, M, MM, E, X> Executor<RS, I, R, M, MM, X>
 where
     I: Instruction,
-    I::Register: Default + Clone + Copy,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
     R: AsyncBytecodeReader<Register = I::Register, Error = E>,
     E: Debug,
     M: MemoryMut<Register = I::Register>,
     MM: Monitor<I>,
+    X: Extension,
 {
-    pub async fn async_run(&mut self, bytes_len: u8) -> Result<(), Error<E>> {
+    /// Async version of `run`.
+    pub async fn async_run(&mut self, bytes_len: u8) -> Result<Outcome, Error<E>> {
         loop {
-            let bytes = self
-                .reader
-                .read(&self.pc, bytes_len)
-                .await
-                .map_err(Error::AppError)?;
+            let outcome = match self.take_decoded() {
+                Some((inst, raw)) => self.execute_decoded(inst, raw)?,
+                None => {
+                    let bytes = self
+                        .reader
+                        .read(&self.pc, bytes_len)
+                        .await
+                        .map_err(Error::AppError)?;
+
+                    let raw = raw_inst(bytes);
+                    match decode(bytes, raw, self.reject) {
+                        Ok(inst) => self.execute_decoded(inst, raw)?,
+                        Err(e) => self.trap(e, raw)?,
+                    }
+                }
+            };
+
+            if outcome != Outcome::Stepped {
+                return Ok(outcome);
+            }
+
+            if self.is_breakpoint() {
+                return Ok(Outcome::Breakpoint(self.pc.into()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const RS: usize, I, R, M, MM, E, X> Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    R: BytecodeReader<Register = I::Register, Error = E>,
+    E: Debug,
+    M: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    /// Record at least last `limit` steps for [`Executor::step_back`], 0 disables
+    /// recording and drops recorded steps.
+    ///
+    /// Changes made outside of `step`, like `set_pc` or `memory_mut`, drop
+    /// recorded steps.
+    pub fn set_history(&mut self, limit: usize) {
+        self.history.set_limit(limit)
+    }
+
+    /// Number of steps can be undone.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Undo register, CSR, extension and memory writes of last step, return false
+    /// if history is empty. Trap taken by guest is a step of its own.
+    ///
+    /// Monitor isn't rewound, and decoded instructions are dropped, like by
+    /// [`Executor::memory_mut`].
+    pub fn step_back(&mut self) -> bool {
+        let undone = self.history.undo(
+            &mut self.pc,
+            &mut self.regs,
+            &mut self.ext,
+            &mut self.csrs,
+            &mut self.memory,
+        );
+        if undone {
+            self.invalidate_decoded();
+        }
+
+        undone
+    }
+
+    /// Step back at least once, until instruction at `pc` is next to execute.
+    ///
+    /// Return false if history runs out before reaching `pc`.
+    pub fn run_back_to(&mut self, pc: I::Register) -> bool {
+        while self.step_back() {
+            if (*self.pc()).into() == pc.into() {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use tangram_instruction::{
+        riscv::{Illegal, Inst},
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvb::RVBitInst,
+        riscvzicsr::RVCsrInst,
+        Aligned, Alignment, IAlign, Misaligned,
+    };
+
+    use crate::{
+        Error, Executor, GuestTrap, MemoryReader, Outcome, CAUSE_LOAD_ACCESS, CAUSE_MACHINE_ECALL,
+        CAUSE_STORE_ACCESS, CSR_MCAUSE, CSR_MEPC, CSR_MTVAL, CSR_MTVEC,
+    };
+
+    const PROGRAM: &str = "
+    start:
+        addi a0, a0, 1
+        bnez a1, flush
+        j start
+    flush:
+        fence.i
+        j start
+    ";
 
-            let mut inst = I::new(bytes)?;
+    #[test]
+    fn test_fence_i() {
+        let mut code = [0u8; 64];
+        assemble(PROGRAM, 0, &mut code).unwrap();
 
-            inst.execute(&mut self.pc, &mut self.regs, &mut self.memory)?;
-            self.monitor
-                .monitor(&inst, &self.pc, &self.regs, &self.memory);
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 64], ());
+        executor.set_decode_cache(true);
+        for _ in 0..3 {
+            executor.step(4).unwrap();
         }
+        assert_eq!(executor.decoded_len(), 3);
+
+        // Patched code isn't fetched before `fence.i`.
+        assemble("addi a0, a0, 10", 0, executor.reader_mut()).unwrap();
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 2);
+
+        executor.regs_mut()[11] = 1;
+        for _ in 0..3 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(executor.decoded_len(), 1);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 12);
+
+        // Without cache every step reads reader.
+        executor.set_decode_cache(false);
+        executor.set_pc(0);
+        assemble("addi a0, a0, 100", 0, executor.reader_mut()).unwrap();
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 112);
+        assert_eq!(executor.decoded_len(), 0);
+    }
+
+    #[test]
+    fn test_fence_i_memory() {
+        const PROGRAM: &str = "
+            jal ra, 40
+            lw t0, 60(zero)
+            sw t0, 40(zero)
+            jal ra, 28
+            fence.i
+            jal ra, 20
+            ecall
+        ";
+
+        let mut memory = [0u8; 64];
+        assemble(PROGRAM, 0, &mut memory).unwrap();
+        assemble("addi a0, a0, 1\njalr zero, 0(ra)", 40, &mut memory[40..]).unwrap();
+        assemble("addi a0, a0, 10", 60, &mut memory[60..]).unwrap();
+
+        // Stored code runs after `fence.i`.
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(MemoryReader::new(), memory, ());
+        executor.set_decode_cache(true);
+        assert!(executor.run(4).is_err());
+        assert_eq!(*executor.pc(), 24);
+        assert_eq!(executor.regs()[10], 12);
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(MemoryReader::new(), memory, ());
+        assert!(executor.run(4).is_err());
+        assert_eq!(executor.regs()[10], 21);
+
+        // Fetch beyond memory fails.
+        executor.set_pc(62);
+        let e = tangram_instruction::Error::ErrBytecodeLengthNotEnough;
+        assert!(matches!(executor.step(4), Err(Error::AppError(a)) if a == e));
+    }
+
+    #[test]
+    fn test_rewrite_drops_decoded() {
+        const PROGRAM: &str = "
+            sw t0, 8(zero)
+            fence.i
+            addi a0, a0, 1
+        ";
+
+        let mut memory = [0u8; 64];
+        assemble(PROGRAM, 0, &mut memory).unwrap();
+        let mut patch = [0u8; 4];
+        assemble("addi a0, a0, 10", 8, &mut patch).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(MemoryReader::new(), memory, ());
+        executor.regs_mut()[5] = u32::from_le_bytes(patch);
+        executor.set_decode_cache(true);
+        executor.set_history(10);
+        for _ in 0..3 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(executor.regs()[10], 10);
+
+        // Undone store brings back old code.
+        assert!(executor.run_back_to(0));
+        assert_eq!(executor.decoded_len(), 0);
+        executor.set_pc(8);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 1);
+
+        // So does code written by host.
+        executor.memory_mut()[8..12].copy_from_slice(&patch);
+        assert_eq!(executor.decoded_len(), 0);
+        executor.set_pc(8);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 11);
+    }
+
+    #[test]
+    fn test_rv32e() {
+        let mut code = [0u8; 64];
+        assemble(
+            "addi a0, zero, 5\nadd a5, a0, a0\nadd a0, a6, a0",
+            0,
+            &mut code,
+        )
+        .unwrap();
+        // `rol a0, a1, s11` and `rol a0, a1, a1`
+        code[12..16].copy_from_slice(&0x61b5_9533u32.to_le_bytes());
+        code[16..20].copy_from_slice(&0x60b5_9533u32.to_le_bytes());
+
+        let mut executor: Executor<16, RV32iBaseInst<RVBitInst<()>>, _, _, _> =
+            Executor::new(code, [0u8; 64], ());
+        executor.step(4).unwrap();
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[15], 10);
+
+        // `x16` doesn't exist in RV32E, so the instruction is illegal.
+        let illegal = tangram_instruction::Error::IllegalInstruction {
+            raw: 0x00a8_0533,
+            pc: 8,
+        };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == illegal));
+        assert_eq!(*executor.pc(), 8);
+
+        // Extensions check their registers too.
+        executor.set_pc(12);
+        assert!(executor.step(4).is_err());
+        executor.set_pc(16);
+        executor.step(4).unwrap();
+        assert_eq!(*executor.pc(), 20);
+    }
+
+    /// Encoding of CSR instruction.
+    fn csr(funct3: u8, rd: usize, rs1: usize, csr: u16) -> [u8; 4] {
+        Inst::build_i(0b1110011, rd, funct3, rs1, csr as i32)
+            .raw()
+            .to_le_bytes()
+    }
+
+    #[test]
+    fn test_trap_vector() {
+        const RAW: u32 = 0x0200_c58b;
+        const MRET: u32 = 0x3020_0073;
+
+        let mut code = [0u8; 64];
+        let program =
+            "addi a0, zero, 1\nnop\naddi a0, a0, 2\naddi t1, zero, 32\nnop\nnop\naddi a0, a0, 4";
+        assemble(program, 0, &mut code).unwrap();
+        code[4..8].copy_from_slice(&RAW.to_le_bytes());
+        code[16..20].copy_from_slice(&csr(0b001, 0, 6, CSR_MTVEC));
+        code[20..24].copy_from_slice(&RAW.to_le_bytes());
+
+        // Handler returns after trapped instruction.
+        assemble("addi a1, zero, 7", 32, &mut code[32..]).unwrap();
+        code[36..40].copy_from_slice(&csr(0b010, 5, 0, CSR_MEPC));
+        assemble("addi t0, t0, 4", 40, &mut code[40..]).unwrap();
+        code[44..48].copy_from_slice(&csr(0b001, 0, 5, CSR_MEPC));
+        code[48..52].copy_from_slice(&csr(0b010, 12, 0, CSR_MCAUSE));
+        code[52..56].copy_from_slice(&MRET.to_le_bytes());
+
+        let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+            Executor::new(code, [0u8; 64], ());
+        executor.step(4).unwrap();
+        let illegal = tangram_instruction::Error::IllegalInstruction { raw: RAW, pc: 4 };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == illegal));
+        assert_eq!(executor.last_trap(), None);
+
+        executor.set_trap_vector(Some(32));
+        assert_eq!(executor.step(4).unwrap(), Outcome::Stepped);
+        assert_eq!(*executor.pc(), 32);
+        let trap = GuestTrap {
+            epc: 4,
+            cause: 2,
+            tval: RAW as u64,
+        };
+        assert_eq!(executor.last_trap(), Some(trap));
+        assert_eq!(executor.read_csr(CSR_MTVEC), Some(32));
+        assert_eq!(executor.read_csr(CSR_MEPC), Some(4));
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(2));
+        assert_eq!(executor.read_csr(CSR_MTVAL), Some(RAW as u64));
+
+        for _ in 0..6 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(executor.regs()[11], 7);
+        assert_eq!(executor.regs()[12], 2);
+        assert_eq!(executor.read_csr(CSR_MEPC), Some(8));
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 3);
+
+        // Guest sets trap vector itself.
+        executor.set_trap_vector(None);
+        executor.step(4).unwrap();
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MTVEC), Some(32));
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MEPC), Some(20));
+        for _ in 0..6 {
+            executor.step(4).unwrap();
+        }
+        executor.step(4).unwrap();
+        assert_eq!(*executor.pc(), 28);
+        assert_eq!(executor.regs()[10], 7);
+    }
+
+    #[test]
+    fn test_trap_ecall() {
+        let mut code = [0u8; 64];
+        assemble("addi a0, zero, 1\necall\naddi a0, a0, 2", 0, &mut code).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+            Executor::new(code, [0u8; 64], ());
+        executor.set_trap_vector(Some(8));
+        executor.step(4).unwrap();
+        assert!(matches!(
+            executor.step(4),
+            Err(Error::InstructionError(
+                tangram_instruction::Error::EnvironmentCall
+            ))
+        ));
+        assert_eq!(*executor.pc(), 4);
+
+        executor.set_trap_ecall(true);
+        assert_eq!(executor.step(4).unwrap(), Outcome::Stepped);
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(
+            executor.last_trap(),
+            Some(GuestTrap {
+                epc: 4,
+                cause: CAUSE_MACHINE_ECALL,
+                tval: 0,
+            })
+        );
+
+        // Without trap vector it is still returned.
+        executor.set_trap_vector(None);
+        executor.set_pc(4);
+        assert!(executor.step(4).is_err());
+    }
+
+    #[test]
+    fn test_alignment() {
+        const PROGRAM: &str = "
+            addi a1, zero, 33
+            lw a0, 0(a1)
+            sh a0, 2(a1)
+            jalr zero, 18(zero)
+        ";
+
+        let mut code = [0u8; 64];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+        let mut memory = [0u8; 64];
+        memory[33..37].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+        let memory = Aligned::new(memory, Alignment::default());
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, memory, ());
+        executor.step(4).unwrap();
+
+        executor.memory_mut().alignment = Alignment::STRICT;
+        let misaligned = tangram_instruction::Error::LoadMisaligned { addr: 33, pc: 4 };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == misaligned));
+        assert_eq!(*executor.pc(), 4);
+
+        executor.set_trap_vector(Some(40));
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(4));
+        assert_eq!(executor.read_csr(CSR_MTVAL), Some(33));
+
+        // Emulated accesses see the same bytes.
+        executor.memory_mut().alignment = Alignment {
+            access: Misaligned::Split,
+            ialign: IAlign::Word,
+        };
+        executor.set_pc(4);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 0x4433_2211);
+        executor.step(4).unwrap();
+        assert_eq!(executor.memory().memory[35..37], [0x11, 0x22]);
+
+        // Target 18 is fine with compressed instructions only.
+        executor.step(4).unwrap();
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(0));
+        assert_eq!(executor.read_csr(CSR_MTVAL), Some(18));
+        executor.memory_mut().alignment = Alignment {
+            access: Misaligned::Trap,
+            ialign: IAlign::Half,
+        };
+        executor.set_pc(12);
+        executor.step(4).unwrap();
+        assert_eq!(*executor.pc(), 18);
+    }
+
+    #[test]
+    fn test_access_fault() {
+        const PROGRAM: &str = "
+            lui a1, 0x10
+            lw a0, 0(a1)
+            sw a0, 126(zero)
+            addi a0, zero, 1
+        ";
+
+        let mut code = [0u8; 64];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        // Accesses beyond memory fail instead of panicking.
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], ());
+        executor.step(4).unwrap();
+        let fault = tangram_instruction::Error::LoadAccessFault {
+            addr: 0x10000,
+            pc: 4,
+        };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == fault));
+        assert_eq!(*executor.pc(), 4);
+
+        // Guest handles them like misaligned accesses.
+        executor.set_trap_vector(Some(12));
+        executor.step(4).unwrap();
+        assert_eq!(*executor.pc(), 12);
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(CAUSE_LOAD_ACCESS));
+        assert_eq!(executor.read_csr(CSR_MTVAL), Some(0x10000));
+
+        executor.set_pc(8);
+        executor.step(4).unwrap();
+        assert_eq!(*executor.pc(), 12);
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(CAUSE_STORE_ACCESS));
+        assert_eq!(executor.read_csr(CSR_MEPC), Some(8));
+        assert_eq!(executor.read_csr(CSR_MTVAL), Some(126));
+        assert_eq!(executor.memory()[120..], [0; 8]);
     }
 }
@@ -0,0 +1,24 @@
+use tangram_instruction::{riscvv::VectorState, Context};
+
+/// State of extensions kept for each hart apart from integer registers, like
+/// [`VectorState`], `()` without any.
+///
+/// Instructions reach it as [`Context`], its CSRs come after machine CSRs of
+/// hart.
+pub trait Extension: Context + Clone + PartialEq + Default {
+    /// Append state to `out`, as committed by state roots.
+    fn encode(&self, out: &mut impl Extend<u8>);
+}
+
+impl Extension for () {
+    fn encode(&self, _out: &mut impl Extend<u8>) {}
+}
+
+/// `vl` and `vtype` as little-endian `u64`, then `v0` to `v31`.
+impl<const VLENB: usize> Extension for VectorState<VLENB> {
+    fn encode(&self, out: &mut impl Extend<u8>) {
+        out.extend(self.vl.to_le_bytes());
+        out.extend(self.vtype.to_le_bytes());
+        out.extend(self.v.iter().flatten().copied());
+    }
+}
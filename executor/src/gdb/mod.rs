@@ -0,0 +1,19 @@
+//! GDB remote serial protocol stub
+//!
+//! [`GdbStub`] serves a debugger over any [`Connection`], driving a [`Target`].
+//! [`Executor`](crate::Executor) is a target, breakpoints and watchpoints use
+//! its own.
+
+mod packet;
+pub use packet::*;
+
+mod target;
+pub use target::*;
+
+mod stub;
+pub use stub::*;
+
+#[cfg(feature = "std")]
+mod tcp;
+#[cfg(feature = "std")]
+pub use tcp::*;
@@ -0,0 +1,188 @@
+use core::fmt::{self, Debug};
+
+/// Max length of packet data, both directions.
+pub const PACKET_SIZE: usize = 4096;
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Byte stream to debugger.
+pub trait Connection {
+    type Error: Debug;
+
+    /// Read one byte, block until it is available.
+    fn read(&mut self) -> Result<u8, Self::Error>;
+
+    /// Read one byte if available, used to check interrupt during continue.
+    fn try_read(&mut self) -> Result<Option<u8>, Self::Error>;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Packet read from connection.
+pub(crate) enum Incoming {
+    /// Data between `$` and `#`, unescaped.
+    Packet(usize),
+    /// `Ctrl-C` sent out of packet.
+    Interrupt,
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Parse hex integer.
+pub(crate) fn parse_hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+
+    s.iter()
+        .try_fold(0u64, |acc, b| Some(acc << 4 | hex_digit(*b)? as u64))
+}
+
+/// Decode hex bytes into `out`, return decoded length.
+pub(crate) fn decode_hex(s: &[u8], out: &mut [u8]) -> Option<usize> {
+    if !s.len().is_multiple_of(2) || s.len() / 2 > out.len() {
+        return None;
+    }
+
+    for (i, c) in s.chunks(2).enumerate() {
+        out[i] = hex_digit(c[0])? << 4 | hex_digit(c[1])?;
+    }
+
+    Some(s.len() / 2)
+}
+
+/// Read next packet into `buf`, acknowledge it unless `no_ack`.
+pub(crate) fn read_packet<C: Connection>(
+    conn: &mut C,
+    buf: &mut [u8; PACKET_SIZE],
+    no_ack: bool,
+) -> Result<Incoming, C::Error> {
+    loop {
+        match conn.read()? {
+            b'$' => {}
+            0x03 => return Ok(Incoming::Interrupt),
+            // Acks of our replies and noise.
+            _ => continue,
+        }
+
+        let mut len = 0;
+        let mut sum = 0u8;
+        let mut escape = false;
+        let mut overflow = false;
+
+        loop {
+            let b = conn.read()?;
+            if b == b'#' {
+                break;
+            }
+            sum = sum.wrapping_add(b);
+
+            let b = if escape {
+                escape = false;
+                b ^ 0x20
+            } else if b == b'}' {
+                escape = true;
+                continue;
+            } else {
+                b
+            };
+
+            if len < PACKET_SIZE {
+                buf[len] = b;
+                len += 1;
+            } else {
+                overflow = true;
+            }
+        }
+
+        let checksum = [conn.read()?, conn.read()?];
+        let valid = parse_hex(&checksum) == Some(sum as u64) && !overflow;
+
+        if !no_ack {
+            conn.write(if valid { b"+" } else { b"-" })?;
+        }
+
+        if valid {
+            return Ok(Incoming::Packet(len));
+        }
+    }
+}
+
+/// Reply under construction.
+pub(crate) struct Reply {
+    buf: [u8; PACKET_SIZE],
+    len: usize,
+}
+
+impl Reply {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: [0; PACKET_SIZE],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        let len = bytes.len().min(PACKET_SIZE - self.len);
+        self.buf[self.len..self.len + len].copy_from_slice(&bytes[..len]);
+        self.len += len;
+    }
+
+    pub(crate) fn push_hex(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.push(&[DIGITS[(b >> 4) as usize], DIGITS[(b & 0xF) as usize]]);
+        }
+    }
+
+    /// Send as packet, escaping special characters.
+    pub(crate) fn send<C: Connection>(&self, conn: &mut C) -> Result<(), C::Error> {
+        let mut sum = 0u8;
+
+        conn.write(b"$")?;
+        for run in self.buf[..self.len].split_inclusive(|b| b"#$}*".contains(b)) {
+            let (last, rest) = run.split_last().unwrap();
+
+            if b"#$}*".contains(last) {
+                let e = [b'}', last ^ 0x20];
+                sum = rest.iter().chain(&e).fold(sum, |s, b| s.wrapping_add(*b));
+                conn.write(rest)?;
+                conn.write(&e)?;
+            } else {
+                sum = run.iter().fold(sum, |s, b| s.wrapping_add(*b));
+                conn.write(run)?;
+            }
+        }
+
+        conn.write(&[
+            b'#',
+            DIGITS[(sum >> 4) as usize],
+            DIGITS[(sum & 0xF) as usize],
+        ])
+    }
+}
+
+impl fmt::Write for Reply {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > PACKET_SIZE {
+            return Err(fmt::Error);
+        }
+
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
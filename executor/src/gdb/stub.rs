@@ -0,0 +1,540 @@
+use core::fmt::Write;
+
+use crate::{
+    gdb::{
+        decode_hex, parse_hex, read_packet, Connection, Incoming, Reply, StopReason, Target,
+        PACKET_SIZE, SIGINT, SIGTRAP,
+    },
+    WatchKind,
+};
+
+/// Steps between checks of interrupt during continue.
+const INTERRUPT_INTERVAL: usize = 1024;
+
+/// How debugger session ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disconnect {
+    Detach,
+    Kill,
+}
+
+/// GDB remote serial protocol server.
+pub struct GdbStub<C> {
+    conn: C,
+    no_ack: bool,
+    reply: Reply,
+}
+
+fn split_once(s: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = s.iter().position(|b| *b == sep)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+/// Parse `addr,len`.
+fn parse_range(s: &[u8]) -> Option<(u64, u64)> {
+    let (addr, len) = split_once(s, b',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+/// Write target description of RISC-V with `xlen` bits registers.
+fn write_description(w: &mut Reply, reg_size: usize, reg_count: usize) -> core::fmt::Result {
+    let xlen = reg_size * 8;
+
+    write!(
+        w,
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\"><target version=\"1.0\">\
+         <architecture>riscv:rv{}</architecture><feature name=\"org.gnu.gdb.riscv.cpu\">",
+        xlen
+    )?;
+
+    for n in 0..reg_count - 1 {
+        let ty = match n {
+            1 => "code_ptr",
+            2 | 8 => "data_ptr",
+            _ => "int",
+        };
+        write!(
+            w,
+            "<reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>",
+            tangram_instruction::riscv::abi_name(n),
+            xlen,
+            ty,
+            n
+        )?;
+    }
+
+    write!(
+        w,
+        "<reg name=\"pc\" bitsize=\"{}\" type=\"code_ptr\" regnum=\"{}\"/></feature></target>",
+        xlen,
+        reg_count - 1
+    )
+}
+
+impl<C: Connection> GdbStub<C> {
+    pub fn new(conn: C) -> Self {
+        Self {
+            conn,
+            no_ack: false,
+            reply: Reply::new(),
+        }
+    }
+
+    pub fn connection(&self) -> &C {
+        &self.conn
+    }
+
+    pub fn into_connection(self) -> C {
+        self.conn
+    }
+
+    /// Serve debugger until it detaches or kills target.
+    pub fn run<T: Target>(&mut self, target: &mut T) -> Result<Disconnect, C::Error> {
+        let mut buf = [0u8; PACKET_SIZE];
+
+        loop {
+            let len = match read_packet(&mut self.conn, &mut buf, self.no_ack)? {
+                Incoming::Packet(len) => len,
+                // Target is already stopped.
+                Incoming::Interrupt => {
+                    self.reply.clear();
+                    self.stop_reply(StopReason::Signal(SIGINT));
+                    self.reply.send(&mut self.conn)?;
+                    continue;
+                }
+            };
+
+            self.reply.clear();
+
+            let packet = &buf[..len];
+
+            match packet.first() {
+                Some(b'D') => {
+                    self.reply.push(b"OK");
+                    self.reply.send(&mut self.conn)?;
+                    return Ok(Disconnect::Detach);
+                }
+                Some(b'k') => return Ok(Disconnect::Kill),
+                _ => {}
+            }
+
+            self.handle(packet, target)?;
+            self.reply.send(&mut self.conn)?;
+        }
+    }
+
+    fn handle<T: Target>(&mut self, packet: &[u8], target: &mut T) -> Result<(), C::Error> {
+        let (cmd, args) = match packet.split_first() {
+            Some((cmd, args)) => (*cmd, args),
+            None => return Ok(()),
+        };
+
+        let ok = match cmd {
+            b'?' => {
+                self.stop_reply(StopReason::Signal(SIGTRAP));
+                return Ok(());
+            }
+            b'g' => {
+                let mut reg = [0u8; 8];
+                for n in 0..target.reg_count() {
+                    let v = target.read_reg(n).unwrap_or_default();
+                    reg.copy_from_slice(&v.to_le_bytes());
+                    self.reply.push_hex(&reg[..target.reg_size()]);
+                }
+                return Ok(());
+            }
+            b'G' => self.write_regs(args, target),
+            b'p' => {
+                match parse_hex(args).and_then(|n| target.read_reg(n as usize)) {
+                    Some(v) => self.reply.push_hex(&v.to_le_bytes()[..target.reg_size()]),
+                    None => self.reply.push(b"E01"),
+                }
+                return Ok(());
+            }
+            b'P' => split_once(args, b'=').is_some_and(|(n, v)| {
+                let mut value = [0u8; 8];
+                let size = target.reg_size();
+
+                match (parse_hex(n), decode_hex(v, &mut value)) {
+                    (Some(n), Some(len)) if len == size => {
+                        target.write_reg(n as usize, u64::from_le_bytes(value))
+                    }
+                    _ => false,
+                }
+            }),
+            b'm' => {
+                let mut data = [0u8; PACKET_SIZE / 2];
+
+                match parse_range(args) {
+                    Some((addr, len)) if len as usize <= data.len() => {
+                        let data = &mut data[..len as usize];
+                        if target.read_mem(addr, data) {
+                            self.reply.push_hex(data);
+                        } else {
+                            self.reply.push(b"E01");
+                        }
+                    }
+                    _ => self.reply.push(b"E01"),
+                }
+                return Ok(());
+            }
+            b'M' => split_once(args, b':').is_some_and(|(range, hex)| {
+                let mut data = [0u8; PACKET_SIZE / 2];
+
+                match (parse_range(range), decode_hex(hex, &mut data)) {
+                    (Some((addr, len)), Some(n)) if len as usize == n => {
+                        target.write_mem(addr, &data[..n])
+                    }
+                    _ => false,
+                }
+            }),
+            // Binary data is unescaped by `read_packet`.
+            b'X' => split_once(args, b':').is_some_and(|(range, data)| match parse_range(range) {
+                Some((addr, len)) if len as usize == data.len() => target.write_mem(addr, data),
+                _ => false,
+            }),
+            b'c' | b's' => {
+                if !args.is_empty() {
+                    match parse_hex(args) {
+                        Some(pc) => {
+                            target.write_reg(target.reg_count() - 1, pc);
+                        }
+                        None => {
+                            self.reply.push(b"E01");
+                            return Ok(());
+                        }
+                    }
+                }
+
+                let r = self.resume(cmd == b's', target)?;
+                self.stop_reply(r);
+                return Ok(());
+            }
+            b'v' => return self.handle_v(args, target),
+            b'Z' | b'z' => match self.breakpoint(cmd == b'Z', args, target) {
+                Some(ok) => ok,
+                None => return Ok(()),
+            },
+            b'q' => {
+                self.handle_query(args, target);
+                return Ok(());
+            }
+            b'Q' if args == b"StartNoAckMode" => {
+                // Reply is still acknowledged by debugger.
+                self.no_ack = true;
+                true
+            }
+            b'H' | b'T' => true,
+            _ => return Ok(()),
+        };
+
+        self.reply.push(if ok { b"OK" } else { b"E01" });
+        Ok(())
+    }
+
+    fn write_regs<T: Target>(&mut self, hex: &[u8], target: &mut T) -> bool {
+        let size = target.reg_size();
+
+        if hex.len() != target.reg_count() * size * 2 {
+            return false;
+        }
+
+        hex.chunks(size * 2).enumerate().all(|(n, h)| {
+            let mut value = [0u8; 8];
+            decode_hex(h, &mut value[..size]).is_some()
+                && target.write_reg(n, u64::from_le_bytes(value))
+        })
+    }
+
+    fn handle_v<T: Target>(&mut self, args: &[u8], target: &mut T) -> Result<(), C::Error> {
+        if args == b"Cont?" {
+            self.reply.push(b"vCont;c;C;s;S");
+            return Ok(());
+        }
+
+        if let Some(actions) = args.strip_prefix(b"Cont;") {
+            // Only one thread, first action applies.
+            let action = actions.split(|b| *b == b';').next().unwrap_or_default();
+            let step = match action.first() {
+                Some(b's' | b'S') => true,
+                Some(b'c' | b'C') => false,
+                _ => {
+                    self.reply.push(b"E01");
+                    return Ok(());
+                }
+            };
+
+            let r = self.resume(step, target)?;
+            self.stop_reply(r);
+        }
+
+        Ok(())
+    }
+
+    /// Handle `Z`/`z` packet, return `None` if type isn't supported.
+    fn breakpoint<T: Target>(&mut self, insert: bool, args: &[u8], target: &mut T) -> Option<bool> {
+        let (ty, range) = split_once(args, b',')?;
+        let (addr, len) = parse_range(range)?;
+
+        let kind = match ty {
+            // Software and hardware breakpoints are the same for us.
+            b"0" | b"1" => {
+                return Some(if insert {
+                    target.set_breakpoint(addr)
+                } else {
+                    target.remove_breakpoint(addr)
+                });
+            }
+            b"2" => WatchKind::Write,
+            b"3" => WatchKind::Read,
+            b"4" => WatchKind::Access,
+            _ => return None,
+        };
+
+        Some(if insert {
+            target.set_watchpoint(kind, addr, len)
+        } else {
+            target.remove_watchpoint(kind, addr, len)
+        })
+    }
+
+    fn handle_query<T: Target>(&mut self, args: &[u8], target: &mut T) {
+        if args.starts_with(b"Supported") {
+            let _ = write!(
+                self.reply,
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+;vContSupported+",
+                PACKET_SIZE
+            );
+        } else if args == b"Attached" {
+            self.reply.push(b"1");
+        } else if args == b"C" {
+            self.reply.push(b"QC1");
+        } else if args == b"fThreadInfo" {
+            self.reply.push(b"m1");
+        } else if args == b"sThreadInfo" {
+            self.reply.push(b"l");
+        } else if let Some(range) = args.strip_prefix(b"Xfer:features:read:target.xml:") {
+            let mut xml = Reply::new();
+            let _ = write_description(&mut xml, target.reg_size(), target.reg_count());
+            let xml = xml.as_bytes();
+
+            match parse_range(range) {
+                Some((offset, len)) => {
+                    let start = (offset as usize).min(xml.len());
+                    let end = start
+                        .saturating_add(len as usize)
+                        .min(xml.len())
+                        .min(start + PACKET_SIZE - 1);
+
+                    self.reply.push(if end == xml.len() { b"l" } else { b"m" });
+                    self.reply.push(&xml[start..end]);
+                }
+                None => self.reply.push(b"E01"),
+            }
+        }
+    }
+
+    /// Run until next stop at breakpoint of target, or interrupt.
+    fn resume<T: Target>(&mut self, step: bool, target: &mut T) -> Result<StopReason, C::Error> {
+        let mut steps = 0;
+
+        loop {
+            let r = target.step();
+            if r != StopReason::Step {
+                return Ok(r);
+            }
+
+            if step || target.is_breakpoint() {
+                return Ok(StopReason::Signal(SIGTRAP));
+            }
+
+            steps += 1;
+            if steps % INTERRUPT_INTERVAL == 0 && self.conn.try_read()? == Some(0x03) {
+                return Ok(StopReason::Signal(SIGINT));
+            }
+        }
+    }
+
+    fn stop_reply(&mut self, r: StopReason) {
+        let _ = match r {
+            StopReason::Step => write!(self.reply, "S{:02x}", SIGTRAP),
+            StopReason::Signal(s) => write!(self.reply, "S{:02x}", s),
+            StopReason::Watch { kind, addr } => {
+                let kind = match kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Access => "awatch",
+                };
+                write!(self.reply, "T{:02x}{}:{:x};", SIGTRAP, kind, addr)
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::{collections::VecDeque, format, string::String, vec::Vec};
+
+    use tangram_instruction::riscv32i::{assemble, RV32iBaseInst};
+
+    use crate::{
+        gdb::{Connection, Disconnect, GdbStub},
+        Executor,
+    };
+
+    const PROGRAM: &str = "
+        li a0, 5
+        sw a0, 64(zero)
+        lw a1, 64(zero)
+        addi a0, a0, 1
+        ecall
+    ";
+
+    #[derive(Default)]
+    struct Script {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Connection for Script {
+        type Error = ();
+
+        fn read(&mut self) -> Result<u8, ()> {
+            self.input.pop_front().ok_or(())
+        }
+
+        fn try_read(&mut self) -> Result<Option<u8>, ()> {
+            Ok(None)
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), ()> {
+            self.output.extend_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    fn packet(data: &str) -> String {
+        let sum = data.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+        format!("${}#{:02x}", data, sum)
+    }
+
+    /// Run session of `packets`, return replies.
+    fn session(packets: &[&str]) -> (Disconnect, Vec<String>) {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], ());
+
+        let mut script = Script::default();
+        for p in packets {
+            script.input.extend(packet(p).bytes());
+        }
+
+        let mut stub = GdbStub::new(script);
+        let r = stub.run(&mut executor).unwrap();
+
+        let output = String::from_utf8(stub.into_connection().output).unwrap();
+        let replies = output
+            .split('$')
+            .skip(1)
+            .map(|p| {
+                let (data, sum) = p.split_once('#').unwrap();
+                assert_eq!(packet(data), format!("${}#{}", data, &sum[..2]));
+                String::from(data)
+            })
+            .collect();
+
+        (r, replies)
+    }
+
+    #[test]
+    fn test_stub() {
+        let (r, replies) = session(&[
+            "QStartNoAckMode",
+            "?",
+            "Z0,8,4",
+            "c",
+            "p20",
+            "pb",
+            "z0,8,4",
+            "Z2,40,4",
+            "s",
+            "s",
+            "P20=00000000",
+            "c",
+            "p20",
+            "z2,40,4",
+            "s",
+            "m40,4",
+            "Pa=07000000",
+            "pa",
+            "M40,4:01020304",
+            "m40,8",
+            "m10000,4",
+            "Z3,40,4",
+            "k",
+        ]);
+
+        assert_eq!(r, Disconnect::Kill);
+        assert_eq!(
+            replies,
+            [
+                "OK",
+                "S05",
+                "OK",
+                "S05",
+                "08000000",
+                "00000000",
+                "OK",
+                "OK",
+                "S05",
+                "S05",
+                "OK",
+                "T05watch:40;",
+                "04000000",
+                "OK",
+                "S05",
+                "05000000",
+                "OK",
+                "07000000",
+                "OK",
+                "0102030400000000",
+                "E01",
+                "OK",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resume_ecall() {
+        let (r, replies) = session(&["c", "p20", "c", "k"]);
+
+        // Stop after `ecall`, then run into zeroed code after program.
+        assert_eq!(r, Disconnect::Kill);
+        assert_eq!(replies, ["S05", "14000000", "S04"]);
+    }
+
+    #[test]
+    fn test_registers() {
+        let (r, replies) = session(&["s", "g", "qXfer:features:read:target.xml:0,fff", "D"]);
+
+        assert_eq!(r, Disconnect::Detach);
+        assert_eq!(replies[0], "S05");
+
+        let g = &replies[1];
+        assert_eq!(g.len(), 33 * 8);
+        assert_eq!(&g[10 * 8..11 * 8], "05000000");
+        assert_eq!(&g[32 * 8..], "04000000");
+
+        let xml = &replies[2];
+        assert!(xml.starts_with("l<?xml"));
+        assert!(xml.contains("<architecture>riscv:rv32</architecture>"));
+        assert!(xml.contains("<reg name=\"a0\" bitsize=\"32\" type=\"int\" regnum=\"10\"/>"));
+        assert!(xml.ends_with("regnum=\"32\"/></feature></target>"));
+
+        assert_eq!(replies[3], "OK");
+    }
+}
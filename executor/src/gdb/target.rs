@@ -0,0 +1,191 @@
+use core::{fmt::Debug, mem::size_of};
+
+use tangram_instruction::{Instruction, MemoryMut};
+
+use crate::{BytecodeReader, Error, Executor, Extension, Monitor, Outcome, WatchKind};
+
+pub const SIGINT: u8 = 2;
+pub const SIGILL: u8 = 4;
+pub const SIGTRAP: u8 = 5;
+pub const SIGSEGV: u8 = 11;
+
+/// Why target stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// One instruction is executed.
+    Step,
+    /// Instruction at pc accesses watched `addr`, it isn't executed.
+    Watch { kind: WatchKind, addr: u64 },
+    /// Stop with signal number, like [`SIGTRAP`].
+    Signal(u8),
+}
+
+/// Machine driven by debugger.
+///
+/// Registers are numbered as GDB does for RISC-V, general registers followed by pc.
+pub trait Target {
+    /// Bytes of one register.
+    fn reg_size(&self) -> usize;
+
+    /// Number of registers, including pc.
+    fn reg_count(&self) -> usize;
+
+    fn read_reg(&self, n: usize) -> Option<u64>;
+
+    fn write_reg(&mut self, n: usize, value: u64) -> bool;
+
+    fn read_mem(&self, addr: u64, out: &mut [u8]) -> bool;
+
+    fn write_mem(&mut self, addr: u64, data: &[u8]) -> bool;
+
+    fn pc(&self) -> u64 {
+        self.read_reg(self.reg_count() - 1).unwrap_or_default()
+    }
+
+    /// Execute one instruction.
+    fn step(&mut self) -> StopReason;
+
+    /// Stop before instruction at `addr`, return false if there is no room.
+    fn set_breakpoint(&mut self, addr: u64) -> bool;
+
+    fn remove_breakpoint(&mut self, addr: u64) -> bool;
+
+    /// Whether instruction at pc has breakpoint.
+    fn is_breakpoint(&self) -> bool;
+
+    fn set_watchpoint(&mut self, kind: WatchKind, addr: u64, len: u64) -> bool;
+
+    fn remove_watchpoint(&mut self, kind: WatchKind, addr: u64, len: u64) -> bool;
+}
+
+/// Length of instruction read on each step.
+const INST_LEN: u8 = 4;
+
+impl<const RS: usize, I, R, M, MM, E, X> Target for Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    R: BytecodeReader<Register = I::Register, Error = E>,
+    E: Debug,
+    M: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    fn reg_size(&self) -> usize {
+        size_of::<I::Register>()
+    }
+
+    fn reg_count(&self) -> usize {
+        RS + 1
+    }
+
+    fn read_reg(&self, n: usize) -> Option<u64> {
+        match n {
+            n if n < RS => Some(self.regs()[n].into()),
+            n if n == RS => Some((*Executor::pc(self)).into()),
+            _ => None,
+        }
+    }
+
+    fn write_reg(&mut self, n: usize, value: u64) -> bool {
+        let value = match I::Register::try_from(value) {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+
+        match n {
+            // x0 is hardwired to zero.
+            0 => {}
+            n if n < RS => self.regs_mut()[n] = value,
+            n if n == RS => self.set_pc(value),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn read_mem(&self, addr: u64, out: &mut [u8]) -> bool {
+        for (i, chunk) in out.chunks_mut(u8::MAX as usize).enumerate() {
+            let pos = addr.checked_add((i * u8::MAX as usize) as u64);
+            let pos = match pos.and_then(|p| I::Register::try_from(p).ok()) {
+                Some(p) => p,
+                None => return false,
+            };
+
+            let len = chunk.len() as u8;
+            if !self.memory().contains(pos, len) {
+                return false;
+            }
+            chunk.copy_from_slice(self.memory().load(pos, len));
+        }
+
+        true
+    }
+
+    fn write_mem(&mut self, addr: u64, data: &[u8]) -> bool {
+        for (i, chunk) in data.chunks(u8::MAX as usize).enumerate() {
+            let pos = addr.checked_add((i * u8::MAX as usize) as u64);
+            let pos = match pos.and_then(|p| I::Register::try_from(p).ok()) {
+                Some(p) => p,
+                None => return false,
+            };
+
+            if !self.memory().contains(pos, chunk.len() as u8) {
+                return false;
+            }
+            self.memory_mut().store(pos, chunk);
+        }
+
+        true
+    }
+
+    fn step(&mut self) -> StopReason {
+        match Executor::step(self, INST_LEN) {
+            Ok(Outcome::Watchpoint { kind, addr }) => StopReason::Watch { kind, addr },
+            Ok(_) => StopReason::Step,
+            Err(Error::Halted) => StopReason::Signal(SIGTRAP),
+            Err(Error::InstructionError(
+                tangram_instruction::Error::Breakpoint
+                | tangram_instruction::Error::EnvironmentCall,
+            )) => {
+                // Stop after it, or resuming executes it and stops again.
+                self.skip_inst(INST_LEN);
+                StopReason::Signal(SIGTRAP)
+            }
+            Err(Error::InstructionError(_)) => StopReason::Signal(SIGILL),
+            Err(Error::AppError(_)) => StopReason::Signal(SIGSEGV),
+        }
+    }
+
+    fn set_breakpoint(&mut self, addr: u64) -> bool {
+        match I::Register::try_from(addr) {
+            Ok(addr) => self.add_breakpoint(addr),
+            Err(_) => false,
+        }
+    }
+
+    fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        match I::Register::try_from(addr) {
+            Ok(addr) => Executor::remove_breakpoint(self, addr),
+            Err(_) => false,
+        }
+    }
+
+    fn is_breakpoint(&self) -> bool {
+        Executor::is_breakpoint(self)
+    }
+
+    fn set_watchpoint(&mut self, kind: WatchKind, addr: u64, len: u64) -> bool {
+        match I::Register::try_from(addr) {
+            Ok(addr) => self.add_watchpoint(kind, addr, len),
+            Err(_) => false,
+        }
+    }
+
+    fn remove_watchpoint(&mut self, kind: WatchKind, addr: u64, len: u64) -> bool {
+        match I::Register::try_from(addr) {
+            Ok(addr) => Executor::remove_watchpoint(self, kind, addr, len),
+            Err(_) => false,
+        }
+    }
+}
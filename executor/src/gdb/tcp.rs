@@ -0,0 +1,84 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::gdb::{Connection, Disconnect, GdbStub, Target};
+
+impl Connection for TcpStream {
+    type Error = io::Error;
+
+    fn read(&mut self) -> io::Result<u8> {
+        let mut b = [0u8];
+        self.read_exact(&mut b)?;
+        Ok(b[0])
+    }
+
+    fn try_read(&mut self) -> io::Result<Option<u8>> {
+        let mut b = [0u8];
+
+        self.set_nonblocking(true)?;
+        let r = Read::read(self, &mut b);
+        self.set_nonblocking(false)?;
+
+        match r {
+            Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+            Ok(_) => Ok(Some(b[0])),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_all(bytes)
+    }
+}
+
+/// Wait for one debugger on `listener` and serve it, like `target remote :1234` in gdb.
+pub fn serve_tcp<T: Target>(listener: &TcpListener, target: &mut T) -> io::Result<Disconnect> {
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(true)?;
+
+    GdbStub::new(stream).run(target)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use tangram_instruction::riscv32i::RV32iBaseInst;
+
+    use crate::{
+        gdb::{serve_tcp, Disconnect},
+        Executor,
+    };
+
+    #[test]
+    fn test_serve_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"$?#3f").unwrap();
+
+            let mut reply = [0u8; 8];
+            stream.read_exact(&mut reply).unwrap();
+            stream.write_all(b"+$k#6b").unwrap();
+
+            reply
+        });
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new([0u8; 16], [0u8; 16], ());
+
+        let r = serve_tcp(&listener, &mut executor).unwrap();
+
+        assert_eq!(r, Disconnect::Kill);
+        assert_eq!(&client.join().unwrap(), b"+$S05#b8");
+    }
+}
@@ -0,0 +1,394 @@
+use alloc::vec::Vec;
+
+use tangram_instruction::MemoryMut;
+
+use crate::HartCsrs;
+
+enum Entry<R, X> {
+    /// Start of step, with pc before it.
+    Step(R),
+    /// Register `index` with value before step.
+    Reg(usize, R),
+    /// State of extensions before step.
+    Ext(X),
+    /// Machine CSRs before step.
+    Csrs(HartCsrs),
+    /// Bytes at `addr` before step, kept in `data[start..start + len]`.
+    Mem { addr: R, start: usize, len: u8 },
+}
+
+/// Undo log of executed steps.
+pub(crate) struct History<R, X> {
+    limit: usize,
+    steps: usize,
+    entries: Vec<Entry<R, X>>,
+    data: Vec<u8>,
+}
+
+impl<R, X> History<R, X> {
+    pub(crate) fn new() -> Self {
+        Self {
+            limit: 0,
+            steps: 0,
+            entries: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Drop recorded steps, they can't be undone on changed state.
+    pub(crate) fn clear(&mut self) {
+        self.steps = 0;
+        self.entries.clear();
+        self.data.clear();
+    }
+}
+
+impl<R: Copy + Into<u64>, X> History<R, X> {
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        if limit == 0 {
+            self.clear();
+        }
+    }
+
+    /// Start recording step at `pc`, return mark to cancel it.
+    pub(crate) fn begin(&mut self, pc: R) -> Option<usize> {
+        if self.limit == 0 {
+            return None;
+        }
+
+        self.entries.push(Entry::Step(pc));
+        Some(self.entries.len() - 1)
+    }
+
+    /// Save bytes before they're overwritten.
+    pub(crate) fn save(&mut self, addr: R, old: &[u8]) {
+        self.entries.push(Entry::Mem {
+            addr,
+            start: self.data.len(),
+            len: old.len() as u8,
+        });
+        self.data.extend_from_slice(old);
+    }
+
+    /// Drop step begun at `mark`.
+    pub(crate) fn cancel(&mut self, mark: usize) {
+        if let Some(Entry::Mem { start, .. }) = self.entries[mark..]
+            .iter()
+            .find(|e| matches!(e, Entry::Mem { .. }))
+        {
+            self.data.truncate(*start);
+        }
+        self.entries.truncate(mark);
+    }
+
+    /// Save registers changed by step, `old` are values before it.
+    pub(crate) fn save_regs(&mut self, old: &[R], new: &[R]) {
+        for (i, (a, b)) in old.iter().zip(new).enumerate() {
+            if (*a).into() != (*b).into() {
+                self.entries.push(Entry::Reg(i, *a));
+            }
+        }
+    }
+
+    /// Save state of extensions if step changed it.
+    pub(crate) fn save_ext(&mut self, old: X, new: &X)
+    where
+        X: PartialEq,
+    {
+        if old != *new {
+            self.entries.push(Entry::Ext(old));
+        }
+    }
+
+    /// Save machine CSRs if step changed them.
+    pub(crate) fn save_csrs(&mut self, old: HartCsrs, new: &HartCsrs) {
+        if old != *new {
+            self.entries.push(Entry::Csrs(old));
+        }
+    }
+
+    /// Finish step begun at `mark`, dropping it if nothing changed.
+    pub(crate) fn end(&mut self, mark: usize, pc: R) {
+        let Entry::Step(old_pc) = self.entries[mark] else {
+            unreachable!()
+        };
+        if self.entries.len() == mark + 1 && old_pc.into() == pc.into() {
+            self.entries.truncate(mark);
+            return;
+        }
+
+        self.steps += 1;
+        if self.steps > self.limit * 2 {
+            self.trim();
+        }
+    }
+
+    /// Keep last `limit` steps.
+    fn trim(&mut self) {
+        let drop = self.steps - self.limit;
+        let index = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| matches!(e, Entry::Step(_)))
+            .nth(drop)
+            .map_or(self.entries.len(), |(i, _)| i);
+        self.entries.drain(..index);
+
+        let offset = self
+            .entries
+            .iter()
+            .find_map(|e| match e {
+                Entry::Mem { start, .. } => Some(*start),
+                _ => None,
+            })
+            .unwrap_or(self.data.len());
+        self.data.drain(..offset);
+
+        for e in self.entries.iter_mut() {
+            if let Entry::Mem { start, .. } = e {
+                *start -= offset;
+            }
+        }
+
+        self.steps = self.limit;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.steps
+    }
+
+    /// Undo last step, return false if there is none.
+    pub(crate) fn undo<M>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        ext: &mut X,
+        csrs: &mut HartCsrs,
+        memory: &mut M,
+    ) -> bool
+    where
+        M: MemoryMut<Register = R>,
+    {
+        if self.steps == 0 {
+            return false;
+        }
+
+        while let Some(entry) = self.entries.pop() {
+            match entry {
+                Entry::Step(old) => {
+                    *pc = old;
+                    break;
+                }
+                Entry::Reg(i, old) => regs[i] = old,
+                Entry::Ext(old) => *ext = old,
+                Entry::Csrs(old) => *csrs = old,
+                Entry::Mem { addr, start, len } => {
+                    memory.store(addr, &self.data[start..start + len as usize]);
+                    self.data.truncate(start);
+                }
+            }
+        }
+
+        self.steps -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::{
+        riscv::{Illegal, Inst},
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvv::{RVVectorInst, VectorState},
+        riscvzicsr::RVCsrInst,
+    };
+
+    use crate::{Executor, Outcome, CSR_MCAUSE, CSR_MEPC, CSR_MSCRATCH, CSR_MTVEC};
+
+    const PROGRAM: &str = "
+        li a0, 0
+        li a1, 64
+    loop:
+        addi a0, a0, 1
+        sb a0, 0(a1)
+        addi a1, a1, 1
+        j loop
+    ";
+
+    fn executor() -> Executor<32, RV32iBaseInst<()>, [u8; 128], [u8; 128], ()> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        Executor::new(code, [0u8; 128], ())
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut executor = executor();
+        executor.set_history(100);
+
+        for _ in 0..22 {
+            assert_eq!(executor.step(4).unwrap(), Outcome::Stepped);
+        }
+        assert_eq!(executor.regs()[10], 5);
+        assert_eq!(executor.memory()[64..70], [1, 2, 3, 4, 5, 0]);
+        assert_eq!(executor.history_len(), 22);
+
+        // Undo `j`, `addi a1`, then `sb`.
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 20);
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 16);
+        assert_eq!(executor.regs()[11], 68);
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 12);
+        assert_eq!(executor.memory()[68], 0);
+
+        assert!(executor.run_back_to(8));
+        assert_eq!(executor.regs()[10], 4);
+        assert!(executor.run_back_to(8));
+        assert_eq!(executor.regs()[10], 3);
+        assert_eq!(executor.memory()[64..70], [1, 2, 3, 0, 0, 0]);
+
+        // Replay gives same state.
+        for _ in 0..8 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(executor.regs()[10], 5);
+        assert_eq!(executor.memory()[64..70], [1, 2, 3, 4, 5, 0]);
+
+        assert!(!executor.run_back_to(100));
+        assert_eq!(*executor.pc(), 0);
+        assert_eq!(executor.regs()[10..12], [0, 0]);
+        assert_eq!(executor.memory()[64..70], [0; 6]);
+        assert!(!executor.step_back());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut executor = executor();
+        executor.set_history(4);
+
+        for _ in 0..50 {
+            executor.step(4).unwrap();
+        }
+        assert!(executor.history_len() >= 4);
+
+        for _ in 0..4 {
+            assert!(executor.step_back());
+        }
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(executor.regs()[10], 11);
+        assert_eq!(executor.memory()[64 + 10], 11);
+        assert_eq!(executor.memory()[64 + 11], 0);
+
+        executor.set_history(0);
+        assert!(!executor.step_back());
+    }
+
+    #[test]
+    fn test_restore_drops_history() {
+        let mut executor = executor();
+        executor.set_history(100);
+
+        for _ in 0..5 {
+            executor.step(4).unwrap();
+        }
+        let snapshot = executor.snapshot();
+        for _ in 0..4 {
+            executor.step(4).unwrap();
+        }
+
+        // Steps after snapshot can't be undone on restored state.
+        executor.restore(&snapshot);
+        assert_eq!(executor.history_len(), 0);
+        assert!(!executor.step_back());
+        assert_eq!(*executor.pc(), 20);
+        assert_eq!(executor.memory()[64..66], [1, 0]);
+
+        executor.step(4).unwrap();
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 20);
+        assert_eq!(executor.regs()[11], 65);
+
+        executor.regs_mut()[10] = 7;
+        assert!(!executor.step_back());
+    }
+
+    #[test]
+    fn test_step_back_vector() {
+        // `vsetvli t0, a0, e32, m1` then `vmv.v.x v1, a1`
+        let mut code = [0u8; 16];
+        code[..4].copy_from_slice(&0x0105_72d7u32.to_le_bytes());
+        code[4..8].copy_from_slice(&0x5e05_c0d7u32.to_le_bytes());
+
+        let mut executor: Executor<
+            32,
+            RV32iBaseInst<RVVectorInst<Illegal, 256>>,
+            _,
+            _,
+            _,
+            VectorState<32>,
+        > = Executor::new(code, [0u8; 16], ());
+        executor.regs_mut()[10] = 8;
+        executor.regs_mut()[11] = 0x0102_0304;
+        executor.set_history(100);
+
+        executor.step(4).unwrap();
+        executor.step(4).unwrap();
+        assert_eq!(executor.ext().vl, 8);
+        assert_eq!(executor.ext().v[1][28..], [4, 3, 2, 1]);
+
+        // All 256 bits of register and `vl` are rewound.
+        assert!(executor.step_back());
+        assert_eq!(executor.ext().v[1], [0; 32]);
+        assert_eq!(executor.ext().vl, 8);
+        assert!(executor.step_back());
+        assert_eq!(*executor.ext(), VectorState::default());
+        assert_eq!(executor.regs()[5], 0);
+    }
+
+    #[test]
+    fn test_step_back_csrs() {
+        let csr = |funct3, rd, rs1, csr: u16| {
+            Inst::build_i(0b1110011, rd, funct3, rs1, csr as i32)
+                .raw()
+                .to_le_bytes()
+        };
+
+        // Illegal instruction at 4, handler at 8 writes `mscratch` and `mtvec`.
+        let mut code = [0u8; 32];
+        assemble("addi a0, zero, 12\nnop", 0, &mut code).unwrap();
+        code[4..8].copy_from_slice(&0x0200_c58bu32.to_le_bytes());
+        code[8..12].copy_from_slice(&csr(0b001, 0, 10, CSR_MSCRATCH));
+        code[12..16].copy_from_slice(&csr(0b001, 0, 10, CSR_MTVEC));
+
+        let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+            Executor::new(code, [0u8; 16], ());
+        executor.set_trap_vector(Some(8));
+        executor.set_history(100);
+        for _ in 0..4 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(executor.history_len(), 4);
+        assert_eq!(executor.read_csr(CSR_MTVEC), Some(12));
+
+        assert!(executor.step_back());
+        assert_eq!(executor.read_csr(CSR_MTVEC), Some(8));
+        assert_eq!(executor.read_csr(CSR_MSCRATCH), Some(12));
+        assert!(executor.step_back());
+        assert_eq!(executor.read_csr(CSR_MSCRATCH), Some(0));
+        assert_eq!(executor.read_csr(CSR_MEPC), Some(4));
+
+        // Trap entry is undone apart from instruction trapping.
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 4);
+        assert_eq!(executor.last_trap(), None);
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(0));
+        assert!(executor.step_back());
+        assert_eq!(*executor.pc(), 0);
+        assert!(!executor.step_back());
+    }
+}
@@ -0,0 +1,338 @@
+use core::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+};
+
+use tangram_instruction::{riscvv::VectorRegs, Alignment, Context, Instruction, Memory, MemoryMut};
+
+#[cfg(feature = "alloc")]
+use crate::History;
+use crate::{
+    AccessKind, Control, HartCsrs, MemAccess, MemAccesses, Monitor, WatchKind, Watchpoint,
+};
+
+/// Memory wrapper checking watchpoints, calling memory hooks of monitor and
+/// remembering accesses.
+pub(crate) struct HookedMemory<'a, I: Instruction, M, MM, X> {
+    memory: &'a mut M,
+    monitor: &'a RefCell<&'a mut MM>,
+    watchpoints: &'a [Option<Watchpoint>],
+    hit: Cell<Option<(WatchKind, u64)>>,
+    access: RefCell<MemAccesses>,
+    control: Cell<Control>,
+    #[cfg(feature = "alloc")]
+    history: Option<&'a mut History<I::Register, X>>,
+    marker: PhantomData<(I, X)>,
+}
+
+impl<'a, I: Instruction, M, MM, X> HookedMemory<'a, I, M, MM, X> {
+    pub(crate) fn new(
+        memory: &'a mut M,
+        monitor: &'a RefCell<&'a mut MM>,
+        watchpoints: &'a [Option<Watchpoint>],
+    ) -> Self {
+        Self {
+            memory,
+            monitor,
+            watchpoints,
+            hit: Cell::new(None),
+            access: RefCell::new(MemAccesses::default()),
+            control: Cell::new(Control::Continue),
+            #[cfg(feature = "alloc")]
+            history: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Save overwritten bytes into `history`.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn with_history(self, history: &'a mut History<I::Register, X>) -> Self {
+        Self {
+            history: Some(history),
+            ..self
+        }
+    }
+
+    pub(crate) fn access(&self) -> MemAccesses {
+        *self.access.borrow()
+    }
+
+    /// Watchpoint triggered by access, with accessed address.
+    pub(crate) fn hit(&self) -> Option<(WatchKind, u64)> {
+        self.hit.get()
+    }
+
+    /// Check watchpoints, return true if one is triggered.
+    fn watch(&self, write: bool, addr: u64, len: usize) -> bool {
+        if self.hit.get().is_some() {
+            return true;
+        }
+
+        let hit = self
+            .watchpoints
+            .iter()
+            .flatten()
+            .find(|w| w.matches(write, addr, len as u64));
+
+        if let Some(w) = hit {
+            self.hit.set(Some((w.kind, addr.max(w.addr))));
+        }
+
+        hit.is_some()
+    }
+
+    /// Whether a memory hook asked to halt.
+    pub(crate) fn control(&self) -> Control {
+        self.control.get()
+    }
+
+    fn record(&self, kind: AccessKind, addr: u64, data: &[u8]) {
+        let mut value = [0u8; 8];
+        let len = data.len().min(8);
+        value[..len].copy_from_slice(&data[..len]);
+
+        self.access.borrow_mut().push(MemAccess {
+            kind,
+            addr,
+            size: data.len() as u8,
+            value: u64::from_le_bytes(value),
+        });
+    }
+}
+
+impl<'a, I, M, MM, X> Memory for HookedMemory<'a, I, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Copy + Into<u64>,
+    M: Memory<Register = I::Register>,
+    MM: Monitor<I>,
+{
+    type Register = M::Register;
+
+    fn length(&self) -> Self::Register {
+        self.memory.length()
+    }
+
+    fn load(&self, pos: Self::Register, length: u8) -> &[u8] {
+        let data = self.memory.load(pos, length);
+        self.record(AccessKind::Read, pos.into(), data);
+
+        if self.watch(false, pos.into(), data.len()) {
+            return data;
+        }
+
+        if self.monitor.borrow_mut().on_load(&pos, data) == Control::Halt {
+            self.control.set(Control::Halt);
+        }
+
+        data
+    }
+
+    fn contains(&self, pos: Self::Register, length: u8) -> bool {
+        self.memory.contains(pos, length)
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.memory.alignment()
+    }
+}
+
+impl<'a, I, M, MM, X> MemoryMut for HookedMemory<'a, I, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Copy + Into<u64>,
+    M: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+{
+    fn store(&mut self, pos: Self::Register, data: &[u8]) {
+        self.record(AccessKind::Write, pos.into(), data);
+
+        if self.watch(true, pos.into(), data.len()) {
+            return;
+        }
+
+        if self.control.get() == Control::Halt
+            || self.monitor.borrow_mut().on_store(&pos, data) == Control::Halt
+        {
+            self.control.set(Control::Halt);
+            return;
+        }
+
+        #[cfg(feature = "alloc")]
+        if let Some(history) = &mut self.history {
+            history.save(pos, self.memory.load(pos, data.len() as u8));
+        }
+
+        self.memory.store(pos, data)
+    }
+}
+
+/// Context of hart in executor, CSRs are its machine CSRs, then those of
+/// extensions and monitor.
+pub(crate) struct HartContext<'a, I, MM, X> {
+    csrs: &'a mut HartCsrs,
+    ext: &'a mut X,
+    monitor: &'a RefCell<&'a mut MM>,
+    marker: PhantomData<I>,
+}
+
+impl<'a, I, MM, X> HartContext<'a, I, MM, X> {
+    pub(crate) fn new(
+        csrs: &'a mut HartCsrs,
+        ext: &'a mut X,
+        monitor: &'a RefCell<&'a mut MM>,
+    ) -> Self {
+        Self {
+            csrs,
+            ext,
+            monitor,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, I, MM, X> Context for HartContext<'a, I, MM, X>
+where
+    I: Instruction,
+    MM: Monitor<I>,
+    X: Context,
+{
+    fn read_csr(&self, csr: u16) -> Option<u64> {
+        self.csrs
+            .read(csr)
+            .or_else(|| self.ext.read_csr(csr))
+            .or_else(|| self.monitor.borrow().read_csr(csr))
+    }
+
+    fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+        self.csrs.write(csr, value)
+            || self.ext.write_csr(csr, value)
+            || self.monitor.borrow_mut().write_csr(csr, value)
+    }
+
+    fn vector(&mut self) -> Option<VectorRegs<'_>> {
+        self.ext.vector()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::{
+        riscv32i::{assemble, RV32iBaseInst},
+        Memory, MemoryMut,
+    };
+
+    use crate::{Control, Error, Executor, Monitor};
+
+    type Inst = RV32iBaseInst<()>;
+
+    const PROGRAM: &str = "
+        li a0, 5
+        lw a1, 64(zero)
+        sw a0, 68(zero)
+        addi a0, a0, 1
+        ecall
+    ";
+
+    #[derive(Default)]
+    struct Hooks {
+        halt_store: bool,
+        halt_pc: Option<u32>,
+        executed: usize,
+        loads: usize,
+        syscall: Option<u32>,
+    }
+
+    impl Monitor<Inst> for Hooks {
+        fn monitor<M>(&mut self, _inst: &Inst, _pc: &u32, _regs: &[u32], _memory: &M) -> Control
+        where
+            M: MemoryMut<Register = u32>,
+        {
+            self.executed += 1;
+            Control::Continue
+        }
+
+        fn before_execute(&mut self, _inst: &Inst, pc: &u32, _regs: &[u32]) -> Control {
+            if self.halt_pc == Some(*pc) {
+                Control::Halt
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn on_load(&mut self, addr: &u32, data: &[u8]) -> Control {
+            assert_eq!((*addr, data), (64, &[7, 0, 0, 0][..]));
+            self.loads += 1;
+            Control::Continue
+        }
+
+        fn on_store(&mut self, _addr: &u32, _data: &[u8]) -> Control {
+            if self.halt_store {
+                Control::Halt
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn on_syscall(&mut self, pc: &u32, _regs: &[u32]) -> Control {
+            self.syscall = Some(*pc);
+            Control::Continue
+        }
+    }
+
+    fn executor(hooks: Hooks) -> Executor<32, Inst, [u8; 128], [u8; 128], Hooks> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let mut memory = [0u8; 128];
+        memory[64] = 7;
+
+        Executor::new(code, memory, hooks)
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut executor = executor(Hooks::default());
+
+        let r = executor.run(4);
+        assert!(matches!(
+            r,
+            Err(Error::InstructionError(
+                tangram_instruction::Error::EnvironmentCall
+            ))
+        ));
+
+        let hooks = executor.monitor();
+        assert_eq!(hooks.executed, 4);
+        assert_eq!(hooks.loads, 1);
+        assert_eq!(hooks.syscall, Some(16));
+        assert_eq!(executor.regs()[10], 6);
+        assert_eq!(executor.regs()[11], 7);
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut executor = executor(Hooks {
+            halt_pc: Some(12),
+            ..Default::default()
+        });
+
+        assert!(matches!(executor.run(4), Err(Error::Halted)));
+        assert_eq!(*executor.pc(), 12);
+        assert_eq!(executor.regs()[10], 5);
+        assert_eq!(executor.monitor().executed, 3);
+    }
+
+    #[test]
+    fn test_halt_store() {
+        let mut executor = executor(Hooks {
+            halt_store: true,
+            ..Default::default()
+        });
+
+        assert!(matches!(executor.run(4), Err(Error::Halted)));
+        assert_eq!(*executor.pc(), 8);
+        assert_eq!(executor.memory().load(68, 4), &[0; 4]);
+        assert_eq!(executor.monitor().executed, 2);
+    }
+}
@@ -1,11 +1,98 @@
 #![no_std]
-#![feature(async_fn_in_trait)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
 
 mod executor;
 pub use executor::*;
 
+mod hook;
+pub(crate) use hook::*;
+
+mod breakpoint;
+pub use breakpoint::*;
+
+mod trap;
+pub use trap::*;
+
+mod extension;
+pub use extension::*;
+
+#[cfg(feature = "alloc")]
+mod history;
+#[cfg(feature = "alloc")]
+pub(crate) use history::*;
+
 mod prelude;
 pub use prelude::*;
 
 mod error;
 pub use error::*;
+
+mod elf;
+pub use elf::*;
+
+mod trace;
+pub use trace::*;
+
+mod lockstep;
+pub use lockstep::*;
+
+mod counter;
+pub use counter::*;
+
+#[cfg(feature = "alloc")]
+mod profile;
+#[cfg(feature = "alloc")]
+pub use profile::*;
+
+#[cfg(feature = "alloc")]
+mod dwarf;
+#[cfg(feature = "alloc")]
+pub use dwarf::*;
+
+#[cfg(feature = "alloc")]
+mod coverage;
+#[cfg(feature = "alloc")]
+pub use coverage::*;
+
+#[cfg(feature = "alloc")]
+mod zk;
+#[cfg(feature = "alloc")]
+pub use zk::*;
+
+pub mod gdb;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod merkle;
+pub use merkle::*;
+
+#[cfg(feature = "alloc")]
+mod paged;
+#[cfg(feature = "alloc")]
+pub use paged::*;
+
+#[cfg(feature = "alloc")]
+mod merkle_memory;
+#[cfg(feature = "alloc")]
+pub use merkle_memory::*;
+
+#[cfg(feature = "alloc")]
+mod deterministic;
+#[cfg(feature = "alloc")]
+pub use deterministic::*;
+
+#[cfg(feature = "alloc")]
+mod machine;
+#[cfg(feature = "alloc")]
+pub use machine::*;
+
+#[cfg(feature = "alloc")]
+mod segment;
+#[cfg(feature = "alloc")]
+pub use segment::*;
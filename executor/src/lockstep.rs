@@ -0,0 +1,480 @@
+use core::{fmt, str::Lines};
+
+use tangram_instruction::{Instruction, MemoryMut};
+
+use crate::{AccessKind, Control, MemAccess, MemAccesses, Monitor, TraceRecord};
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+impl TraceRecord {
+    /// Parse one line of Spike commit log (`spike --log-commits`).
+    ///
+    /// Line looks like `core   0: 3 0x80000000 (0x00000297) x5  0x80000000 mem 0x80001000`.
+    /// Return `None` for lines without commit, like disassembly or exception.
+    ///
+    /// Spike doesn't log size and value of load, so they are `0` in result.
+    pub fn parse_spike(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace().peekable();
+
+        if tokens.next()? != "core" {
+            return None;
+        }
+        tokens.next()?.strip_suffix(':')?;
+
+        let privilege = tokens.next()?;
+        if privilege.len() != 1 || !privilege.as_bytes()[0].is_ascii_digit() {
+            return None;
+        }
+
+        let pc = parse_hex(tokens.next()?)?;
+        let inst = tokens.next()?.strip_prefix('(')?.strip_suffix(')')?;
+        let inst = parse_hex(inst)? as u32;
+
+        let mut rd = None;
+        let mut mem = MemAccesses::default();
+
+        while let Some(token) = tokens.next() {
+            if token == "mem" {
+                let addr = parse_hex(tokens.next()?)?;
+
+                let access = match tokens.peek().copied().and_then(parse_hex) {
+                    Some(value) => {
+                        let size = (tokens.next()?.len() - 2) / 2;
+                        MemAccess {
+                            kind: AccessKind::Write,
+                            addr,
+                            size: size as u8,
+                            value,
+                        }
+                    }
+                    None => MemAccess {
+                        kind: AccessKind::Read,
+                        addr,
+                        size: 0,
+                        value: 0,
+                    },
+                };
+
+                mem.push(access);
+            } else if let Some(index) = token.strip_prefix('x').and_then(|i| i.parse().ok()) {
+                let value = parse_hex(tokens.next()?)?;
+                rd.get_or_insert((index, value));
+            } else if tokens.peek().is_some_and(|t| t.starts_with("0x")) {
+                // Float register or CSR write.
+                tokens.next();
+            }
+        }
+
+        Some(Self { pc, inst, rd, mem })
+    }
+}
+
+/// Iterator over commits of Spike commit log, other lines are skipped.
+pub struct SpikeLog<'a> {
+    lines: Lines<'a>,
+}
+
+impl<'a> SpikeLog<'a> {
+    pub fn new(log: &'a str) -> Self {
+        Self { lines: log.lines() }
+    }
+}
+
+impl<'a> Iterator for SpikeLog<'a> {
+    type Item = TraceRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.by_ref().find_map(TraceRecord::parse_spike)
+    }
+}
+
+/// What differs between reference and executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    Pc,
+    Inst,
+    /// Register value after execute, `None` means register isn't written.
+    Reg {
+        index: u8,
+        expected: Option<u64>,
+        actual: Option<u64>,
+    },
+    Mem,
+    /// Reference trace has no more records.
+    TraceEnd,
+}
+
+/// First step where executor diverges from reference trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Step counted from 0.
+    pub step: u64,
+    pub mismatch: Mismatch,
+    pub expected: Option<TraceRecord>,
+    pub actual: TraceRecord,
+}
+
+struct MemDiff<'a>(&'a MemAccesses);
+
+impl<'a> fmt::Display for MemDiff<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("none");
+        }
+
+        for (i, m) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+
+            let kind = match m.kind {
+                AccessKind::Read => "read",
+                AccessKind::Write => "write",
+            };
+
+            if m.kind == AccessKind::Read && m.size == 0 {
+                write!(f, "read {:#x}", m.addr)?;
+            } else {
+                write!(
+                    f,
+                    "{} {:#x} size {} value {:#x}",
+                    kind, m.addr, m.size, m.value
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Diff of divergence, one line per field.
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let actual = &self.actual;
+
+        writeln!(f, "diverged at step {}, pc {:#x}", self.step, actual.pc)?;
+
+        let expected = match &self.expected {
+            Some(e) => e,
+            None => return write!(f, "  reference trace ended"),
+        };
+
+        match self.mismatch {
+            Mismatch::Pc => write!(
+                f,
+                "  pc: expected {:#x}, actual {:#x}",
+                expected.pc, actual.pc
+            ),
+            Mismatch::Inst => write!(
+                f,
+                "  inst: expected {:#010x}, actual {:#010x}",
+                expected.inst, actual.inst
+            ),
+            Mismatch::Reg {
+                index,
+                expected,
+                actual,
+            } => {
+                write!(f, "  x{}: expected ", index)?;
+                match expected {
+                    Some(v) => write!(f, "{:#x}", v)?,
+                    None => f.write_str("unchanged")?,
+                }
+                f.write_str(", actual ")?;
+                match actual {
+                    Some(v) => write!(f, "{:#x}", v),
+                    None => f.write_str("unchanged"),
+                }
+            }
+            Mismatch::Mem => write!(
+                f,
+                "  mem: expected {}, actual {}",
+                MemDiff(&expected.mem),
+                MemDiff(&actual.mem)
+            ),
+            Mismatch::TraceEnd => write!(f, "  reference trace ended"),
+        }
+    }
+}
+
+fn mem_matches(expected: &MemAccesses, actual: &MemAccesses) -> bool {
+    expected.len() == actual.len()
+        && expected.iter().zip(actual.iter()).all(|(e, a)| {
+            e.kind == a.kind
+                && e.addr == a.addr
+                && (e.size == 0 || (e.size == a.size && e.value == a.value))
+        })
+}
+
+/// Monitor comparing each step with a reference trace, halt at first divergence.
+///
+/// Writes to `x0` are ignored on both sides. Register value of reference is
+/// checked against registers after execute.
+pub struct LockstepMonitor<T> {
+    reference: T,
+    steps: u64,
+    /// Reference of current step, register is checked after execute.
+    pending: Option<(TraceRecord, TraceRecord)>,
+    divergence: Option<Divergence>,
+}
+
+impl<T: Iterator<Item = TraceRecord>> LockstepMonitor<T> {
+    pub fn new(reference: T) -> Self {
+        Self {
+            reference,
+            steps: 0,
+            pending: None,
+            divergence: None,
+        }
+    }
+
+    /// Number of compared steps.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    fn diverge(
+        &mut self,
+        mismatch: Mismatch,
+        expected: Option<TraceRecord>,
+        actual: TraceRecord,
+    ) -> Control {
+        self.divergence = Some(Divergence {
+            step: self.steps - 1,
+            mismatch,
+            expected,
+            actual,
+        });
+
+        Control::Halt
+    }
+}
+
+impl<I, T> Monitor<I> for LockstepMonitor<T>
+where
+    I: Instruction,
+    I::Register: Copy + Into<u64>,
+    T: Iterator<Item = TraceRecord>,
+{
+    const RECORD: bool = true;
+
+    fn monitor<M>(
+        &mut self,
+        _inst: &I,
+        _pc: &I::Register,
+        regs: &[I::Register],
+        _memory: &M,
+    ) -> Control
+    where
+        M: MemoryMut<Register = I::Register>,
+    {
+        let (expected, actual) = match self.pending.take() {
+            Some(r) => r,
+            None => return Control::Continue,
+        };
+
+        if let Some((index, value)) = expected.rd {
+            let v = regs.get(index as usize).map(|r| (*r).into());
+
+            if v != Some(value) {
+                let mismatch = Mismatch::Reg {
+                    index,
+                    expected: Some(value),
+                    actual: actual.rd.map(|(_, v)| v),
+                };
+                return self.diverge(mismatch, Some(expected), actual);
+            }
+        }
+
+        Control::Continue
+    }
+
+    fn record(&mut self, actual: &TraceRecord) -> Control {
+        if self.divergence.is_some() {
+            return Control::Halt;
+        }
+
+        self.steps += 1;
+
+        let mut expected = match self.reference.next() {
+            Some(r) => r,
+            None => return self.diverge(Mismatch::TraceEnd, None, *actual),
+        };
+
+        let mut actual = *actual;
+        for r in [&mut expected.rd, &mut actual.rd] {
+            if r.is_some_and(|(i, _)| i == 0) {
+                *r = None;
+            }
+        }
+
+        let mismatch = if expected.pc != actual.pc {
+            Some(Mismatch::Pc)
+        } else if expected.inst != actual.inst {
+            Some(Mismatch::Inst)
+        } else if !mem_matches(&expected.mem, &actual.mem) {
+            Some(Mismatch::Mem)
+        } else {
+            match actual.rd {
+                Some((index, value)) if expected.rd.map(|(i, _)| i) != Some(index) => {
+                    Some(Mismatch::Reg {
+                        index,
+                        expected: None,
+                        actual: Some(value),
+                    })
+                }
+                _ => None,
+            }
+        };
+
+        match mismatch {
+            Some(m) => self.diverge(m, Some(expected), actual),
+            None => {
+                self.pending = Some((expected, actual));
+                Control::Continue
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::{string::ToString, vec::Vec};
+
+    use tangram_instruction::riscv32i::{assemble, RV32iBaseInst};
+
+    use crate::{AccessKind, Error, Executor, MemAccess, TraceRecord};
+
+    use super::{Divergence, LockstepMonitor, Mismatch, SpikeLog};
+
+    const PROGRAM: &str = "
+        li a0, 5
+        sw a0, 64(zero)
+        lw a1, 64(zero)
+        ecall
+    ";
+
+    const SPIKE_LOG: &str = "\
+core   0: 0x00000000 (0x00500513) li      a0, 5
+core   0: 3 0x00000000 (0x00500513) x10 0x00000005
+core   0: 3 0x00000004 (0x04a02023) mem 0x00000040 0x00000005
+core   0: 3 0x00000008 (0x04002583) x11 0x00000005 mem 0x00000040
+core   0: 3 0x0000000c (0x00000073)
+";
+
+    fn run(log: &str) -> Option<Divergence> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let monitor = LockstepMonitor::new(SpikeLog::new(log));
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], monitor);
+
+        match executor.run(4) {
+            Err(Error::Halted) => {}
+            Err(Error::InstructionError(tangram_instruction::Error::EnvironmentCall)) => {}
+            r => panic!("unexpected {:?}", r),
+        }
+
+        executor.monitor().divergence().copied()
+    }
+
+    #[test]
+    fn test_parse_spike() {
+        let records: Vec<_> = SpikeLog::new(SPIKE_LOG).collect();
+        assert_eq!(records.len(), 4);
+
+        assert_eq!(
+            records[1],
+            TraceRecord {
+                pc: 4,
+                inst: 0x04a02023,
+                rd: None,
+                mem: MemAccess {
+                    kind: AccessKind::Write,
+                    addr: 0x40,
+                    size: 4,
+                    value: 5,
+                }
+                .into(),
+            }
+        );
+        assert_eq!(records[2].rd, Some((11, 5)));
+        assert_eq!(records[2].mem[0].kind, AccessKind::Read);
+
+        let r =
+            TraceRecord::parse_spike("core   0: 3 0x80000100 (0x30529073) c773_mtvec 0x80000104")
+                .unwrap();
+        assert_eq!(r.pc, 0x80000100);
+        assert_eq!(r.rd, None);
+
+        assert_eq!(
+            TraceRecord::parse_spike("core   0: exception trap_user_ecall, epc 0x0000000c"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_lockstep_match() {
+        assert_eq!(run(SPIKE_LOG), None);
+    }
+
+    #[test]
+    fn test_lockstep_diverge() {
+        let log = SPIKE_LOG.replace("x11 0x00000005", "x11 0x00000006");
+        let d = run(&log).unwrap();
+
+        assert_eq!(d.step, 2);
+        assert_eq!(
+            d.mismatch,
+            Mismatch::Reg {
+                index: 11,
+                expected: Some(6),
+                actual: Some(5),
+            }
+        );
+        assert_eq!(
+            d.to_string(),
+            "diverged at step 2, pc 0x8\n  x11: expected 0x6, actual 0x5"
+        );
+
+        let log = SPIKE_LOG.replace("mem 0x00000040 0x00000005", "mem 0x00000044 0x00000005");
+        let d = run(&log).unwrap();
+
+        assert_eq!(d.step, 1);
+        assert_eq!(d.mismatch, Mismatch::Mem);
+        assert_eq!(
+            d.to_string(),
+            "diverged at step 1, pc 0x4\n  mem: expected write 0x44 size 4 value 0x5, \
+             actual write 0x40 size 4 value 0x5"
+        );
+
+        // Register beyond register file of executor is a divergence, not a panic.
+        let log = SPIKE_LOG.replace("(0x04a02023) mem", "(0x04a02023) x40 0x00000005 mem");
+        let d = run(&log).unwrap();
+
+        assert_eq!(d.step, 1);
+        assert_eq!(
+            d.mismatch,
+            Mismatch::Reg {
+                index: 40,
+                expected: Some(5),
+                actual: None,
+            }
+        );
+
+        let log = SPIKE_LOG.replace("core   0: 3 0x0000000c (0x00000073)\n", "");
+        let d = run(&log).unwrap();
+
+        assert_eq!(d.step, 3);
+        assert_eq!(d.mismatch, Mismatch::TraceEnd);
+    }
+}
@@ -0,0 +1,400 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use tangram_instruction::{Instruction, MemoryMut, Reg32};
+
+use crate::{BytecodeReader, Error, Executor, Extension, HartCsrs, Monitor, Outcome};
+
+/// Register `a0`, holding hart id when hart starts.
+const A0: usize = 10;
+
+/// Hart which stops [`Machine`], with result of its last step.
+pub type HartResult<E> = (usize, Result<Outcome, Error<E>>);
+
+#[derive(Clone)]
+struct Hart<const RS: usize, R, X> {
+    pc: R,
+    regs: [R; RS],
+    csrs: HartCsrs,
+    ext: X,
+    parked: bool,
+}
+
+/// Harts sharing memory, reader and monitor, scheduled round-robin.
+///
+/// Each hart runs `quantum` steps before next unparked hart, so schedule depends
+/// only on steps executed. Steps which fail don't use quantum. Each hart has its
+/// own machine CSRs, `mhartid` is its index. Breakpoints and watchpoints of
+/// executor are shared by all harts, and its history only has steps of current
+/// turn, it is dropped when another hart is scheduled.
+pub struct Machine<const RS: usize, I, R, M, MM, X = ()>
+where
+    I: Instruction,
+{
+    executor: Executor<RS, I, R, M, MM, X>,
+    /// Saved state of harts, the current one is in executor.
+    harts: Vec<Hart<RS, I::Register, X>>,
+    current: usize,
+    used: u64,
+    quantum: u64,
+}
+
+impl<const RS: usize, I, R, M, MM, X> Machine<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Reg32,
+    X: Extension,
+{
+    /// Machine with `harts` harts, all at pc 0 with `a0` set to hart id, like
+    /// `mhartid`.
+    pub fn new(reader: R, memory: M, monitor: MM, harts: usize, quantum: u64) -> Self {
+        assert!(harts > 0, "machine needs at least one hart");
+
+        let harts = (0..harts)
+            .map(|id| {
+                let mut regs = [I::Register::default(); RS];
+                if let Some(a0) = regs.get_mut(A0) {
+                    a0.set_reg32(id as u32);
+                }
+
+                Hart {
+                    pc: I::Register::default(),
+                    regs,
+                    csrs: HartCsrs {
+                        hart_id: id as u64,
+                        ..Default::default()
+                    },
+                    ext: X::default(),
+                    parked: false,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut executor = Executor::new(reader, memory, monitor);
+        let mut hart = harts[0].clone();
+        executor.swap_hart(&mut hart.pc, &mut hart.regs, &mut hart.csrs, &mut hart.ext);
+
+        Self {
+            executor,
+            harts,
+            current: 0,
+            used: 0,
+            quantum: quantum.max(1),
+        }
+    }
+}
+
+impl<const RS: usize, I, R, M, MM, X> Machine<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy,
+    X: Extension,
+{
+    /// Number of harts
+    pub fn harts(&self) -> usize {
+        self.harts.len()
+    }
+
+    /// Hart running now, or next to run.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn quantum(&self) -> u64 {
+        self.quantum
+    }
+
+    /// Set steps of each turn, at least 1. Current turn ends when it is used up.
+    pub fn set_quantum(&mut self, quantum: u64) {
+        self.quantum = quantum.max(1);
+    }
+
+    pub fn pc(&self, hart: usize) -> I::Register {
+        match hart == self.current {
+            true => *self.executor.pc(),
+            false => self.harts[hart].pc,
+        }
+    }
+
+    pub fn set_pc(&mut self, hart: usize, pc: I::Register) {
+        match hart == self.current {
+            true => self.executor.set_pc(pc),
+            false => self.harts[hart].pc = pc,
+        }
+    }
+
+    pub fn regs(&self, hart: usize) -> &[I::Register; RS] {
+        match hart == self.current {
+            true => self.executor.regs(),
+            false => &self.harts[hart].regs,
+        }
+    }
+
+    pub fn regs_mut(&mut self, hart: usize) -> &mut [I::Register; RS] {
+        match hart == self.current {
+            true => self.executor.regs_mut(),
+            false => &mut self.harts[hart].regs,
+        }
+    }
+
+    /// State of extensions of `hart`, like its vector registers.
+    pub fn ext(&self, hart: usize) -> &X {
+        match hart == self.current {
+            true => self.executor.ext(),
+            false => &self.harts[hart].ext,
+        }
+    }
+
+    pub fn ext_mut(&mut self, hart: usize) -> &mut X {
+        match hart == self.current {
+            true => self.executor.ext_mut(),
+            false => &mut self.harts[hart].ext,
+        }
+    }
+
+    /// Stop scheduling `hart`, like `wfi` without interrupts.
+    pub fn park(&mut self, hart: usize) {
+        self.harts[hart].parked = true;
+    }
+
+    pub fn unpark(&mut self, hart: usize) {
+        self.harts[hart].parked = false;
+    }
+
+    pub fn is_parked(&self, hart: usize) -> bool {
+        self.harts[hart].parked
+    }
+
+    /// Executor running current hart, memory and monitor are shared.
+    pub fn executor(&self) -> &Executor<RS, I, R, M, MM, X> {
+        &self.executor
+    }
+
+    pub fn executor_mut(&mut self) -> &mut Executor<RS, I, R, M, MM, X> {
+        &mut self.executor
+    }
+
+    /// Make next unparked hart current if turn is over, return false if all
+    /// harts are parked.
+    fn schedule(&mut self) -> bool {
+        if self.used < self.quantum && !self.harts[self.current].parked {
+            return true;
+        }
+
+        let count = self.harts.len();
+        let next = (1..=count)
+            .map(|i| (self.current + i) % count)
+            .find(|h| !self.harts[*h].parked);
+
+        let Some(next) = next else {
+            return false;
+        };
+
+        if next != self.current {
+            let mut hart = self.harts[next].clone();
+            self.executor
+                .swap_hart(&mut hart.pc, &mut hart.regs, &mut hart.csrs, &mut hart.ext);
+
+            let current = &mut self.harts[self.current];
+            (current.pc, current.regs, current.csrs, current.ext) =
+                (hart.pc, hart.regs, hart.csrs, hart.ext);
+            self.current = next;
+        }
+        self.used = 0;
+
+        true
+    }
+}
+
+impl<const RS: usize, I, R, M, MM, E, X> Machine<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    R: BytecodeReader<Register = I::Register, Error = E>,
+    E: Debug,
+    M: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    /// Read CSR of `hart` as it sees it, `None` if it doesn't exist.
+    pub fn read_csr(&self, hart: usize, csr: u16) -> Option<u64> {
+        match hart == self.current {
+            true => self.executor.read_csr(csr),
+            false => self
+                .harts
+                .get(hart)?
+                .csrs
+                .read(csr)
+                .or_else(|| self.executor.monitor().read_csr(csr)),
+        }
+    }
+
+    /// Execute one instruction of scheduled hart, `None` if all harts are parked.
+    pub fn step(&mut self, bytes_len: u8) -> Option<HartResult<E>> {
+        if !self.schedule() {
+            return None;
+        }
+
+        let result = self.executor.step(bytes_len);
+        if let Ok(Outcome::Stepped) = result {
+            self.used += 1;
+        }
+
+        Some((self.current, result))
+    }
+
+    /// Execute until a hart reaches breakpoint, watchpoint or error, `None` if all
+    /// harts are parked.
+    pub fn run(&mut self, bytes_len: u8) -> Option<HartResult<E>> {
+        loop {
+            let (hart, result) = self.step(bytes_len)?;
+            if !matches!(result, Ok(Outcome::Stepped)) {
+                return Some((hart, result));
+            }
+
+            if self.executor.is_breakpoint() {
+                let pc = (*self.executor.pc()).into();
+                return Some((hart, Ok(Outcome::Breakpoint(pc))));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use tangram_instruction::{
+        riscv::Inst,
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvzicsr::RVCsrInst,
+        Memory,
+    };
+
+    use super::Machine;
+    use crate::{Error, Outcome, CSR_MHARTID, CSR_MSCRATCH};
+
+    const PROGRAM: &str = "
+        slli t0, a0, 2
+        addi t1, a0, 1
+        sw t1, 256(t0)
+        bnez a0, done
+        li t2, 256
+        li t3, 272
+        li a1, 0
+    wait:
+        lw t4, 0(t2)
+        beqz t4, wait
+        add a1, a1, t4
+        addi t2, t2, 4
+        bne t2, t3, wait
+    done:
+        ecall
+    ";
+
+    type Vm = Machine<32, RV32iBaseInst<()>, [u8; 128], [u8; 512], ()>;
+
+    fn machine(quantum: u64) -> Vm {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        Machine::new(code, [0u8; 512], (), 4, quantum)
+    }
+
+    /// Run until all harts call `ecall`, return harts in order of calls.
+    fn run(machine: &mut Vm) -> Vec<usize> {
+        let mut order = Vec::new();
+        while let Some((hart, result)) = machine.run(4) {
+            assert!(matches!(
+                result,
+                Err(Error::InstructionError(
+                    tangram_instruction::Error::EnvironmentCall
+                ))
+            ));
+            order.push(hart);
+            machine.park(hart);
+        }
+
+        order
+    }
+
+    #[test]
+    fn test_harts() {
+        let mut machine = machine(2);
+        assert_eq!(machine.read_csr(3, CSR_MHARTID), Some(3));
+        assert_eq!(machine.read_csr(4, CSR_MHARTID), None);
+
+        let order = run(&mut machine);
+        assert_eq!(order, [1, 2, 3, 0]);
+        assert_eq!(machine.regs(0)[11], 10);
+        assert_eq!(machine.regs(2)[10], 2);
+        assert_eq!(machine.pc(3), 48);
+        assert_eq!(machine.executor().memory().load(256, 4), &[1, 0, 0, 0]);
+        assert!(machine.step(4).is_none());
+
+        // Same quantum, same schedule.
+        let mut again = self::machine(2);
+        assert_eq!(run(&mut again), order);
+
+        // Hart 0 runs alone until it waits for others.
+        let mut long = self::machine(100);
+        assert_eq!(run(&mut long), [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_quantum() {
+        let mut machine = machine(3);
+        machine.park(2);
+        machine.park(3);
+
+        let mut harts = Vec::new();
+        for _ in 0..8 {
+            let (hart, result) = machine.step(4).unwrap();
+            assert!(matches!(result, Ok(Outcome::Stepped)));
+            harts.push(hart);
+        }
+        assert_eq!(harts, [0, 0, 0, 1, 1, 1, 0, 0]);
+
+        machine.set_pc(1, 0);
+        machine.unpark(2);
+        assert_eq!(machine.step(4).unwrap().0, 0);
+        assert_eq!(machine.step(4).unwrap().0, 1);
+        assert_eq!(machine.pc(1), 4);
+    }
+
+    #[test]
+    fn test_hart_csrs() {
+        // csrr a1, mhartid; csrw mscratch, a1; ecall
+        let mut code = [0u8; 16];
+        let csrr = Inst::build_i(0b1110011, 11, 0b010, 0, CSR_MHARTID as i32);
+        let csrw = Inst::build_i(0b1110011, 0, 0b001, 11, CSR_MSCRATCH as i32);
+        code[0..4].copy_from_slice(&csrr.raw().to_le_bytes());
+        code[4..8].copy_from_slice(&csrw.raw().to_le_bytes());
+        assemble("ecall", 8, &mut code[8..]).unwrap();
+
+        let mut machine: Machine<32, RV32iBaseInst<RVCsrInst<()>>, _, _, _> =
+            Machine::new(code, [0u8; 16], (), 3, 2);
+        machine.executor_mut().set_history(8);
+
+        // History has steps of current turn only.
+        assert_eq!(machine.step(4).unwrap().0, 0);
+        assert!(machine.executor_mut().step_back());
+        assert_eq!(machine.pc(0), 0);
+        assert_eq!(machine.step(4).unwrap().0, 0);
+        assert_eq!(machine.step(4).unwrap().0, 1);
+        assert!(machine.executor_mut().step_back());
+        assert!(!machine.executor_mut().step_back());
+        assert_eq!(machine.pc(0), 4);
+
+        while let Some((hart, result)) = machine.run(4) {
+            assert!(result.is_err());
+            machine.park(hart);
+        }
+        for hart in 0..3 {
+            assert_eq!(machine.regs(hart)[11], hart as u32);
+            assert_eq!(machine.read_csr(hart, CSR_MHARTID), Some(hart as u64));
+            assert_eq!(machine.read_csr(hart, CSR_MSCRATCH), Some(hart as u64));
+        }
+        assert_eq!(machine.read_csr(3, CSR_MHARTID), None);
+    }
+}
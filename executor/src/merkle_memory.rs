@@ -0,0 +1,296 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use core::{cell::RefCell, mem};
+
+use tangram_instruction::{Memory, MemoryMut};
+
+use crate::{paged::zero_hashes, Hasher, PagedMemory, Sha256, PAGE_SIZE};
+
+/// Paged memory keeping Merkle tree of its pages.
+///
+/// Tree is the same as [`PagedMemory::root`]. Stores mark pages dirty, only
+/// their paths are hashed again on next [`MerkleMemory::root`] or proof.
+pub struct MerkleMemory<H: Hasher = Sha256, R = u32> {
+    memory: PagedMemory<R>,
+    zero: Vec<H::Digest>,
+    /// Hashes of non-empty subtrees, leaves at level 0.
+    nodes: RefCell<Vec<BTreeMap<u64, H::Digest>>>,
+    dirty: RefCell<BTreeSet<u64>>,
+}
+
+/// Inclusion proof of one page, returned by [`MerkleMemory::prove`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof<D> {
+    /// Index of page.
+    pub page: u64,
+    /// Data of page, `PAGE_SIZE` bytes.
+    pub data: Vec<u8>,
+    /// Siblings from leaf to root.
+    pub siblings: Vec<D>,
+}
+
+impl<H: Hasher, R> MerkleMemory<H, R> {
+    /// Memory of `size` bytes, all zero.
+    pub fn new(size: u64) -> Self {
+        Self::from_memory(PagedMemory::new(size))
+    }
+
+    /// Build tree over existing memory.
+    pub fn from_memory(memory: PagedMemory<R>) -> Self {
+        let depth = memory.depth();
+        let dirty = memory.pages().map(|(i, _)| i).collect();
+
+        Self {
+            memory,
+            zero: zero_hashes::<H>(depth),
+            nodes: RefCell::new((0..=depth).map(|_| BTreeMap::new()).collect()),
+            dirty: RefCell::new(dirty),
+        }
+    }
+
+    pub fn memory(&self) -> &PagedMemory<R> {
+        &self.memory
+    }
+
+    pub fn into_memory(self) -> PagedMemory<R> {
+        self.memory
+    }
+
+    /// Number of pages changed since root was last computed.
+    pub fn dirty_pages(&self) -> usize {
+        self.dirty.borrow().len()
+    }
+
+    /// Root of tree, equal to [`PagedMemory::root`] of inner memory.
+    pub fn root(&self) -> H::Digest {
+        self.update();
+
+        let depth = self.zero.len() - 1;
+        self.node(&self.nodes.borrow(), depth, 0)
+    }
+
+    /// Inclusion proof of page holding `addr`, `None` if it is out of memory.
+    pub fn prove(&self, addr: u64) -> Option<MerkleProof<H::Digest>> {
+        if addr >= self.memory.size() {
+            return None;
+        }
+        self.update();
+
+        let page = addr / PAGE_SIZE as u64;
+        let data = match self.memory.page(page) {
+            Some(data) => data.to_vec(),
+            None => alloc::vec![0; PAGE_SIZE],
+        };
+
+        let nodes = self.nodes.borrow();
+        let siblings = (0..self.zero.len() - 1)
+            .map(|level| self.node(&nodes, level, (page >> level) ^ 1))
+            .collect();
+
+        Some(MerkleProof {
+            page,
+            data,
+            siblings,
+        })
+    }
+
+    fn node(&self, nodes: &[BTreeMap<u64, H::Digest>], level: usize, index: u64) -> H::Digest {
+        nodes[level]
+            .get(&index)
+            .copied()
+            .unwrap_or(self.zero[level])
+    }
+
+    /// Hash dirty pages and their paths to root.
+    fn update(&self) {
+        let mut indexes = mem::take(&mut *self.dirty.borrow_mut());
+        if indexes.is_empty() {
+            return;
+        }
+
+        let mut nodes = self.nodes.borrow_mut();
+        for index in indexes.iter() {
+            let leaf = match self.memory.page(*index) {
+                Some(data) => H::leaf(data),
+                None => self.zero[0],
+            };
+            self.set(&mut nodes, 0, *index, leaf);
+        }
+
+        for level in 1..self.zero.len() {
+            indexes = indexes.iter().map(|i| i / 2).collect();
+            for index in indexes.iter() {
+                let left = self.node(&nodes, level - 1, index * 2);
+                let right = self.node(&nodes, level - 1, index * 2 + 1);
+                self.set(&mut nodes, level, *index, H::node(&left, &right));
+            }
+        }
+    }
+
+    fn set(
+        &self,
+        nodes: &mut [BTreeMap<u64, H::Digest>],
+        level: usize,
+        index: u64,
+        hash: H::Digest,
+    ) {
+        if hash == self.zero[level] {
+            nodes[level].remove(&index);
+        } else {
+            nodes[level].insert(index, hash);
+        }
+    }
+
+    /// Mark pages written by store of `data` at `pos`, bytes beyond memory are
+    /// dropped by it.
+    fn mark_dirty(&mut self, pos: u64, data: &[u8]) {
+        let end = pos
+            .saturating_add(data.len() as u64)
+            .min(self.memory.size());
+        if pos < end {
+            let first = pos / PAGE_SIZE as u64;
+            let last = (end - 1) / PAGE_SIZE as u64;
+            self.dirty.get_mut().extend(first..=last);
+        }
+    }
+}
+
+impl<D: PartialEq> MerkleProof<D> {
+    /// Check proof against `root`.
+    pub fn verify<H: Hasher<Digest = D>>(&self, root: &D) -> bool {
+        if self.data.len() != PAGE_SIZE || self.siblings.len() >= 64 {
+            return false;
+        }
+
+        let mut hash = H::leaf(&self.data);
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            hash = if (self.page >> level) & 1 == 0 {
+                H::node(&hash, sibling)
+            } else {
+                H::node(sibling, &hash)
+            };
+        }
+
+        self.page >> self.siblings.len() == 0 && hash == *root
+    }
+
+    /// Proven bytes at `addr`, `None` if they aren't all in the page.
+    pub fn read(&self, addr: u64, length: usize) -> Option<&[u8]> {
+        let start = addr.checked_sub(self.page.checked_mul(PAGE_SIZE as u64)?)? as usize;
+        self.data.get(start..start.checked_add(length)?)
+    }
+}
+
+macro_rules! impl_memory {
+    ($reg:ty) => {
+        impl<H: Hasher> Memory for MerkleMemory<H, $reg> {
+            type Register = $reg;
+
+            fn length(&self) -> Self::Register {
+                self.memory.length()
+            }
+
+            fn load(&self, pos: Self::Register, length: u8) -> &[u8] {
+                self.memory.load(pos, length)
+            }
+
+            fn contains(&self, pos: Self::Register, length: u8) -> bool {
+                self.memory.contains(pos, length)
+            }
+        }
+
+        impl<H: Hasher> MemoryMut for MerkleMemory<H, $reg> {
+            fn store(&mut self, pos: Self::Register, data: &[u8]) {
+                self.mark_dirty(pos as u64, data);
+                self.memory.store(pos, data)
+            }
+        }
+    };
+}
+
+impl_memory!(u32);
+impl_memory!(u64);
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::{Memory, MemoryMut};
+
+    use super::MerkleMemory;
+    use crate::{PagedMemory, Sha256};
+
+    #[test]
+    fn test_incremental_root() {
+        let mut memory = MerkleMemory::<Sha256>::new(1 << 20);
+        let mut paged = PagedMemory::<u32>::new(1 << 20);
+        assert_eq!(memory.root(), paged.root::<Sha256>());
+
+        for (pos, data) in [
+            (0x10, &[1, 2][..]),
+            (0x3FFE, &[3, 4, 5, 6]),
+            (0xF_F000, &[7]),
+            (0x10, &[0, 0]),
+        ] {
+            memory.store(pos, data);
+            paged.store(pos, data);
+            assert!(memory.dirty_pages() > 0);
+            assert_eq!(memory.root(), paged.root::<Sha256>());
+            assert_eq!(memory.dirty_pages(), 0);
+        }
+        assert_eq!(memory.load(0x3FFE, 4), &[3, 4, 5, 6]);
+
+        let rebuilt = MerkleMemory::<Sha256>::from_memory(paged);
+        assert_eq!(rebuilt.root(), memory.root());
+    }
+
+    #[test]
+    fn test_proof() {
+        let mut memory = MerkleMemory::<Sha256>::new(5 * 4096);
+        memory.store(0x2FFE, &[1, 2, 3, 4]);
+        let root = memory.root();
+
+        let proof = memory.prove(0x3001).unwrap();
+        assert_eq!(proof.page, 3);
+        assert_eq!(proof.siblings.len(), 3);
+        assert!(proof.verify::<Sha256>(&root));
+        assert_eq!(proof.read(0x3000, 2), Some(&[3, 4][..]));
+        assert_eq!(proof.read(0x2FFF, 2), None);
+
+        // Missing page is proven as zero.
+        let empty = memory.prove(0x4FFF).unwrap();
+        assert!(empty.verify::<Sha256>(&root));
+        assert_eq!(empty.read(0x4FFC, 4), Some(&[0; 4][..]));
+        assert!(memory.prove(0x5000).is_none());
+
+        let mut forged = proof.clone();
+        forged.data[0] = 9;
+        assert!(!forged.verify::<Sha256>(&root));
+        forged = proof.clone();
+        forged.page = 2;
+        assert!(!forged.verify::<Sha256>(&root));
+
+        memory.store(0x3000, &[9]);
+        assert!(!proof.verify::<Sha256>(&memory.root()));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut memory = MerkleMemory::<Sha256>::new(1 << 16);
+        let root = memory.root();
+
+        memory.store(0x20000, &[1]);
+        assert_eq!(memory.dirty_pages(), 0);
+        assert_eq!(memory.root(), root);
+
+        memory.store((1 << 16) - 1, &[1, 2]);
+        assert_eq!(memory.dirty_pages(), 1);
+        assert_eq!(memory.root(), memory.memory().root::<Sha256>());
+        assert_ne!(memory.root(), root);
+
+        // Proof of page far beyond any address reads nothing.
+        let mut proof = memory.prove(0).unwrap();
+        proof.page = u64::MAX;
+        assert_eq!(proof.read(0, 1), None);
+    }
+}
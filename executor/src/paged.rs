@@ -0,0 +1,365 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{cell::RefCell, marker::PhantomData};
+
+use tangram_instruction::{Memory, MemoryMut};
+
+use crate::Hasher;
+
+/// Page size of [`PagedMemory`].
+pub const PAGE_SIZE: usize = 4096;
+
+/// Bytes of next page mirrored at the end of each page, so any load returns one slice.
+const PAGE_TAIL: usize = u8::MAX as usize;
+
+type Page = [u8; PAGE_SIZE + PAGE_TAIL];
+
+static ZERO: [u8; PAGE_TAIL] = [0; PAGE_TAIL];
+
+/// Sparse memory allocating pages on write.
+///
+/// Pages are shared between clones and copied on write, so cloning is cheap.
+/// Missing pages read as zero, and stores beyond size are ignored, instructions
+/// raise access faults before reaching them. Writing first bytes of a page also allocates the
+/// previous page, which keeps a copy of them.
+#[derive(Clone)]
+pub struct PagedMemory<R = u32> {
+    pages: BTreeMap<u64, Arc<Page>>,
+    size: u64,
+    /// Pages accessed since tracking started.
+    touched: Option<RefCell<BTreeSet<u64>>>,
+    marker: PhantomData<R>,
+}
+
+impl<R> PagedMemory<R> {
+    /// Memory of `size` bytes, all zero.
+    pub fn new(size: u64) -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            size,
+            touched: None,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Index and data of allocated pages, in order.
+    pub fn pages(&self) -> impl Iterator<Item = (u64, &[u8; PAGE_SIZE])> {
+        self.pages
+            .iter()
+            .map(|(i, p)| (*i, p[..PAGE_SIZE].try_into().unwrap()))
+    }
+
+    /// Number of pages shared with `other`.
+    pub fn shared_pages(&self, other: &Self) -> usize {
+        self.pages
+            .iter()
+            .filter(|(i, p)| other.pages.get(i).is_some_and(|o| Arc::ptr_eq(p, o)))
+            .count()
+    }
+
+    /// Start recording indexes of accessed pages, clearing recorded ones.
+    pub fn track_pages(&mut self) {
+        self.touched = Some(RefCell::default());
+    }
+
+    /// Stop recording and return indexes of accessed pages, in order.
+    pub fn take_touched(&mut self) -> Vec<u64> {
+        self.touched
+            .take()
+            .map(|t| t.into_inner().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Copy of `pages` only, other pages read as zero.
+    pub fn subset(&self, pages: &[u64]) -> Self {
+        let mut memory = Self::new(self.size);
+        for index in pages {
+            if let Some(page) = self.pages.get(index) {
+                memory.pages.insert(*index, page.clone());
+            }
+        }
+
+        memory
+    }
+
+    /// Root of Merkle tree with one leaf for each page, missing pages are zero.
+    ///
+    /// Leaves are padded with zero pages to a power of two.
+    pub fn root<H: Hasher>(&self) -> H::Digest {
+        let depth = self.depth();
+        self.subtree::<H>(depth, 0, &zero_hashes::<H>(depth))
+    }
+
+    /// Depth of Merkle tree, see [`PagedMemory::root`].
+    pub(crate) fn depth(&self) -> u32 {
+        let count = self.size.div_ceil(PAGE_SIZE as u64).max(1);
+        count.next_power_of_two().trailing_zeros()
+    }
+
+    /// Data of page `index` if it is allocated, without tracking access.
+    pub(crate) fn page(&self, index: u64) -> Option<&[u8; PAGE_SIZE]> {
+        self.pages
+            .get(&index)
+            .map(|p| p[..PAGE_SIZE].try_into().unwrap())
+    }
+
+    /// Root of subtree at `level` above leaves, covering pages from `first`.
+    fn subtree<H: Hasher>(&self, level: u32, first: u64, zero: &[H::Digest]) -> H::Digest {
+        let end = first + (1 << level);
+        if self.pages.range(first..end).next().is_none() {
+            return zero[level as usize];
+        }
+
+        if level == 0 {
+            return H::leaf(&self.pages[&first][..PAGE_SIZE]);
+        }
+
+        let half = 1 << (level - 1);
+        let left = self.subtree::<H>(level - 1, first, zero);
+        let right = self.subtree::<H>(level - 1, first + half, zero);
+        H::node(&left, &right)
+    }
+
+    fn touch(&self, pos: u64, length: usize) {
+        if let Some(touched) = &self.touched {
+            let last = pos + length.max(1) as u64 - 1;
+            touched
+                .borrow_mut()
+                .extend(pos / PAGE_SIZE as u64..=last / PAGE_SIZE as u64);
+        }
+    }
+
+    fn read(&self, pos: u64, length: usize) -> &[u8] {
+        self.touch(pos, length);
+
+        let offset = (pos % PAGE_SIZE as u64) as usize;
+
+        match self.pages.get(&(pos / PAGE_SIZE as u64)) {
+            Some(page) => &page[offset..offset + length],
+            None => &ZERO[..length],
+        }
+    }
+
+    fn page_mut(&mut self, index: u64) -> &mut Page {
+        let page = self
+            .pages
+            .entry(index)
+            .or_insert_with(|| Arc::new([0; PAGE_SIZE + PAGE_TAIL]));
+
+        Arc::make_mut(page)
+    }
+
+    /// Write `data` at `pos`, bytes beyond size are dropped, as they are out of
+    /// state committed by [`PagedMemory::root`].
+    fn write(&mut self, mut pos: u64, data: &[u8]) {
+        let end = pos.saturating_add(data.len() as u64).min(self.size);
+        if pos >= end {
+            return;
+        }
+        let mut data = &data[..(end - pos) as usize];
+        self.touch(pos, data.len());
+
+        while !data.is_empty() {
+            let index = pos / PAGE_SIZE as u64;
+            let offset = (pos % PAGE_SIZE as u64) as usize;
+            let len = data.len().min(PAGE_SIZE - offset);
+            let (head, rest) = data.split_at(len);
+
+            self.page_mut(index)[offset..offset + len].copy_from_slice(head);
+
+            // Keep mirror in previous page.
+            if offset < PAGE_TAIL && index > 0 {
+                let end = (offset + len).min(PAGE_TAIL);
+                let prev = self.page_mut(index - 1);
+                prev[PAGE_SIZE + offset..PAGE_SIZE + end].copy_from_slice(&head[..end - offset]);
+            }
+
+            pos += len as u64;
+            data = rest;
+        }
+    }
+
+    /// Append image of memory to `out`.
+    ///
+    /// Layout is `size: u64, count: u64`, then `index: u64, data: [u8; PAGE_SIZE]` for
+    /// each allocated page, integers are little-endian.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.extend_from_slice(&(self.pages.len() as u64).to_le_bytes());
+
+        for (index, data) in self.pages() {
+            out.extend_from_slice(&index.to_le_bytes());
+            out.extend_from_slice(data);
+        }
+    }
+
+    /// Decode image written by [`PagedMemory::encode`], return it with consumed length.
+    pub fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let u64_at = |pos: usize| -> Option<u64> {
+            let b = bytes.get(pos..pos + 8)?;
+            Some(u64::from_le_bytes(b.try_into().ok()?))
+        };
+
+        let mut memory = Self::new(u64_at(0)?);
+        let count = u64_at(8)?;
+        let mut pos = 16;
+
+        for _ in 0..count {
+            let index = u64_at(pos)?;
+            let data = bytes.get(pos + 8..pos + 8 + PAGE_SIZE)?;
+            if index.checked_mul(PAGE_SIZE as u64)? >= memory.size {
+                return None;
+            }
+
+            let mut page = [0; PAGE_SIZE + PAGE_TAIL];
+            page[..PAGE_SIZE].copy_from_slice(data);
+            memory.pages.insert(index, Arc::new(page));
+            pos += 8 + PAGE_SIZE;
+        }
+
+        // Rebuild mirrors of next pages.
+        let indexes: Vec<u64> = memory.pages.keys().copied().collect();
+        for index in indexes {
+            if let Some(next) = memory.pages.get(&(index + 1)).cloned() {
+                memory.page_mut(index)[PAGE_SIZE..].copy_from_slice(&next[..PAGE_TAIL]);
+            }
+        }
+
+        Some((memory, pos))
+    }
+}
+
+/// Root of empty subtree at each level up to `depth`.
+pub(crate) fn zero_hashes<H: Hasher>(depth: u32) -> Vec<H::Digest> {
+    let mut zero = Vec::with_capacity(depth as usize + 1);
+    zero.push(H::leaf(&[0; PAGE_SIZE]));
+    for level in 0..depth as usize {
+        zero.push(H::node(&zero[level], &zero[level]));
+    }
+    zero
+}
+
+macro_rules! impl_memory {
+    ($reg:ty) => {
+        impl Memory for PagedMemory<$reg> {
+            type Register = $reg;
+
+            fn length(&self) -> Self::Register {
+                self.size as $reg
+            }
+
+            fn load(&self, pos: Self::Register, length: u8) -> &[u8] {
+                self.read(pos as u64, length as usize)
+            }
+
+            fn contains(&self, pos: Self::Register, length: u8) -> bool {
+                (pos as u64)
+                    .checked_add(length as u64)
+                    .is_some_and(|end| end <= self.size)
+            }
+        }
+
+        impl MemoryMut for PagedMemory<$reg> {
+            fn store(&mut self, pos: Self::Register, data: &[u8]) {
+                self.write(pos as u64, data)
+            }
+        }
+    };
+}
+
+impl_memory!(u32);
+impl_memory!(u64);
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use tangram_instruction::{
+        riscv32i::{assemble, RV32iBaseInst},
+        Memory, MemoryMut,
+    };
+
+    use super::{PagedMemory, PAGE_SIZE};
+    use crate::{Error, Executor, Sha256};
+
+    #[test]
+    fn test_cross_page() {
+        let mut memory = PagedMemory::<u32>::new(1 << 20);
+        let pos = 3 * PAGE_SIZE as u32 - 2;
+
+        memory.store(pos, &[1, 2, 3, 4]);
+        assert_eq!(memory.load(pos, 4), &[1, 2, 3, 4]);
+        assert_eq!(memory.load(pos + 2, 2), &[3, 4]);
+        assert_eq!(memory.load(0x8000, 4), &[0; 4]);
+
+        memory.store(pos + 3, &[5]);
+        assert_eq!(memory.load(pos, 8), &[1, 2, 3, 5, 0, 0, 0, 0]);
+        assert!(memory.contains((1 << 20) - 4, 4));
+        assert!(!memory.contains((1 << 20) - 3, 4));
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let mut memory = PagedMemory::<u32>::new(1 << 20);
+        memory.store(0x10, &[1]);
+        memory.store(0x3100, &[2]);
+
+        let fork = memory.clone();
+        assert_eq!(memory.shared_pages(&fork), 2);
+
+        memory.store(0x3101, &[3]);
+        assert_eq!(memory.shared_pages(&fork), 1);
+        assert_eq!(fork.load(0x3100, 2), &[2, 0]);
+        assert_eq!(memory.load(0x3100, 2), &[2, 3]);
+
+        // Previous page keeps copy of first bytes.
+        memory.store(0x4000, &[4]);
+        assert_eq!(memory.pages().count(), 3);
+        assert_eq!(memory.load(0x3FFF, 2), &[0, 4]);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut memory = PagedMemory::<u32>::new(1 << 16);
+        let root = memory.root::<Sha256>();
+
+        memory.store(0x20000, &[1, 2, 3, 4]);
+        assert_eq!(memory.pages().count(), 0);
+        assert_eq!(memory.root::<Sha256>(), root);
+
+        // Part within memory is kept, and round-trips.
+        memory.store((1 << 16) - 2, &[1, 2, 3, 4]);
+        assert_ne!(memory.root::<Sha256>(), root);
+        let mut bytes = Vec::new();
+        memory.encode(&mut bytes);
+        let (decoded, _) = PagedMemory::<u32>::decode(&bytes).unwrap();
+        assert_eq!(decoded.load((1 << 16) - 2, 2), &[1, 2]);
+        assert_eq!(decoded.root::<Sha256>(), memory.root::<Sha256>());
+
+        // Guest stores beyond memory fault.
+        let mut code = [0u8; 16];
+        assemble(
+            "lui a1, 0x20
+sw a1, 0(a1)",
+            0,
+            &mut code,
+        )
+        .unwrap();
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, PagedMemory::<u32>::new(1 << 16), ());
+        executor.step(4).unwrap();
+        let fault = tangram_instruction::Error::StoreAccessFault {
+            addr: 0x20000,
+            pc: 4,
+        };
+        assert!(matches!(executor.step(4), Err(Error::InstructionError(e)) if e == fault));
+        assert_eq!(executor.memory().root::<Sha256>(), root);
+    }
+}
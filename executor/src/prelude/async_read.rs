@@ -1,3 +1,4 @@
+#[allow(async_fn_in_trait)]
 pub trait AsyncBytecodeReader {
     type Error;
 
@@ -1,7 +1,94 @@
-use tangram_instruction::{Instruction, MemoryMut};
+use tangram_instruction::{Error, Instruction, MemoryMut};
+
+use crate::TraceRecord;
+
+/// Returned by hooks of [`Monitor`] to control executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Control {
+    #[default]
+    Continue,
+    /// Stop executor, it returns [`Error::Halted`](crate::Error::Halted).
+    Halt,
+}
 
 pub trait Monitor<I: Instruction> {
-    fn monitor<M>(&mut self, inst: &I, pc: &I::Register, regs: &[I::Register], memory: &M)
+    /// Whether executor should build [`TraceRecord`] for [`Monitor::record`].
+    ///
+    /// Building records has a cost on each step, so it is off by default.
+    const RECORD: bool = false;
+
+    /// Called after instruction is executed.
+    fn monitor<M>(
+        &mut self,
+        inst: &I,
+        pc: &I::Register,
+        regs: &[I::Register],
+        memory: &M,
+    ) -> Control
     where
         M: MemoryMut<Register = I::Register>;
+
+    /// Receive effects of each executed instruction, only called when `RECORD` is true.
+    fn record(&mut self, _record: &TraceRecord) -> Control {
+        Control::Continue
+    }
+
+    /// Called before instruction is executed, halt here leaves state untouched.
+    fn before_execute(&mut self, _inst: &I, _pc: &I::Register, _regs: &[I::Register]) -> Control {
+        Control::Continue
+    }
+
+    /// Called after instruction loads `data` from `addr`.
+    ///
+    /// Halt cancels the instruction, pc and registers are restored.
+    fn on_load(&mut self, _addr: &I::Register, _data: &[u8]) -> Control {
+        Control::Continue
+    }
+
+    /// Called before instruction stores `data` to `addr`.
+    ///
+    /// Halt cancels the instruction, memory isn't written and pc and registers are restored.
+    fn on_store(&mut self, _addr: &I::Register, _data: &[u8]) -> Control {
+        Control::Continue
+    }
+
+    /// Called when decoding or executing instruction at `pc` fails, except `ecall`.
+    ///
+    /// Executor returns the error unless halted.
+    fn on_trap(&mut self, _pc: &I::Register, _error: &Error) -> Control {
+        Control::Continue
+    }
+
+    /// Called on `ecall` at `pc`, executor returns [`Error::EnvironmentCall`] unless halted
+    /// or it traps `ecall`, see [`Executor::set_trap_ecall`](crate::Executor::set_trap_ecall).
+    fn on_syscall(&mut self, _pc: &I::Register, _regs: &[I::Register]) -> Control {
+        Control::Continue
+    }
+
+    /// Read CSR of guest other than machine CSRs of hart in executor, `None` if it
+    /// doesn't exist.
+    fn read_csr(&self, _csr: u16) -> Option<u64> {
+        None
+    }
+
+    /// Write CSR of guest other than machine CSRs of hart in executor, return
+    /// false if it doesn't exist or is read-only.
+    fn write_csr(&mut self, _csr: u16, _value: u64) -> bool {
+        false
+    }
+}
+
+impl<I: Instruction> Monitor<I> for () {
+    fn monitor<M>(
+        &mut self,
+        _inst: &I,
+        _pc: &I::Register,
+        _regs: &[I::Register],
+        _memory: &M,
+    ) -> Control
+    where
+        M: MemoryMut<Register = I::Register>,
+    {
+        Control::Continue
+    }
 }
@@ -1,7 +1,84 @@
+use core::marker::PhantomData;
+
+use tangram_instruction::Memory;
+
 pub trait BytecodeReader {
     type Register;
 
     type Error;
 
     fn read(&mut self, offset: &Self::Register, length: u8) -> Result<&[u8], Self::Error>;
+
+    /// Read instruction for executor, whose memory is `memory`.
+    ///
+    /// Readers of separate code read themselves, [`MemoryReader`] reads memory.
+    fn fetch<'a, M>(
+        &'a mut self,
+        _memory: &'a M,
+        offset: &Self::Register,
+        length: u8,
+    ) -> Result<&'a [u8], Self::Error>
+    where
+        M: Memory<Register = Self::Register>,
+    {
+        self.read(offset, length)
+    }
+}
+
+impl<const N: usize> BytecodeReader for [u8; N] {
+    type Register = u32;
+
+    type Error = tangram_instruction::Error;
+
+    fn read(&mut self, offset: &Self::Register, length: u8) -> Result<&[u8], Self::Error> {
+        let pos = *offset as usize;
+        let end = pos + length as usize;
+
+        self.get(pos..end)
+            .ok_or(tangram_instruction::Error::ErrBytecodeLengthNotEnough)
+    }
+}
+
+/// Reader fetching instructions from memory of executor, like harts with code
+/// and data in one memory.
+///
+/// Code stored by guest runs once it is fetched, after `fence.i` when decode
+/// cache is enabled. It has no bytes of its own, so `read` always fails.
+pub struct MemoryReader<R>(PhantomData<R>);
+
+impl<R> MemoryReader<R> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R> Default for MemoryReader<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Copy> BytecodeReader for MemoryReader<R> {
+    type Register = R;
+
+    type Error = tangram_instruction::Error;
+
+    fn read(&mut self, _offset: &R, _length: u8) -> Result<&[u8], Self::Error> {
+        Err(tangram_instruction::Error::ErrBytecodeLengthNotEnough)
+    }
+
+    fn fetch<'a, M>(
+        &'a mut self,
+        memory: &'a M,
+        offset: &R,
+        length: u8,
+    ) -> Result<&'a [u8], Self::Error>
+    where
+        M: Memory<Register = R>,
+    {
+        match memory.contains(*offset, length) {
+            true => Ok(memory.load(*offset, length)),
+            false => Err(tangram_instruction::Error::ErrBytecodeLengthNotEnough),
+        }
+    }
 }
@@ -0,0 +1,302 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use tangram_instruction::{riscv32i::RV32iBaseInst, Instruction, MemoryMut, Reg32};
+
+use crate::{Control, Elf, Monitor};
+
+/// Name of frames outside of any known function.
+const UNKNOWN: &str = "[unknown]";
+
+/// Function symbols for resolving pc.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    /// Sorted by start address, as `(start, end, name)`.
+    funcs: Vec<(u64, u64, String)>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Function symbols of ELF file, symbols without size are skipped.
+    pub fn from_elf(elf: &Elf) -> Self {
+        let mut table = Self::new();
+        for s in elf.symbols().filter(|s| s.is_function() && s.size != 0) {
+            table.insert(s.name, s.value, s.size);
+        }
+
+        table
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u64, size: u64) {
+        let i = self.funcs.partition_point(|f| f.0 < addr);
+        self.funcs
+            .insert(i, (addr, addr.saturating_add(size), name.to_string()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.funcs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.funcs.is_empty()
+    }
+
+    /// Index of function containing `pc`.
+    fn index(&self, pc: u64) -> Option<usize> {
+        let i = self.funcs.partition_point(|f| f.0 <= pc).checked_sub(1)?;
+        (pc < self.funcs[i].1).then_some(i)
+    }
+
+    /// Name of function containing `pc`.
+    pub fn resolve(&self, pc: u64) -> Option<&str> {
+        self.index(pc).map(|i| self.funcs[i].2.as_str())
+    }
+
+    fn name(&self, index: Option<usize>) -> &str {
+        index.map_or(UNKNOWN, |i| self.funcs[i].2.as_str())
+    }
+}
+
+/// Node of call tree.
+struct Frame {
+    func: Option<usize>,
+    parent: usize,
+    children: BTreeMap<Option<usize>, usize>,
+    count: u64,
+}
+
+/// Root of call tree, it has no function.
+const ROOT: usize = 0;
+
+/// Monitor attributing every retired instruction to its call stack.
+///
+/// Calls are `jal`/`jalr` linking `ra`, returns are `ret`. Jumps without link into
+/// another function are taken as tail calls and replace current frame.
+pub struct ProfileMonitor {
+    symbols: SymbolTable,
+    frames: Vec<Frame>,
+    current: Option<usize>,
+}
+
+impl ProfileMonitor {
+    pub fn new(symbols: SymbolTable) -> Self {
+        let root = Frame {
+            func: None,
+            parent: ROOT,
+            children: BTreeMap::new(),
+            count: 0,
+        };
+
+        Self {
+            symbols,
+            frames: alloc::vec![root],
+            current: None,
+        }
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Depth of current call stack.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut frame = self.current.unwrap_or(ROOT);
+        while frame != ROOT {
+            depth += 1;
+            frame = self.frames[frame].parent;
+        }
+
+        depth
+    }
+
+    /// Child of `parent` calling function containing `pc`.
+    fn child(&mut self, parent: usize, pc: u64) -> usize {
+        let func = self.symbols.index(pc);
+        if let Some(i) = self.frames[parent].children.get(&func) {
+            return *i;
+        }
+
+        let i = self.frames.len();
+        self.frames.push(Frame {
+            func,
+            parent,
+            children: BTreeMap::new(),
+            count: 0,
+        });
+        self.frames[parent].children.insert(func, i);
+        i
+    }
+
+    /// Retired instructions of each function, excluding callees, in order of address.
+    pub fn functions(&self) -> Vec<(&str, u64)> {
+        let mut counts = BTreeMap::new();
+        for frame in self.frames.iter().skip(1) {
+            *counts.entry(frame.func).or_insert(0) += frame.count;
+        }
+
+        counts
+            .into_iter()
+            .filter(|(_, c)| *c != 0)
+            .map(|(f, c)| (self.symbols.name(f), c))
+            .collect()
+    }
+
+    /// Write stacks in folded format of flamegraph, as `outer;inner count` per line.
+    pub fn write_folded<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        let mut path = Vec::new();
+        self.write_frame(out, ROOT, &mut path)
+    }
+
+    fn write_frame<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        index: usize,
+        path: &mut Vec<usize>,
+    ) -> fmt::Result {
+        let frame = &self.frames[index];
+
+        if frame.count != 0 {
+            for (i, f) in path.iter().enumerate() {
+                if i != 0 {
+                    out.write_char(';')?;
+                }
+                out.write_str(self.symbols.name(self.frames[*f].func))?;
+            }
+            writeln!(out, " {}", frame.count)?;
+        }
+
+        for child in frame.children.values() {
+            path.push(*child);
+            self.write_frame(out, *child, path)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+impl<I, R> Monitor<RV32iBaseInst<I>> for ProfileMonitor
+where
+    I: Instruction<Register = R>,
+    R: Reg32 + Clone,
+{
+    fn before_execute(&mut self, _inst: &RV32iBaseInst<I>, pc: &R, _regs: &[R]) -> Control {
+        if self.current.is_none() {
+            self.current = Some(self.child(ROOT, pc.reg32() as u64));
+        }
+
+        Control::Continue
+    }
+
+    fn monitor<M>(&mut self, inst: &RV32iBaseInst<I>, pc: &R, _regs: &[R], _memory: &M) -> Control
+    where
+        M: MemoryMut<Register = R>,
+    {
+        let current = match self.current {
+            Some(c) => c,
+            None => return Control::Continue,
+        };
+        self.frames[current].count += 1;
+
+        let pc = pc.reg32() as u64;
+        let (link, ret) = match inst {
+            RV32iBaseInst::Jal(i) => (i.rd() == 1, false),
+            RV32iBaseInst::Jalr(i) => (i.rd() == 1, i.rd() == 0 && i.rs1() == 1),
+            _ => return Control::Continue,
+        };
+
+        let parent = self.frames[current].parent;
+        self.current = Some(if link {
+            self.child(current, pc)
+        } else if ret && parent != ROOT {
+            parent
+        } else if self.symbols.index(pc) != self.frames[current].func {
+            // Tail call, or return from outermost frame.
+            self.child(parent, pc)
+        } else {
+            current
+        });
+
+        Control::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::String, vec};
+
+    use tangram_instruction::riscv32i::{assemble, RV32iBaseInst};
+
+    use crate::{Executor, ProfileMonitor, SymbolTable};
+
+    const PROGRAM: &str = "
+    main:
+        li s0, 2
+    loop:
+        jal double
+        addi s0, s0, -1
+        bnez s0, loop
+        ecall
+    double:
+        mv t0, ra
+        jal inc
+        mv ra, t0
+        j inc
+    inc:
+        addi a0, a0, 1
+        ret
+    ";
+
+    fn symbols() -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        symbols.insert("double", 20, 16);
+        symbols.insert("inc", 36, 8);
+        symbols.insert("main", 0, 20);
+        symbols
+    }
+
+    #[test]
+    fn test_symbol_table() {
+        let symbols = symbols();
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.resolve(0), Some("main"));
+        assert_eq!(symbols.resolve(24), Some("double"));
+        assert_eq!(symbols.resolve(40), Some("inc"));
+        assert_eq!(symbols.resolve(44), None);
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let monitor = ProfileMonitor::new(symbols());
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], monitor);
+        assert!(executor.run(4).is_err());
+        assert_eq!(executor.regs()[10], 4);
+
+        let monitor = executor.monitor();
+        assert_eq!(monitor.depth(), 1);
+        assert_eq!(
+            monitor.functions(),
+            vec![("main", 7), ("double", 8), ("inc", 8)]
+        );
+
+        let mut folded = String::new();
+        monitor.write_folded(&mut folded).unwrap();
+        assert_eq!(
+            folded,
+            "main 7\nmain;double 8\nmain;double;inc 4\nmain;inc 4\n"
+        );
+    }
+}
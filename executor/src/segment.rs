@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use tangram_instruction::{Instruction, MemoryMut};
+
+use crate::{BytecodeReader, Error, Executor, Extension, Hasher, Monitor, Outcome, PagedMemory};
+
+/// State of executor between two segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentBoundary<const RS: usize, R, D> {
+    pub pc: R,
+    pub regs: [R; RS],
+    /// Merkle root of memory, see [`PagedMemory::root`].
+    pub root: D,
+}
+
+/// Part of execution which can be verified or re-executed alone.
+#[derive(Clone)]
+pub struct ExecSegment<const RS: usize, R, D> {
+    pub start: SegmentBoundary<RS, R, D>,
+    pub end: SegmentBoundary<RS, R, D>,
+    /// Executed steps, one cycle each.
+    pub cycles: u64,
+    /// Indexes of pages read or written, in order.
+    pub pages: Vec<u64>,
+    /// Touched pages at start of segment, enough to re-execute it.
+    pub memory: PagedMemory<R>,
+}
+
+/// Segment with why it ends, returned by [`Executor::run_segment`].
+pub type SegmentRun<const RS: usize, R, D, E> = (ExecSegment<RS, R, D>, Result<Outcome, Error<E>>);
+
+impl<const RS: usize, I, R, MM, E, X> Executor<RS, I, R, PagedMemory<I::Register>, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy + Into<u64> + TryFrom<u64>,
+    R: BytecodeReader<Register = I::Register, Error = E>,
+    E: Debug,
+    PagedMemory<I::Register>: MemoryMut<Register = I::Register>,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    /// Execute at most `cycles` steps as one segment, breakpoints are ignored.
+    ///
+    /// Result is [`Outcome::Stepped`] if segment is full, otherwise why execution
+    /// stopped early. Segment ends before a watched or failed instruction.
+    pub fn run_segment<H: Hasher>(
+        &mut self,
+        bytes_len: u8,
+        cycles: u64,
+    ) -> SegmentRun<RS, I::Register, H::Digest, E> {
+        let start = self.boundary::<H>();
+        let memory = self.memory().clone();
+        self.memory_mut().track_pages();
+
+        let mut done = 0;
+        let mut result = Ok(Outcome::Stepped);
+        while done < cycles {
+            match self.step(bytes_len) {
+                Ok(Outcome::Stepped) => done += 1,
+                r => {
+                    result = r;
+                    break;
+                }
+            }
+        }
+
+        let pages = self.memory_mut().take_touched();
+        let segment = ExecSegment {
+            start,
+            end: self.boundary::<H>(),
+            cycles: done,
+            memory: memory.subset(&pages),
+            pages,
+        };
+
+        (segment, result)
+    }
+
+    fn boundary<H: Hasher>(&self) -> SegmentBoundary<RS, I::Register, H::Digest> {
+        SegmentBoundary {
+            pc: *self.pc(),
+            regs: *self.regs(),
+            root: self.memory().root::<H>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use tangram_instruction::{
+        riscv32i::{assemble, RV32iBaseInst},
+        Memory,
+    };
+
+    use crate::{Error, Executor, Hasher, Outcome, PagedMemory, Sha256};
+
+    const PROGRAM: &str = "
+        li a0, 0
+        li a1, 0x1000
+        li t0, 0x1000
+        li t1, 5
+    loop:
+        addi a0, a0, 1
+        sw a0, 0(a1)
+        add a1, a1, t0
+        bne a0, t1, loop
+        sub a1, a1, t0
+        lw a2, 0(a1)
+        ecall
+    ";
+
+    type Vm = Executor<32, RV32iBaseInst<()>, [u8; 128], PagedMemory, ()>;
+
+    fn executor() -> Vm {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        Executor::new(code, PagedMemory::new(1 << 16), ())
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut executor = executor();
+        let mut segments = Vec::new();
+
+        loop {
+            let (segment, result) = executor.run_segment::<Sha256>(4, 6);
+            segments.push(segment);
+            match result {
+                Ok(Outcome::Stepped) => {}
+                Err(Error::InstructionError(tangram_instruction::Error::EnvironmentCall)) => break,
+                r => panic!("{:?}", r),
+            }
+        }
+
+        // 4 + 5 * 4 + 2 steps before ecall.
+        assert_eq!(segments.len(), 5);
+        assert_eq!(
+            segments.iter().map(|s| s.cycles).collect::<Vec<_>>(),
+            [6, 6, 6, 6, 2]
+        );
+        assert_eq!(executor.regs()[12], 5);
+
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(
+            segments[0].start.root,
+            PagedMemory::<u32>::new(1 << 16).root::<Sha256>()
+        );
+        assert_eq!(segments[4].end.root, executor.memory().root::<Sha256>());
+        assert_eq!(segments[0].pages, [1]);
+        assert_eq!(segments[2].pages, [3, 4]);
+        assert_eq!(segments[3].pages, [5]);
+        assert_eq!(segments[4].pages, [5]);
+
+        // Re-execute each segment from its boundary and touched pages.
+        for segment in segments.iter() {
+            let mut replay = self::executor();
+            replay.set_pc(segment.start.pc);
+            *replay.regs_mut() = segment.start.regs;
+            *replay.memory_mut() = segment.memory.clone();
+
+            let (again, _) = replay.run_segment::<Sha256>(4, 6);
+            assert_eq!(again.cycles, segment.cycles);
+            assert_eq!(
+                (again.end.pc, again.end.regs),
+                (segment.end.pc, segment.end.regs)
+            );
+            assert_eq!(again.pages, segment.pages);
+
+            for page in segment.pages.iter() {
+                let addr = (*page * 4096) as u32;
+                assert_eq!(
+                    replay.memory().load(addr, 4),
+                    executor.memory().load(addr, 4)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_root() {
+        let mut memory = PagedMemory::<u32>::new(3 * 4096);
+        let empty = memory.root::<Sha256>();
+
+        let zero = Sha256::leaf(&[0; 4096]);
+        let node = Sha256::node(&zero, &zero);
+        assert_eq!(empty, Sha256::node(&node, &node));
+
+        tangram_instruction::MemoryMut::store(&mut memory, 0x2000, &[1]);
+        let mut page = [0; 4096];
+        page[0] = 1;
+        let right = Sha256::node(&Sha256::leaf(&page), &zero);
+        assert_eq!(memory.root::<Sha256>(), Sha256::node(&node, &right));
+
+        // Mirrored copy in previous page isn't part of root.
+        assert_eq!(memory.pages().count(), 2);
+        tangram_instruction::MemoryMut::store(&mut memory, 0x2000, &[0]);
+        assert_eq!(memory.root::<Sha256>(), empty);
+    }
+}
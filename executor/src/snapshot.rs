@@ -0,0 +1,427 @@
+use tangram_instruction::Instruction;
+
+use crate::{
+    Executor, Extension, GuestTrap, Monitor, CSR_MCYCLE, CSR_MCYCLEH, CSR_MINSTRET, CSR_MINSTRETH,
+};
+
+/// Magic bytes at the beginning of serialised snapshot.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"TGS2";
+
+/// CSRs of [`Snapshot`], machine CSRs of hart and counters of monitor.
+///
+/// `mhartid` isn't part of it, restored hart keeps its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SnapshotCsrs {
+    /// `mtvec`, `None` while traps return errors.
+    pub trap_vector: Option<u64>,
+    pub mscratch: u64,
+    /// `mepc`, `mcause` and `mtval`, `None` until guest takes a trap or
+    /// writes one of them.
+    pub trap: Option<GuestTrap>,
+    /// `mcycle` of monitor, `None` if it has none.
+    pub mcycle: Option<u64>,
+    /// `minstret` of monitor, `None` if it has none.
+    pub minstret: Option<u64>,
+}
+
+/// State of executor at one point, restored by [`Executor::restore`].
+///
+/// Breakpoints, watchpoints and monitor aren't part of the state, except for
+/// counter CSRs of monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<const RS: usize, R, M, X = ()> {
+    pub pc: R,
+    pub regs: [R; RS],
+    pub csrs: SnapshotCsrs,
+    /// State of extensions, see [`Executor::ext`].
+    pub ext: X,
+    pub memory: M,
+}
+
+/// Read 64-bit counter of RV32 monitor from its `low` and `high` CSRs.
+fn read_counter<I: Instruction, MM: Monitor<I>>(monitor: &MM, low: u16, high: u16) -> Option<u64> {
+    let value = monitor.read_csr(low)?;
+    Some(value | monitor.read_csr(high).unwrap_or(0) << 32)
+}
+
+impl<const RS: usize, I, R, M, MM, X> Executor<RS, I, R, M, MM, X>
+where
+    I: Instruction,
+    I::Register: Default + Clone + Copy,
+    M: Clone,
+    MM: Monitor<I>,
+    X: Extension,
+{
+    /// Capture current state, cost depends on `Clone` of memory.
+    pub fn snapshot(&self) -> Snapshot<RS, I::Register, M, X> {
+        let csrs = self.csrs();
+
+        Snapshot {
+            pc: *self.pc(),
+            regs: *self.regs(),
+            csrs: SnapshotCsrs {
+                trap_vector: csrs.vector,
+                mscratch: csrs.scratch,
+                trap: csrs.trap,
+                mcycle: read_counter(self.monitor(), CSR_MCYCLE, CSR_MCYCLEH),
+                minstret: read_counter(self.monitor(), CSR_MINSTRET, CSR_MINSTRETH),
+            },
+            ext: self.ext().clone(),
+            memory: self.memory().clone(),
+        }
+    }
+
+    /// Restore state of `snapshot`, decoded instructions and recorded history
+    /// are dropped.
+    pub fn restore(&mut self, snapshot: &Snapshot<RS, I::Register, M, X>) {
+        self.set_pc(snapshot.pc);
+        *self.regs_mut() = snapshot.regs;
+        *self.ext_mut() = snapshot.ext.clone();
+        *self.memory_mut() = snapshot.memory.clone();
+
+        let csrs = self.csrs_mut();
+        csrs.vector = snapshot.csrs.trap_vector;
+        csrs.scratch = snapshot.csrs.mscratch;
+        csrs.trap = snapshot.csrs.trap;
+
+        let monitor = self.monitor_mut();
+        for (value, low, high) in [
+            (snapshot.csrs.mcycle, CSR_MCYCLE, CSR_MCYCLEH),
+            (snapshot.csrs.minstret, CSR_MINSTRET, CSR_MINSTRETH),
+        ] {
+            if let Some(value) = value {
+                monitor.write_csr(low, value & 0xFFFF_FFFF);
+                monitor.write_csr(high, value >> 32);
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        self.invalidate_decoded();
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod bytes {
+    use alloc::vec::Vec;
+    use core::mem::size_of;
+
+    use crate::{GuestTrap, PagedMemory};
+
+    use super::{Snapshot, SnapshotCsrs, SNAPSHOT_MAGIC};
+
+    /// Length of serialised [`SnapshotCsrs`].
+    const CSRS_LEN: usize = 9 + 8 + 25 + 9 + 9;
+
+    /// Append `value` as flag byte and `u64`, 0 if it is `None`.
+    fn push_opt(out: &mut Vec<u8>, value: Option<u64>) {
+        out.push(value.is_some() as u8);
+        out.extend_from_slice(&value.unwrap_or(0).to_le_bytes());
+    }
+
+    /// Reader of fields written by `SnapshotCsrs::encode`.
+    struct Fields<'a>(&'a [u8]);
+
+    impl Fields<'_> {
+        fn u64(&mut self) -> Option<u64> {
+            let (head, rest) = self.0.split_at_checked(8)?;
+            self.0 = rest;
+            Some(u64::from_le_bytes(head.try_into().ok()?))
+        }
+
+        fn flag(&mut self) -> Option<bool> {
+            let (head, rest) = self.0.split_first()?;
+            self.0 = rest;
+            match head {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            }
+        }
+
+        fn opt(&mut self) -> Option<Option<u64>> {
+            let some = self.flag()?;
+            let value = self.u64()?;
+            Some(some.then_some(value))
+        }
+    }
+
+    impl SnapshotCsrs {
+        /// Layout is `mtvec`, `mscratch`, then `mepc`, `mcause` and `mtval` behind
+        /// one flag, then `mcycle` and `minstret`. Optional values have a flag
+        /// byte before them, 1 if they are set.
+        fn encode(&self, out: &mut Vec<u8>) {
+            push_opt(out, self.trap_vector);
+            out.extend_from_slice(&self.mscratch.to_le_bytes());
+
+            let trap = self.trap.unwrap_or_default();
+            out.push(self.trap.is_some() as u8);
+            for v in [trap.epc, trap.cause, trap.tval] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+
+            push_opt(out, self.mcycle);
+            push_opt(out, self.minstret);
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            let mut f = Fields(bytes);
+
+            let trap_vector = f.opt()?;
+            let mscratch = f.u64()?;
+            let has_trap = f.flag()?;
+            let trap = GuestTrap {
+                epc: f.u64()?,
+                cause: f.u64()?,
+                tval: f.u64()?,
+            };
+
+            Some(Self {
+                trap_vector,
+                mscratch,
+                trap: has_trap.then_some(trap),
+                mcycle: f.opt()?,
+                minstret: f.opt()?,
+            })
+        }
+    }
+
+    impl<const RS: usize, R> Snapshot<RS, R, PagedMemory<R>>
+    where
+        R: Copy + Default + Into<u64> + TryFrom<u64>,
+    {
+        /// Serialise snapshot.
+        ///
+        /// Layout is [`SNAPSHOT_MAGIC`], `xlen: u8` in bytes, `count: u16` of registers,
+        /// pc and registers in `xlen` bytes, CSRs, then image of
+        /// [`PagedMemory::encode`]. CSRs are `mtvec`, `mscratch`, `mepc`, `mcause`,
+        /// `mtval`, `mcycle` and `minstret` as `u64`, unset `mtvec` and counters
+        /// have flag byte 0 before them and set ones 1, trap CSRs share one flag.
+        /// All integers are little-endian.
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let xlen = size_of::<R>();
+            let mut out = Vec::new();
+
+            out.extend_from_slice(&SNAPSHOT_MAGIC);
+            out.push(xlen as u8);
+            out.extend_from_slice(&(RS as u16).to_le_bytes());
+
+            for r in core::iter::once(&self.pc).chain(&self.regs) {
+                out.extend_from_slice(&(*r).into().to_le_bytes()[..xlen]);
+            }
+
+            self.csrs.encode(&mut out);
+            self.memory.encode(&mut out);
+            out
+        }
+
+        /// Deserialise snapshot, return `None` if bytes are invalid or register
+        /// layout doesn't match.
+        pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+            let xlen = size_of::<R>();
+
+            let bytes = bytes.strip_prefix(&SNAPSHOT_MAGIC)?;
+            if *bytes.first()? as usize != xlen {
+                return None;
+            }
+            if u16::from_le_bytes(bytes.get(1..3)?.try_into().ok()?) as usize != RS {
+                return None;
+            }
+
+            let mut regs = [R::default(); RS];
+            let mut values = bytes.get(3..3 + (RS + 1) * xlen)?.chunks(xlen).map(|c| {
+                let mut v = [0u8; 8];
+                v[..xlen].copy_from_slice(c);
+                R::try_from(u64::from_le_bytes(v)).ok()
+            });
+
+            let pc = values.next()??;
+            for r in regs.iter_mut() {
+                *r = values.next()??;
+            }
+
+            let start = 3 + (RS + 1) * xlen;
+            let csrs = SnapshotCsrs::decode(bytes.get(start..start + CSRS_LEN)?)?;
+
+            let (memory, len) = PagedMemory::decode(&bytes[start + CSRS_LEN..])?;
+            if start + CSRS_LEN + len != bytes.len() {
+                return None;
+            }
+
+            Some(Self {
+                pc,
+                regs,
+                csrs,
+                ext: (),
+                memory,
+            })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use alloc::vec::Vec;
+
+    use tangram_instruction::{
+        riscv::Illegal,
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvzicsr::RVCsrInst,
+        Memory, MemoryMut,
+    };
+
+    use crate::{
+        CounterMonitor, Executor, GuestTrap, MemoryReader, Outcome, PagedMemory, Snapshot,
+        CSR_MCAUSE, CSR_MINSTRET, CSR_MTVEC,
+    };
+
+    const PROGRAM: &str = "
+        li a0, 0
+        li a1, 0x1800
+        li t0, 0x1000
+    loop:
+        addi a0, a0, 1
+        sw a0, 0(a1)
+        add a1, a1, t0
+        j loop
+    ";
+
+    type Vm = Executor<32, RV32iBaseInst<()>, [u8; 128], PagedMemory, ()>;
+
+    fn executor() -> Vm {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        Executor::new(code, PagedMemory::new(1 << 20), ())
+    }
+
+    /// Run until `n` is stored.
+    fn run(executor: &mut Vm, n: u32) {
+        while executor.regs()[10] != n {
+            assert_eq!(executor.step(4).unwrap(), Outcome::Stepped);
+        }
+        executor.step(4).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut executor = executor();
+        run(&mut executor, 3);
+
+        let snapshot = executor.snapshot();
+        run(&mut executor, 8);
+
+        // Pages written before snapshot are still shared.
+        assert_eq!(executor.memory().shared_pages(&snapshot.memory), 3);
+        assert_eq!(executor.memory().load(0x8800, 4), &[8, 0, 0, 0]);
+
+        executor.restore(&snapshot);
+        assert_eq!(executor.regs()[10], 3);
+        assert_eq!(executor.memory().load(0x3800, 4), &[3, 0, 0, 0]);
+        assert_eq!(executor.memory().load(0x8800, 4), &[0; 4]);
+
+        run(&mut executor, 8);
+        assert_eq!(executor.memory().load(0x8800, 4), &[8, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_snapshot_bytes() {
+        let mut executor = executor();
+        run(&mut executor, 10);
+        executor.memory_mut().store(0xFFFFE, &[1, 2]);
+
+        let snapshot = executor.snapshot();
+        let bytes = snapshot.to_bytes();
+        let decoded = Snapshot::<32, u32, PagedMemory>::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.pc, snapshot.pc);
+        assert_eq!(decoded.regs, snapshot.regs);
+        assert_eq!(
+            decoded.memory.pages().collect::<Vec<_>>(),
+            snapshot.memory.pages().collect::<Vec<_>>()
+        );
+        assert_eq!(decoded.to_bytes(), bytes);
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(Snapshot::<32, u32, PagedMemory>::from_bytes(truncated).is_none());
+        assert!(Snapshot::<16, u32, PagedMemory>::from_bytes(&bytes).is_none());
+        assert!(Snapshot::<32, u64, PagedMemory<u64>>::from_bytes(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_csrs() {
+        // Illegal instruction at 8, handler at 16.
+        let mut code = [0u8; 128];
+        assemble(
+            "addi a0, a0, 1\naddi a0, a0, 1\nnop\nnop\naddi a1, a1, 1",
+            0,
+            &mut code,
+        )
+        .unwrap();
+        code[8..12].copy_from_slice(&0x0200_c58bu32.to_le_bytes());
+
+        let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+            Executor::new(code, PagedMemory::new(1 << 16), CounterMonitor::new());
+        executor.set_trap_vector(Some(16));
+        for _ in 0..3 {
+            executor.step(4).unwrap();
+        }
+        assert_eq!(*executor.pc(), 16);
+
+        let snapshot = executor.snapshot();
+        let trap = GuestTrap {
+            epc: 8,
+            cause: 2,
+            tval: 0x0200_c58b,
+        };
+        assert_eq!(snapshot.csrs.trap, Some(trap));
+        assert_eq!(snapshot.csrs.trap_vector, Some(16));
+        assert_eq!(snapshot.csrs.minstret, Some(2));
+
+        // Trap CSRs and counters are restored.
+        executor.step(4).unwrap();
+        executor.set_trap_vector(None);
+        executor.monitor_mut().counters_mut().instret = 1 << 40;
+        executor.restore(&snapshot);
+        assert_eq!(executor.last_trap(), Some(trap));
+        assert_eq!(executor.read_csr(CSR_MTVEC), Some(16));
+        assert_eq!(executor.read_csr(CSR_MINSTRET), Some(2));
+        assert_eq!(executor.monitor().counters().instret, 2);
+
+        let bytes = snapshot.to_bytes();
+        let decoded = Snapshot::<32, u32, PagedMemory>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.csrs, snapshot.csrs);
+        assert_eq!(decoded.to_bytes(), bytes);
+
+        // Flags are 0 or 1.
+        let mut invalid = bytes.clone();
+        invalid[4 + 3 + 33 * 4] = 2;
+        assert!(Snapshot::<32, u32, PagedMemory>::from_bytes(&invalid).is_none());
+
+        // Monitor without counters has none.
+        let executor = Vm::new([0u8; 128], PagedMemory::new(1 << 16), ());
+        let snapshot = executor.snapshot();
+        assert_eq!(snapshot.csrs.minstret, None);
+        assert_eq!(executor.read_csr(CSR_MCAUSE), Some(0));
+    }
+
+    #[test]
+    fn test_restore_drops_decoded() {
+        let mut memory = [0u8; 128];
+        assemble("addi a0, a0, 1", 0, &mut memory).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(MemoryReader::new(), memory, ());
+        let snapshot = executor.snapshot();
+
+        assemble("addi a0, a0, 10", 0, executor.memory_mut()).unwrap();
+        executor.set_decode_cache(true);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 10);
+
+        // Restored code runs instead of cached one.
+        executor.restore(&snapshot);
+        assert_eq!(executor.decoded_len(), 0);
+        executor.step(4).unwrap();
+        assert_eq!(executor.regs()[10], 1);
+    }
+}
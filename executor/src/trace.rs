@@ -0,0 +1,533 @@
+use core::fmt::{self, Write};
+
+use tangram_instruction::{Instruction, MemoryMut};
+
+use crate::{Control, Monitor};
+
+/// Magic bytes at the beginning of binary trace.
+pub const TRACE_MAGIC: [u8; 4] = *b"TGT2";
+
+/// Max number of memory accesses kept for one instruction.
+pub const TRACE_ACCESS_MAX: usize = 16;
+
+/// Length of one binary encoded memory access.
+const ACCESS_LEN: usize = 18;
+
+/// Max length of one binary encoded record.
+pub const TRACE_RECORD_MAX: usize = 1 + 8 + 4 + 9 + 1 + TRACE_ACCESS_MAX * ACCESS_LEN;
+
+const FLAG_RD: u8 = 1;
+const FLAG_MEM: u8 = 2;
+const FLAG_TRUNCATED: u8 = 4;
+
+/// Kind of memory access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessKind {
+    #[default]
+    Read,
+    Write,
+}
+
+/// Memory access of one instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemAccess {
+    pub kind: AccessKind,
+    pub addr: u64,
+    pub size: u8,
+    /// Little-endian value of accessed bytes.
+    pub value: u64,
+}
+
+/// Memory accesses of one instruction in order, up to [`TRACE_ACCESS_MAX`].
+///
+/// Adjacent accesses of the same kind are merged while they fit in 8 bytes,
+/// so a misaligned access split into bytes is one access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemAccesses {
+    len: u8,
+    items: [MemAccess; TRACE_ACCESS_MAX],
+    truncated: bool,
+}
+
+impl MemAccesses {
+    /// Append access, it is dropped and record is marked truncated if full.
+    pub fn push(&mut self, access: MemAccess) {
+        if let Some(last) = self.items[..self.len as usize].last_mut() {
+            let adjacent = last.kind == access.kind
+                && last.size > 0
+                && access.size > 0
+                && last.addr.wrapping_add(last.size as u64) == access.addr
+                && last.size + access.size <= 8;
+
+            if adjacent {
+                last.value |= access.value << (last.size * 8);
+                last.size += access.size;
+                return;
+            }
+        }
+
+        match self.items.get_mut(self.len as usize) {
+            Some(item) => {
+                *item = access;
+                self.len += 1;
+            }
+            None => self.truncated = true,
+        }
+    }
+
+    /// Whether accesses beyond [`TRACE_ACCESS_MAX`] are dropped.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl core::ops::Deref for MemAccesses {
+    type Target = [MemAccess];
+
+    fn deref(&self) -> &[MemAccess] {
+        &self.items[..self.len as usize]
+    }
+}
+
+impl From<MemAccess> for MemAccesses {
+    fn from(access: MemAccess) -> Self {
+        let mut r = Self::default();
+        r.push(access);
+        r
+    }
+}
+
+/// Effects of one executed instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// Program counter before execute.
+    pub pc: u64,
+    /// Raw instruction, little-endian.
+    pub inst: u32,
+    /// Destination register of instruction with its value after execute,
+    /// reported even if value is unchanged or register is `x0`.
+    pub rd: Option<(u8, u64)>,
+    /// Memory accesses.
+    pub mem: MemAccesses,
+}
+
+fn put(out: &mut [u8], pos: &mut usize, bytes: &[u8]) {
+    out[*pos..*pos + bytes.len()].copy_from_slice(bytes);
+    *pos += bytes.len();
+}
+
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, len: usize) -> Option<&'a [u8]> {
+    let r = bytes.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(r)
+}
+
+fn take_u64(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    let b = take(bytes, pos, 8)?;
+    Some(u64::from_le_bytes([
+        b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+    ]))
+}
+
+impl TraceRecord {
+    /// Encode record into compact binary format, return encoded length.
+    ///
+    /// Layout is `flags: u8, pc: u64, inst: u32`, then `rd: u8, value: u64`
+    /// if `FLAG_RD`, then `count: u8` and `kind: u8, addr: u64, size: u8,
+    /// value: u64` of each access if `FLAG_MEM`. Kind is 0 for read and 1 for
+    /// write. All integers are little-endian.
+    pub fn encode(&self, out: &mut [u8; TRACE_RECORD_MAX]) -> usize {
+        let mut flags = 0;
+        if self.rd.is_some() {
+            flags |= FLAG_RD;
+        }
+        if !self.mem.is_empty() {
+            flags |= FLAG_MEM;
+        }
+        if self.mem.is_truncated() {
+            flags |= FLAG_TRUNCATED;
+        }
+
+        let mut pos = 0;
+        put(out, &mut pos, &[flags]);
+        put(out, &mut pos, &self.pc.to_le_bytes());
+        put(out, &mut pos, &self.inst.to_le_bytes());
+
+        if let Some((rd, v)) = self.rd {
+            put(out, &mut pos, &[rd]);
+            put(out, &mut pos, &v.to_le_bytes());
+        }
+
+        if !self.mem.is_empty() {
+            put(out, &mut pos, &[self.mem.len() as u8]);
+        }
+        for m in self.mem.iter() {
+            put(out, &mut pos, &[(m.kind == AccessKind::Write) as u8]);
+            put(out, &mut pos, &m.addr.to_le_bytes());
+            put(out, &mut pos, &[m.size]);
+            put(out, &mut pos, &m.value.to_le_bytes());
+        }
+
+        pos
+    }
+
+    /// Decode one record, return it with consumed length.
+    pub fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let mut pos = 0;
+
+        let flags = take(bytes, &mut pos, 1)?[0];
+        let pc = take_u64(bytes, &mut pos)?;
+        let b = take(bytes, &mut pos, 4)?;
+        let inst = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+        let rd = if flags & FLAG_RD != 0 {
+            let rd = take(bytes, &mut pos, 1)?[0];
+            Some((rd, take_u64(bytes, &mut pos)?))
+        } else {
+            None
+        };
+
+        let mut mem = MemAccesses::default();
+        if flags & FLAG_MEM != 0 {
+            let count = take(bytes, &mut pos, 1)?[0];
+            if count as usize > TRACE_ACCESS_MAX {
+                return None;
+            }
+
+            for _ in 0..count {
+                let kind = match take(bytes, &mut pos, 1)?[0] {
+                    0 => AccessKind::Read,
+                    _ => AccessKind::Write,
+                };
+                let addr = take_u64(bytes, &mut pos)?;
+                let size = take(bytes, &mut pos, 1)?[0];
+                let value = take_u64(bytes, &mut pos)?;
+
+                // Encoded accesses are already merged.
+                mem.items[mem.len as usize] = MemAccess {
+                    kind,
+                    addr,
+                    size,
+                    value,
+                };
+                mem.len += 1;
+            }
+        }
+        mem.truncated = flags & FLAG_TRUNCATED != 0;
+
+        Some((Self { pc, inst, rd, mem }, pos))
+    }
+
+    /// Write fields of JSON object.
+    fn write_json_fields<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        write!(
+            w,
+            "\"pc\":\"{:#x}\",\"inst\":\"{:#010x}\"",
+            self.pc, self.inst
+        )?;
+
+        if let Some((rd, v)) = self.rd {
+            write!(w, ",\"rd\":{},\"rd_value\":\"{:#x}\"", rd, v)?;
+        }
+
+        if !self.mem.is_empty() {
+            w.write_str(",\"mem\":[")?;
+
+            for (i, m) in self.mem.iter().enumerate() {
+                let kind = match m.kind {
+                    AccessKind::Read => "read",
+                    AccessKind::Write => "write",
+                };
+
+                write!(
+                    w,
+                    "{}{{\"kind\":\"{}\",\"addr\":\"{:#x}\",\"size\":{},\"value\":\"{:#x}\"}}",
+                    if i == 0 { "" } else { "," },
+                    kind,
+                    m.addr,
+                    m.size,
+                    m.value
+                )?;
+            }
+
+            w.write_str("]")?;
+        }
+
+        if self.mem.is_truncated() {
+            w.write_str(",\"mem_truncated\":true")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// JSON object of record, without trailing newline.
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        self.write_json_fields(f)?;
+        f.write_str("}")
+    }
+}
+
+/// Iterator over records of binary trace.
+pub struct TraceReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> TraceReader<'a> {
+    /// Create reader, return `None` if [`TRACE_MAGIC`] is missing.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        let bytes = bytes.strip_prefix(&TRACE_MAGIC)?;
+
+        Some(Self { bytes })
+    }
+
+    /// Bytes not decoded yet, non-empty after iteration means trace is truncated.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for TraceReader<'a> {
+    type Item = TraceRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (r, len) = TraceRecord::decode(self.bytes)?;
+        self.bytes = &self.bytes[len..];
+        Some(r)
+    }
+}
+
+/// Output of trace
+pub trait TraceSink {
+    fn write(&mut self, bytes: &[u8]);
+}
+
+struct SinkWriter<'a, S>(&'a mut S);
+
+impl<'a, S: TraceSink> fmt::Write for SinkWriter<'a, S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Format of trace output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// [`TRACE_MAGIC`] followed by records encoded by [`TraceRecord::encode`].
+    Binary,
+    /// One JSON object per line, with `step` counted from 0.
+    JsonLines,
+}
+
+/// Monitor writing every executed instruction to a [`TraceSink`].
+pub struct TraceMonitor<S> {
+    sink: S,
+    format: TraceFormat,
+    steps: u64,
+}
+
+impl<S: TraceSink> TraceMonitor<S> {
+    pub fn new(mut sink: S, format: TraceFormat) -> Self {
+        if format == TraceFormat::Binary {
+            sink.write(&TRACE_MAGIC);
+        }
+
+        Self {
+            sink,
+            format,
+            steps: 0,
+        }
+    }
+
+    /// Number of recorded steps.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+impl<I: Instruction, S: TraceSink> Monitor<I> for TraceMonitor<S> {
+    const RECORD: bool = true;
+
+    fn monitor<M>(
+        &mut self,
+        _inst: &I,
+        _pc: &I::Register,
+        _regs: &[I::Register],
+        _memory: &M,
+    ) -> Control
+    where
+        M: MemoryMut<Register = I::Register>,
+    {
+        Control::Continue
+    }
+
+    fn record(&mut self, record: &TraceRecord) -> Control {
+        match self.format {
+            TraceFormat::Binary => {
+                let mut buf = [0u8; TRACE_RECORD_MAX];
+                let len = record.encode(&mut buf);
+                self.sink.write(&buf[..len]);
+            }
+            TraceFormat::JsonLines => {
+                let mut w = SinkWriter(&mut self.sink);
+                let _ = write!(w, "{{\"step\":{},", self.steps)
+                    .and_then(|_| record.write_json_fields(&mut w))
+                    .and_then(|_| w.write_str("}\n"));
+            }
+        }
+
+        self.steps += 1;
+
+        Control::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::{string::String, vec::Vec};
+
+    use tangram_instruction::{
+        riscv32i::{assemble, RV32iBaseInst},
+        riscvv::{RVVectorInst, VectorState},
+        Aligned, Alignment, IAlign, Misaligned,
+    };
+
+    use crate::{Error, Executor};
+
+    use super::{
+        AccessKind, MemAccess, TraceFormat, TraceMonitor, TraceReader, TraceSink, TRACE_MAGIC,
+    };
+
+    impl TraceSink for Vec<u8> {
+        fn write(&mut self, bytes: &[u8]) {
+            self.extend_from_slice(bytes)
+        }
+    }
+
+    const PROGRAM: &str = "
+        li a0, 5
+        sw a0, 64(zero)
+        lw a1, 64(zero)
+        ecall
+    ";
+
+    fn trace(format: TraceFormat) -> Vec<u8> {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let monitor = TraceMonitor::new(Vec::new(), format);
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], monitor);
+
+        let r = executor.run(4);
+        assert!(matches!(
+            r,
+            Err(Error::InstructionError(
+                tangram_instruction::Error::EnvironmentCall
+            ))
+        ));
+        assert_eq!(executor.monitor().steps(), 4);
+
+        executor.monitor().sink().clone()
+    }
+
+    #[test]
+    fn test_trace_json() {
+        let out = String::from_utf8(trace(TraceFormat::JsonLines)).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            r#"{"step":0,"pc":"0x0","inst":"0x00500513","rd":10,"rd_value":"0x5"}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"step":1,"pc":"0x4","inst":"0x04a02023","mem":[{"kind":"write","addr":"0x40","size":4,"value":"0x5"}]}"#
+        );
+        assert_eq!(
+            lines[2],
+            r#"{"step":2,"pc":"0x8","inst":"0x04002583","rd":11,"rd_value":"0x5","mem":[{"kind":"read","addr":"0x40","size":4,"value":"0x5"}]}"#
+        );
+        assert_eq!(lines[3], r#"{"step":3,"pc":"0xc","inst":"0x00000073"}"#);
+    }
+
+    #[test]
+    fn test_trace_binary() {
+        let out = trace(TraceFormat::Binary);
+        assert_eq!(out[..4], TRACE_MAGIC);
+
+        let mut reader = TraceReader::new(&out).unwrap();
+        let records: Vec<_> = reader.by_ref().collect();
+
+        assert!(reader.remaining().is_empty());
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[2].pc, 8);
+        assert_eq!(records[2].rd, Some((11, 5)));
+        assert_eq!(records[2].mem[0].kind, AccessKind::Read);
+        assert_eq!(records[3].inst, 0x73);
+    }
+
+    #[test]
+    fn test_trace_effects() {
+        let mut code = [0u8; 128];
+        assemble(
+            "addi a0, a0, 0\naddi zero, a0, 1\nsw a0, 65(zero)",
+            0,
+            &mut code,
+        )
+        .unwrap();
+        // `vsetivli zero, 4, e32, m1` and `vlse32.v v1, (a0), a1`
+        code[12..16].copy_from_slice(&0xc102_7057u32.to_le_bytes());
+        code[16..20].copy_from_slice(&0x0ab5_6087u32.to_le_bytes());
+
+        let alignment = Alignment {
+            access: Misaligned::Split,
+            ialign: IAlign::Word,
+        };
+        let memory = Aligned::new([0u8; 128], alignment);
+        let monitor = TraceMonitor::new(Vec::new(), TraceFormat::Binary);
+        let mut executor: Executor<32, RV32iBaseInst<RVVectorInst<()>>, _, _, _, VectorState> =
+            Executor::new(code, memory, monitor);
+        executor.regs_mut()[10] = 64;
+        executor.regs_mut()[11] = 8;
+        for _ in 0..5 {
+            executor.step(4).unwrap();
+        }
+
+        let out = executor.monitor().sink();
+        let records: Vec<_> = TraceReader::new(out).unwrap().collect();
+
+        // Unchanged register and `x0` are written too.
+        assert_eq!(records[0].rd, Some((10, 64)));
+        assert_eq!(records[1].rd, Some((0, 0)));
+
+        // Bytes of split store are one access.
+        let store = MemAccess {
+            kind: AccessKind::Write,
+            addr: 65,
+            size: 4,
+            value: 64,
+        };
+        assert_eq!(records[2].rd, None);
+        assert_eq!(records[2].mem[..], [store]);
+
+        // Vector registers aren't reported, every element is.
+        assert_eq!(records[3].rd, Some((0, 0)));
+        assert_eq!(records[4].rd, None);
+        let addrs: Vec<_> = records[4].mem.iter().map(|m| m.addr).collect();
+        assert_eq!(addrs, [64, 72, 80, 88]);
+    }
+}
@@ -0,0 +1,95 @@
+pub use tangram_instruction::riscvzicsr::{
+    CSR_MCAUSE, CSR_MEPC, CSR_MHARTID, CSR_MSCRATCH, CSR_MTVAL, CSR_MTVEC,
+};
+
+/// Exception code of misaligned jump target in `mcause`.
+pub const CAUSE_INSTRUCTION_MISALIGNED: u64 = 0;
+
+/// Exception code of illegal instruction in `mcause`.
+pub const CAUSE_ILLEGAL_INSTRUCTION: u64 = 2;
+
+/// Exception code of misaligned load in `mcause`.
+pub const CAUSE_LOAD_MISALIGNED: u64 = 4;
+
+/// Exception code of load beyond memory in `mcause`.
+pub const CAUSE_LOAD_ACCESS: u64 = 5;
+
+/// Exception code of misaligned store in `mcause`.
+pub const CAUSE_STORE_MISALIGNED: u64 = 6;
+
+/// Exception code of store beyond memory in `mcause`.
+pub const CAUSE_STORE_ACCESS: u64 = 7;
+
+/// Exception code of `ecall` from machine mode in `mcause`.
+pub const CAUSE_MACHINE_ECALL: u64 = 11;
+
+/// Trap taken by guest, values of machine trap CSRs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GuestTrap {
+    pub epc: u64,
+    pub cause: u64,
+    pub tval: u64,
+}
+
+/// Machine CSRs of a hart, set by host or by guest CSR instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct HartCsrs {
+    /// `mhartid`, read-only.
+    pub(crate) hart_id: u64,
+    /// `mtvec`, traps return errors while it is `None`.
+    pub(crate) vector: Option<u64>,
+    pub(crate) scratch: u64,
+    /// `mepc`, `mcause` and `mtval`, `None` until guest takes a trap or
+    /// writes one of them.
+    pub(crate) trap: Option<GuestTrap>,
+}
+
+impl HartCsrs {
+    pub(crate) fn read(&self, csr: u16) -> Option<u64> {
+        let trap = self.trap.unwrap_or_default();
+
+        match csr {
+            CSR_MHARTID => Some(self.hart_id),
+            CSR_MTVEC => Some(self.vector.unwrap_or(0)),
+            CSR_MSCRATCH => Some(self.scratch),
+            CSR_MEPC => Some(trap.epc),
+            CSR_MCAUSE => Some(trap.cause),
+            CSR_MTVAL => Some(trap.tval),
+            _ => None,
+        }
+    }
+
+    /// Append CSRs to `out` as little-endian `u64`: `mhartid`, `mtvec`,
+    /// `mscratch`, `mepc`, `mcause` and `mtval`. Unset `mtvec` and trap CSRs
+    /// are marked by a zero byte before them, set ones by one.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn encode(&self, out: &mut impl Extend<u8>) {
+        out.extend(self.hart_id.to_le_bytes());
+        out.extend([self.vector.is_some() as u8]);
+        out.extend(self.vector.unwrap_or(0).to_le_bytes());
+        out.extend(self.scratch.to_le_bytes());
+
+        let trap = self.trap.unwrap_or_default();
+        out.extend([self.trap.is_some() as u8]);
+        for v in [trap.epc, trap.cause, trap.tval] {
+            out.extend(v.to_le_bytes());
+        }
+    }
+
+    /// Write CSR, return false if it doesn't exist or is read-only.
+    ///
+    /// Only direct mode of `mtvec` is supported, so its mode bits are dropped,
+    /// like bit 0 of `mepc`.
+    pub(crate) fn write(&mut self, csr: u16, value: u64) -> bool {
+        match csr {
+            CSR_MTVEC => self.vector = Some(value & !0b11),
+            CSR_MSCRATCH => self.scratch = value,
+            CSR_MEPC => self.trap.get_or_insert_default().epc = value & !1,
+            CSR_MCAUSE => self.trap.get_or_insert_default().cause = value,
+            CSR_MTVAL => self.trap.get_or_insert_default().tval = value,
+            _ => return false,
+        }
+
+        true
+    }
+}
@@ -0,0 +1,425 @@
+use alloc::vec::Vec;
+
+use tangram_instruction::{riscv32i::RV32iBaseInst, Instruction, Memory, MemoryMut, Reg32};
+
+use crate::{Control, Monitor};
+
+/// Names of columns in order of [`ZkTrace::columns`].
+pub const ZK_COLUMNS: [&str; 17] = [
+    "clk", "pc", "next_pc", "inst", "opcode", "funct3", "funct7", "rd", "rs1", "rs2", "imm",
+    "rs1_val", "rs2_val", "rd_val", "mem_op", "mem_addr", "mem_val",
+];
+
+/// `mem_op` of step without memory access.
+pub const MEM_NONE: u32 = 0;
+/// `mem_op` of step loading memory.
+pub const MEM_LOAD: u32 = 1;
+/// `mem_op` of step storing memory.
+pub const MEM_STORE: u32 = 2;
+
+/// Access of one byte in memory log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEvent {
+    pub addr: u32,
+    /// Step of access.
+    pub clk: u32,
+    pub write: bool,
+    pub value: u8,
+}
+
+/// Execution trace with one column per field and one row per retired instruction.
+///
+/// `opcode` is [`RV32iBaseInst::index`], `inst` keeps the raw encoding. Fields not
+/// used by an instruction are 0, `rd_val` is value of `rd` after step. `mem_*` are
+/// first memory access of step, with `mem_val` zero extended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZkTrace {
+    pub clk: Vec<u32>,
+    pub pc: Vec<u32>,
+    pub next_pc: Vec<u32>,
+    pub inst: Vec<u32>,
+    pub opcode: Vec<u32>,
+    pub funct3: Vec<u32>,
+    pub funct7: Vec<u32>,
+    pub rd: Vec<u32>,
+    pub rs1: Vec<u32>,
+    pub rs2: Vec<u32>,
+    pub imm: Vec<u32>,
+    pub rs1_val: Vec<u32>,
+    pub rs2_val: Vec<u32>,
+    pub rd_val: Vec<u32>,
+    pub mem_op: Vec<u32>,
+    pub mem_addr: Vec<u32>,
+    pub mem_val: Vec<u32>,
+    /// Byte accesses in order of execution.
+    pub memory: Vec<MemoryEvent>,
+}
+
+impl ZkTrace {
+    /// Number of rows.
+    pub fn len(&self) -> usize {
+        self.clk.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clk.is_empty()
+    }
+
+    /// Columns named by [`ZK_COLUMNS`].
+    pub fn columns(&self) -> [&[u32]; 17] {
+        [
+            &self.clk,
+            &self.pc,
+            &self.next_pc,
+            &self.inst,
+            &self.opcode,
+            &self.funct3,
+            &self.funct7,
+            &self.rd,
+            &self.rs1,
+            &self.rs2,
+            &self.imm,
+            &self.rs1_val,
+            &self.rs2_val,
+            &self.rd_val,
+            &self.mem_op,
+            &self.mem_addr,
+            &self.mem_val,
+        ]
+    }
+
+    /// Memory log sorted by address, then by step.
+    pub fn memory_log(&self) -> Vec<MemoryEvent> {
+        let mut log = self.memory.clone();
+        log.sort_by_key(|e| (e.addr, e.clk));
+        log
+    }
+
+    /// Whether every read in memory log returns last written value, or value in
+    /// `initial` memory before first write to its address.
+    pub fn check_memory<M: Memory<Register = u32>>(&self, initial: &M) -> bool {
+        let log = self.memory_log();
+        let mut last: Option<&MemoryEvent> = None;
+
+        for e in log.iter() {
+            if !e.write {
+                let expected = match last {
+                    Some(l) if l.addr == e.addr => l.value,
+                    _ if initial.contains(e.addr, 1) => initial.load(e.addr, 1)[0],
+                    _ => return false,
+                };
+
+                if e.value != expected {
+                    return false;
+                }
+            }
+
+            last = Some(e);
+        }
+
+        true
+    }
+
+    fn push(&mut self, row: &Row) {
+        let values = [
+            row.clk,
+            row.pc,
+            row.next_pc,
+            row.inst,
+            row.opcode,
+            row.funct3,
+            row.funct7,
+            row.rd,
+            row.rs1,
+            row.rs2,
+            row.imm,
+            row.rs1_val,
+            row.rs2_val,
+            row.rd_val,
+            row.mem_op,
+            row.mem_addr,
+            row.mem_val,
+        ];
+
+        let columns = [
+            &mut self.clk,
+            &mut self.pc,
+            &mut self.next_pc,
+            &mut self.inst,
+            &mut self.opcode,
+            &mut self.funct3,
+            &mut self.funct7,
+            &mut self.rd,
+            &mut self.rs1,
+            &mut self.rs2,
+            &mut self.imm,
+            &mut self.rs1_val,
+            &mut self.rs2_val,
+            &mut self.rd_val,
+            &mut self.mem_op,
+            &mut self.mem_addr,
+            &mut self.mem_val,
+        ];
+
+        for (column, v) in columns.into_iter().zip(values) {
+            column.push(v);
+        }
+    }
+}
+
+/// Row being built for current step.
+#[derive(Default)]
+struct Row {
+    clk: u32,
+    pc: u32,
+    next_pc: u32,
+    inst: u32,
+    opcode: u32,
+    funct3: u32,
+    funct7: u32,
+    rd: u32,
+    rs1: u32,
+    rs2: u32,
+    imm: u32,
+    rs1_val: u32,
+    rs2_val: u32,
+    rd_val: u32,
+    mem_op: u32,
+    mem_addr: u32,
+    mem_val: u32,
+}
+
+impl Row {
+    /// Fill decoded fields of `inst`.
+    fn decode<I>(&mut self, inst: &RV32iBaseInst<I>) {
+        use RV32iBaseInst::*;
+
+        // Funct fields are kept for formats having them.
+        let (raw, rd, rs1, rs2, imm, f3, f7) = match inst {
+            Lui(i) | Auipc(i) => (i.inst().raw(), i.rd(), 0, 0, i.imm(), 0, 0),
+            Jal(i) => (i.inst().raw(), i.rd(), 0, 0, i.imm(), 0, 0),
+            Jalr(i) | Lb(i) | Lh(i) | Lw(i) | Lbu(i) | Lhu(i) | Lwu(i) | Addi(i) | Slti(i)
+            | Sltiu(i) | Xori(i) | Ori(i) | Andi(i) | Slli(i) | Srli(i) | Srai(i) | Fence(i)
+            | FenceI(i) | ECall(i) | EBreak(i) => {
+                (i.inst().raw(), i.rd(), i.rs1(), 0, i.imm(), 0x7, 0)
+            }
+            Beq(i) | Bne(i) | Blt(i) | Bge(i) | Bltu(i) | Bgeu(i) => {
+                (i.inst().raw(), 0, i.rs1(), i.rs2(), i.imm(), 0x7, 0)
+            }
+            Sb(i) | Sh(i) | Sw(i) => (i.inst().raw(), 0, i.rs1(), i.rs2(), i.imm(), 0x7, 0),
+            Add(i) | Sub(i) | Sll(i) | Slt(i) | Sltu(i) | Xor(i) | Srl(i) | Sra(i) | Or(i)
+            | And(i) => (i.inst().raw(), i.rd(), i.rs1(), i.rs2(), 0, 0x7, 0x7F),
+            Other(_) => (0, 0, 0, 0, 0, 0, 0),
+        };
+
+        self.inst = raw;
+        self.opcode = inst.index() as u32;
+        self.funct3 = (raw >> 12) & f3;
+        self.funct7 = (raw >> 25) & f7;
+        self.rd = rd as u32;
+        self.rs1 = rs1 as u32;
+        self.rs2 = rs2 as u32;
+        self.imm = imm;
+    }
+}
+
+/// Monitor building [`ZkTrace`] of retired instructions.
+#[derive(Default)]
+pub struct ZkTraceMonitor {
+    trace: ZkTrace,
+    row: Row,
+    /// Memory events of current step.
+    memory: Vec<MemoryEvent>,
+}
+
+impl ZkTraceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trace(&self) -> &ZkTrace {
+        &self.trace
+    }
+
+    pub fn into_trace(self) -> ZkTrace {
+        self.trace
+    }
+
+    fn access(&mut self, op: u32, addr: u32, data: &[u8]) {
+        if self.row.mem_op == MEM_NONE {
+            let mut value = [0u8; 4];
+            let len = data.len().min(4);
+            value[..len].copy_from_slice(&data[..len]);
+
+            self.row.mem_op = op;
+            self.row.mem_addr = addr;
+            self.row.mem_val = u32::from_le_bytes(value);
+        }
+
+        for (i, b) in data.iter().enumerate() {
+            self.memory.push(MemoryEvent {
+                addr: addr.wrapping_add(i as u32),
+                clk: self.row.clk,
+                write: op == MEM_STORE,
+                value: *b,
+            });
+        }
+    }
+}
+
+impl<I, R> Monitor<RV32iBaseInst<I>> for ZkTraceMonitor
+where
+    I: Instruction<Register = R>,
+    R: Reg32 + Clone,
+{
+    fn before_execute(&mut self, inst: &RV32iBaseInst<I>, pc: &R, regs: &[R]) -> Control {
+        self.row = Row {
+            clk: self.trace.len() as u32,
+            pc: pc.reg32(),
+            ..Row::default()
+        };
+        self.row.decode(inst);
+        // Fields beyond register file, like `x16` of RV32E, trap before reading.
+        let value = |r: u32| regs.get(r as usize).map_or(0, Reg32::reg32);
+        self.row.rs1_val = value(self.row.rs1);
+        self.row.rs2_val = value(self.row.rs2);
+        self.memory.clear();
+
+        Control::Continue
+    }
+
+    fn on_load(&mut self, addr: &R, data: &[u8]) -> Control {
+        self.access(MEM_LOAD, addr.reg32(), data);
+
+        Control::Continue
+    }
+
+    fn on_store(&mut self, addr: &R, data: &[u8]) -> Control {
+        self.access(MEM_STORE, addr.reg32(), data);
+
+        Control::Continue
+    }
+
+    fn monitor<M>(&mut self, _inst: &RV32iBaseInst<I>, pc: &R, regs: &[R], _memory: &M) -> Control
+    where
+        M: MemoryMut<Register = R>,
+    {
+        self.row.next_pc = pc.reg32();
+        self.row.rd_val = regs.get(self.row.rd as usize).map_or(0, Reg32::reg32);
+
+        self.trace.push(&self.row);
+        self.trace.memory.append(&mut self.memory);
+
+        Control::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tangram_instruction::riscv32i::{assemble, RV32iBaseInst, RV32I_NAMES};
+
+    use crate::{Executor, MemoryEvent, ZkTraceMonitor, MEM_LOAD, MEM_NONE, MEM_STORE, ZK_COLUMNS};
+
+    const PROGRAM: &str = "
+        li a0, 0x1234
+        sw a0, 64(zero)
+        lbu a1, 65(zero)
+        sb a1, 64(zero)
+        lw a2, 64(zero)
+        ecall
+    ";
+
+    #[test]
+    fn test_zk_trace() {
+        let mut code = [0u8; 128];
+        assemble(PROGRAM, 0, &mut code).unwrap();
+
+        let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], ZkTraceMonitor::new());
+        assert!(executor.run(4).is_err());
+        assert_eq!(executor.regs()[12], 0x1212);
+
+        let trace = executor.monitor().trace();
+        assert_eq!(trace.len(), 6);
+        assert!(trace.columns().iter().all(|c| c.len() == 6));
+        assert_eq!(ZK_COLUMNS[4], "opcode");
+
+        let name = |row: usize| RV32I_NAMES[trace.opcode[row] as usize];
+        assert_eq!(
+            (0..6).map(name).collect::<alloc::vec::Vec<_>>(),
+            ["lui", "addi", "sw", "lbu", "sb", "lw"]
+        );
+
+        // sw a0, 64(zero)
+        assert_eq!((trace.rd[2], trace.funct3[2], trace.funct7[2]), (0, 2, 0));
+        assert_eq!((trace.rs1[2], trace.rs2[2], trace.imm[2]), (0, 10, 64));
+        assert_eq!(trace.rs2_val[2], 0x1234);
+        assert_eq!(
+            (trace.mem_op[2], trace.mem_addr[2], trace.mem_val[2]),
+            (MEM_STORE, 64, 0x1234)
+        );
+
+        // lbu a1, 65(zero)
+        assert_eq!((trace.rd[3], trace.rd_val[3]), (11, 0x12));
+        assert_eq!((trace.mem_op[3], trace.mem_val[3]), (MEM_LOAD, 0x12));
+        assert_eq!(trace.mem_op[1], MEM_NONE);
+        assert_eq!((trace.pc[5], trace.next_pc[5]), (20, 24));
+
+        let log = trace.memory_log();
+        assert_eq!(log.len(), 4 + 1 + 1 + 4);
+        assert_eq!(
+            &log[..3],
+            [
+                MemoryEvent {
+                    addr: 64,
+                    clk: 2,
+                    write: true,
+                    value: 0x34
+                },
+                MemoryEvent {
+                    addr: 64,
+                    clk: 4,
+                    write: true,
+                    value: 0x12
+                },
+                MemoryEvent {
+                    addr: 64,
+                    clk: 5,
+                    write: false,
+                    value: 0x12
+                },
+            ]
+        );
+        let initial = [0u8; 128];
+        assert!(trace.check_memory(&initial));
+
+        let mut forged = trace.clone();
+        forged.memory[6].value = 0;
+        assert!(!forged.check_memory(&initial));
+
+        // First read of an address returns initial memory.
+        let mut forged = trace.clone();
+        forged.memory.push(MemoryEvent {
+            addr: 100,
+            clk: 6,
+            write: false,
+            value: 9,
+        });
+        assert!(!forged.check_memory(&initial));
+        let mut memory = initial;
+        memory[100] = 9;
+        assert!(forged.check_memory(&memory));
+    }
+
+    #[test]
+    fn test_zk_rv32e() {
+        let mut code = [0u8; 128];
+        assemble("add a0, a6, a0", 0, &mut code).unwrap();
+
+        // `x16` doesn't exist, the row isn't added and nothing is indexed by it.
+        let mut executor: Executor<16, RV32iBaseInst<()>, _, _, _> =
+            Executor::new(code, [0u8; 128], ZkTraceMonitor::new());
+        assert!(executor.step(4).is_err());
+        assert!(executor.monitor().trace().is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! RV32I conformance tests in the style of `riscv-tests`.
+//!
+//! Programs in `tests/conformance/rv32i` are self-written with environment and
+//! test macros modelled on `riscv-tests`, they aren't the upstream suite and
+//! bypass tests of upstream are left out. Their environment follows the
+//! upstream `p` environment: it sets up CSRs, enters the test with `mret` and
+//! reports the result by `ecall` to its trap handler.
+//!
+//! Each ELF runs until it writes `tohost`, `1` means pass, otherwise
+//! `tohost >> 1` is the failed test number.
+//!
+//! Fixtures are built by `tests/conformance/build.sh`. Upstream `rv32ui-p-*`
+//! tests aren't vendored, `tests/riscv-tests/fetch.sh` builds them into
+//! `tests/riscv-tests/rv32ui` and `cargo test -- --ignored` runs them. RV64
+//! tests need an RV64I instruction set, which isn't implemented yet.
+
+use std::fs;
+
+use tangram_executor::{Control, Elf, Executor, MemoryReader, Monitor};
+use tangram_instruction::{
+    riscv::Illegal, riscv32i::RV32iBaseInst, riscvzicsr::RVCsrInst, Instruction, Memory, MemoryMut,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/conformance/bin");
+
+const UPSTREAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/riscv-tests/rv32ui");
+
+/// CSRs written by test environment before it sets `mtvec` to skip them,
+/// they are missing in executor.
+const CSR_MSTATUS: u16 = 0x300;
+const CSR_MIE: u16 = 0x304;
+
+const RAM_BASE: u32 = 0x80000000;
+const RAM_SIZE: usize = 0x10000;
+
+const STEP_LIMIT: usize = 100_000;
+
+struct Ram {
+    data: Vec<u8>,
+}
+
+impl Ram {
+    fn load(elf: &Elf) -> Self {
+        let mut data = vec![0; RAM_SIZE];
+
+        for seg in elf.segments() {
+            let start = seg.vaddr as usize - RAM_BASE as usize;
+            data[start..start + seg.data.len()].copy_from_slice(seg.data);
+        }
+
+        Self { data }
+    }
+
+    fn index(&self, pos: u32, length: usize) -> Option<std::ops::Range<usize>> {
+        let start = pos.checked_sub(RAM_BASE)? as usize;
+        let end = start + length;
+
+        (end <= self.data.len()).then_some(start..end)
+    }
+}
+
+impl Memory for Ram {
+    type Register = u32;
+
+    fn length(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn load(&self, pos: u32, length: u8) -> &[u8] {
+        let range = self.index(pos, length as usize).expect("load out of range");
+        &self.data[range]
+    }
+
+    fn contains(&self, pos: u32, length: u8) -> bool {
+        self.index(pos, length as usize).is_some()
+    }
+}
+
+impl MemoryMut for Ram {
+    fn store(&mut self, pos: u32, data: &[u8]) {
+        let range = self.index(pos, data.len()).expect("store out of range");
+        self.data[range].copy_from_slice(data)
+    }
+}
+
+/// Monitor keeping `mstatus` and `mie`, which environment clears before
+/// entering test.
+#[derive(Default)]
+struct Env {
+    mstatus: u64,
+    mie: u64,
+}
+
+impl<I: Instruction> Monitor<I> for Env {
+    fn monitor<M>(
+        &mut self,
+        _inst: &I,
+        _pc: &I::Register,
+        _regs: &[I::Register],
+        _memory: &M,
+    ) -> Control
+    where
+        M: MemoryMut<Register = I::Register>,
+    {
+        Control::Continue
+    }
+
+    fn read_csr(&self, csr: u16) -> Option<u64> {
+        match csr {
+            CSR_MSTATUS => Some(self.mstatus),
+            CSR_MIE => Some(self.mie),
+            _ => None,
+        }
+    }
+
+    fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+        match csr {
+            CSR_MSTATUS => self.mstatus = value,
+            CSR_MIE => self.mie = value,
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Pass,
+    Fail(u32),
+    Error(String),
+}
+
+fn run_rv32(bytes: &[u8]) -> Outcome {
+    let elf = match Elf::parse(bytes) {
+        Ok(e) => e,
+        Err(e) => return Outcome::Error(format!("{:?}", e)),
+    };
+
+    let tohost = match elf.symbol("tohost") {
+        Some(s) => s.value as u32,
+        None => return Outcome::Error("no tohost symbol".into()),
+    };
+
+    // Code and data share memory, as `fence.i` tests store instructions.
+    let mut executor: Executor<32, RV32iBaseInst<RVCsrInst<Illegal>>, _, _, _> =
+        Executor::new(MemoryReader::new(), Ram::load(&elf), Env::default());
+    executor.set_pc(elf.entry() as u32);
+    executor.set_trap_ecall(true);
+
+    for _ in 0..STEP_LIMIT {
+        if let Err(e) = executor.step(4) {
+            return Outcome::Error(format!("{:?} at pc {:#x}", e, executor.pc()));
+        }
+
+        let m = executor.memory().load(tohost, 4);
+        match u32::from_le_bytes([m[0], m[1], m[2], m[3]]) {
+            0 => {}
+            1 => return Outcome::Pass,
+            v => return Outcome::Fail(v >> 1),
+        }
+    }
+
+    Outcome::Error(format!("step limit exceeded at pc {:#x}", executor.pc()))
+}
+
+fn run(name: &str) -> Outcome {
+    let path = format!("{}/{}", FIXTURES, name);
+    run_rv32(&fs::read(path).unwrap())
+}
+
+/// Run upstream `rv32ui-p-*` tests, reporting each of them.
+#[test]
+#[ignore = "needs upstream tests built by tests/riscv-tests/fetch.sh"]
+fn upstream_rv32ui() {
+    let entries = fs::read_dir(UPSTREAM)
+        .unwrap_or_else(|e| panic!("{}: {}, run tests/riscv-tests/fetch.sh", UPSTREAM, e));
+
+    let mut names = entries
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|n| n.starts_with("rv32ui-p-"))
+        .collect::<Vec<_>>();
+    names.sort();
+    assert!(!names.is_empty(), "no rv32ui-p-* tests in {}", UPSTREAM);
+
+    let mut failed = Vec::new();
+    for name in names {
+        let outcome = run_rv32(&fs::read(format!("{}/{}", UPSTREAM, name)).unwrap());
+        println!("{}: {:?}", name, outcome);
+
+        if outcome != Outcome::Pass {
+            failed.push(name);
+        }
+    }
+
+    assert!(failed.is_empty(), "failed: {:?}", failed);
+}
+
+macro_rules! rv32i {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                assert_eq!(run(concat!("rv32i-", stringify!($name))), Outcome::Pass);
+            }
+        )*
+    };
+}
+
+rv32i!(
+    add, addi, and, andi, auipc, beq, bge, bgeu, blt, bltu, bne, jal, jalr, lb, lbu, lh, lhu, lui,
+    lw, or, ori, sb, sh, simple, sll, slli, slt, slti, sltiu, sltu, sra, srai, srl, srli, sub, sw,
+    xor, xori,
+);
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Build rv32i fixtures into `bin/`.
+#
+# Requires `cpp`, `llvm-mc` and an ELF linker supporting RISC-V, e.g.
+# `ld.lld` or `rust-lld -flavor gnu`.
+
+set -e
+
+cd "$(dirname "$0")"
+
+CPP=${CPP:-cpp}
+LLVM_MC=${LLVM_MC:-llvm-mc}
+LD=${LD:-ld.lld}
+
+OBJ=$(mktemp -d)
+trap 'rm -rf "$OBJ"' EXIT
+
+mkdir -p bin
+
+for src in rv32i/*.S; do
+    name=rv32i-$(basename "$src" .S)
+
+    $CPP -x assembler-with-cpp -P -I env -I macros "$src" |
+        $LLVM_MC -triple=riscv32 -mattr=-relax -filetype=obj -o "$OBJ/$name.o"
+    $LD -m elf32lriscv -T env/link.ld -o "bin/$name" "$OBJ/$name.o"
+done
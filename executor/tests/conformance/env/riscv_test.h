@@ -0,0 +1,117 @@
+// Test environment modelled on riscv-tests `env/p/riscv_test.h`.
+//
+// Like the upstream `p` environment, tests start at a reset vector which sets
+// `mtvec`, skips CSRs the hart lacks through traps and enters the test with
+// `mret`. Result is reported by `ecall` to `trap_vector`, which writes `tohost`:
+// 1 means pass, otherwise the failed test number shifted left by 1 with bit 0
+// set. Other traps write the test number or'ed with 1337.
+//
+// Weak `mtvec_handler` and `stvec_handler`, PMP and multicore setup of
+// upstream are left out.
+
+#ifndef _ENV_P_RISCV_TEST_H
+#define _ENV_P_RISCV_TEST_H
+
+#define RVTEST_RV32U                                                    \
+  .macro init;                                                          \
+  .endm
+
+#define RVTEST_RV64U RVTEST_RV32U
+
+#define TESTNUM gp
+
+#define CAUSE_USER_ECALL 0x8
+#define CAUSE_SUPERVISOR_ECALL 0x9
+#define CAUSE_MACHINE_ECALL 0xb
+
+#define INIT_XREG                                                       \
+  li x1, 0;  li x2, 0;  li x3, 0;  li x4, 0;                            \
+  li x5, 0;  li x6, 0;  li x7, 0;  li x8, 0;                            \
+  li x9, 0;  li x10, 0; li x11, 0; li x12, 0;                           \
+  li x13, 0; li x14, 0; li x15, 0; li x16, 0;                           \
+  li x17, 0; li x18, 0; li x19, 0; li x20, 0;                           \
+  li x21, 0; li x22, 0; li x23, 0; li x24, 0;                           \
+  li x25, 0; li x26, 0; li x27, 0; li x28, 0;                           \
+  li x29, 0; li x30, 0; li x31, 0;
+
+#define INIT_SATP                                                       \
+  la t0, 1f;                                                            \
+  csrw mtvec, t0;                                                       \
+  csrwi satp, 0;                                                        \
+  .align 2;                                                             \
+1:
+
+#define DELEGATE_NO_TRAPS                                               \
+  csrwi mie, 0;                                                         \
+  la t0, 1f;                                                            \
+  csrw mtvec, t0;                                                       \
+  csrwi medeleg, 0;                                                     \
+  csrwi mideleg, 0;                                                     \
+  .align 2;                                                             \
+1:
+
+#define RVTEST_CODE_BEGIN                                               \
+        .section .text.init;                                            \
+        .align  6;                                                      \
+        .globl _start;                                                  \
+_start:                                                                 \
+        j reset_vector;                                                 \
+        .align 2;                                                       \
+trap_vector:                                                            \
+        csrr t5, mcause;                                                \
+        li t6, CAUSE_USER_ECALL;                                        \
+        beq t5, t6, write_tohost;                                       \
+        li t6, CAUSE_SUPERVISOR_ECALL;                                  \
+        beq t5, t6, write_tohost;                                       \
+        li t6, CAUSE_MACHINE_ECALL;                                     \
+        beq t5, t6, write_tohost;                                       \
+        ori TESTNUM, TESTNUM, 1337;                                     \
+write_tohost:                                                           \
+        la t5, tohost;                                                  \
+        sw TESTNUM, 0(t5);                                              \
+        sw zero, 4(t5);                                                 \
+        j write_tohost;                                                 \
+reset_vector:                                                           \
+        INIT_XREG;                                                      \
+        INIT_SATP;                                                      \
+        DELEGATE_NO_TRAPS;                                              \
+        li TESTNUM, 0;                                                  \
+        la t0, trap_vector;                                             \
+        csrw mtvec, t0;                                                 \
+        csrwi mstatus, 0;                                               \
+        init;                                                           \
+        la t0, 1f;                                                      \
+        csrw mepc, t0;                                                  \
+        csrr a0, mhartid;                                               \
+        mret;                                                           \
+1:
+
+#define RVTEST_CODE_END                                                 \
+        unimp
+
+#define RVTEST_PASS                                                     \
+        fence;                                                          \
+        li TESTNUM, 1;                                                  \
+        li a7, 93;                                                      \
+        li a0, 0;                                                       \
+        ecall
+
+#define RVTEST_FAIL                                                     \
+        fence;                                                          \
+1:      beqz TESTNUM, 1b;                                               \
+        sll TESTNUM, TESTNUM, 1;                                        \
+        or TESTNUM, TESTNUM, 1;                                         \
+        li a7, 93;                                                      \
+        addi a0, TESTNUM, 0;                                            \
+        ecall
+
+#define RVTEST_DATA_BEGIN                                               \
+        .pushsection .tohost,"aw",@progbits;                            \
+        .align 6; .global tohost; tohost: .dword 0; .size tohost, 8;    \
+        .align 6; .global fromhost; fromhost: .dword 0; .size fromhost, 8; \
+        .popsection;                                                    \
+        .align 4; .global begin_signature; begin_signature:
+
+#define RVTEST_DATA_END .align 4; .global end_signature; end_signature:
+
+#endif
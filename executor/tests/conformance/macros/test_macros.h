@@ -0,0 +1,166 @@
+// Scalar test macros modelled on riscv-tests `isa/macros/scalar/test_macros.h`,
+// without bypass tests, which target pipeline forwarding of hardware.
+
+#ifndef __TEST_MACROS_SCALAR_H
+#define __TEST_MACROS_SCALAR_H
+
+#define MASK_XLEN(x) ((x) & 0xffffffff)
+#define SEXT_IMM(x) ((x) | (-(((x) >> 11) & 1) << 11))
+
+#define TEST_CASE( testnum, testreg, correctval, code... )             \
+test_ ## testnum:                                                       \
+    li  TESTNUM, testnum;                                               \
+    code;                                                               \
+    li  x7, MASK_XLEN(correctval);                                      \
+    bne testreg, x7, fail;
+
+//-----------------------------------------------------------------------
+// Tests for instructions with immediate operand
+//-----------------------------------------------------------------------
+
+#define TEST_IMM_OP( testnum, inst, result, val1, imm )                \
+    TEST_CASE( testnum, x14, result,                                    \
+      li  x1, MASK_XLEN(val1);                                          \
+      inst x14, x1, SEXT_IMM(imm);                                      \
+    )
+
+#define TEST_IMM_SRC1_EQ_DEST( testnum, inst, result, val1, imm )      \
+    TEST_CASE( testnum, x1, result,                                     \
+      li  x1, MASK_XLEN(val1);                                          \
+      inst x1, x1, SEXT_IMM(imm);                                       \
+    )
+
+#define TEST_IMM_ZEROSRC1( testnum, inst, result, imm )                \
+    TEST_CASE( testnum, x1, result,                                     \
+      inst x1, x0, SEXT_IMM(imm);                                       \
+    )
+
+#define TEST_IMM_ZERODEST( testnum, inst, val1, imm )                  \
+    TEST_CASE( testnum, x0, 0,                                          \
+      li  x1, MASK_XLEN(val1);                                          \
+      inst x0, x1, SEXT_IMM(imm);                                       \
+    )
+
+//-----------------------------------------------------------------------
+// Tests for register-register instructions
+//-----------------------------------------------------------------------
+
+#define TEST_RR_OP( testnum, inst, result, val1, val2 )                \
+    TEST_CASE( testnum, x14, result,                                    \
+      li  x1, MASK_XLEN(val1);                                          \
+      li  x2, MASK_XLEN(val2);                                          \
+      inst x14, x1, x2;                                                 \
+    )
+
+#define TEST_RR_SRC1_EQ_DEST( testnum, inst, result, val1, val2 )      \
+    TEST_CASE( testnum, x1, result,                                     \
+      li  x1, MASK_XLEN(val1);                                          \
+      li  x2, MASK_XLEN(val2);                                          \
+      inst x1, x1, x2;                                                  \
+    )
+
+#define TEST_RR_SRC2_EQ_DEST( testnum, inst, result, val1, val2 )      \
+    TEST_CASE( testnum, x2, result,                                     \
+      li  x1, MASK_XLEN(val1);                                          \
+      li  x2, MASK_XLEN(val2);                                          \
+      inst x2, x1, x2;                                                  \
+    )
+
+#define TEST_RR_SRC12_EQ_DEST( testnum, inst, result, val1 )           \
+    TEST_CASE( testnum, x1, result,                                     \
+      li  x1, MASK_XLEN(val1);                                          \
+      inst x1, x1, x1;                                                  \
+    )
+
+#define TEST_RR_ZEROSRC1( testnum, inst, result, val )                 \
+    TEST_CASE( testnum, x2, result,                                     \
+      li x1, MASK_XLEN(val);                                            \
+      inst x2, x0, x1;                                                  \
+    )
+
+#define TEST_RR_ZEROSRC2( testnum, inst, result, val )                 \
+    TEST_CASE( testnum, x2, result,                                     \
+      li x1, MASK_XLEN(val);                                            \
+      inst x2, x1, x0;                                                  \
+    )
+
+#define TEST_RR_ZEROSRC12( testnum, inst, result )                     \
+    TEST_CASE( testnum, x1, result,                                     \
+      inst x1, x0, x0;                                                  \
+    )
+
+#define TEST_RR_ZERODEST( testnum, inst, val1, val2 )                  \
+    TEST_CASE( testnum, x0, 0,                                          \
+      li x1, MASK_XLEN(val1);                                           \
+      li x2, MASK_XLEN(val2);                                           \
+      inst x0, x1, x2;                                                  \
+    )
+
+//-----------------------------------------------------------------------
+// Tests for load and store instructions
+//-----------------------------------------------------------------------
+
+#define TEST_LD_OP( testnum, inst, result, offset, base )              \
+    TEST_CASE( testnum, x14, result,                                    \
+      la  x1, base;                                                     \
+      inst x14, offset(x1);                                             \
+    )
+
+#define TEST_ST_OP( testnum, load_inst, store_inst, result, offset, base ) \
+    TEST_CASE( testnum, x14, result,                                    \
+      la  x1, base;                                                     \
+      li  x2, result;                                                   \
+      store_inst x2, offset(x1);                                        \
+      load_inst x14, offset(x1);                                        \
+    )
+
+#define TEST_LD_ST_BYPASS( testnum, load_inst, store_inst, result, offset, base ) \
+    TEST_CASE( testnum, x14, result,                                    \
+      la  x1, base;                                                     \
+      li  x2, result;                                                   \
+      store_inst x2, offset(x1);                                        \
+      load_inst x14, offset(x1);                                        \
+      store_inst x14, offset(x1);                                       \
+      load_inst x2, offset(x1);                                         \
+      mv x14, x2;                                                       \
+    )
+
+//-----------------------------------------------------------------------
+// Tests for branch instructions
+//-----------------------------------------------------------------------
+
+#define TEST_BR2_OP_TAKEN( testnum, inst, val1, val2 )                 \
+test_ ## testnum:                                                       \
+    li  TESTNUM, testnum;                                               \
+    li  x1, val1;                                                       \
+    li  x2, val2;                                                       \
+    inst x1, x2, 2f;                                                    \
+    bne x0, TESTNUM, fail;                                              \
+1:  bne x0, TESTNUM, 3f;                                                \
+2:  inst x1, x2, 1b;                                                    \
+    bne x0, TESTNUM, fail;                                              \
+3:
+
+#define TEST_BR2_OP_NOTTAKEN( testnum, inst, val1, val2 )              \
+test_ ## testnum:                                                       \
+    li  TESTNUM, testnum;                                               \
+    li  x1, val1;                                                       \
+    li  x2, val2;                                                       \
+    inst x1, x2, 1f;                                                    \
+    bne x0, TESTNUM, 2f;                                                \
+1:  bne x0, TESTNUM, fail;                                              \
+2:  inst x1, x2, 1b;                                                    \
+3:
+
+//-----------------------------------------------------------------------
+// Pass and fail code (assumes test num is in TESTNUM)
+//-----------------------------------------------------------------------
+
+#define TEST_PASSFAIL                                                   \
+        bne x0, TESTNUM, pass;                                          \
+fail:                                                                   \
+        RVTEST_FAIL;                                                    \
+pass:                                                                   \
+        RVTEST_PASS                                                     \
+
+#endif
@@ -0,0 +1,104 @@
+//! Coverage of `tests/coverage/sum.S`, mapped to lines by DWARF 4 and DWARF 5
+//! line tables.
+//!
+//! Fixtures are built by `tests/coverage/build.sh`.
+
+use std::fs;
+
+use tangram_executor::{CoverageMonitor, Elf, Error, Executor, LineTable};
+use tangram_instruction::riscv32i::{assemble, RV32iBaseInst};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/coverage/bin");
+
+const EXPECTED: &str = "SF:sum.S
+BRDA:12,0,0,2
+BRDA:12,0,1,3
+BRDA:16,0,0,4
+BRDA:16,0,1,1
+BRF:4
+BRH:4
+DA:6,1
+DA:7,1
+DA:8,1
+DA:10,5
+DA:11,5
+DA:12,5
+DA:13,3
+DA:15,5
+DA:16,5
+DA:17,1
+DA:19,0
+DA:20,0
+LF:12
+LH:10
+end_of_record
+";
+
+fn run(elf: &Elf) -> CoverageMonitor {
+    let mut code = [0u8; 64];
+    for seg in elf.segments() {
+        let start = seg.vaddr as usize;
+        code[start..start + seg.data.len()].copy_from_slice(seg.data);
+    }
+
+    let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+        Executor::new(code, [0u8; 64], CoverageMonitor::new());
+    executor.set_pc(elf.entry() as u32);
+
+    assert!(matches!(
+        executor.run(4),
+        Err(Error::InstructionError(
+            tangram_instruction::Error::EnvironmentCall
+        ))
+    ));
+    assert_eq!(executor.regs()[10], 15);
+    assert_eq!(executor.regs()[11], 3);
+
+    std::mem::take(executor.monitor_mut())
+}
+
+#[test]
+fn test_lcov() {
+    for version in [4, 5] {
+        let bytes = fs::read(format!("{}/sum-dwarf{}", FIXTURES, version)).unwrap();
+        let elf = Elf::parse(&bytes).unwrap();
+
+        let lines = LineTable::from_elf(&elf).unwrap();
+        assert_eq!(lines.files(), ["sum.S"]);
+        assert_eq!(lines.lookup(0x18), Some((0, 13)));
+        assert_eq!(lines.lookup(0x30), None);
+
+        let coverage = run(&elf);
+        assert_eq!(coverage.branch(0x14), Some((2, 3)));
+        assert_eq!(coverage.hits(0x24), 1);
+
+        let mut lcov = String::new();
+        coverage.write_lcov(&mut lcov, Some(&lines)).unwrap();
+        assert_eq!(lcov, EXPECTED, "DWARF {}", version);
+    }
+}
+
+#[test]
+fn test_lcov_without_lines() {
+    let bytes = fs::read(format!("{}/sum-dwarf5", FIXTURES)).unwrap();
+    let coverage = run(&Elf::parse(&bytes).unwrap());
+
+    let mut lcov = String::new();
+    coverage.write_lcov(&mut lcov, None).unwrap();
+
+    assert!(lcov.starts_with("SF:[pc]\nBRDA:20,0,0,2\n"));
+    assert!(lcov.contains("\nDA:36,1\nLF:10\nLH:10\n"));
+}
+
+#[test]
+fn test_branch_to_next() {
+    let mut code = [0u8; 64];
+    assemble("li a0, 1\nbnez a0, next\nnext:\necall", 0, &mut code).unwrap();
+
+    let mut executor: Executor<32, RV32iBaseInst<()>, _, _, _> =
+        Executor::new(code, [0u8; 64], CoverageMonitor::new());
+    assert!(executor.run(4).is_err());
+
+    // Taken branch lands where falling through would.
+    assert_eq!(executor.monitor().branch(4), Some((1, 0)));
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Build coverage fixtures into `bin/`, with DWARF 4 and DWARF 5 line tables.
+#
+# Requires `llvm-mc` and an ELF linker supporting RISC-V, e.g. `ld.lld` or
+# `rust-lld -flavor gnu`.
+
+set -e
+
+cd "$(dirname "$0")"
+
+LLVM_MC=${LLVM_MC:-llvm-mc}
+LD=${LD:-ld.lld}
+
+OBJ=$(mktemp -d)
+trap 'rm -rf "$OBJ"' EXIT
+
+mkdir -p bin
+
+for version in 4 5; do
+    $LLVM_MC -triple=riscv32 -mattr=-relax -filetype=obj -g -dwarf-version=$version \
+        -fdebug-compilation-dir=. \
+        -o "$OBJ/sum.o" sum.S
+    $LD -m elf32lriscv -N --image-base=0 -Ttext=0 -o "bin/sum-dwarf$version" "$OBJ/sum.o"
+done
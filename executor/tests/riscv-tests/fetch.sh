@@ -0,0 +1,35 @@
+#!/bin/sh
+# Fetch upstream riscv-tests and build `rv32ui-p-*` tests into `rv32ui/`, they
+# are run by `cargo test --test conformance -- --ignored`.
+#
+# Requires `git`, network access and a RISC-V GCC, e.g.
+# `riscv64-unknown-elf-gcc`. `REV` selects revision of riscv-tests, record it
+# when committing the built tests.
+
+set -e
+
+cd "$(dirname "$0")"
+
+REPO=${REPO:-https://github.com/riscv-software-src/riscv-tests.git}
+REV=${REV:-master}
+CC=${CC:-riscv64-unknown-elf-gcc}
+
+SRC=$(mktemp -d)
+trap 'rm -rf "$SRC"' EXIT
+
+git clone --quiet "$REPO" "$SRC"
+git -C "$SRC" checkout --quiet "$REV"
+git -C "$SRC" submodule update --quiet --init env
+
+mkdir -p rv32ui
+
+for src in "$SRC"/isa/rv32ui/*.S; do
+    name=rv32ui-p-$(basename "$src" .S)
+
+    $CC -march=rv32i_zicsr_zifencei -mabi=ilp32 -static -mcmodel=medany \
+        -fvisibility=hidden -nostdlib -nostartfiles \
+        -I "$SRC/env/p" -I "$SRC/isa/macros/scalar" -T "$SRC/env/p/link.ld" \
+        -o "rv32ui/$name" "$src"
+done
+
+git -C "$SRC" rev-parse HEAD > rv32ui/REVISION
@@ -5,6 +5,53 @@ pub enum Error {
     Breakpoint,
     ErrFailedDeocdeInstructon,
     ErrBytecodeLengthNotEnough,
+    /// Reserved or unsupported encoding `raw` at `pc`, or registers it names
+    /// are beyond register file.
+    IllegalInstruction {
+        raw: u32,
+        pc: u64,
+    },
+    /// Jump or taken branch at `pc` to `target` not aligned to instruction
+    /// alignment.
+    InstructionMisaligned {
+        target: u64,
+        pc: u64,
+    },
+    /// Load at `pc` of `addr` not aligned to its size.
+    LoadMisaligned {
+        addr: u64,
+        pc: u64,
+    },
+    /// Store at `pc` to `addr` not aligned to its size.
+    StoreMisaligned {
+        addr: u64,
+        pc: u64,
+    },
+    /// Load at `pc` of `addr` beyond memory.
+    LoadAccessFault {
+        addr: u64,
+        pc: u64,
+    },
+    /// Store at `pc` to `addr` beyond memory.
+    StoreAccessFault {
+        addr: u64,
+        pc: u64,
+    },
+}
+
+impl Error {
+    /// Illegal instruction of `raw` encoding, pc is set by [`Error::with_pc`].
+    pub fn illegal(raw: u32) -> Self {
+        Self::IllegalInstruction { raw, pc: 0 }
+    }
+
+    /// Set pc of [`Error::IllegalInstruction`], other errors are returned as is.
+    pub fn with_pc(self, pc: u64) -> Self {
+        match self {
+            Self::IllegalInstruction { raw, .. } => Self::IllegalInstruction { raw, pc },
+            e => e,
+        }
+    }
 }
 
 /// Error type
@@ -4,6 +4,10 @@ pub mod riscv;
 pub mod riscv32i;
 pub mod riscv32p;
 pub mod riscv64i;
+pub mod riscvb;
+pub mod riscvk;
+pub mod riscvv;
+pub mod riscvzicsr;
 pub mod wasm;
 
 mod error;
@@ -11,3 +15,6 @@ pub use error::*;
 
 mod prelude;
 pub use prelude::*;
+
+#[cfg(test)]
+mod testing;
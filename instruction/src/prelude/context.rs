@@ -0,0 +1,23 @@
+use crate::riscvv::VectorRegs;
+
+/// State of hart other than pc, integer registers and memory, like CSRs and
+/// vector registers
+pub trait Context {
+    /// Read CSR `csr`, `None` if it doesn't exist.
+    fn read_csr(&self, _csr: u16) -> Option<u64> {
+        None
+    }
+
+    /// Write CSR `csr`, return false if it doesn't exist or is read-only.
+    fn write_csr(&mut self, _csr: u16, _value: u64) -> bool {
+        false
+    }
+
+    /// Vector registers, `None` if hart has no vector extension.
+    fn vector(&mut self) -> Option<VectorRegs<'_>> {
+        None
+    }
+}
+
+/// Hart without CSRs and vector registers.
+impl Context for () {}
@@ -0,0 +1,33 @@
+use core::fmt;
+
+/// Instruction which can be rendered as assembly text.
+pub trait Disassemble {
+    /// Write assembly text of this instruction located at `pc`.
+    ///
+    /// Branch and jump targets are absolute addresses in hex if `pc` is known,
+    /// like GNU objdump, otherwise offsets relative to the instruction.
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Display this instruction located at `pc`.
+    fn at(&self, pc: u64) -> At<'_, Self> {
+        At { inst: self, pc }
+    }
+}
+
+impl Disassemble for () {
+    fn disassemble(&self, _pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown")
+    }
+}
+
+/// Instruction displayed with absolute targets, see [`Disassemble::at`].
+pub struct At<'a, I: ?Sized> {
+    inst: &'a I,
+    pc: u64,
+}
+
+impl<I: Disassemble + ?Sized> fmt::Display for At<'_, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inst.disassemble(Some(self.pc), f)
+    }
+}
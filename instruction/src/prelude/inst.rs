@@ -1,4 +1,4 @@
-use crate::{Error, Memory, MemoryMut, Result};
+use crate::{Context, Error, Memory, MemoryMut, Result};
 
 /// Instruction
 pub trait Instruction: Sized {
@@ -7,17 +7,34 @@ pub trait Instruction: Sized {
 
     fn new(bytes: &[u8]) -> Result<Self>;
 
-    /// Execute an anstruction.
-    fn execute<M>(
+    /// Execute an anstruction, with other state of hart in `context`.
+    fn execute<M, C>(
         &mut self,
         pc: &mut Self::Register,
         regs: &mut [Self::Register],
         memory: &mut M,
+        context: &mut C,
     ) -> Result<()>
     where
-        M: Memory<Register = Self::Register> + MemoryMut;
+        M: Memory<Register = Self::Register> + MemoryMut,
+        C: Context;
+
+    /// Whether instruction orders stores before later fetches, like `fence.i`.
+    ///
+    /// Executors drop decoded instructions after executing it.
+    fn is_fence_i(&self) -> bool {
+        false
+    }
+
+    /// Integer register written by instruction, even if it is `x0` or value
+    /// doesn't change.
+    fn rd(&self) -> Option<usize> {
+        None
+    }
 }
 
+/// Terminal instruction set failing on every instruction left by other layers,
+/// use [`Illegal`](crate::riscv::Illegal) to know which one is rejected.
 impl Instruction for () {
     type Register = u32;
 
@@ -25,14 +42,16 @@ impl Instruction for () {
         Ok(())
     }
 
-    fn execute<M>(
+    fn execute<M, C>(
         &mut self,
         _pc: &mut Self::Register,
         _regs: &mut [Self::Register],
         _memory: &mut M,
+        _context: &mut C,
     ) -> Result<()>
     where
         M: Memory<Register = Self::Register> + MemoryMut,
+        C: Context,
     {
         Err(Error::ErrFailedDeocdeInstructon)
     }
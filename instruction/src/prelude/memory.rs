@@ -1,3 +1,66 @@
+/// Handling of loads and stores not aligned to their size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Misaligned {
+    /// Raise load or store address-misaligned exception.
+    Trap,
+    /// Emulate with byte accesses, like trap handlers of firmware.
+    Split,
+    /// Access in one piece, as if it were aligned.
+    #[default]
+    Allow,
+}
+
+/// Alignment of jump and taken branch targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IAlign {
+    /// Any target is accepted.
+    #[default]
+    Any,
+    /// 2 bytes, with compressed instructions.
+    Half,
+    /// 4 bytes, without compressed instructions.
+    Word,
+}
+
+impl IAlign {
+    /// Alignment in bytes.
+    pub fn bytes(self) -> u32 {
+        match self {
+            Self::Any => 1,
+            Self::Half => 2,
+            Self::Word => 4,
+        }
+    }
+}
+
+/// Alignment rules of a hardware target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    /// Handling of misaligned loads and stores.
+    pub access: Misaligned,
+    /// Alignment of jump and taken branch targets.
+    pub ialign: IAlign,
+}
+
+impl Alignment {
+    /// Trap on every misaligned access and target, like RV32I hardware without
+    /// misaligned access support.
+    pub const STRICT: Self = Self {
+        access: Misaligned::Trap,
+        ialign: IAlign::Word,
+    };
+}
+
+/// Nothing is checked.
+impl Default for Alignment {
+    fn default() -> Self {
+        Self {
+            access: Misaligned::Allow,
+            ialign: IAlign::Any,
+        }
+    }
+}
+
 /// Readable Linear Memory
 pub trait Memory {
     type Register;
@@ -8,6 +71,20 @@ pub trait Memory {
     /// Load data from memory
     // TODO: Add return result
     fn load(&self, pos: Self::Register, length: u8) -> &[u8];
+
+    /// Whether `length` bytes at `pos` can be loaded and stored.
+    ///
+    /// Instructions, fetches and debuggers check it before accessing memory
+    /// instead of letting `load` panic.
+    fn contains(&self, pos: Self::Register, length: u8) -> bool;
+
+    /// Alignment rules of accesses and jumps, nothing is checked by default.
+    ///
+    /// It is the only place they're set, wrap memory in [`Aligned`] to choose
+    /// them.
+    fn alignment(&self) -> Alignment {
+        Alignment::default()
+    }
 }
 
 /// Writable Linear memory
@@ -28,6 +105,12 @@ impl<const N: usize> Memory for [u8; N] {
 
         &self[pos..end]
     }
+
+    fn contains(&self, pos: Self::Register, length: u8) -> bool {
+        (pos as usize)
+            .checked_add(length as usize)
+            .is_some_and(|end| end <= N)
+    }
 }
 
 impl<const N: usize> MemoryMut for [u8; N] {
@@ -38,3 +121,43 @@ impl<const N: usize> MemoryMut for [u8; N] {
         self[pos..end].copy_from_slice(data)
     }
 }
+
+/// Memory `M` with alignment rules of a hardware target instead of its own.
+pub struct Aligned<M> {
+    /// Wrapped memory
+    pub memory: M,
+    /// Rules returned by [`Memory::alignment`]
+    pub alignment: Alignment,
+}
+
+impl<M> Aligned<M> {
+    pub fn new(memory: M, alignment: Alignment) -> Self {
+        Self { memory, alignment }
+    }
+}
+
+impl<M: Memory> Memory for Aligned<M> {
+    type Register = M::Register;
+
+    fn length(&self) -> Self::Register {
+        self.memory.length()
+    }
+
+    fn load(&self, pos: Self::Register, length: u8) -> &[u8] {
+        self.memory.load(pos, length)
+    }
+
+    fn contains(&self, pos: Self::Register, length: u8) -> bool {
+        self.memory.contains(pos, length)
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+}
+
+impl<M: MemoryMut> MemoryMut for Aligned<M> {
+    fn store(&mut self, pos: Self::Register, data: &[u8]) {
+        self.memory.store(pos, data)
+    }
+}
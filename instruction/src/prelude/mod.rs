@@ -4,8 +4,17 @@ pub use inst::*;
 mod memory;
 pub use memory::*;
 
+mod context;
+pub use context::*;
+
 mod reg32;
 pub use reg32::*;
 
 mod reg64;
 pub use reg64::*;
+
+mod regx;
+pub use regx::*;
+
+mod disasm;
+pub use disasm::*;
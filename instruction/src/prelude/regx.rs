@@ -0,0 +1,35 @@
+/// Register of either RV32 or RV64, for instructions defined on both.
+pub trait RegX {
+    /// Width of register in bits, 32 or 64.
+    const XLEN: u32;
+
+    /// Zero-extended value.
+    fn regx(&self) -> u64;
+
+    /// Set value, bits above `XLEN` are dropped.
+    fn set_regx(&mut self, v: u64);
+}
+
+impl RegX for u32 {
+    const XLEN: u32 = 32;
+
+    fn regx(&self) -> u64 {
+        *self as u64
+    }
+
+    fn set_regx(&mut self, v: u64) {
+        *self = v as u32;
+    }
+}
+
+impl RegX for u64 {
+    const XLEN: u32 = 64;
+
+    fn regx(&self) -> u64 {
+        *self
+    }
+
+    fn set_regx(&mut self, v: u64) {
+        *self = v;
+    }
+}
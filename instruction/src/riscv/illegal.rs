@@ -0,0 +1,115 @@
+use core::{fmt, marker::PhantomData};
+
+use crate::{Context, Disassemble, Error, Instruction, Memory, MemoryMut, RegX, Result};
+
+use super::Inst;
+
+/// Terminal instruction set rejecting every instruction left by other layers.
+///
+/// Unlike `()`, executing it reports [`Error::IllegalInstruction`] with the
+/// encoding and pc, and it disassembles to its opcode and funct fields.
+pub struct Illegal<R = u32> {
+    inst: Inst,
+    marker: PhantomData<R>,
+}
+
+impl<R> Illegal<R> {
+    pub fn raw(&self) -> u32 {
+        self.inst.raw()
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.inst.opcode()
+    }
+
+    pub fn funct3(&self) -> u8 {
+        self.inst.funct3()
+    }
+
+    pub fn funct7(&self) -> u8 {
+        self.inst.funct7()
+    }
+}
+
+impl<R: RegX> Instruction for Illegal<R> {
+    type Register = R;
+
+    /// Keep first 4 bytes, shorter encodings are zero-extended.
+    fn new(bytes: &[u8]) -> Result<Self> {
+        if bytes.is_empty() {
+            return Err(Error::ErrBytecodeLengthNotEnough);
+        }
+
+        let mut b = [0u8; 4];
+        let len = bytes.len().min(4);
+        b[..len].copy_from_slice(&bytes[..len]);
+
+        Ok(Self {
+            inst: Inst::new(b),
+            marker: PhantomData,
+        })
+    }
+
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        _regs: &mut [R],
+        _memory: &mut M,
+        _context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        Err(Error::IllegalInstruction {
+            raw: self.raw(),
+            pc: pc.regx(),
+        })
+    }
+}
+
+impl<R> Disassemble for Illegal<R> {
+    fn disassemble(&self, _pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "illegal {:#010x} (opcode {:#09b}, funct3 {:#05b}, funct7 {:#09b})",
+            self.raw(),
+            self.opcode(),
+            self.funct3(),
+            self.funct7()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use crate::{riscv32i::RV32iBaseInst, Error, Instruction};
+
+    use super::Illegal;
+
+    #[test]
+    fn test_illegal() {
+        // custom-0 opcode
+        let raw = 0x0200_c58bu32;
+        let mut inst = RV32iBaseInst::<Illegal>::new(&raw.to_le_bytes()).unwrap();
+        assert_eq!(
+            inst.to_string(),
+            "illegal 0x0200c58b (opcode 0b0001011, funct3 0b100, funct7 0b0000001)"
+        );
+
+        let (mut pc, mut regs) = (0x100u32, [0u32; 32]);
+        let r = inst.execute(&mut pc, &mut regs, &mut [0u8; 0], &mut ());
+        assert_eq!(r, Err(Error::IllegalInstruction { raw, pc: 0x100 }));
+        assert_eq!(pc, 0x100);
+
+        // Registers beyond register file of RV32E.
+        let raw = 0x0106_8533u32;
+        let mut inst = RV32iBaseInst::<Illegal>::new(&raw.to_le_bytes()).unwrap();
+        let r = inst.execute(&mut pc, &mut regs[..16], &mut [0u8; 0], &mut ());
+        assert_eq!(r, Err(Error::IllegalInstruction { raw, pc: 0x100 }));
+    }
+}
@@ -17,6 +17,93 @@ impl Inst {
         }
     }
 
+    /// Create RiscV Instruction from raw 32-bit encoding.
+    pub fn from_raw(inst: u32) -> Self {
+        Self { inst }
+    }
+
+    /// Build `R` type instruction.
+    pub fn build_r(opcode: u8, rd: usize, funct3: u8, rs1: usize, rs2: usize, funct7: u8) -> Self {
+        Self::from_raw(
+            ((funct7 as u32 & 0x7F) << 25)
+                | ((rs2 as u32 & 0x1F) << 20)
+                | ((rs1 as u32 & 0x1F) << 15)
+                | ((funct3 as u32 & 0x7) << 12)
+                | ((rd as u32 & 0x1F) << 7)
+                | (opcode as u32 & 0x7F),
+        )
+    }
+
+    /// Build `I` type instruction, only low 12 bits of `imm` are used.
+    pub fn build_i(opcode: u8, rd: usize, funct3: u8, rs1: usize, imm: i32) -> Self {
+        Self::from_raw(
+            ((imm as u32 & 0xFFF) << 20)
+                | ((rs1 as u32 & 0x1F) << 15)
+                | ((funct3 as u32 & 0x7) << 12)
+                | ((rd as u32 & 0x1F) << 7)
+                | (opcode as u32 & 0x7F),
+        )
+    }
+
+    /// Build `S` type instruction, only low 12 bits of `imm` are used.
+    pub fn build_s(opcode: u8, funct3: u8, rs1: usize, rs2: usize, imm: i32) -> Self {
+        let imm = imm as u32;
+
+        Self::from_raw(
+            ((imm & 0xFE0) << 20)
+                | ((rs2 as u32 & 0x1F) << 20)
+                | ((rs1 as u32 & 0x1F) << 15)
+                | ((funct3 as u32 & 0x7) << 12)
+                | ((imm & 0x1F) << 7)
+                | (opcode as u32 & 0x7F),
+        )
+    }
+
+    /// Build `B` type instruction, `imm` is 13 bits and bit 0 is ignored.
+    pub fn build_b(opcode: u8, funct3: u8, rs1: usize, rs2: usize, imm: i32) -> Self {
+        let imm = imm as u32;
+
+        Self::from_raw(
+            ((imm & 0x1000) << 19)
+                | ((imm & 0x7E0) << 20)
+                | ((rs2 as u32 & 0x1F) << 20)
+                | ((rs1 as u32 & 0x1F) << 15)
+                | ((funct3 as u32 & 0x7) << 12)
+                | ((imm & 0x1E) << 7)
+                | ((imm & 0x800) >> 4)
+                | (opcode as u32 & 0x7F),
+        )
+    }
+
+    /// Build `U` type instruction, `imm` is the upper 20 bits as returned by [`Inst::imm_u`].
+    pub fn build_u(opcode: u8, rd: usize, imm: u32) -> Self {
+        Self::from_raw((imm & 0xFFFFF000) | ((rd as u32 & 0x1F) << 7) | (opcode as u32 & 0x7F))
+    }
+
+    /// Build `J` type instruction, `imm` is 21 bits and bit 0 is ignored.
+    pub fn build_j(opcode: u8, rd: usize, imm: i32) -> Self {
+        let imm = imm as u32;
+
+        Self::from_raw(
+            ((imm & 0x100000) << 11)
+                | ((imm & 0x7FE) << 20)
+                | ((imm & 0x800) << 9)
+                | (imm & 0xFF000)
+                | ((rd as u32 & 0x1F) << 7)
+                | (opcode as u32 & 0x7F),
+        )
+    }
+
+    /// Raw 32-bit encoding.
+    pub fn raw(&self) -> u32 {
+        self.inst
+    }
+
+    /// Little-endian bytes of this instruction.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        self.inst.to_le_bytes()
+    }
+
     pub fn opcode(&self) -> u8 {
         (self.inst & 0x7F) as u8
     }
@@ -33,17 +120,17 @@ impl Inst {
 
     /// Read funct7
     pub fn funct7(&self) -> u8 {
-        ((self.inst & 0x40000000) >> 25) as u8
+        ((self.inst & 0xFE000000) >> 25) as u8
     }
 
     /// Read rs1
     pub fn rs1(&self) -> usize {
-        ((self.inst & 0x001F000) >> 15) as usize
+        ((self.inst & 0x000F8000) >> 15) as usize
     }
 
     /// Read rs2
     pub fn rs2(&self) -> usize {
-        ((self.inst & 0x01F0000) >> 20) as usize
+        ((self.inst & 0x01F00000) >> 20) as usize
     }
 
     /// Read `U` type immediate value.
@@ -64,17 +151,17 @@ impl Inst {
     /// Read `B` type immediate value.
     pub fn imm_sb(&self) -> u32 {
         ((self.inst & 0x80000000) >> 19)
-            | ((self.inst & 0x7C000000) >> 10)
+            | ((self.inst & 0x7E000000) >> 20)
             | ((self.inst & 0xF00) >> 7)
-            | ((self.inst & 80) >> 11)
+            | ((self.inst & 0x80) << 4)
     }
 
     /// Read `B` type symbol extend immediate value.
     pub fn imm_sb_symbol(&self) -> i32 {
         (((self.inst & 0x80000000) as i32) >> 19)
-            | ((self.inst & 0x7C000000) >> 10) as i32
+            | ((self.inst & 0x7E000000) >> 20) as i32
             | ((self.inst & 0xF00) >> 7) as i32
-            | ((self.inst & 80) >> 11) as i32
+            | ((self.inst & 0x80) << 4) as i32
     }
 
     /// Read `I` type immediate value.
@@ -89,7 +176,7 @@ impl Inst {
 
     /// Read `J` type immediate value.
     pub fn imm_uj(&self) -> u32 {
-        (self.inst & 0x80000000 >> 11)
+        ((self.inst & 0x80000000) >> 11)
             | ((self.inst & 0x100000) >> 9)
             | ((self.inst & 0x7FE00000) >> 20)
             | (self.inst & 0xFF000)
@@ -139,14 +226,36 @@ mod test {
         assert_eq!(i.imm_i(), 72);
     }
 
-    /*     #[test]
+    #[test]
     fn test_inst_s() {
-        let inst = [0x23, 0x00, 0xb5, 0x00];
+        let inst = [0x23, 0x24, 0xa1, 0x00];
         let i = Inst::new(inst);
 
-        assert_eq!(i.funct3(), 0);
-        assert_eq!(i.rs1(), 11);
+        assert_eq!(i.funct3(), 2);
+        assert_eq!(i.rs1(), 2);
         assert_eq!(i.rs2(), 10);
-        assert_eq!(i.imm_sb(), 0xb8000);
-    } */
+        assert_eq!(i.imm_s(), 8);
+    }
+
+    #[test]
+    fn test_inst_sb() {
+        let inst = [0xe3, 0x10, 0xb5, 0x80];
+        let i = Inst::new(inst);
+
+        assert_eq!(i.funct3(), 1);
+        assert_eq!(i.rs1(), 10);
+        assert_eq!(i.rs2(), 11);
+        assert_eq!(i.imm_sb_symbol(), -2048);
+    }
+
+    #[test]
+    fn test_inst_r() {
+        let inst = [0x33, 0x85, 0xc5, 0x40];
+        let i = Inst::new(inst);
+
+        assert_eq!(i.rd(), 10);
+        assert_eq!(i.rs1(), 11);
+        assert_eq!(i.rs2(), 12);
+        assert_eq!(i.funct7(), 0b0100000);
+    }
 }
@@ -1,6 +1,8 @@
+use core::fmt;
+
 use crate::define_from_inner;
 
-use super::Inst;
+use super::{abi_name, Inst};
 
 pub struct InstB(Inst);
 
@@ -11,6 +13,10 @@ impl InstB {
         Self(Inst::new(inst))
     }
 
+    pub fn build(opcode: u8, funct3: u8, rs1: usize, rs2: usize, imm: i32) -> Self {
+        Self(Inst::build_b(opcode, funct3, rs1, rs2, imm))
+    }
+
     pub fn inst(&self) -> &Inst {
         &self.0
     }
@@ -39,3 +45,15 @@ impl InstB {
         self.0.imm_sb_symbol()
     }
 }
+
+impl fmt::Display for InstB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            abi_name(self.rs1()),
+            abi_name(self.rs2()),
+            self.imm_symbol()
+        )
+    }
+}
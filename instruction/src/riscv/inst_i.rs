@@ -1,6 +1,8 @@
+use core::fmt;
+
 use crate::define_from_inner;
 
-use super::Inst;
+use super::{abi_name, Inst};
 
 pub struct InstI(Inst);
 
@@ -11,6 +13,10 @@ impl InstI {
         Self(Inst::new(inst))
     }
 
+    pub fn build(opcode: u8, rd: usize, funct3: u8, rs1: usize, imm: i32) -> Self {
+        Self(Inst::build_i(opcode, rd, funct3, rs1, imm))
+    }
+
     pub fn inst(&self) -> &Inst {
         &self.0
     }
@@ -39,3 +45,15 @@ impl InstI {
         self.0.imm_i_symbol()
     }
 }
+
+impl fmt::Display for InstI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            abi_name(self.rd()),
+            abi_name(self.rs1()),
+            self.imm_symbol()
+        )
+    }
+}
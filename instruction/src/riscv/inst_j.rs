@@ -1,6 +1,8 @@
+use core::fmt;
+
 use crate::define_from_inner;
 
-use super::Inst;
+use super::{abi_name, Inst};
 
 pub struct InstJ(Inst);
 
@@ -11,6 +13,10 @@ impl InstJ {
         Self(Inst::new(inst))
     }
 
+    pub fn build(opcode: u8, rd: usize, imm: i32) -> Self {
+        Self(Inst::build_j(opcode, rd, imm))
+    }
+
     pub fn inst(&self) -> &Inst {
         &self.0
     }
@@ -31,3 +37,9 @@ impl InstJ {
         self.0.imm_uj_symbol()
     }
 }
+
+impl fmt::Display for InstJ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", abi_name(self.rd()), self.imm_symbol())
+    }
+}
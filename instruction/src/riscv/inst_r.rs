@@ -1,6 +1,8 @@
+use core::fmt;
+
 use crate::define_from_inner;
 
-use super::Inst;
+use super::{abi_name, Inst};
 
 pub struct InstR(Inst);
 
@@ -11,6 +13,10 @@ impl InstR {
         Self(Inst::new(inst))
     }
 
+    pub fn build(opcode: u8, rd: usize, funct3: u8, rs1: usize, rs2: usize, funct7: u8) -> Self {
+        Self(Inst::build_r(opcode, rd, funct3, rs1, rs2, funct7))
+    }
+
     pub fn inst(&self) -> &Inst {
         &self.0
     }
@@ -34,4 +40,20 @@ impl InstR {
     pub fn rs2(&self) -> usize {
         self.0.rs2()
     }
+
+    pub fn funct7(&self) -> u8 {
+        self.0.funct7()
+    }
+}
+
+impl fmt::Display for InstR {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            abi_name(self.rd()),
+            abi_name(self.rs1()),
+            abi_name(self.rs2())
+        )
+    }
 }
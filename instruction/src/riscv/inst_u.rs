@@ -1,6 +1,8 @@
+use core::fmt;
+
 use crate::define_from_inner;
 
-use super::Inst;
+use super::{abi_name, Inst};
 
 pub struct InstU(Inst);
 
@@ -11,6 +13,10 @@ impl InstU {
         Self(Inst::new(inst))
     }
 
+    pub fn build(opcode: u8, rd: usize, imm: u32) -> Self {
+        Self(Inst::build_u(opcode, rd, imm))
+    }
+
     pub fn inst(&self) -> &Inst {
         &self.0
     }
@@ -31,3 +37,9 @@ impl InstU {
         self.0.imm_u() as i32
     }
 }
+
+impl fmt::Display for InstU {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{:#x}", abi_name(self.rd()), self.imm() >> 12)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::define_from_inner;
+
+use super::Inst;
+
+/// Instruction of vector extension, arithmetic, configuration or memory.
+///
+/// `vd` and `vs3` share the `rd` field, `vs1` shares `rs1` with scalar and
+/// immediate operand.
+pub struct InstV(Inst);
+
+define_from_inner!(Inst, InstV);
+
+impl InstV {
+    pub fn new(inst: [u8; 4]) -> Self {
+        Self(Inst::new(inst))
+    }
+
+    /// Build `OP-V` arithmetic instruction.
+    pub fn build(funct6: u8, vm: bool, vs2: usize, vs1: usize, funct3: u8, vd: usize) -> Self {
+        let funct7 = funct6 << 1 | vm as u8;
+        Self(Inst::build_r(0b1010111, vd, funct3, vs1, vs2, funct7))
+    }
+
+    pub fn inst(&self) -> &Inst {
+        &self.0
+    }
+
+    pub fn opcode(&self) -> u8 {
+        self.0.opcode()
+    }
+
+    pub fn vd(&self) -> usize {
+        self.0.rd()
+    }
+
+    pub fn funct3(&self) -> u8 {
+        self.0.funct3()
+    }
+
+    pub fn vs1(&self) -> usize {
+        self.0.rs1()
+    }
+
+    pub fn rs1(&self) -> usize {
+        self.0.rs1()
+    }
+
+    pub fn vs2(&self) -> usize {
+        self.0.rs2()
+    }
+
+    /// Unmasked, mask bit is set.
+    pub fn vm(&self) -> bool {
+        self.0.funct7() & 1 == 1
+    }
+
+    pub fn funct6(&self) -> u8 {
+        self.0.funct7() >> 1
+    }
+
+    /// Sign extended 5 bits immediate in `vs1` field.
+    pub fn simm5(&self) -> i32 {
+        ((self.0.rs1() as i32) << 27) >> 27
+    }
+
+    /// Addressing mode of memory instruction.
+    pub fn mop(&self) -> u8 {
+        (self.0.funct7() >> 1) & 0b11
+    }
+
+    /// Extended width bit of memory instruction.
+    pub fn mew(&self) -> bool {
+        self.0.funct7() >> 3 & 1 == 1
+    }
+
+    /// Number of fields minus 1 of segment memory instruction.
+    pub fn nf(&self) -> u8 {
+        self.0.funct7() >> 4
+    }
+}
@@ -24,6 +24,12 @@ pub use inst_u::*;
 mod inst_j;
 pub use inst_j::*;
 
+mod inst_v;
+pub use inst_v::*;
+
+mod illegal;
+pub use illegal::*;
+
 #[macro_export]
 macro_rules! define_from_inner {
     ($inner: ty, $outer: ty) => {
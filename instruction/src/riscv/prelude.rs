@@ -1 +1,29 @@
+use crate::{Error, Result};
 
+use super::Inst;
+
+/// ABI names of integer registers, indexed by register number.
+pub const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Get ABI name of register.
+///
+/// Return `"unknown"` when register index out of range.
+pub fn abi_name(reg: usize) -> &'static str {
+    ABI_NAMES.get(reg).copied().unwrap_or("unknown")
+}
+
+/// Check every register index of instruction `inst` is in register file of
+/// `len` registers.
+///
+/// RV32E and RV64E have only `x0` to `x15`, so executors of 16 registers reject
+/// instructions naming `x16` to `x31` as illegal instead of panicking.
+pub fn check_regs(inst: &Inst, len: usize, regs: &[usize]) -> Result<()> {
+    match regs.iter().all(|r| *r < len) {
+        true => Ok(()),
+        false => Err(Error::illegal(inst.raw())),
+    }
+}
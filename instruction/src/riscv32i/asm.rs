@@ -0,0 +1,706 @@
+use crate::riscv::{Inst, ABI_NAMES};
+
+use super::RV32iBaseInst;
+
+/// Kind of assembler error.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic,
+    InvalidOperand,
+    UnknownLabel,
+    DuplicateLabel,
+    TooManyLabels,
+    ImmediateOutOfRange,
+    BufferTooSmall,
+}
+
+/// Assembler error, `line` starts from 1.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+type AsmResult<T> = core::result::Result<T, AsmErrorKind>;
+
+type Base = RV32iBaseInst<()>;
+
+/// Max number of labels in a source.
+pub const MAX_LABELS: usize = 256;
+
+struct Operands<'a> {
+    ops: [&'a str; 3],
+    len: usize,
+}
+
+impl<'a> Operands<'a> {
+    fn parse(s: &'a str) -> AsmResult<Self> {
+        let mut ops = [""; 3];
+        let mut len = 0;
+
+        let s = s.trim();
+        if !s.is_empty() {
+            for op in s.split(',') {
+                if len == ops.len() {
+                    return Err(AsmErrorKind::InvalidOperand);
+                }
+                ops[len] = op.trim();
+                len += 1;
+            }
+        }
+
+        Ok(Self { ops, len })
+    }
+
+    fn expect(&self, len: usize) -> AsmResult<&[&'a str]> {
+        if self.len == len {
+            Ok(&self.ops[..len])
+        } else {
+            Err(AsmErrorKind::InvalidOperand)
+        }
+    }
+}
+
+struct Line<'a> {
+    label: Option<&'a str>,
+    mnemonic: Option<&'a str>,
+    operands: &'a str,
+}
+
+fn is_symbol(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+fn parse_line(text: &str) -> AsmResult<Line<'_>> {
+    let text = match text.find('#') {
+        Some(i) => &text[..i],
+        None => text,
+    };
+    let mut text = text.trim();
+
+    let mut label = None;
+    if let Some(i) = text.find(':') {
+        let name = text[..i].trim();
+        if !is_symbol(name) {
+            return Err(AsmErrorKind::InvalidOperand);
+        }
+        label = Some(name);
+        text = text[i + 1..].trim();
+    }
+
+    if text.is_empty() {
+        return Ok(Line {
+            label,
+            mnemonic: None,
+            operands: "",
+        });
+    }
+
+    let (mnemonic, operands) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text, ""),
+    };
+
+    Ok(Line {
+        label,
+        mnemonic: Some(mnemonic),
+        operands,
+    })
+}
+
+fn reg(s: &str) -> AsmResult<usize> {
+    if let Some(n) = s.strip_prefix('x') {
+        if let Ok(n) = n.parse::<usize>() {
+            if n < 32 {
+                return Ok(n);
+            }
+        }
+    }
+
+    if s == "fp" {
+        return Ok(8);
+    }
+
+    ABI_NAMES
+        .iter()
+        .position(|n| *n == s)
+        .ok_or(AsmErrorKind::InvalidOperand)
+}
+
+/// Parse ordering set of fence, like `rw` or `iorw`.
+fn fence_set(s: &str) -> AsmResult<u8> {
+    let mut set = 0;
+    let mut rest = s;
+    for (bit, c) in [(8, 'i'), (4, 'o'), (2, 'r'), (1, 'w')] {
+        if let Some(r) = rest.strip_prefix(c) {
+            set |= bit;
+            rest = r;
+        }
+    }
+
+    match set != 0 && rest.is_empty() {
+        true => Ok(set),
+        false => Err(AsmErrorKind::InvalidOperand),
+    }
+}
+
+fn number(s: &str) -> AsmResult<i64> {
+    let (neg, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+
+    let v = if let Some(h) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        i64::from_str_radix(h, 16)
+    } else {
+        s.parse::<i64>()
+    }
+    .map_err(|_| AsmErrorKind::InvalidOperand)?;
+
+    Ok(if neg { -v } else { v })
+}
+
+fn imm(s: &str, min: i64, max: i64) -> AsmResult<i64> {
+    let v = number(s)?;
+
+    if v < min || v > max {
+        Err(AsmErrorKind::ImmediateOutOfRange)
+    } else {
+        Ok(v)
+    }
+}
+
+fn imm12(s: &str) -> AsmResult<i32> {
+    imm(s, -2048, 2047).map(|v| v as i32)
+}
+
+fn shamt(s: &str) -> AsmResult<u32> {
+    imm(s, 0, 31).map(|v| v as u32)
+}
+
+fn imm20(s: &str) -> AsmResult<u32> {
+    imm(s, 0, 0xFFFFF).map(|v| v as u32)
+}
+
+/// Parse `imm(reg)` operand.
+fn mem(s: &str) -> AsmResult<(i32, usize)> {
+    let open = s.find('(').ok_or(AsmErrorKind::InvalidOperand)?;
+    let reg_s = s[open + 1..]
+        .strip_suffix(')')
+        .ok_or(AsmErrorKind::InvalidOperand)?;
+
+    let offset = s[..open].trim();
+    let offset = if offset.is_empty() { 0 } else { imm12(offset)? };
+
+    Ok((offset, reg(reg_s.trim())?))
+}
+
+/// Value of `li` immediate, accepting both signed and unsigned 32-bit values.
+fn li_value(s: &str) -> AsmResult<i32> {
+    imm(s, i32::MIN as i64, u32::MAX as i64).map(|v| v as u32 as i32)
+}
+
+/// Whether `li` needs only one instruction, `addi` or `lui`.
+fn li_single(v: i32) -> bool {
+    (-2048..2048).contains(&v) || v & 0xFFF == 0
+}
+
+/// Number of bytes emitted by a statement.
+fn size(mnemonic: &str, operands: &str) -> u32 {
+    if mnemonic == "li" {
+        let v =
+            Operands::parse(operands).and_then(|ops| ops.expect(2).and_then(|o| li_value(o[1])));
+
+        if let Ok(v) = v {
+            if !li_single(v) {
+                return 8;
+            }
+        }
+    }
+
+    4
+}
+
+/// Label name, address and line of definition.
+type Label<'a> = (&'a str, u32, usize);
+
+struct Assembler<'a> {
+    labels: [Label<'a>; MAX_LABELS],
+    len: usize,
+    pc: u32,
+}
+
+impl<'a> Assembler<'a> {
+    /// Collect labels of `src`, sorted by name.
+    ///
+    /// Lines that fail to parse are skipped, they are reported when encoding.
+    fn new(src: &'a str, base: u32) -> Result<Self, AsmError> {
+        let mut asm = Self {
+            labels: [("", 0, 0); MAX_LABELS],
+            len: 0,
+            pc: base,
+        };
+        let mut pc = base;
+
+        for (n, text) in src.lines().enumerate() {
+            let line = match parse_line(text) {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+
+            if let Some(name) = line.label {
+                let label = asm.labels.get_mut(asm.len).ok_or(AsmError {
+                    line: n + 1,
+                    kind: AsmErrorKind::TooManyLabels,
+                })?;
+
+                *label = (name, pc, n + 1);
+                asm.len += 1;
+            }
+
+            if let Some(m) = line.mnemonic {
+                pc = pc.wrapping_add(size(m, line.operands));
+            }
+        }
+
+        let labels = &mut asm.labels[..asm.len];
+        labels.sort_unstable_by_key(|&(name, _, line)| (name, line));
+
+        if let Some(w) = labels.windows(2).find(|w| w[0].0 == w[1].0) {
+            return Err(AsmError {
+                line: w[1].2,
+                kind: AsmErrorKind::DuplicateLabel,
+            });
+        }
+
+        Ok(asm)
+    }
+
+    fn label(&self, name: &str) -> AsmResult<u32> {
+        let labels = &self.labels[..self.len];
+
+        labels
+            .binary_search_by(|&(n, _, _)| n.cmp(name))
+            .map(|i| labels[i].1)
+            .map_err(|_| AsmErrorKind::UnknownLabel)
+    }
+
+    /// Parse jump target, either numeric offset or label.
+    fn target(&self, s: &str, bits: u32) -> AsmResult<i32> {
+        let offset = if is_symbol(s) {
+            self.label(s)?.wrapping_sub(self.pc) as i32 as i64
+        } else {
+            number(s)?
+        };
+
+        let bound = 1i64 << bits;
+        if offset < -bound || offset >= bound || offset & 1 != 0 {
+            return Err(AsmErrorKind::ImmediateOutOfRange);
+        }
+
+        Ok(offset as i32)
+    }
+
+    fn branch(&self, rs1: &str, rs2: &str, target: &str) -> AsmResult<(usize, usize, i32)> {
+        Ok((reg(rs1)?, reg(rs2)?, self.target(target, 12)?))
+    }
+
+    fn encode(&self, mnemonic: &str, operands: &str, out: &mut [Base; 2]) -> AsmResult<usize> {
+        let ops = Operands::parse(operands)?;
+
+        let inst = match mnemonic {
+            "lui" | "auipc" => {
+                let o = ops.expect(2)?;
+                let (rd, v) = (reg(o[0])?, imm20(o[1])?);
+
+                if mnemonic == "lui" {
+                    Base::lui(rd, v)
+                } else {
+                    Base::auipc(rd, v)
+                }
+            }
+            "jal" => match ops.len {
+                1 => Base::jal(1, self.target(ops.ops[0], 20)?),
+                _ => {
+                    let o = ops.expect(2)?;
+                    Base::jal(reg(o[0])?, self.target(o[1], 20)?)
+                }
+            },
+            "jalr" => match ops.len {
+                1 => Base::jalr(1, reg(ops.ops[0])?, 0),
+                2 => {
+                    let (offset, rs1) = mem(ops.ops[1])?;
+                    Base::jalr(reg(ops.ops[0])?, rs1, offset)
+                }
+                _ => {
+                    let o = ops.expect(3)?;
+                    Base::jalr(reg(o[0])?, reg(o[1])?, imm12(o[2])?)
+                }
+            },
+            "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" => {
+                let o = ops.expect(3)?;
+                let (rs1, rs2, offset) = self.branch(o[0], o[1], o[2])?;
+
+                match mnemonic {
+                    "beq" => Base::beq(rs1, rs2, offset),
+                    "bne" => Base::bne(rs1, rs2, offset),
+                    "blt" => Base::blt(rs1, rs2, offset),
+                    "bge" => Base::bge(rs1, rs2, offset),
+                    "bltu" => Base::bltu(rs1, rs2, offset),
+                    _ => Base::bgeu(rs1, rs2, offset),
+                }
+            }
+            "bgt" | "ble" | "bgtu" | "bleu" => {
+                let o = ops.expect(3)?;
+                let (rs2, rs1, offset) = self.branch(o[0], o[1], o[2])?;
+
+                match mnemonic {
+                    "bgt" => Base::blt(rs1, rs2, offset),
+                    "ble" => Base::bge(rs1, rs2, offset),
+                    "bgtu" => Base::bltu(rs1, rs2, offset),
+                    _ => Base::bgeu(rs1, rs2, offset),
+                }
+            }
+            "beqz" | "bnez" | "bltz" | "bgez" => {
+                let o = ops.expect(2)?;
+                let (rs1, _, offset) = self.branch(o[0], "zero", o[1])?;
+
+                match mnemonic {
+                    "beqz" => Base::beq(rs1, 0, offset),
+                    "bnez" => Base::bne(rs1, 0, offset),
+                    "bltz" => Base::blt(rs1, 0, offset),
+                    _ => Base::bge(rs1, 0, offset),
+                }
+            }
+            "bgtz" | "blez" => {
+                let o = ops.expect(2)?;
+                let (rs2, _, offset) = self.branch(o[0], "zero", o[1])?;
+
+                if mnemonic == "bgtz" {
+                    Base::blt(0, rs2, offset)
+                } else {
+                    Base::bge(0, rs2, offset)
+                }
+            }
+            "lb" | "lh" | "lw" | "lbu" | "lhu" | "lwu" => {
+                let o = ops.expect(2)?;
+                let (rd, (offset, rs1)) = (reg(o[0])?, mem(o[1])?);
+
+                match mnemonic {
+                    "lb" => Base::lb(rd, rs1, offset),
+                    "lh" => Base::lh(rd, rs1, offset),
+                    "lw" => Base::lw(rd, rs1, offset),
+                    "lbu" => Base::lbu(rd, rs1, offset),
+                    "lhu" => Base::lhu(rd, rs1, offset),
+                    _ => Base::lwu(rd, rs1, offset),
+                }
+            }
+            "sb" | "sh" | "sw" => {
+                let o = ops.expect(2)?;
+                let (rs2, (offset, rs1)) = (reg(o[0])?, mem(o[1])?);
+
+                match mnemonic {
+                    "sb" => Base::sb(rs2, rs1, offset),
+                    "sh" => Base::sh(rs2, rs1, offset),
+                    _ => Base::sw(rs2, rs1, offset),
+                }
+            }
+            "addi" | "slti" | "sltiu" | "xori" | "ori" | "andi" => {
+                let o = ops.expect(3)?;
+                let (rd, rs1, v) = (reg(o[0])?, reg(o[1])?, imm12(o[2])?);
+
+                match mnemonic {
+                    "addi" => Base::addi(rd, rs1, v),
+                    "slti" => Base::slti(rd, rs1, v),
+                    "sltiu" => Base::sltiu(rd, rs1, v),
+                    "xori" => Base::xori(rd, rs1, v),
+                    "ori" => Base::ori(rd, rs1, v),
+                    _ => Base::andi(rd, rs1, v),
+                }
+            }
+            "slli" | "srli" | "srai" => {
+                let o = ops.expect(3)?;
+                let (rd, rs1, v) = (reg(o[0])?, reg(o[1])?, shamt(o[2])?);
+
+                match mnemonic {
+                    "slli" => Base::slli(rd, rs1, v),
+                    "srli" => Base::srli(rd, rs1, v),
+                    _ => Base::srai(rd, rs1, v),
+                }
+            }
+            "add" | "sub" | "sll" | "slt" | "sltu" | "xor" | "srl" | "sra" | "or" | "and" => {
+                let o = ops.expect(3)?;
+                let (rd, rs1, rs2) = (reg(o[0])?, reg(o[1])?, reg(o[2])?);
+
+                match mnemonic {
+                    "add" => Base::add(rd, rs1, rs2),
+                    "sub" => Base::sub(rd, rs1, rs2),
+                    "sll" => Base::sll(rd, rs1, rs2),
+                    "slt" => Base::slt(rd, rs1, rs2),
+                    "sltu" => Base::sltu(rd, rs1, rs2),
+                    "xor" => Base::xor(rd, rs1, rs2),
+                    "srl" => Base::srl(rd, rs1, rs2),
+                    "sra" => Base::sra(rd, rs1, rs2),
+                    "or" => Base::or(rd, rs1, rs2),
+                    _ => Base::and(rd, rs1, rs2),
+                }
+            }
+            "fence" => match ops.len {
+                0 => Base::fence(0xF, 0xF),
+                _ => {
+                    let o = ops.expect(2)?;
+                    Base::fence(fence_set(o[0])?, fence_set(o[1])?)
+                }
+            },
+            "fence.tso" => {
+                ops.expect(0)?;
+                Base::fence_tso()
+            }
+            "fence.i" => {
+                ops.expect(0)?;
+                Base::fence_i()
+            }
+            "ecall" => {
+                ops.expect(0)?;
+                Base::ecall()
+            }
+            "ebreak" => {
+                ops.expect(0)?;
+                Base::ebreak()
+            }
+            "nop" => {
+                ops.expect(0)?;
+                Base::addi(0, 0, 0)
+            }
+            "ret" => {
+                ops.expect(0)?;
+                Base::jalr(0, 1, 0)
+            }
+            "j" => {
+                let o = ops.expect(1)?;
+                Base::jal(0, self.target(o[0], 20)?)
+            }
+            "jr" => {
+                let o = ops.expect(1)?;
+                Base::jalr(0, reg(o[0])?, 0)
+            }
+            "li" => {
+                let o = ops.expect(2)?;
+                let (rd, v) = (reg(o[0])?, li_value(o[1])?);
+
+                if (-2048..2048).contains(&v) {
+                    Base::addi(rd, 0, v)
+                } else if v & 0xFFF == 0 {
+                    Base::lui(rd, v as u32 >> 12)
+                } else {
+                    let hi = (v as u32).wrapping_add(0x800) >> 12;
+                    let lo = v.wrapping_sub((hi << 12) as i32);
+
+                    out[0] = Base::lui(rd, hi);
+                    out[1] = Base::addi(rd, rd, lo);
+                    return Ok(2);
+                }
+            }
+            "mv" | "not" | "neg" | "seqz" | "snez" | "sltz" | "sgtz" => {
+                let o = ops.expect(2)?;
+                let (rd, rs) = (reg(o[0])?, reg(o[1])?);
+
+                match mnemonic {
+                    "mv" => Base::addi(rd, rs, 0),
+                    "not" => Base::xori(rd, rs, -1),
+                    "neg" => Base::sub(rd, 0, rs),
+                    "seqz" => Base::sltiu(rd, rs, 1),
+                    "snez" => Base::sltu(rd, 0, rs),
+                    "sltz" => Base::slt(rd, rs, 0),
+                    _ => Base::slt(rd, 0, rs),
+                }
+            }
+            _ => return Err(AsmErrorKind::UnknownMnemonic),
+        };
+
+        out[0] = inst;
+        Ok(1)
+    }
+}
+
+fn raw(inst: &Base) -> u32 {
+    inst.inst().map(Inst::raw).unwrap_or_default()
+}
+
+/// Assemble RV32I assembly text into `out`, return the number of bytes written.
+///
+/// `base` is the address of the first instruction, used to resolve labels.
+/// Supports labels, `#` comments, `.word` and common pseudo instructions.
+/// At most [`MAX_LABELS`] labels can be defined, each only once.
+pub fn assemble(src: &str, base: u32, out: &mut [u8]) -> Result<usize, AsmError> {
+    let mut asm = Assembler::new(src, base)?;
+    let mut len = 0;
+
+    for (n, text) in src.lines().enumerate() {
+        let err = |kind| AsmError { line: n + 1, kind };
+
+        let line = parse_line(text).map_err(err)?;
+        let mnemonic = match line.mnemonic {
+            Some(m) => m,
+            None => continue,
+        };
+
+        let mut insts = [Base::addi(0, 0, 0), Base::addi(0, 0, 0)];
+        let mut words = [0u32; 2];
+
+        let count = if mnemonic == ".word" {
+            let ops = Operands::parse(line.operands).map_err(err)?;
+            let o = ops.expect(1).map_err(err)?;
+
+            words[0] = if is_symbol(o[0]) {
+                asm.label(o[0]).map_err(err)?
+            } else {
+                imm(o[0], i32::MIN as i64, u32::MAX as i64).map_err(err)? as u32
+            };
+            1
+        } else {
+            let count = asm
+                .encode(mnemonic, line.operands, &mut insts)
+                .map_err(err)?;
+            for (w, i) in words.iter_mut().zip(insts.iter()) {
+                *w = raw(i);
+            }
+            count
+        };
+
+        for w in &words[..count] {
+            let dst = out
+                .get_mut(len..len + 4)
+                .ok_or(AsmErrorKind::BufferTooSmall)
+                .map_err(err)?;
+
+            dst.copy_from_slice(&w.to_le_bytes());
+            len += 4;
+            asm.pc = asm.pc.wrapping_add(4);
+        }
+    }
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assemble, AsmError, AsmErrorKind};
+
+    #[test]
+    fn test_assemble() {
+        let src = "
+            # sum 1..=10
+                li a0, 0
+                li a1, 10
+            loop:
+                add a0, a0, a1
+                addi a1, a1, -1
+                bnez a1, loop
+                sw a0, 8(sp)
+                li t0, 0x12345fff
+                j end
+                .word 0xdeadbeef
+            end: ret
+        ";
+
+        let mut out = [0u8; 64];
+        let len = assemble(src, 0, &mut out).unwrap();
+
+        let expect = [
+            0x13, 0x05, 0x00, 0x00, // li a0, 0
+            0x93, 0x05, 0xa0, 0x00, // li a1, 10
+            0x33, 0x05, 0xb5, 0x00, // add a0, a0, a1
+            0x93, 0x85, 0xf5, 0xff, // addi a1, a1, -1
+            0xe3, 0x9c, 0x05, 0xfe, // bnez a1, -8
+            0x23, 0x24, 0xa1, 0x00, // sw a0, 8(sp)
+            0xb7, 0x62, 0x34, 0x12, // lui t0, 0x12346
+            0x93, 0x82, 0xf2, 0xff, // addi t0, t0, -1
+            0x6f, 0x00, 0x80, 0x00, // j 8
+            0xef, 0xbe, 0xad, 0xde, // .word
+            0x67, 0x80, 0x00, 0x00, // ret
+        ];
+
+        assert_eq!(&out[..len], &expect);
+    }
+
+    #[test]
+    fn test_assemble_li() {
+        let src = "
+                li a0, 0x10000
+                li a1, -4096
+                li a2, 0x10001
+                j end
+            end:
+        ";
+
+        let mut out = [0u8; 32];
+        let len = assemble(src, 0, &mut out).unwrap();
+
+        let expect = [
+            0x37, 0x05, 0x01, 0x00, // lui a0, 0x10
+            0xb7, 0xf5, 0xff, 0xff, // lui a1, 0xfffff
+            0x37, 0x06, 0x01, 0x00, // lui a2, 0x10
+            0x13, 0x06, 0x16, 0x00, // addi a2, a2, 1
+            0x6f, 0x00, 0x40, 0x00, // j 4
+        ];
+
+        assert_eq!(&out[..len], &expect);
+    }
+
+    #[test]
+    fn test_assemble_fence() {
+        let mut out = [0u8; 16];
+        let len = assemble("fence\nfence r, rw\nfence.tso\nfence.i", 0, &mut out).unwrap();
+
+        assert_eq!(len, 16);
+        assert_eq!(
+            out,
+            [
+                0x0f, 0x00, 0xf0, 0x0f, 0x0f, 0x00, 0x30, 0x02, 0x0f, 0x00, 0x30, 0x83, 0x0f, 0x10,
+                0x00, 0x00
+            ]
+        );
+        assert_eq!(
+            assemble("fence rx, w", 0, &mut out).unwrap_err().kind,
+            AsmErrorKind::InvalidOperand
+        );
+    }
+
+    #[test]
+    fn test_assemble_error() {
+        let mut out = [0u8; 16];
+
+        assert_eq!(
+            assemble("nop\nj missing", 0, &mut out),
+            Err(AsmError {
+                line: 2,
+                kind: AsmErrorKind::UnknownLabel
+            })
+        );
+        assert_eq!(
+            assemble("addi a0, a0, 4096", 0, &mut out),
+            Err(AsmError {
+                line: 1,
+                kind: AsmErrorKind::ImmediateOutOfRange
+            })
+        );
+        assert_eq!(
+            assemble("a: nop\nb: nop\na: nop", 0, &mut out),
+            Err(AsmError {
+                line: 3,
+                kind: AsmErrorKind::DuplicateLabel
+            })
+        );
+        assert_eq!(
+            assemble("nop\nnop\nnop\nnop\nnop", 0, &mut out),
+            Err(AsmError {
+                line: 5,
+                kind: AsmErrorKind::BufferTooSmall
+            })
+        );
+    }
+}
@@ -1,13 +1,13 @@
 use crate::{
-    riscv::{Inst, InstB, InstI, InstJ, InstR, InstS, InstU},
-    Error, Instruction, Memory, MemoryMut, Reg32, Result,
+    riscv::{check_regs, Inst, InstB, InstI, InstJ, InstR, InstS, InstU},
+    Context, Error, Instruction, Memory, MemoryMut, Reg32, Result,
 };
 
 use super::execute;
 
 /// Instruction for base of RISCV32i
 ///
-/// These instruction have no CSR and FENCE included
+/// These instruction have no CSR included
 pub enum RV32iBaseInst<I> {
     /// Load Upper Immediate
     Lui(InstU),
@@ -85,6 +85,10 @@ pub enum RV32iBaseInst<I> {
     Or(InstR),
     /// And
     And(InstR),
+    /// Memory ordering fence
+    Fence(InstI),
+    /// Instruction fetch fence, makes earlier stores visible to fetch
+    FenceI(InstI),
     /// Env call
     ECall(InstI),
     /// Env break
@@ -93,6 +97,129 @@ pub enum RV32iBaseInst<I> {
     Other(I),
 }
 
+/// Mnemonic of each [`RV32iBaseInst`] variant, indexed by [`RV32iBaseInst::index`].
+pub const RV32I_NAMES: [&str; 43] = [
+    "lui", "auipc", "jal", "jalr", "beq", "bne", "blt", "bge", "bltu", "bgeu", "lb", "lh", "lw",
+    "lbu", "lhu", "lwu", "sb", "sh", "sw", "addi", "slti", "sltiu", "xori", "ori", "andi", "slli",
+    "srli", "srai", "add", "sub", "sll", "slt", "sltu", "xor", "srl", "sra", "or", "and", "fence",
+    "fence.i", "ecall", "ebreak", "other",
+];
+
+impl<I> RV32iBaseInst<I> {
+    /// Index of variant in [`RV32I_NAMES`].
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Lui(_) => 0,
+            Self::Auipc(_) => 1,
+            Self::Jal(_) => 2,
+            Self::Jalr(_) => 3,
+            Self::Beq(_) => 4,
+            Self::Bne(_) => 5,
+            Self::Blt(_) => 6,
+            Self::Bge(_) => 7,
+            Self::Bltu(_) => 8,
+            Self::Bgeu(_) => 9,
+            Self::Lb(_) => 10,
+            Self::Lh(_) => 11,
+            Self::Lw(_) => 12,
+            Self::Lbu(_) => 13,
+            Self::Lhu(_) => 14,
+            Self::Lwu(_) => 15,
+            Self::Sb(_) => 16,
+            Self::Sh(_) => 17,
+            Self::Sw(_) => 18,
+            Self::Addi(_) => 19,
+            Self::Slti(_) => 20,
+            Self::Sltiu(_) => 21,
+            Self::Xori(_) => 22,
+            Self::Ori(_) => 23,
+            Self::Andi(_) => 24,
+            Self::Slli(_) => 25,
+            Self::Srli(_) => 26,
+            Self::Srai(_) => 27,
+            Self::Add(_) => 28,
+            Self::Sub(_) => 29,
+            Self::Sll(_) => 30,
+            Self::Slt(_) => 31,
+            Self::Sltu(_) => 32,
+            Self::Xor(_) => 33,
+            Self::Srl(_) => 34,
+            Self::Sra(_) => 35,
+            Self::Or(_) => 36,
+            Self::And(_) => 37,
+            Self::Fence(_) => 38,
+            Self::FenceI(_) => 39,
+            Self::ECall(_) => 40,
+            Self::EBreak(_) => 41,
+            Self::Other(_) => 42,
+        }
+    }
+
+    /// Whether branch is taken with `regs` before execute, `None` for other
+    /// instructions.
+    pub fn branch_taken<R: Reg32>(&self, regs: &[R]) -> Option<bool> {
+        let (i, cond): (_, fn(u32, u32) -> bool) = match self {
+            Self::Beq(i) => (i, |a, b| a == b),
+            Self::Bne(i) => (i, |a, b| a != b),
+            Self::Blt(i) => (i, |a, b| (a as i32) < (b as i32)),
+            Self::Bge(i) => (i, |a, b| (a as i32) >= (b as i32)),
+            Self::Bltu(i) => (i, |a, b| a < b),
+            Self::Bgeu(i) => (i, |a, b| a >= b),
+            _ => return None,
+        };
+
+        let (a, b) = (regs.get(i.rs1())?, regs.get(i.rs2())?);
+        Some(cond(a.reg32(), b.reg32()))
+    }
+
+    /// Reject registers beyond register file of `len` registers, like `x16` of
+    /// RV32E.
+    fn check_regs(&self, len: usize) -> Result<()> {
+        match self {
+            Self::Lui(i) | Self::Auipc(i) => check_regs(i.inst(), len, &[i.rd()]),
+            Self::Jal(i) => check_regs(i.inst(), len, &[i.rd()]),
+            Self::Beq(i)
+            | Self::Bne(i)
+            | Self::Blt(i)
+            | Self::Bge(i)
+            | Self::Bltu(i)
+            | Self::Bgeu(i) => check_regs(i.inst(), len, &[i.rs1(), i.rs2()]),
+            Self::Sb(i) | Self::Sh(i) | Self::Sw(i) => {
+                check_regs(i.inst(), len, &[i.rs1(), i.rs2()])
+            }
+            Self::Jalr(i)
+            | Self::Lb(i)
+            | Self::Lh(i)
+            | Self::Lw(i)
+            | Self::Lbu(i)
+            | Self::Lhu(i)
+            | Self::Lwu(i)
+            | Self::Addi(i)
+            | Self::Slti(i)
+            | Self::Sltiu(i)
+            | Self::Xori(i)
+            | Self::Ori(i)
+            | Self::Andi(i)
+            | Self::Slli(i)
+            | Self::Srli(i)
+            | Self::Srai(i) => check_regs(i.inst(), len, &[i.rd(), i.rs1()]),
+            Self::Add(i)
+            | Self::Sub(i)
+            | Self::Sll(i)
+            | Self::Slt(i)
+            | Self::Sltu(i)
+            | Self::Xor(i)
+            | Self::Srl(i)
+            | Self::Sra(i)
+            | Self::Or(i)
+            | Self::And(i) => check_regs(i.inst(), len, &[i.rd(), i.rs1(), i.rs2()]),
+            // Register fields of fences and environment instructions are ignored.
+            Self::Fence(_) | Self::FenceI(_) | Self::ECall(_) | Self::EBreak(_) => Ok(()),
+            Self::Other(_) => Ok(()),
+        }
+    }
+}
+
 impl<I: Instruction> RV32iBaseInst<I> {
     fn _new(bytes: &[u8]) -> Result<Self> {
         if bytes.len() < 4 {
@@ -152,15 +279,10 @@ impl<I: Instruction> RV32iBaseInst<I> {
                     0b100 => Self::Xori(i),
                     0b110 => Self::Ori(i),
                     0b111 => Self::Andi(i),
-                    0b001 => Self::Slli(i),
-                    0b101 => {
-                        let t = imm_i & 0x400;
-                        if t == 0 {
-                            Self::Srli(i)
-                        } else {
-                            Self::Srai(i)
-                        }
-                    }
+                    // Other upper bits of shift immediate are for extensions.
+                    0b001 if imm_i >> 5 == 0 => Self::Slli(i),
+                    0b101 if imm_i >> 5 == 0 => Self::Srli(i),
+                    0b101 if imm_i >> 5 == 0b0100000 => Self::Srai(i),
                     _ => Self::Other(I::new(bytes)?),
                 }
             }
@@ -169,30 +291,25 @@ impl<I: Instruction> RV32iBaseInst<I> {
                 let funct7 = inst.funct7();
                 let i = inst.into();
 
-                match funct3 {
-                    0b000 => {
-                        if funct7 == 0 {
-                            Self::Add(i)
-                        } else {
-                            Self::Sub(i)
-                        }
-                    }
-                    0b001 => Self::Sll(i),
-                    0b010 => Self::Slt(i),
-                    0b011 => Self::Sltu(i),
-                    0b100 => Self::Xor(i),
-                    0b101 => {
-                        if funct7 == 0 {
-                            Self::Srl(i)
-                        } else {
-                            Self::Sra(i)
-                        }
-                    }
-                    0b110 => Self::Or(i),
-                    0b111 => Self::Add(i),
+                match (funct3, funct7) {
+                    (0b000, 0b0000000) => Self::Add(i),
+                    (0b000, 0b0100000) => Self::Sub(i),
+                    (0b001, 0b0000000) => Self::Sll(i),
+                    (0b010, 0b0000000) => Self::Slt(i),
+                    (0b011, 0b0000000) => Self::Sltu(i),
+                    (0b100, 0b0000000) => Self::Xor(i),
+                    (0b101, 0b0000000) => Self::Srl(i),
+                    (0b101, 0b0100000) => Self::Sra(i),
+                    (0b110, 0b0000000) => Self::Or(i),
+                    (0b111, 0b0000000) => Self::And(i),
                     _ => Self::Other(I::new(bytes)?),
                 }
             }
+            0b0001111 => match inst.funct3() {
+                0b000 => Self::Fence(inst.into()),
+                0b001 => Self::FenceI(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
             0b1110011 => {
                 let funct3 = inst.funct3();
                 let rd = inst.rd();
@@ -230,32 +347,111 @@ where
         Self::_new(bytes)
     }
 
-    fn execute<M>(&mut self, pc: &mut R, regs: &mut [R], memory: &mut M) -> Result<()>
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
     where
         M: Memory<Register = R> + MemoryMut,
+        C: Context,
     {
+        self.check_regs(regs.len())
+            .map_err(|e| e.with_pc(pc.reg32() as u64))?;
+        let ialign = memory.alignment().ialign;
+
         match self {
             Self::Lui(inst) => execute::lui(inst, pc, regs),
             Self::Auipc(inst) => execute::auipc(inst, pc, regs),
-            Self::Jal(inst) => execute::jal(inst, pc, regs),
-            Self::Jalr(inst) => execute::jalr(inst, pc, regs),
-            Self::Beq(inst) => execute::beq(inst, pc, regs),
-            Self::Bne(inst) => execute::bne(inst, pc, regs),
-            Self::Blt(inst) => execute::blt(inst, pc, regs),
-            Self::Bge(inst) => execute::bge(inst, pc, regs),
-            Self::Bltu(inst) => execute::bltu(inst, pc, regs),
-            Self::Bgeu(inst) => execute::bgeu(inst, pc, regs),
-            Self::Lb(inst) => execute::lb(inst, pc, regs, memory),
-            Self::Lh(inst) => execute::lh(inst, pc, regs, memory),
-            Self::Lw(inst) => execute::lw(inst, pc, regs, memory),
-            Self::Lbu(inst) => execute::lbu(inst, pc, regs, memory),
-            Self::Lhu(inst) => execute::lhu(inst, pc, regs, memory),
-            Self::Lwu(inst) => execute::lwu(inst, pc, regs, memory),
-            _ => {}
+            Self::Jal(inst) => execute::jal(inst, pc, regs, ialign)?,
+            Self::Jalr(inst) => execute::jalr(inst, pc, regs, ialign)?,
+            Self::Beq(inst) => execute::beq(inst, pc, regs, ialign)?,
+            Self::Bne(inst) => execute::bne(inst, pc, regs, ialign)?,
+            Self::Blt(inst) => execute::blt(inst, pc, regs, ialign)?,
+            Self::Bge(inst) => execute::bge(inst, pc, regs, ialign)?,
+            Self::Bltu(inst) => execute::bltu(inst, pc, regs, ialign)?,
+            Self::Bgeu(inst) => execute::bgeu(inst, pc, regs, ialign)?,
+            Self::Lb(inst) => execute::lb(inst, pc, regs, memory)?,
+            Self::Lh(inst) => execute::lh(inst, pc, regs, memory)?,
+            Self::Lw(inst) => execute::lw(inst, pc, regs, memory)?,
+            Self::Lbu(inst) => execute::lbu(inst, pc, regs, memory)?,
+            Self::Lhu(inst) => execute::lhu(inst, pc, regs, memory)?,
+            Self::Lwu(inst) => execute::lwu(inst, pc, regs, memory)?,
+            Self::Sb(inst) => execute::sb(inst, pc, regs, memory)?,
+            Self::Sh(inst) => execute::sh(inst, pc, regs, memory)?,
+            Self::Sw(inst) => execute::sw(inst, pc, regs, memory)?,
+            Self::Addi(inst) => execute::addi(inst, pc, regs),
+            Self::Slti(inst) => execute::slti(inst, pc, regs),
+            Self::Sltiu(inst) => execute::sltiu(inst, pc, regs),
+            Self::Xori(inst) => execute::xori(inst, pc, regs),
+            Self::Ori(inst) => execute::ori(inst, pc, regs),
+            Self::Andi(inst) => execute::andi(inst, pc, regs),
+            Self::Slli(inst) => execute::slli(inst, pc, regs),
+            Self::Srli(inst) => execute::srli(inst, pc, regs),
+            Self::Srai(inst) => execute::srai(inst, pc, regs),
+            Self::Add(inst) => execute::add(inst, pc, regs),
+            Self::Sub(inst) => execute::sub(inst, pc, regs),
+            Self::Sll(inst) => execute::sll(inst, pc, regs),
+            Self::Slt(inst) => execute::slt(inst, pc, regs),
+            Self::Sltu(inst) => execute::sltu(inst, pc, regs),
+            Self::Xor(inst) => execute::xor(inst, pc, regs),
+            Self::Srl(inst) => execute::srl(inst, pc, regs),
+            Self::Sra(inst) => execute::sra(inst, pc, regs),
+            Self::Or(inst) => execute::or(inst, pc, regs),
+            Self::And(inst) => execute::and(inst, pc, regs),
+            Self::Fence(_) | Self::FenceI(_) => execute::fence(pc),
+            Self::ECall(_) => return Err(Error::EnvironmentCall),
+            Self::EBreak(_) => return Err(Error::Breakpoint),
+            Self::Other(inst) => inst.execute(pc, regs, memory, context)?,
         }
 
         clear_x0(regs);
 
         Ok(())
     }
+
+    fn is_fence_i(&self) -> bool {
+        match self {
+            Self::FenceI(_) => true,
+            Self::Other(inst) => inst.is_fence_i(),
+            _ => false,
+        }
+    }
+
+    fn rd(&self) -> Option<usize> {
+        match self {
+            Self::Lui(i) | Self::Auipc(i) => Some(i.rd()),
+            Self::Jal(i) => Some(i.rd()),
+            Self::Jalr(i)
+            | Self::Lb(i)
+            | Self::Lh(i)
+            | Self::Lw(i)
+            | Self::Lbu(i)
+            | Self::Lhu(i)
+            | Self::Lwu(i)
+            | Self::Addi(i)
+            | Self::Slti(i)
+            | Self::Sltiu(i)
+            | Self::Xori(i)
+            | Self::Ori(i)
+            | Self::Andi(i)
+            | Self::Slli(i)
+            | Self::Srli(i)
+            | Self::Srai(i) => Some(i.rd()),
+            Self::Add(i)
+            | Self::Sub(i)
+            | Self::Sll(i)
+            | Self::Slt(i)
+            | Self::Sltu(i)
+            | Self::Xor(i)
+            | Self::Srl(i)
+            | Self::Sra(i)
+            | Self::Or(i)
+            | Self::And(i) => Some(i.rd()),
+            Self::Other(inst) => inst.rd(),
+            _ => None,
+        }
+    }
 }
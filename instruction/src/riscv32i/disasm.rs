@@ -0,0 +1,224 @@
+use core::fmt;
+
+use crate::{
+    riscv::{abi_name, InstB, InstI},
+    Disassemble,
+};
+
+use super::RV32iBaseInst;
+
+fn load(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstI) -> fmt::Result {
+    write!(
+        f,
+        "{} {},{}({})",
+        name,
+        abi_name(inst.rd()),
+        inst.imm_symbol(),
+        abi_name(inst.rs1())
+    )
+}
+
+fn shift(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstI) -> fmt::Result {
+    write!(
+        f,
+        "{} {},{},{}",
+        name,
+        abi_name(inst.rd()),
+        abi_name(inst.rs1()),
+        inst.imm() & 0x1F
+    )
+}
+
+/// Jump target, absolute if `pc` is known.
+struct Target(Option<u64>, i32);
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(pc) => write!(f, "{:x}", pc.wrapping_add(self.1 as i64 as u64)),
+            None => write!(f, "{}", self.1),
+        }
+    }
+}
+
+fn branch(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstB, pc: Option<u64>) -> fmt::Result {
+    let (rs1, rs2) = (inst.rs1(), inst.rs2());
+    let target = Target(pc, inst.imm_symbol());
+
+    match (name, rs1, rs2) {
+        ("beq" | "bne" | "blt" | "bge", _, 0) => {
+            write!(f, "{}z {},{}", name, abi_name(rs1), target)
+        }
+        ("blt", 0, _) => write!(f, "bgtz {},{}", abi_name(rs2), target),
+        ("bge", 0, _) => write!(f, "blez {},{}", abi_name(rs2), target),
+        _ => write!(f, "{} {},{},{}", name, abi_name(rs1), abi_name(rs2), target),
+    }
+}
+
+/// Write ordering set of fence as `iorw` letters.
+fn fence_set(f: &mut fmt::Formatter<'_>, set: u32) -> fmt::Result {
+    for (bit, c) in [(8, 'i'), (4, 'o'), (2, 'r'), (1, 'w')] {
+        if set & bit != 0 {
+            write!(f, "{}", c)?;
+        }
+    }
+    Ok(())
+}
+
+fn fence(f: &mut fmt::Formatter<'_>, inst: &InstI) -> fmt::Result {
+    let imm = inst.imm() & 0xFFF;
+    let (fm, pred, succ) = (imm >> 8, (imm >> 4) & 0xF, imm & 0xF);
+
+    match (fm, pred, succ) {
+        (0b1000, 0b0011, 0b0011) => f.write_str("fence.tso"),
+        (0, 0xF, 0xF) => f.write_str("fence"),
+        _ => {
+            f.write_str("fence ")?;
+            fence_set(f, pred)?;
+            f.write_str(",")?;
+            fence_set(f, succ)
+        }
+    }
+}
+
+impl<I: Disassemble> Disassemble for RV32iBaseInst<I> {
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lui(i) => write!(f, "lui {}", i),
+            Self::Auipc(i) => write!(f, "auipc {}", i),
+            Self::Jal(i) => match (i.rd(), Target(pc, i.imm_symbol())) {
+                (0, target) => write!(f, "j {}", target),
+                (1, target) => write!(f, "jal {}", target),
+                (rd, target) => write!(f, "jal {},{}", abi_name(rd), target),
+            },
+            Self::Jalr(i) => match (i.rd(), i.rs1(), i.imm_symbol()) {
+                (0, 1, 0) => f.write_str("ret"),
+                (0, rs1, 0) => write!(f, "jr {}", abi_name(rs1)),
+                (1, rs1, 0) => write!(f, "jalr {}", abi_name(rs1)),
+                _ => load(f, "jalr", i),
+            },
+            Self::Beq(i) => branch(f, "beq", i, pc),
+            Self::Bne(i) => branch(f, "bne", i, pc),
+            Self::Blt(i) => branch(f, "blt", i, pc),
+            Self::Bge(i) => branch(f, "bge", i, pc),
+            Self::Bltu(i) => branch(f, "bltu", i, pc),
+            Self::Bgeu(i) => branch(f, "bgeu", i, pc),
+            Self::Lb(i) => load(f, "lb", i),
+            Self::Lh(i) => load(f, "lh", i),
+            Self::Lw(i) => load(f, "lw", i),
+            Self::Lbu(i) => load(f, "lbu", i),
+            Self::Lhu(i) => load(f, "lhu", i),
+            Self::Lwu(i) => load(f, "lwu", i),
+            Self::Sb(i) => write!(f, "sb {}", i),
+            Self::Sh(i) => write!(f, "sh {}", i),
+            Self::Sw(i) => write!(f, "sw {}", i),
+            Self::Addi(i) => match (i.rd(), i.rs1(), i.imm_symbol()) {
+                (0, 0, 0) => f.write_str("nop"),
+                (rd, 0, imm) => write!(f, "li {},{}", abi_name(rd), imm),
+                (rd, rs1, 0) => write!(f, "mv {},{}", abi_name(rd), abi_name(rs1)),
+                _ => write!(f, "addi {}", i),
+            },
+            Self::Slti(i) => write!(f, "slti {}", i),
+            Self::Sltiu(i) => match i.imm_symbol() {
+                1 => write!(f, "seqz {},{}", abi_name(i.rd()), abi_name(i.rs1())),
+                _ => write!(f, "sltiu {}", i),
+            },
+            Self::Xori(i) => match i.imm_symbol() {
+                -1 => write!(f, "not {},{}", abi_name(i.rd()), abi_name(i.rs1())),
+                _ => write!(f, "xori {}", i),
+            },
+            Self::Ori(i) => write!(f, "ori {}", i),
+            Self::Andi(i) => write!(f, "andi {}", i),
+            Self::Slli(i) => shift(f, "slli", i),
+            Self::Srli(i) => shift(f, "srli", i),
+            Self::Srai(i) => shift(f, "srai", i),
+            Self::Add(i) => write!(f, "add {}", i),
+            Self::Sub(i) => match i.rs1() {
+                0 => write!(f, "neg {},{}", abi_name(i.rd()), abi_name(i.rs2())),
+                _ => write!(f, "sub {}", i),
+            },
+            Self::Sll(i) => write!(f, "sll {}", i),
+            Self::Slt(i) => match (i.rs1(), i.rs2()) {
+                (rs1, 0) => write!(f, "sltz {},{}", abi_name(i.rd()), abi_name(rs1)),
+                (0, rs2) => write!(f, "sgtz {},{}", abi_name(i.rd()), abi_name(rs2)),
+                _ => write!(f, "slt {}", i),
+            },
+            Self::Sltu(i) => match i.rs1() {
+                0 => write!(f, "snez {},{}", abi_name(i.rd()), abi_name(i.rs2())),
+                _ => write!(f, "sltu {}", i),
+            },
+            Self::Xor(i) => write!(f, "xor {}", i),
+            Self::Srl(i) => write!(f, "srl {}", i),
+            Self::Sra(i) => write!(f, "sra {}", i),
+            Self::Or(i) => write!(f, "or {}", i),
+            Self::And(i) => write!(f, "and {}", i),
+            Self::Fence(i) => fence(f, i),
+            Self::FenceI(_) => f.write_str("fence.i"),
+            Self::ECall(_) => f.write_str("ecall"),
+            Self::EBreak(_) => f.write_str("ebreak"),
+            Self::Other(i) => i.disassemble(pc, f),
+        }
+    }
+}
+
+impl<I: Disassemble> fmt::Display for RV32iBaseInst<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.disassemble(None, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use crate::{Disassemble, Instruction};
+
+    use super::RV32iBaseInst;
+
+    fn disasm(bytes: [u8; 4]) -> std::string::String {
+        RV32iBaseInst::<()>::new(&bytes).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_disasm_base() {
+        assert_eq!(disasm([0x37, 0x85, 0x0b, 0x00]), "lui a0,0xb8");
+        assert_eq!(disasm([0x23, 0x24, 0xa1, 0x00]), "sw a0,8(sp)");
+        assert_eq!(disasm([0xe3, 0x10, 0xb5, 0x80]), "bne a0,a1,-2048");
+        assert_eq!(disasm([0x33, 0x85, 0xc5, 0x40]), "sub a0,a1,a2");
+        assert_eq!(disasm([0x13, 0xd5, 0x35, 0x40]), "srai a0,a1,3");
+        assert_eq!(disasm([0x73, 0x00, 0x00, 0x00]), "ecall");
+        assert_eq!(disasm([0x0f, 0x00, 0xf0, 0x0f]), "fence");
+        assert_eq!(disasm([0x0f, 0x00, 0x30, 0x03]), "fence rw,rw");
+        assert_eq!(disasm([0x0f, 0x00, 0x30, 0x83]), "fence.tso");
+        assert_eq!(disasm([0x0f, 0x10, 0x00, 0x00]), "fence.i");
+    }
+
+    #[test]
+    fn test_disasm_pseudo() {
+        assert_eq!(disasm([0x93, 0x05, 0x80, 0x04]), "li a1,72");
+        assert_eq!(disasm([0x6f, 0xf0, 0x1f, 0xfa]), "j -96");
+        assert_eq!(disasm([0xef, 0xf0, 0xf7, 0x7f]), "jal 524286");
+        assert_eq!(disasm([0x67, 0x80, 0x00, 0x00]), "ret");
+        assert_eq!(disasm([0x13, 0x00, 0x00, 0x00]), "nop");
+        assert_eq!(disasm([0x13, 0x85, 0x05, 0x00]), "mv a0,a1");
+        assert_eq!(disasm([0x63, 0x04, 0x05, 0x00]), "beqz a0,8");
+    }
+
+    #[test]
+    fn test_disasm_target() {
+        let at = |bytes: [u8; 4], pc| {
+            let inst = RV32iBaseInst::<()>::new(&bytes).unwrap();
+            inst.at(pc).to_string()
+        };
+
+        assert_eq!(at([0x6f, 0xf0, 0x1f, 0xfa], 0x1000), "j fa0");
+        assert_eq!(at([0xef, 0x00, 0x00, 0x01], 0x1000), "jal 1010");
+        assert_eq!(at([0x6f, 0x05, 0x80, 0x00], 0x80000000), "jal a0,80000008");
+        assert_eq!(at([0x63, 0x04, 0x05, 0x00], 0x104), "beqz a0,10c");
+        assert_eq!(at([0xe3, 0x10, 0xb5, 0x80], 0x1000), "bne a0,a1,800");
+        assert_eq!(at([0x63, 0x64, 0xb5, 0x00], 0x20), "bltu a0,a1,28");
+        assert_eq!(at([0x13, 0x85, 0x05, 0x00], 0x20), "mv a0,a1");
+    }
+}
@@ -0,0 +1,356 @@
+use crate::riscv::{Inst, InstB, InstI, InstJ, InstR, InstS, InstU};
+
+use super::RV32iBaseInst;
+
+/// Builders of each instruction.
+///
+/// Register arguments are register numbers, immediate arguments use the
+/// same convention as assembly text.
+impl<I> RV32iBaseInst<I> {
+    /// Build `lui rd, imm`, `imm` is the 20-bit upper immediate.
+    pub fn lui(rd: usize, imm: u32) -> Self {
+        Self::Lui(InstU::build(0b0110111, rd, imm << 12))
+    }
+
+    /// Build `auipc rd, imm`, `imm` is the 20-bit upper immediate.
+    pub fn auipc(rd: usize, imm: u32) -> Self {
+        Self::Auipc(InstU::build(0b0010111, rd, imm << 12))
+    }
+
+    /// Build `jal rd, offset`.
+    pub fn jal(rd: usize, offset: i32) -> Self {
+        Self::Jal(InstJ::build(0b1101111, rd, offset))
+    }
+
+    /// Build `jalr rd, imm(rs1)`.
+    pub fn jalr(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Jalr(InstI::build(0b1100111, rd, 0b000, rs1, imm))
+    }
+
+    /// Build `beq rs1, rs2, offset`.
+    pub fn beq(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Beq(InstB::build(0b1100011, 0b000, rs1, rs2, offset))
+    }
+
+    /// Build `bne rs1, rs2, offset`.
+    pub fn bne(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Bne(InstB::build(0b1100011, 0b001, rs1, rs2, offset))
+    }
+
+    /// Build `blt rs1, rs2, offset`.
+    pub fn blt(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Blt(InstB::build(0b1100011, 0b100, rs1, rs2, offset))
+    }
+
+    /// Build `bge rs1, rs2, offset`.
+    pub fn bge(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Bge(InstB::build(0b1100011, 0b101, rs1, rs2, offset))
+    }
+
+    /// Build `bltu rs1, rs2, offset`.
+    pub fn bltu(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Bltu(InstB::build(0b1100011, 0b110, rs1, rs2, offset))
+    }
+
+    /// Build `bgeu rs1, rs2, offset`.
+    pub fn bgeu(rs1: usize, rs2: usize, offset: i32) -> Self {
+        Self::Bgeu(InstB::build(0b1100011, 0b111, rs1, rs2, offset))
+    }
+
+    /// Build `lb rd, imm(rs1)`.
+    pub fn lb(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lb(InstI::build(0b0000011, rd, 0b000, rs1, imm))
+    }
+
+    /// Build `lh rd, imm(rs1)`.
+    pub fn lh(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lh(InstI::build(0b0000011, rd, 0b001, rs1, imm))
+    }
+
+    /// Build `lw rd, imm(rs1)`.
+    pub fn lw(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lw(InstI::build(0b0000011, rd, 0b010, rs1, imm))
+    }
+
+    /// Build `lbu rd, imm(rs1)`.
+    pub fn lbu(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lbu(InstI::build(0b0000011, rd, 0b100, rs1, imm))
+    }
+
+    /// Build `lhu rd, imm(rs1)`.
+    pub fn lhu(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lhu(InstI::build(0b0000011, rd, 0b101, rs1, imm))
+    }
+
+    /// Build `lwu rd, imm(rs1)`.
+    pub fn lwu(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Lwu(InstI::build(0b0000011, rd, 0b110, rs1, imm))
+    }
+
+    /// Build `sb rs2, imm(rs1)`.
+    pub fn sb(rs2: usize, rs1: usize, imm: i32) -> Self {
+        Self::Sb(InstS::build(0b0100011, 0b000, rs1, rs2, imm))
+    }
+
+    /// Build `sh rs2, imm(rs1)`.
+    pub fn sh(rs2: usize, rs1: usize, imm: i32) -> Self {
+        Self::Sh(InstS::build(0b0100011, 0b001, rs1, rs2, imm))
+    }
+
+    /// Build `sw rs2, imm(rs1)`.
+    pub fn sw(rs2: usize, rs1: usize, imm: i32) -> Self {
+        Self::Sw(InstS::build(0b0100011, 0b010, rs1, rs2, imm))
+    }
+
+    /// Build `addi rd, rs1, imm`.
+    pub fn addi(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Addi(InstI::build(0b0010011, rd, 0b000, rs1, imm))
+    }
+
+    /// Build `slti rd, rs1, imm`.
+    pub fn slti(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Slti(InstI::build(0b0010011, rd, 0b010, rs1, imm))
+    }
+
+    /// Build `sltiu rd, rs1, imm`.
+    pub fn sltiu(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Sltiu(InstI::build(0b0010011, rd, 0b011, rs1, imm))
+    }
+
+    /// Build `xori rd, rs1, imm`.
+    pub fn xori(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Xori(InstI::build(0b0010011, rd, 0b100, rs1, imm))
+    }
+
+    /// Build `ori rd, rs1, imm`.
+    pub fn ori(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Ori(InstI::build(0b0010011, rd, 0b110, rs1, imm))
+    }
+
+    /// Build `andi rd, rs1, imm`.
+    pub fn andi(rd: usize, rs1: usize, imm: i32) -> Self {
+        Self::Andi(InstI::build(0b0010011, rd, 0b111, rs1, imm))
+    }
+
+    /// Build `slli rd, rs1, shamt`.
+    pub fn slli(rd: usize, rs1: usize, shamt: u32) -> Self {
+        Self::Slli(InstI::build(
+            0b0010011,
+            rd,
+            0b001,
+            rs1,
+            (shamt & 0x1F) as i32,
+        ))
+    }
+
+    /// Build `srli rd, rs1, shamt`.
+    pub fn srli(rd: usize, rs1: usize, shamt: u32) -> Self {
+        Self::Srli(InstI::build(
+            0b0010011,
+            rd,
+            0b101,
+            rs1,
+            (shamt & 0x1F) as i32,
+        ))
+    }
+
+    /// Build `srai rd, rs1, shamt`.
+    pub fn srai(rd: usize, rs1: usize, shamt: u32) -> Self {
+        Self::Srai(InstI::build(
+            0b0010011,
+            rd,
+            0b101,
+            rs1,
+            0x400 | (shamt & 0x1F) as i32,
+        ))
+    }
+
+    /// Build `add rd, rs1, rs2`.
+    pub fn add(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Add(InstR::build(0b0110011, rd, 0b000, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `sub rd, rs1, rs2`.
+    pub fn sub(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Sub(InstR::build(0b0110011, rd, 0b000, rs1, rs2, 0b0100000))
+    }
+
+    /// Build `sll rd, rs1, rs2`.
+    pub fn sll(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Sll(InstR::build(0b0110011, rd, 0b001, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `slt rd, rs1, rs2`.
+    pub fn slt(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Slt(InstR::build(0b0110011, rd, 0b010, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `sltu rd, rs1, rs2`.
+    pub fn sltu(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Sltu(InstR::build(0b0110011, rd, 0b011, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `xor rd, rs1, rs2`.
+    pub fn xor(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Xor(InstR::build(0b0110011, rd, 0b100, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `srl rd, rs1, rs2`.
+    pub fn srl(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Srl(InstR::build(0b0110011, rd, 0b101, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `sra rd, rs1, rs2`.
+    pub fn sra(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Sra(InstR::build(0b0110011, rd, 0b101, rs1, rs2, 0b0100000))
+    }
+
+    /// Build `or rd, rs1, rs2`.
+    pub fn or(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::Or(InstR::build(0b0110011, rd, 0b110, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `and rd, rs1, rs2`.
+    pub fn and(rd: usize, rs1: usize, rs2: usize) -> Self {
+        Self::And(InstR::build(0b0110011, rd, 0b111, rs1, rs2, 0b0000000))
+    }
+
+    /// Build `fence pred, succ`, sets are bits of `iorw` from high to low.
+    pub fn fence(pred: u8, succ: u8) -> Self {
+        let imm = ((pred as i32 & 0xF) << 4) | (succ as i32 & 0xF);
+        Self::Fence(InstI::build(0b0001111, 0, 0b000, 0, imm))
+    }
+
+    /// Build `fence.tso`.
+    pub fn fence_tso() -> Self {
+        Self::Fence(InstI::build(0b0001111, 0, 0b000, 0, 0x833))
+    }
+
+    /// Build `fence.i`.
+    pub fn fence_i() -> Self {
+        Self::FenceI(InstI::build(0b0001111, 0, 0b001, 0, 0))
+    }
+
+    /// Build `ecall`.
+    pub fn ecall() -> Self {
+        Self::ECall(InstI::build(0b1110011, 0, 0b000, 0, 0))
+    }
+
+    /// Build `ebreak`.
+    pub fn ebreak() -> Self {
+        Self::EBreak(InstI::build(0b1110011, 0, 0b000, 0, 1))
+    }
+
+    /// Underlying encoded instruction, `None` for [`RV32iBaseInst::Other`].
+    pub fn inst(&self) -> Option<&Inst> {
+        match self {
+            Self::Lui(i) => Some(i.inst()),
+            Self::Auipc(i) => Some(i.inst()),
+            Self::Jal(i) => Some(i.inst()),
+            Self::Jalr(i) => Some(i.inst()),
+            Self::Beq(i) => Some(i.inst()),
+            Self::Bne(i) => Some(i.inst()),
+            Self::Blt(i) => Some(i.inst()),
+            Self::Bge(i) => Some(i.inst()),
+            Self::Bltu(i) => Some(i.inst()),
+            Self::Bgeu(i) => Some(i.inst()),
+            Self::Lb(i) => Some(i.inst()),
+            Self::Lh(i) => Some(i.inst()),
+            Self::Lw(i) => Some(i.inst()),
+            Self::Lbu(i) => Some(i.inst()),
+            Self::Lhu(i) => Some(i.inst()),
+            Self::Lwu(i) => Some(i.inst()),
+            Self::Sb(i) => Some(i.inst()),
+            Self::Sh(i) => Some(i.inst()),
+            Self::Sw(i) => Some(i.inst()),
+            Self::Addi(i) => Some(i.inst()),
+            Self::Slti(i) => Some(i.inst()),
+            Self::Sltiu(i) => Some(i.inst()),
+            Self::Xori(i) => Some(i.inst()),
+            Self::Ori(i) => Some(i.inst()),
+            Self::Andi(i) => Some(i.inst()),
+            Self::Slli(i) => Some(i.inst()),
+            Self::Srli(i) => Some(i.inst()),
+            Self::Srai(i) => Some(i.inst()),
+            Self::Add(i) => Some(i.inst()),
+            Self::Sub(i) => Some(i.inst()),
+            Self::Sll(i) => Some(i.inst()),
+            Self::Slt(i) => Some(i.inst()),
+            Self::Sltu(i) => Some(i.inst()),
+            Self::Xor(i) => Some(i.inst()),
+            Self::Srl(i) => Some(i.inst()),
+            Self::Sra(i) => Some(i.inst()),
+            Self::Or(i) => Some(i.inst()),
+            Self::And(i) => Some(i.inst()),
+            Self::Fence(i) => Some(i.inst()),
+            Self::FenceI(i) => Some(i.inst()),
+            Self::ECall(i) => Some(i.inst()),
+            Self::EBreak(i) => Some(i.inst()),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Little-endian bytes of this instruction, `None` for [`RV32iBaseInst::Other`].
+    pub fn to_bytes(&self) -> Option<[u8; 4]> {
+        self.inst().map(Inst::to_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use crate::Instruction;
+
+    use super::RV32iBaseInst;
+
+    type Base = RV32iBaseInst<()>;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            Base::lui(10, 0xb8).to_bytes(),
+            Some([0x37, 0x85, 0x0b, 0x00])
+        );
+        assert_eq!(Base::jal(0, -96).to_bytes(), Some([0x6f, 0xf0, 0x1f, 0xfa]));
+        assert_eq!(
+            Base::sw(10, 2, 8).to_bytes(),
+            Some([0x23, 0x24, 0xa1, 0x00])
+        );
+        assert_eq!(
+            Base::bne(10, 11, -2048).to_bytes(),
+            Some([0xe3, 0x10, 0xb5, 0x80])
+        );
+        assert_eq!(
+            Base::srai(10, 11, 3).to_bytes(),
+            Some([0x13, 0xd5, 0x35, 0x40])
+        );
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let insts = [
+            Base::auipc(5, 0xfffff),
+            Base::jalr(1, 6, -4),
+            Base::bgeu(7, 8, 4094),
+            Base::lhu(9, 10, -2048),
+            Base::sb(11, 12, 2047),
+            Base::andi(13, 14, -1),
+            Base::sltu(15, 16, 17),
+            Base::fence(0b0010, 0b0101),
+            Base::fence_tso(),
+            Base::fence_i(),
+            Base::ebreak(),
+        ];
+
+        for inst in insts {
+            let bytes = inst.to_bytes().unwrap();
+            let decoded = Base::new(&bytes).unwrap();
+
+            assert_eq!(decoded.to_bytes(), Some(bytes));
+            assert_eq!(decoded.to_string(), inst.to_string());
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    riscv::{InstB, InstI, InstJ, InstU},
-    Memory, MemoryMut, Reg32,
+    riscv::{InstB, InstI, InstJ, InstR, InstS, InstU},
+    Error, IAlign, Memory, MemoryMut, Misaligned, Reg32, Result,
 };
 
 fn next_inst<R: Reg32>(pc: &mut R) {
@@ -13,139 +13,392 @@ pub fn lui<R: Reg32>(inst: &InstU, pc: &mut R, regs: &mut [R]) {
 }
 
 pub fn auipc<R: Reg32>(inst: &InstU, pc: &mut R, regs: &mut [R]) {
-    regs[inst.rd()].set_reg32(pc.reg32() + inst.imm());
+    regs[inst.rd()].set_reg32(pc.reg32().wrapping_add(inst.imm()));
     next_inst(pc)
 }
 
-pub fn jal<R: Reg32>(inst: &InstJ, pc: &mut R, regs: &mut [R]) {
-    regs[inst.rd()].set_reg32(pc.reg32() + 4);
-    pc.add_symbol32(inst.imm_symbol())
+pub fn jal<R: Reg32>(inst: &InstJ, pc: &mut R, regs: &mut [R], ialign: IAlign) -> Result<()> {
+    check_target(
+        pc,
+        pc.reg32().wrapping_add_signed(inst.imm_symbol()),
+        ialign,
+    )?;
+
+    regs[inst.rd()].set_reg32(pc.reg32().wrapping_add(4));
+    pc.add_symbol32(inst.imm_symbol());
+
+    Ok(())
+}
+
+pub fn jalr<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R], ialign: IAlign) -> Result<()> {
+    let r = regs[inst.rs1()].symbol32().wrapping_add(inst.imm_symbol()) & (!1);
+    check_target(pc, r as u32, ialign)?;
+
+    regs[inst.rd()].set_reg32(pc.reg32().wrapping_add(4));
+    pc.set_symbol32(r);
+
+    Ok(())
 }
 
-pub fn jalr<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
-    regs[inst.rd()].set_reg32(pc.reg32() + 4);
-    let r = regs[inst.rs1()].symbol32() + inst.imm_symbol();
-    pc.set_symbol32(r & (!1));
+/// Memory is sequentially consistent and fetch caches are flushed by executor,
+/// so fences only move to next instruction.
+pub fn fence<R: Reg32>(pc: &mut R) {
+    next_inst(pc)
 }
 
-fn branch<R: Reg32>(b: bool, inst: &InstB, pc: &mut R) {
+/// Check jump target of instruction at `pc` is aligned to `ialign`.
+fn check_target<R: Reg32>(pc: &R, target: u32, ialign: IAlign) -> Result<()> {
+    match target % ialign.bytes() {
+        0 => Ok(()),
+        _ => Err(Error::InstructionMisaligned {
+            target: target as u64,
+            pc: pc.reg32() as u64,
+        }),
+    }
+}
+
+fn branch<R: Reg32>(b: bool, inst: &InstB, pc: &mut R, ialign: IAlign) -> Result<()> {
     if b {
+        check_target(
+            pc,
+            pc.reg32().wrapping_add_signed(inst.imm_symbol()),
+            ialign,
+        )?;
         pc.add_symbol32(inst.imm_symbol());
     } else {
         next_inst(pc)
     }
+
+    Ok(())
 }
 
-pub fn beq<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn beq<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].reg32() == regs[inst.rs2()].reg32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
 }
 
-pub fn bne<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn bne<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].reg32() != regs[inst.rs2()].reg32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
 }
 
-pub fn blt<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn blt<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].symbol32() < regs[inst.rs2()].symbol32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
 }
 
-pub fn bge<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn bge<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].symbol32() >= regs[inst.rs2()].symbol32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
 }
 
-pub fn bltu<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn bltu<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].reg32() < regs[inst.rs2()].reg32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
 }
 
-pub fn bgeu<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R]) {
+pub fn bgeu<R: Reg32>(inst: &InstB, pc: &mut R, regs: &[R], ialign: IAlign) -> Result<()> {
     let b = regs[inst.rs1()].reg32() >= regs[inst.rs2()].reg32();
-    branch(b, inst, pc)
+    branch(b, inst, pc, ialign)
+}
+
+fn address<R: Reg32 + Clone>(regs: &[R], rs1: usize, imm: i32) -> R {
+    let mut offset = regs[rs1].clone();
+    offset.add_symbol32(imm);
+    offset
+}
+
+/// Whether access of `len` bytes at `addr` by instruction at `pc` is split
+/// into bytes, error if misaligned accesses trap.
+fn split<R, M>(memory: &M, addr: &R, len: u32, pc: &R, store: bool) -> Result<bool>
+where
+    R: Reg32,
+    M: Memory<Register = R>,
+{
+    if addr.reg32().is_multiple_of(len) {
+        return Ok(false);
+    }
+
+    let (addr, pc) = (addr.reg32() as u64, pc.reg32() as u64);
+    match memory.alignment().access {
+        Misaligned::Trap if store => Err(Error::StoreMisaligned { addr, pc }),
+        Misaligned::Trap => Err(Error::LoadMisaligned { addr, pc }),
+        Misaligned::Split => Ok(true),
+        Misaligned::Allow => Ok(false),
+    }
 }
 
-pub fn lb<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+/// Load `N` bytes at `addr` by instruction at `pc`.
+fn load<R, M, const N: usize>(memory: &M, addr: R, pc: &R) -> Result<[u8; N]>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 1);
+    let mut m = [0u8; N];
+    let split = split(memory, &addr, N as u32, pc, false)?;
+    if !memory.contains(addr.clone(), N as u8) {
+        let (addr, pc) = (addr.reg32() as u64, pc.reg32() as u64);
+        return Err(Error::LoadAccessFault { addr, pc });
+    }
 
-    regs[inst.rd()].set_symbol32(i32::from_le_bytes([0, 0, 0, m[0]]) >> 24);
+    if split {
+        for (i, b) in m.iter_mut().enumerate() {
+            let mut addr = addr.clone();
+            addr.add_u32(i as u32);
+            *b = memory.load(addr, 1)[0];
+        }
+    } else {
+        m.copy_from_slice(&memory.load(addr, N as u8)[..N]);
+    }
 
-    next_inst(pc)
+    Ok(m)
+}
+
+/// Store `data` at `addr` by instruction at `pc`.
+fn store<R, M>(memory: &mut M, addr: R, data: &[u8], pc: &R) -> Result<()>
+where
+    R: Reg32 + Clone,
+    M: MemoryMut<Register = R>,
+{
+    let split = split(memory, &addr, data.len() as u32, pc, true)?;
+    if !memory.contains(addr.clone(), data.len() as u8) {
+        let (addr, pc) = (addr.reg32() as u64, pc.reg32() as u64);
+        return Err(Error::StoreAccessFault { addr, pc });
+    }
+
+    if split {
+        for (i, b) in data.iter().enumerate() {
+            let mut addr = addr.clone();
+            addr.add_u32(i as u32);
+            memory.store(addr, &[*b]);
+        }
+    } else {
+        memory.store(addr, data);
+    }
+
+    Ok(())
 }
 
-pub fn lh<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+pub fn lb<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 2);
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 1>(memory, offset, pc)?;
 
-    regs[inst.rd()].set_symbol32(i32::from_le_bytes([0, 0, m[0], m[1]]) >> 24);
+    regs[inst.rd()].set_symbol32(m[0] as i8 as i32);
 
-    next_inst(pc)
+    next_inst(pc);
+
+    Ok(())
 }
 
-pub fn lw<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+pub fn lh<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 4);
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 2>(memory, offset, pc)?;
 
-    regs[inst.rd()].set_symbol32(i32::from_le_bytes([m[0], m[1], m[2], m[3]]) >> 24);
+    regs[inst.rd()].set_symbol32(i16::from_le_bytes(m) as i32);
 
-    next_inst(pc)
+    next_inst(pc);
+
+    Ok(())
 }
 
-pub fn lbu<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+pub fn lw<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 1);
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 4>(memory, offset, pc)?;
 
-    regs[inst.rd()].set_reg32(u32::from_le_bytes([0, 0, 0, m[0]]) >> 24);
+    regs[inst.rd()].set_symbol32(i32::from_le_bytes(m));
 
-    next_inst(pc)
+    next_inst(pc);
+
+    Ok(())
 }
 
-pub fn lhu<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+pub fn lbu<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 2);
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 1>(memory, offset, pc)?;
 
-    regs[inst.rd()].set_reg32(u32::from_le_bytes([0, 0, m[0], m[1]]) >> 24);
+    regs[inst.rd()].set_reg32(m[0] as u32);
 
-    next_inst(pc)
+    next_inst(pc);
+
+    Ok(())
+}
+
+pub fn lhu<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
+where
+    R: Reg32 + Clone,
+    M: Memory<Register = R>,
+{
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 2>(memory, offset, pc)?;
+
+    regs[inst.rd()].set_reg32(u16::from_le_bytes(m) as u32);
+
+    next_inst(pc);
+
+    Ok(())
 }
 
-pub fn lwu<R: Reg32, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M)
+pub fn lwu<R, M>(inst: &InstI, pc: &mut R, regs: &mut [R], memory: &M) -> Result<()>
 where
     R: Reg32 + Clone,
     M: Memory<Register = R>,
 {
-    let mut offset = regs[inst.rs1()].clone();
-    offset.add_symbol32(inst.imm_symbol());
-    let m = memory.load(offset, 4);
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    let m = load::<_, _, 4>(memory, offset, pc)?;
+
+    regs[inst.rd()].set_reg32(u32::from_le_bytes(m));
+
+    next_inst(pc);
+
+    Ok(())
+}
+
+pub fn sb<R, M>(inst: &InstS, pc: &mut R, regs: &[R], memory: &mut M) -> Result<()>
+where
+    R: Reg32 + Clone,
+    M: MemoryMut<Register = R>,
+{
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    store(memory, offset, &[regs[inst.rs2()].reg32() as u8], pc)?;
 
-    regs[inst.rd()].set_reg32(u32::from_le_bytes([m[0], m[1], m[2], m[3]]) >> 24);
+    next_inst(pc);
+
+    Ok(())
+}
+
+pub fn sh<R, M>(inst: &InstS, pc: &mut R, regs: &[R], memory: &mut M) -> Result<()>
+where
+    R: Reg32 + Clone,
+    M: MemoryMut<Register = R>,
+{
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    store(
+        memory,
+        offset,
+        &(regs[inst.rs2()].reg32() as u16).to_le_bytes(),
+        pc,
+    )?;
+
+    next_inst(pc);
+
+    Ok(())
+}
+
+pub fn sw<R, M>(inst: &InstS, pc: &mut R, regs: &[R], memory: &mut M) -> Result<()>
+where
+    R: Reg32 + Clone,
+    M: MemoryMut<Register = R>,
+{
+    let offset = address(regs, inst.rs1(), inst.imm_symbol());
+    store(memory, offset, &regs[inst.rs2()].reg32().to_le_bytes(), pc)?;
+
+    next_inst(pc);
+
+    Ok(())
+}
+
+fn op_imm<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R], f: fn(u32, i32) -> u32) {
+    let r = f(regs[inst.rs1()].reg32(), inst.imm_symbol());
+    regs[inst.rd()].set_reg32(r);
 
     next_inst(pc)
 }
+
+pub fn addi<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a.wrapping_add_signed(b))
+}
+
+pub fn slti<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| ((a as i32) < b) as u32)
+}
+
+pub fn sltiu<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| (a < b as u32) as u32)
+}
+
+pub fn xori<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a ^ b as u32)
+}
+
+pub fn ori<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a | b as u32)
+}
+
+pub fn andi<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a & b as u32)
+}
+
+pub fn slli<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a << (b & 0x1F))
+}
+
+pub fn srli<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| a >> (b & 0x1F))
+}
+
+pub fn srai<R: Reg32>(inst: &InstI, pc: &mut R, regs: &mut [R]) {
+    op_imm(inst, pc, regs, |a, b| ((a as i32) >> (b & 0x1F)) as u32)
+}
+
+fn op<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R], f: fn(u32, u32) -> u32) {
+    let r = f(regs[inst.rs1()].reg32(), regs[inst.rs2()].reg32());
+    regs[inst.rd()].set_reg32(r);
+
+    next_inst(pc)
+}
+
+pub fn add<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a.wrapping_add(b))
+}
+
+pub fn sub<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a.wrapping_sub(b))
+}
+
+pub fn sll<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a << (b & 0x1F))
+}
+
+pub fn slt<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| ((a as i32) < (b as i32)) as u32)
+}
+
+pub fn sltu<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| (a < b) as u32)
+}
+
+pub fn xor<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a ^ b)
+}
+
+pub fn srl<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a >> (b & 0x1F))
+}
+
+pub fn sra<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| ((a as i32) >> (b & 0x1F)) as u32)
+}
+
+pub fn or<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a | b)
+}
+
+pub fn and<R: Reg32>(inst: &InstR, pc: &mut R, regs: &mut [R]) {
+    op(inst, pc, regs, |a, b| a & b)
+}
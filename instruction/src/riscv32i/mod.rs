@@ -5,6 +5,13 @@ pub use base::*;
 
 mod execute;
 
+mod disasm;
+
+mod encode;
+
+mod asm;
+pub use asm::*;
+
 /*
 mod lite;
 pub use lite::*; */
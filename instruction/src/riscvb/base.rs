@@ -0,0 +1,493 @@
+use crate::{
+    riscv::{Inst, InstI, InstR},
+    Context, Error, Instruction, Memory, MemoryMut, RegX, Result,
+};
+
+use super::execute;
+
+/// Instruction of bit manipulation extensions for RV32 and RV64
+///
+/// Layer it under a base instruction set, like `RV32iBaseInst<RVBitInst<()>>`.
+/// Width comes from register type, instructions ending with `w` or `uw` are
+/// only decoded for RV64.
+pub enum RVBitInst<I> {
+    /// Shift left by 1 and add
+    Sh1add(InstR),
+    /// Shift left by 2 and add
+    Sh2add(InstR),
+    /// Shift left by 3 and add
+    Sh3add(InstR),
+    /// Add unsigned word
+    AddUw(InstR),
+    /// Shift unsigned word left by 1 and add
+    Sh1addUw(InstR),
+    /// Shift unsigned word left by 2 and add
+    Sh2addUw(InstR),
+    /// Shift unsigned word left by 3 and add
+    Sh3addUw(InstR),
+    /// Shift left unsigned word immediate
+    SlliUw(InstI),
+    /// And with inverted operand
+    Andn(InstR),
+    /// Or with inverted operand
+    Orn(InstR),
+    /// Exclusive nor
+    Xnor(InstR),
+    /// Count leading zeros
+    Clz(InstI),
+    /// Count trailing zeros
+    Ctz(InstI),
+    /// Count set bits
+    Cpop(InstI),
+    /// Count leading zeros in word
+    Clzw(InstI),
+    /// Count trailing zeros in word
+    Ctzw(InstI),
+    /// Count set bits in word
+    Cpopw(InstI),
+    /// Maximum
+    Max(InstR),
+    /// Maximum in Unsigned Int
+    Maxu(InstR),
+    /// Minimum
+    Min(InstR),
+    /// Minimum in Unsigned Int
+    Minu(InstR),
+    /// Sign extend byte
+    SextB(InstI),
+    /// Sign extend half word
+    SextH(InstI),
+    /// Zero extend half word
+    ZextH(InstR),
+    /// Rotate left
+    Rol(InstR),
+    /// Rotate right
+    Ror(InstR),
+    /// Rotate right immediate
+    Rori(InstI),
+    /// Rotate word left
+    Rolw(InstR),
+    /// Rotate word right
+    Rorw(InstR),
+    /// Rotate word right immediate
+    Roriw(InstI),
+    /// Or combine each byte
+    OrcB(InstI),
+    /// Reverse bytes
+    Rev8(InstI),
+    /// Carry-less multiply, low half
+    Clmul(InstR),
+    /// Carry-less multiply, high half
+    Clmulh(InstR),
+    /// Carry-less multiply, reversed
+    Clmulr(InstR),
+    /// Clear bit
+    Bclr(InstR),
+    /// Clear bit immediate
+    Bclri(InstI),
+    /// Extract bit
+    Bext(InstR),
+    /// Extract bit immediate
+    Bexti(InstI),
+    /// Invert bit
+    Binv(InstR),
+    /// Invert bit immediate
+    Binvi(InstI),
+    /// Set bit
+    Bset(InstR),
+    /// Set bit immediate
+    Bseti(InstI),
+    /// Other Instruction
+    Other(I),
+}
+
+/// Encoding of `rev8` for each XLEN.
+const fn rev8_imm(xlen: u32) -> u32 {
+    match xlen {
+        32 => 0x698,
+        _ => 0x6B8,
+    }
+}
+
+impl<I: Instruction> RVBitInst<I> {
+    fn _new(bytes: &[u8], xlen: u32) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(Error::ErrBytecodeLengthNotEnough);
+        }
+
+        let inst = Inst::new([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let rv64 = xlen == 64;
+
+        let imm = inst.imm_i();
+        // Upper bits of shift immediate, shamt is 5 bits on RV32.
+        let funct6 = match rv64 || imm & 0x20 == 0 {
+            true => Some(imm >> 6),
+            false => None,
+        };
+
+        let r = match inst.opcode() {
+            0b0110011 => match (inst.funct7(), inst.funct3()) {
+                (0b0010000, 0b010) => Self::Sh1add(inst.into()),
+                (0b0010000, 0b100) => Self::Sh2add(inst.into()),
+                (0b0010000, 0b110) => Self::Sh3add(inst.into()),
+                (0b0100000, 0b111) => Self::Andn(inst.into()),
+                (0b0100000, 0b110) => Self::Orn(inst.into()),
+                (0b0100000, 0b100) => Self::Xnor(inst.into()),
+                (0b0000101, 0b110) => Self::Max(inst.into()),
+                (0b0000101, 0b111) => Self::Maxu(inst.into()),
+                (0b0000101, 0b100) => Self::Min(inst.into()),
+                (0b0000101, 0b101) => Self::Minu(inst.into()),
+                (0b0000101, 0b001) => Self::Clmul(inst.into()),
+                (0b0000101, 0b010) => Self::Clmulr(inst.into()),
+                (0b0000101, 0b011) => Self::Clmulh(inst.into()),
+                (0b0000100, 0b100) if !rv64 && inst.rs2() == 0 => Self::ZextH(inst.into()),
+                (0b0110000, 0b001) => Self::Rol(inst.into()),
+                (0b0110000, 0b101) => Self::Ror(inst.into()),
+                (0b0100100, 0b001) => Self::Bclr(inst.into()),
+                (0b0100100, 0b101) => Self::Bext(inst.into()),
+                (0b0110100, 0b001) => Self::Binv(inst.into()),
+                (0b0010100, 0b001) => Self::Bset(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            0b0010011 => match (inst.funct3(), imm) {
+                (0b001, 0x600) => Self::Clz(inst.into()),
+                (0b001, 0x601) => Self::Ctz(inst.into()),
+                (0b001, 0x602) => Self::Cpop(inst.into()),
+                (0b001, 0x604) => Self::SextB(inst.into()),
+                (0b001, 0x605) => Self::SextH(inst.into()),
+                (0b101, 0x287) => Self::OrcB(inst.into()),
+                (0b101, imm) if imm == rev8_imm(xlen) => Self::Rev8(inst.into()),
+                (f3, _) => match (f3, funct6) {
+                    (0b001, Some(0b010010)) => Self::Bclri(inst.into()),
+                    (0b001, Some(0b011010)) => Self::Binvi(inst.into()),
+                    (0b001, Some(0b001010)) => Self::Bseti(inst.into()),
+                    (0b101, Some(0b011000)) => Self::Rori(inst.into()),
+                    (0b101, Some(0b010010)) => Self::Bexti(inst.into()),
+                    _ => Self::Other(I::new(bytes)?),
+                },
+            },
+            0b0111011 if rv64 => match (inst.funct7(), inst.funct3()) {
+                (0b0000100, 0b000) => Self::AddUw(inst.into()),
+                (0b0010000, 0b010) => Self::Sh1addUw(inst.into()),
+                (0b0010000, 0b100) => Self::Sh2addUw(inst.into()),
+                (0b0010000, 0b110) => Self::Sh3addUw(inst.into()),
+                (0b0000100, 0b100) if inst.rs2() == 0 => Self::ZextH(inst.into()),
+                (0b0110000, 0b001) => Self::Rolw(inst.into()),
+                (0b0110000, 0b101) => Self::Rorw(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            0b0011011 if rv64 => match (inst.funct3(), imm) {
+                (0b001, 0x600) => Self::Clzw(inst.into()),
+                (0b001, 0x601) => Self::Ctzw(inst.into()),
+                (0b001, 0x602) => Self::Cpopw(inst.into()),
+                (0b001, imm) if imm >> 6 == 0b000010 => Self::SlliUw(inst.into()),
+                (0b101, imm) if imm >> 5 == 0b0110000 => Self::Roriw(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            _ => Self::Other(I::new(bytes)?),
+        };
+
+        Ok(r)
+    }
+}
+
+impl<I, R> Instruction for RVBitInst<I>
+where
+    I: Instruction<Register = R>,
+    R: RegX,
+{
+    type Register = R;
+
+    fn new(bytes: &[u8]) -> Result<Self> {
+        Self::_new(bytes, R::XLEN)
+    }
+
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        use execute::*;
+
+        let (rd, v) = match self {
+            Self::Sh1add(i) => r(i, regs, |a, b| (a << 1).wrapping_add(b)),
+            Self::Sh2add(i) => r(i, regs, |a, b| (a << 2).wrapping_add(b)),
+            Self::Sh3add(i) => r(i, regs, |a, b| (a << 3).wrapping_add(b)),
+            Self::AddUw(i) => r(i, regs, |a, b| (a as u32 as u64).wrapping_add(b)),
+            Self::Sh1addUw(i) => r(i, regs, |a, b| ((a as u32 as u64) << 1).wrapping_add(b)),
+            Self::Sh2addUw(i) => r(i, regs, |a, b| ((a as u32 as u64) << 2).wrapping_add(b)),
+            Self::Sh3addUw(i) => r(i, regs, |a, b| ((a as u32 as u64) << 3).wrapping_add(b)),
+            Self::SlliUw(i) => imm(i, regs, |a, s| (a as u32 as u64) << (s & 0x3F)),
+            Self::Andn(i) => r(i, regs, |a, b| a & !b),
+            Self::Orn(i) => r(i, regs, |a, b| a | !b),
+            Self::Xnor(i) => r(i, regs, |a, b| !(a ^ b)),
+            Self::Clz(i) => unary(i, regs, |a| clz(a, R::XLEN)),
+            Self::Ctz(i) => unary(i, regs, |a| ctz(a, R::XLEN)),
+            Self::Cpop(i) => unary(i, regs, |a| a.count_ones() as u64),
+            Self::Clzw(i) => unary(i, regs, |a| clz(a, 32)),
+            Self::Ctzw(i) => unary(i, regs, |a| ctz(a, 32)),
+            Self::Cpopw(i) => unary(i, regs, |a| (a as u32).count_ones() as u64),
+            Self::Max(i) => r(i, regs, |a, b| {
+                match signed(a, R::XLEN) < signed(b, R::XLEN) {
+                    true => b,
+                    false => a,
+                }
+            }),
+            Self::Maxu(i) => r(i, regs, u64::max),
+            Self::Min(i) => r(i, regs, |a, b| {
+                match signed(a, R::XLEN) < signed(b, R::XLEN) {
+                    true => a,
+                    false => b,
+                }
+            }),
+            Self::Minu(i) => r(i, regs, u64::min),
+            Self::SextB(i) => unary(i, regs, |a| a as i8 as i64 as u64),
+            Self::SextH(i) => unary(i, regs, |a| a as i16 as i64 as u64),
+            Self::ZextH(i) => r(i, regs, |a, _| a as u16 as u64),
+            Self::Rol(i) => r(i, regs, |a, b| rotate_left(a, b as u32, R::XLEN)),
+            Self::Ror(i) => r(i, regs, |a, b| rotate_right(a, b as u32, R::XLEN)),
+            Self::Rori(i) => imm(i, regs, |a, s| rotate_right(a, s, R::XLEN)),
+            Self::Rolw(i) => r(i, regs, |a, b| word((a as u32).rotate_left(b as u32))),
+            Self::Rorw(i) => r(i, regs, |a, b| word((a as u32).rotate_right(b as u32))),
+            Self::Roriw(i) => imm(i, regs, |a, s| word((a as u32).rotate_right(s))),
+            Self::OrcB(i) => unary(i, regs, orc_b),
+            Self::Rev8(i) => unary(i, regs, |a| a.swap_bytes() >> (64 - R::XLEN)),
+            Self::Clmul(i) => r(i, regs, |a, b| clmul(a, b, R::XLEN) as u64),
+            Self::Clmulh(i) => r(i, regs, |a, b| (clmul(a, b, R::XLEN) >> R::XLEN) as u64),
+            Self::Clmulr(i) => r(i, regs, |a, b| {
+                (clmul(a, b, R::XLEN) >> (R::XLEN - 1)) as u64
+            }),
+            Self::Bclr(i) => r(i, regs, |a, b| a & !bit(b, R::XLEN)),
+            Self::Bclri(i) => imm(i, regs, |a, s| a & !bit(s as u64, R::XLEN)),
+            Self::Bext(i) => r(i, regs, |a, b| (a & bit(b, R::XLEN) != 0) as u64),
+            Self::Bexti(i) => imm(i, regs, |a, s| (a & bit(s as u64, R::XLEN) != 0) as u64),
+            Self::Binv(i) => r(i, regs, |a, b| a ^ bit(b, R::XLEN)),
+            Self::Binvi(i) => imm(i, regs, |a, s| a ^ bit(s as u64, R::XLEN)),
+            Self::Bset(i) => r(i, regs, |a, b| a | bit(b, R::XLEN)),
+            Self::Bseti(i) => imm(i, regs, |a, s| a | bit(s as u64, R::XLEN)),
+            Self::Other(inst) => return inst.execute(pc, regs, memory, context),
+        }
+        .map_err(|e| e.with_pc(pc.regx()))?;
+
+        if rd != 0 {
+            regs[rd].set_regx(v);
+        }
+        pc.set_regx(pc.regx().wrapping_add(4));
+
+        Ok(())
+    }
+
+    fn is_fence_i(&self) -> bool {
+        match self {
+            Self::Other(inst) => inst.is_fence_i(),
+            _ => false,
+        }
+    }
+
+    fn rd(&self) -> Option<usize> {
+        match self {
+            Self::Sh1add(i)
+            | Self::Sh2add(i)
+            | Self::Sh3add(i)
+            | Self::AddUw(i)
+            | Self::Sh1addUw(i)
+            | Self::Sh2addUw(i)
+            | Self::Sh3addUw(i)
+            | Self::Andn(i)
+            | Self::Orn(i)
+            | Self::Xnor(i)
+            | Self::Max(i)
+            | Self::Maxu(i)
+            | Self::Min(i)
+            | Self::Minu(i)
+            | Self::ZextH(i)
+            | Self::Rol(i)
+            | Self::Ror(i)
+            | Self::Rolw(i)
+            | Self::Rorw(i)
+            | Self::Clmul(i)
+            | Self::Clmulh(i)
+            | Self::Clmulr(i)
+            | Self::Bclr(i)
+            | Self::Bext(i)
+            | Self::Binv(i)
+            | Self::Bset(i) => Some(i.rd()),
+            Self::SlliUw(i)
+            | Self::Clz(i)
+            | Self::Ctz(i)
+            | Self::Cpop(i)
+            | Self::Clzw(i)
+            | Self::Ctzw(i)
+            | Self::Cpopw(i)
+            | Self::SextB(i)
+            | Self::SextH(i)
+            | Self::Rori(i)
+            | Self::Roriw(i)
+            | Self::OrcB(i)
+            | Self::Rev8(i)
+            | Self::Bclri(i)
+            | Self::Bexti(i)
+            | Self::Binvi(i)
+            | Self::Bseti(i) => Some(i.rd()),
+            Self::Other(inst) => inst.rd(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        riscv::{Illegal, Inst},
+        riscv32i::RV32iBaseInst,
+        testing::{run, Mem64},
+        Instruction,
+    };
+
+    use super::RVBitInst;
+
+    fn r(opcode: u8, funct3: u8, funct7: u8) -> u32 {
+        Inst::build_r(opcode, 10, funct3, 11, 12, funct7).raw()
+    }
+
+    fn i(opcode: u8, funct3: u8, imm: i32) -> u32 {
+        Inst::build_i(opcode, 10, funct3, 11, imm).raw()
+    }
+
+    fn rv32(inst: u32, a1: u32, a2: u32) -> u32 {
+        run::<RV32iBaseInst<RVBitInst<()>>, _>(inst, a1, a2, [0u8; 0])
+    }
+
+    fn rv64(inst: u32, a1: u64, a2: u64) -> u64 {
+        run::<RVBitInst<Illegal<u64>>, _>(inst, a1, a2, Mem64)
+    }
+
+    const OP: u8 = 0b0110011;
+    const OP_IMM: u8 = 0b0010011;
+    const OP_32: u8 = 0b0111011;
+    const OP_IMM_32: u8 = 0b0011011;
+
+    #[test]
+    fn test_zba() {
+        assert_eq!(rv32(r(OP, 0b100, 0b0010000), 3, 10), 22);
+        assert_eq!(rv32(r(OP, 0b110, 0b0010000), 0x2000_0001, 1), 9);
+        assert_eq!(
+            rv64(r(OP_32, 0b000, 0b0000100), 0xFFFF_FFFF_8000_0000, 1),
+            0x8000_0001
+        );
+        assert_eq!(
+            rv64(r(OP_32, 0b010, 0b0010000), u64::MAX, 2),
+            0x1_FFFF_FFFE + 2
+        );
+        assert_eq!(
+            rv64(i(OP_IMM_32, 0b001, 0x84), 0xFFFF_FFFF_0000_0001, 0),
+            0x10
+        );
+    }
+
+    #[test]
+    fn test_zbb() {
+        assert_eq!(rv32(r(OP, 0b111, 0b0100000), 0xFF, 0x0F), 0xF0);
+        assert_eq!(rv32(r(OP, 0b110, 0b0100000), 0, 0xFFFF_FFF0), 0xF);
+        assert_eq!(rv32(r(OP, 0b100, 0b0100000), 0xF0, 0xFF), 0xFFFF_FFF0);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x600), 0xF000, 0), 16);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x601), 0xF000, 0), 12);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x601), 0, 0), 32);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x602), 0xF0F0, 0), 8);
+        assert_eq!(rv32(r(OP, 0b100, 0b0000101), u32::MAX, 1), u32::MAX);
+        assert_eq!(rv32(r(OP, 0b101, 0b0000101), u32::MAX, 1), 1);
+        assert_eq!(rv32(r(OP, 0b110, 0b0000101), u32::MAX, 1), 1);
+        assert_eq!(rv32(r(OP, 0b111, 0b0000101), u32::MAX, 1), u32::MAX);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x604), 0x80, 0), 0xFFFF_FF80);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x605), 0x7FFF, 0), 0x7FFF);
+        assert_eq!(
+            rv32(
+                Inst::build_r(OP, 10, 0b100, 11, 0, 0b0000100).raw(),
+                0xFFFF_1234,
+                0
+            ),
+            0x1234
+        );
+        assert_eq!(rv32(r(OP, 0b001, 0b0110000), 0x8000_0001, 33), 3);
+        assert_eq!(rv32(r(OP, 0b101, 0b0110000), 1, 1), 0x8000_0000);
+        assert_eq!(rv32(i(OP_IMM, 0b101, 0x604), 0x10, 0), 0x1);
+        assert_eq!(rv32(i(OP_IMM, 0b101, 0x287), 0x0012_0300, 0), 0x00FF_FF00);
+        assert_eq!(rv32(i(OP_IMM, 0b101, 0x698), 0x1234_5678, 0), 0x7856_3412);
+
+        assert_eq!(
+            rv64(i(OP_IMM, 0b101, 0x6B8), 0x0102_0304_0506_0708, 0),
+            0x0807_0605_0403_0201
+        );
+        assert_eq!(rv64(i(OP_IMM, 0b001, 0x600), 1, 0), 63);
+        assert_eq!(rv64(i(OP_IMM_32, 0b001, 0x600), 1, 0), 31);
+        assert_eq!(
+            rv64(i(OP_IMM_32, 0b001, 0x602), 0xFFFF_FFFF_0000_000F, 0),
+            4
+        );
+        assert_eq!(rv64(r(OP, 0b001, 0b0110000), 0x8000_0000_0000_0001, 1), 3);
+        assert_eq!(
+            rv64(r(OP_32, 0b101, 0b0110000), 1, 1),
+            0xFFFF_FFFF_8000_0000
+        );
+        assert_eq!(rv64(i(OP_IMM, 0b101, 0x620), 1, 0), 1 << 32);
+        assert_eq!(rv64(i(OP_IMM_32, 0b101, 0x601), 2, 0), 1);
+        assert_eq!(
+            rv64(
+                Inst::build_r(OP_32, 10, 0b100, 11, 0, 0b0000100).raw(),
+                0xFFFF_FFFF,
+                0
+            ),
+            0xFFFF
+        );
+    }
+
+    #[test]
+    fn test_zbc() {
+        assert_eq!(rv32(r(OP, 0b001, 0b0000101), 0b11, 0b11), 0b101);
+        assert_eq!(
+            rv32(r(OP, 0b011, 0b0000101), 0x8000_0000, 0x8000_0000),
+            0x4000_0000
+        );
+        assert_eq!(
+            rv32(r(OP, 0b010, 0b0000101), 0x8000_0000, 0x8000_0000),
+            0x8000_0000
+        );
+        assert_eq!(rv64(r(OP, 0b011, 0b0000101), u64::MAX, 2), 1);
+    }
+
+    #[test]
+    fn test_zbs() {
+        assert_eq!(rv32(r(OP, 0b001, 0b0010100), 0, 31), 0x8000_0000);
+        assert_eq!(rv32(r(OP, 0b001, 0b0100100), u32::MAX, 32), u32::MAX - 1);
+        assert_eq!(rv32(r(OP, 0b101, 0b0100100), 0x10, 4), 1);
+        assert_eq!(rv32(r(OP, 0b001, 0b0110100), 0x10, 4), 0);
+        assert_eq!(rv32(i(OP_IMM, 0b001, 0x283), 0, 0), 8);
+        assert_eq!(rv32(i(OP_IMM, 0b101, 0x483), 8, 0), 1);
+        assert_eq!(rv64(i(OP_IMM, 0b001, 0x2BF), 0, 0), 1 << 63);
+        assert_eq!(rv64(i(OP_IMM, 0b001, 0x4A0), u64::MAX, 0), !(1 << 32));
+    }
+
+    #[test]
+    fn test_decode_xlen() {
+        let decode = |raw: u32| {
+            RVBitInst::<()>::new(&raw.to_le_bytes()).map(|i| matches!(i, RVBitInst::Other(())))
+        };
+
+        // RV64 only encodings fall through on RV32.
+        assert_eq!(decode(i(OP_IMM, 0b101, 0x6B8)), Ok(true));
+        assert_eq!(decode(i(OP_IMM, 0b101, 0x620)), Ok(true));
+        assert_eq!(decode(r(OP_32, 0b000, 0b0000100)), Ok(true));
+        assert_eq!(decode(i(OP_IMM, 0b101, 0x61F)), Ok(false));
+
+        // Base shifts with extension bits aren't taken by RV32I.
+        assert!(matches!(
+            RV32iBaseInst::<()>::new(&i(OP_IMM, 0b001, 0x600).to_le_bytes()),
+            Ok(RV32iBaseInst::Other(()))
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+use core::fmt;
+
+use crate::{
+    riscv::{abi_name, InstI},
+    Disassemble,
+};
+
+use super::RVBitInst;
+
+fn unary(f: &mut fmt::Formatter<'_>, name: &str, rd: usize, rs1: usize) -> fmt::Result {
+    write!(f, "{} {},{}", name, abi_name(rd), abi_name(rs1))
+}
+
+fn shift(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstI) -> fmt::Result {
+    write!(
+        f,
+        "{} {},{},{}",
+        name,
+        abi_name(inst.rd()),
+        abi_name(inst.rs1()),
+        inst.imm() & 0x3F
+    )
+}
+
+impl<I: Disassemble> Disassemble for RVBitInst<I> {
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sh1add(i) => write!(f, "sh1add {}", i),
+            Self::Sh2add(i) => write!(f, "sh2add {}", i),
+            Self::Sh3add(i) => write!(f, "sh3add {}", i),
+            Self::AddUw(i) => match i.rs2() {
+                0 => unary(f, "zext.w", i.rd(), i.rs1()),
+                _ => write!(f, "add.uw {}", i),
+            },
+            Self::Sh1addUw(i) => write!(f, "sh1add.uw {}", i),
+            Self::Sh2addUw(i) => write!(f, "sh2add.uw {}", i),
+            Self::Sh3addUw(i) => write!(f, "sh3add.uw {}", i),
+            Self::SlliUw(i) => shift(f, "slli.uw", i),
+            Self::Andn(i) => write!(f, "andn {}", i),
+            Self::Orn(i) => write!(f, "orn {}", i),
+            Self::Xnor(i) => write!(f, "xnor {}", i),
+            Self::Clz(i) => unary(f, "clz", i.rd(), i.rs1()),
+            Self::Ctz(i) => unary(f, "ctz", i.rd(), i.rs1()),
+            Self::Cpop(i) => unary(f, "cpop", i.rd(), i.rs1()),
+            Self::Clzw(i) => unary(f, "clzw", i.rd(), i.rs1()),
+            Self::Ctzw(i) => unary(f, "ctzw", i.rd(), i.rs1()),
+            Self::Cpopw(i) => unary(f, "cpopw", i.rd(), i.rs1()),
+            Self::Max(i) => write!(f, "max {}", i),
+            Self::Maxu(i) => write!(f, "maxu {}", i),
+            Self::Min(i) => write!(f, "min {}", i),
+            Self::Minu(i) => write!(f, "minu {}", i),
+            Self::SextB(i) => unary(f, "sext.b", i.rd(), i.rs1()),
+            Self::SextH(i) => unary(f, "sext.h", i.rd(), i.rs1()),
+            Self::ZextH(i) => unary(f, "zext.h", i.rd(), i.rs1()),
+            Self::Rol(i) => write!(f, "rol {}", i),
+            Self::Ror(i) => write!(f, "ror {}", i),
+            Self::Rori(i) => shift(f, "rori", i),
+            Self::Rolw(i) => write!(f, "rolw {}", i),
+            Self::Rorw(i) => write!(f, "rorw {}", i),
+            Self::Roriw(i) => shift(f, "roriw", i),
+            Self::OrcB(i) => unary(f, "orc.b", i.rd(), i.rs1()),
+            Self::Rev8(i) => unary(f, "rev8", i.rd(), i.rs1()),
+            Self::Clmul(i) => write!(f, "clmul {}", i),
+            Self::Clmulh(i) => write!(f, "clmulh {}", i),
+            Self::Clmulr(i) => write!(f, "clmulr {}", i),
+            Self::Bclr(i) => write!(f, "bclr {}", i),
+            Self::Bclri(i) => shift(f, "bclri", i),
+            Self::Bext(i) => write!(f, "bext {}", i),
+            Self::Bexti(i) => shift(f, "bexti", i),
+            Self::Binv(i) => write!(f, "binv {}", i),
+            Self::Binvi(i) => shift(f, "binvi", i),
+            Self::Bset(i) => write!(f, "bset {}", i),
+            Self::Bseti(i) => shift(f, "bseti", i),
+            Self::Other(i) => i.disassemble(pc, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::{String, ToString};
+
+    use crate::{riscv32i::RV32iBaseInst, Instruction};
+
+    use super::RVBitInst;
+
+    fn disasm(raw: u32) -> String {
+        let inst = RV32iBaseInst::<RVBitInst<()>>::new(&raw.to_le_bytes()).unwrap();
+        inst.to_string()
+    }
+
+    #[test]
+    fn test_disasm_bit() {
+        assert_eq!(disasm(0x20c5c533), "sh2add a0,a1,a2");
+        assert_eq!(disasm(0x60059513), "clz a0,a1");
+        assert_eq!(disasm(0x6985d513), "rev8 a0,a1");
+        assert_eq!(disasm(0x6035d513), "rori a0,a1,3");
+        assert_eq!(disasm(0x0ac5b533), "clmulh a0,a1,a2");
+        assert_eq!(disasm(0x28559513), "bseti a0,a1,5");
+        assert_eq!(disasm(0x0805c533), "zext.h a0,a1");
+        assert_eq!(disasm(0x00b50533), "add a0,a0,a1");
+    }
+}
@@ -0,0 +1,92 @@
+//! Operations on zero-extended values of `xlen` bits, results are truncated by
+//! [`RegX::set_regx`].
+
+use crate::{
+    riscv::{check_regs, InstI, InstR},
+    RegX, Result,
+};
+
+/// Register to write and result of `f(rs1, rs2)`.
+pub fn r<R: RegX>(inst: &InstR, regs: &[R], f: impl Fn(u64, u64) -> u64) -> Result<(usize, u64)> {
+    check_regs(
+        inst.inst(),
+        regs.len(),
+        &[inst.rd(), inst.rs1(), inst.rs2()],
+    )?;
+
+    let v = f(regs[inst.rs1()].regx(), regs[inst.rs2()].regx());
+    Ok((inst.rd(), v))
+}
+
+/// Register to write and result of `f(rs1)`.
+pub fn unary<R: RegX>(inst: &InstI, regs: &[R], f: impl Fn(u64) -> u64) -> Result<(usize, u64)> {
+    check_regs(inst.inst(), regs.len(), &[inst.rd(), inst.rs1()])?;
+
+    Ok((inst.rd(), f(regs[inst.rs1()].regx())))
+}
+
+/// Register to write and result of `f(rs1, shamt)`.
+pub fn imm<R: RegX>(inst: &InstI, regs: &[R], f: impl Fn(u64, u32) -> u64) -> Result<(usize, u64)> {
+    check_regs(inst.inst(), regs.len(), &[inst.rd(), inst.rs1()])?;
+
+    let v = f(regs[inst.rs1()].regx(), inst.imm() & 0x3F);
+    Ok((inst.rd(), v))
+}
+
+pub fn mask(xlen: u32) -> u64 {
+    u64::MAX >> (64 - xlen)
+}
+
+pub fn signed(a: u64, xlen: u32) -> i64 {
+    ((a << (64 - xlen)) as i64) >> (64 - xlen)
+}
+
+/// Sign extend result of word instruction.
+pub fn word(a: u32) -> u64 {
+    a as i32 as i64 as u64
+}
+
+pub fn clz(a: u64, xlen: u32) -> u64 {
+    ((a & mask(xlen)).leading_zeros() - (64 - xlen)) as u64
+}
+
+pub fn ctz(a: u64, xlen: u32) -> u64 {
+    (a & mask(xlen)).trailing_zeros().min(xlen) as u64
+}
+
+pub fn rotate_right(a: u64, shamt: u32, xlen: u32) -> u64 {
+    let (a, s) = (a & mask(xlen), shamt & (xlen - 1));
+    match s {
+        0 => a,
+        _ => ((a >> s) | (a << (xlen - s))) & mask(xlen),
+    }
+}
+
+pub fn rotate_left(a: u64, shamt: u32, xlen: u32) -> u64 {
+    rotate_right(a, xlen - (shamt & (xlen - 1)), xlen)
+}
+
+/// Each non-zero byte becomes `0xFF`.
+pub fn orc_b(a: u64) -> u64 {
+    let mut bytes = a.to_le_bytes();
+    for b in bytes.iter_mut() {
+        if *b != 0 {
+            *b = 0xFF;
+        }
+    }
+    u64::from_le_bytes(bytes)
+}
+
+/// Carry-less product of `xlen` bit operands.
+pub fn clmul(a: u64, b: u64, xlen: u32) -> u128 {
+    let (a, b) = (a & mask(xlen), b & mask(xlen));
+
+    (0..xlen)
+        .filter(|i| b >> i & 1 == 1)
+        .fold(0, |r, i| r ^ ((a as u128) << i))
+}
+
+/// Single bit at index `b` modulo `xlen`.
+pub fn bit(b: u64, xlen: u32) -> u64 {
+    1 << (b & (xlen as u64 - 1))
+}
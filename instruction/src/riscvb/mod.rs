@@ -0,0 +1,8 @@
+//! RISCV bit manipulation extensions, Zba, Zbb, Zbc and Zbs
+
+mod base;
+pub use base::*;
+
+pub(crate) mod execute;
+
+mod disasm;
@@ -0,0 +1,799 @@
+use crate::{
+    riscv::{Inst, InstI, InstR},
+    Context, Error, Instruction, Memory, MemoryMut, RegX, Result,
+};
+
+use super::execute;
+
+/// Instruction of scalar cryptography extensions for RV32 and RV64
+///
+/// Covers Zbkb, Zbkx, Zkne, Zknd, Zknh, Zksed and Zksh. Rotations, `andn`,
+/// `orn`, `xnor`, `rev8` of Zbkb and Zbkc are in
+/// [`RVBitInst`](crate::riscvb::RVBitInst), layer both for full Zkn and Zks,
+/// like `RV32iBaseInst<RVBitInst<RVCryptoInst<()>>>`. Width comes from register
+/// type.
+pub enum RVCryptoInst<I> {
+    /// Pack low halves of registers
+    Pack(InstR),
+    /// Pack low bytes of registers
+    Packh(InstR),
+    /// Pack low half words of registers into word
+    Packw(InstR),
+    /// Reverse bits in each byte
+    Brev8(InstI),
+    /// Interleave bits of halves
+    Zip(InstI),
+    /// Deinterleave bits into halves
+    Unzip(InstI),
+    /// Crossbar permutation of nibbles
+    Xperm4(InstR),
+    /// Crossbar permutation of bytes
+    Xperm8(InstR),
+    /// AES final round encryption of one byte
+    Aes32Esi(InstR),
+    /// AES middle round encryption of one byte
+    Aes32Esmi(InstR),
+    /// AES final round decryption of one byte
+    Aes32Dsi(InstR),
+    /// AES middle round decryption of one byte
+    Aes32Dsmi(InstR),
+    /// AES final round encryption of half state
+    Aes64Es(InstR),
+    /// AES middle round encryption of half state
+    Aes64Esm(InstR),
+    /// AES final round decryption of half state
+    Aes64Ds(InstR),
+    /// AES middle round decryption of half state
+    Aes64Dsm(InstR),
+    /// AES inverse MixColumns for decryption key schedule
+    Aes64Im(InstI),
+    /// AES key schedule, first step
+    Aes64Ks1i(InstI),
+    /// AES key schedule, second step
+    Aes64Ks2(InstR),
+    /// SHA-256 sigma0
+    Sha256Sig0(InstI),
+    /// SHA-256 sigma1
+    Sha256Sig1(InstI),
+    /// SHA-256 sum0
+    Sha256Sum0(InstI),
+    /// SHA-256 sum1
+    Sha256Sum1(InstI),
+    /// SHA-512 sum0 on half of register pair
+    Sha512Sum0r(InstR),
+    /// SHA-512 sum1 on half of register pair
+    Sha512Sum1r(InstR),
+    /// SHA-512 sigma0, low half
+    Sha512Sig0l(InstR),
+    /// SHA-512 sigma0, high half
+    Sha512Sig0h(InstR),
+    /// SHA-512 sigma1, low half
+    Sha512Sig1l(InstR),
+    /// SHA-512 sigma1, high half
+    Sha512Sig1h(InstR),
+    /// SHA-512 sigma0
+    Sha512Sig0(InstI),
+    /// SHA-512 sigma1
+    Sha512Sig1(InstI),
+    /// SHA-512 sum0
+    Sha512Sum0(InstI),
+    /// SHA-512 sum1
+    Sha512Sum1(InstI),
+    /// SM4 encryption and decryption of one byte
+    Sm4Ed(InstR),
+    /// SM4 key schedule of one byte
+    Sm4Ks(InstR),
+    /// SM3 permutation P0
+    Sm3P0(InstI),
+    /// SM3 permutation P1
+    Sm3P1(InstI),
+    /// Other Instruction
+    Other(I),
+}
+
+impl<I: Instruction> RVCryptoInst<I> {
+    fn _new(bytes: &[u8], xlen: u32) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(Error::ErrBytecodeLengthNotEnough);
+        }
+
+        let inst = Inst::new([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let rv64 = xlen == 64;
+
+        let r = match inst.opcode() {
+            // Low 5 bits of funct7 select byte instructions, upper 2 bits are
+            // byte index.
+            0b0110011 => match (inst.funct7(), inst.funct3()) {
+                (0b0000100, 0b100) => Self::Pack(inst.into()),
+                (0b0000100, 0b111) => Self::Packh(inst.into()),
+                (0b0010100, 0b010) => Self::Xperm4(inst.into()),
+                (0b0010100, 0b100) => Self::Xperm8(inst.into()),
+                (f7, 0b000) if !rv64 && f7 & 0x1F == 0b10001 => Self::Aes32Esi(inst.into()),
+                (f7, 0b000) if !rv64 && f7 & 0x1F == 0b10011 => Self::Aes32Esmi(inst.into()),
+                (f7, 0b000) if !rv64 && f7 & 0x1F == 0b10101 => Self::Aes32Dsi(inst.into()),
+                (f7, 0b000) if !rv64 && f7 & 0x1F == 0b10111 => Self::Aes32Dsmi(inst.into()),
+                (f7, 0b000) if f7 & 0x1F == 0b11000 => Self::Sm4Ed(inst.into()),
+                (f7, 0b000) if f7 & 0x1F == 0b11010 => Self::Sm4Ks(inst.into()),
+                (0b0101000, 0b000) if !rv64 => Self::Sha512Sum0r(inst.into()),
+                (0b0101001, 0b000) if !rv64 => Self::Sha512Sum1r(inst.into()),
+                (0b0101010, 0b000) if !rv64 => Self::Sha512Sig0l(inst.into()),
+                (0b0101110, 0b000) if !rv64 => Self::Sha512Sig0h(inst.into()),
+                (0b0101011, 0b000) if !rv64 => Self::Sha512Sig1l(inst.into()),
+                (0b0101111, 0b000) if !rv64 => Self::Sha512Sig1h(inst.into()),
+                (0b0011001, 0b000) if rv64 => Self::Aes64Es(inst.into()),
+                (0b0011011, 0b000) if rv64 => Self::Aes64Esm(inst.into()),
+                (0b0011101, 0b000) if rv64 => Self::Aes64Ds(inst.into()),
+                (0b0011111, 0b000) if rv64 => Self::Aes64Dsm(inst.into()),
+                (0b0111111, 0b000) if rv64 => Self::Aes64Ks2(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            0b0010011 => match (inst.funct3(), inst.imm_i()) {
+                (0b001, 0x100) => Self::Sha256Sum0(inst.into()),
+                (0b001, 0x101) => Self::Sha256Sum1(inst.into()),
+                (0b001, 0x102) => Self::Sha256Sig0(inst.into()),
+                (0b001, 0x103) => Self::Sha256Sig1(inst.into()),
+                (0b001, 0x104) if rv64 => Self::Sha512Sum0(inst.into()),
+                (0b001, 0x105) if rv64 => Self::Sha512Sum1(inst.into()),
+                (0b001, 0x106) if rv64 => Self::Sha512Sig0(inst.into()),
+                (0b001, 0x107) if rv64 => Self::Sha512Sig1(inst.into()),
+                (0b001, 0x108) => Self::Sm3P0(inst.into()),
+                (0b001, 0x109) => Self::Sm3P1(inst.into()),
+                (0b001, 0x300) if rv64 => Self::Aes64Im(inst.into()),
+                // Round number above 10 is reserved.
+                (0b001, imm) if rv64 && imm >> 4 == 0x31 && imm & 0xF <= 0xA => {
+                    Self::Aes64Ks1i(inst.into())
+                }
+                (0b001, 0x08F) if !rv64 => Self::Zip(inst.into()),
+                (0b101, 0x08F) if !rv64 => Self::Unzip(inst.into()),
+                (0b101, 0x687) => Self::Brev8(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            0b0111011 if rv64 => match (inst.funct7(), inst.funct3()) {
+                (0b0000100, 0b100) => Self::Packw(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            _ => Self::Other(I::new(bytes)?),
+        };
+
+        Ok(r)
+    }
+}
+
+impl<I, R> Instruction for RVCryptoInst<I>
+where
+    I: Instruction<Register = R>,
+    R: RegX,
+{
+    type Register = R;
+
+    fn new(bytes: &[u8]) -> Result<Self> {
+        Self::_new(bytes, R::XLEN)
+    }
+
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        use execute::*;
+
+        let bs = |i: &InstR| (i.funct7() >> 5) as u32;
+
+        let (rd, v) = match self {
+            Self::Pack(i) => r(i, regs, |a, b| pack(a, b, R::XLEN / 2)),
+            Self::Packh(i) => r(i, regs, |a, b| pack(a, b, 8)),
+            Self::Packw(i) => r(i, regs, |a, b| word(pack(a, b, 16) as u32)),
+            Self::Brev8(i) => unary(i, regs, brev8),
+            Self::Zip(i) => unary(i, regs, zip),
+            Self::Unzip(i) => unary(i, regs, unzip),
+            Self::Xperm4(i) => r(i, regs, |a, b| xperm(a, b, 4, R::XLEN)),
+            Self::Xperm8(i) => r(i, regs, |a, b| xperm(a, b, 8, R::XLEN)),
+            Self::Aes32Esi(i) => r(i, regs, |a, b| {
+                byte_select(a, b, bs(i), |x| AES_SBOX[x as usize] as u32)
+            }),
+            Self::Aes32Esmi(i) => r(i, regs, |a, b| {
+                byte_select(a, b, bs(i), |x| aes_mix_byte(AES_SBOX[x as usize]))
+            }),
+            Self::Aes32Dsi(i) => r(i, regs, |a, b| {
+                byte_select(a, b, bs(i), |x| AES_INV_SBOX[x as usize] as u32)
+            }),
+            Self::Aes32Dsmi(i) => r(i, regs, |a, b| {
+                byte_select(a, b, bs(i), |x| aes_inv_mix_byte(AES_INV_SBOX[x as usize]))
+            }),
+            Self::Aes64Es(i) => r(i, regs, |a, b| u64::from_le_bytes(aes64_rows(a, b, false))),
+            Self::Aes64Esm(i) => r(i, regs, |a, b| aes64_mix(aes64_rows(a, b, false), false)),
+            Self::Aes64Ds(i) => r(i, regs, |a, b| u64::from_le_bytes(aes64_rows(a, b, true))),
+            Self::Aes64Dsm(i) => r(i, regs, |a, b| aes64_mix(aes64_rows(a, b, true), true)),
+            Self::Aes64Im(i) => unary(i, regs, |a| aes64_mix(a.to_le_bytes(), true)),
+            Self::Aes64Ks1i(i) => {
+                let rnum = i.imm() & 0xF;
+                unary(i, regs, |a| aes64_ks1(a, rnum))
+            }
+            Self::Aes64Ks2(i) => r(i, regs, aes64_ks2),
+            Self::Sha256Sig0(i) => unary(i, regs, |a| sig32(a, 7, 18, |a| a >> 3)),
+            Self::Sha256Sig1(i) => unary(i, regs, |a| sig32(a, 17, 19, |a| a >> 10)),
+            Self::Sha256Sum0(i) => unary(i, regs, |a| sig32(a, 2, 13, |a| a.rotate_right(22))),
+            Self::Sha256Sum1(i) => unary(i, regs, |a| sig32(a, 6, 11, |a| a.rotate_right(25))),
+            Self::Sha512Sum0r(i) => r(i, regs, sha512_sum0r),
+            Self::Sha512Sum1r(i) => r(i, regs, sha512_sum1r),
+            Self::Sha512Sig0l(i) => r(i, regs, |a, b| sha512_sig0(a, b, true)),
+            Self::Sha512Sig0h(i) => r(i, regs, |a, b| sha512_sig0(a, b, false)),
+            Self::Sha512Sig1l(i) => r(i, regs, |a, b| sha512_sig1(a, b, true)),
+            Self::Sha512Sig1h(i) => r(i, regs, |a, b| sha512_sig1(a, b, false)),
+            Self::Sha512Sig0(i) => unary(i, regs, |a| sig64(a, 1, 8, |a| a >> 7)),
+            Self::Sha512Sig1(i) => unary(i, regs, |a| sig64(a, 19, 61, |a| a >> 6)),
+            Self::Sha512Sum0(i) => unary(i, regs, |a| sig64(a, 28, 34, |a| a.rotate_right(39))),
+            Self::Sha512Sum1(i) => unary(i, regs, |a| sig64(a, 14, 18, |a| a.rotate_right(41))),
+            Self::Sm4Ed(i) => r(i, regs, |a, b| byte_select(a, b, bs(i), sm4_ed)),
+            Self::Sm4Ks(i) => r(i, regs, |a, b| byte_select(a, b, bs(i), sm4_ks)),
+            Self::Sm3P0(i) => unary(i, regs, |a| sm3_p(a, 9, 17)),
+            Self::Sm3P1(i) => unary(i, regs, |a| sm3_p(a, 15, 23)),
+            Self::Other(inst) => return inst.execute(pc, regs, memory, context),
+        }
+        .map_err(|e| e.with_pc(pc.regx()))?;
+
+        if rd != 0 {
+            regs[rd].set_regx(v);
+        }
+        pc.set_regx(pc.regx().wrapping_add(4));
+
+        Ok(())
+    }
+
+    fn is_fence_i(&self) -> bool {
+        match self {
+            Self::Other(inst) => inst.is_fence_i(),
+            _ => false,
+        }
+    }
+
+    fn rd(&self) -> Option<usize> {
+        match self {
+            Self::Pack(i)
+            | Self::Packh(i)
+            | Self::Packw(i)
+            | Self::Xperm4(i)
+            | Self::Xperm8(i)
+            | Self::Aes32Esi(i)
+            | Self::Aes32Esmi(i)
+            | Self::Aes32Dsi(i)
+            | Self::Aes32Dsmi(i)
+            | Self::Aes64Es(i)
+            | Self::Aes64Esm(i)
+            | Self::Aes64Ds(i)
+            | Self::Aes64Dsm(i)
+            | Self::Aes64Ks2(i)
+            | Self::Sha512Sum0r(i)
+            | Self::Sha512Sum1r(i)
+            | Self::Sha512Sig0l(i)
+            | Self::Sha512Sig0h(i)
+            | Self::Sha512Sig1l(i)
+            | Self::Sha512Sig1h(i)
+            | Self::Sm4Ed(i)
+            | Self::Sm4Ks(i) => Some(i.rd()),
+            Self::Brev8(i)
+            | Self::Zip(i)
+            | Self::Unzip(i)
+            | Self::Aes64Im(i)
+            | Self::Aes64Ks1i(i)
+            | Self::Sha256Sig0(i)
+            | Self::Sha256Sig1(i)
+            | Self::Sha256Sum0(i)
+            | Self::Sha256Sum1(i)
+            | Self::Sha512Sig0(i)
+            | Self::Sha512Sig1(i)
+            | Self::Sha512Sum0(i)
+            | Self::Sha512Sum1(i)
+            | Self::Sm3P0(i)
+            | Self::Sm3P1(i) => Some(i.rd()),
+            Self::Other(inst) => inst.rd(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::array;
+
+    use crate::{
+        riscv::{Illegal, Inst},
+        testing::{run, Mem64},
+        Instruction,
+    };
+
+    use super::RVCryptoInst;
+
+    fn rv32(inst: u32, a1: u32, a2: u32) -> u32 {
+        run::<RVCryptoInst<()>, _>(inst, a1, a2, [0u8; 0])
+    }
+
+    fn rv64(inst: u32, a1: u64, a2: u64) -> u64 {
+        run::<RVCryptoInst<Illegal<u64>>, _>(inst, a1, a2, Mem64)
+    }
+
+    fn r(funct3: u8, funct7: u8) -> u32 {
+        Inst::build_r(0b0110011, 10, funct3, 11, 12, funct7).raw()
+    }
+
+    fn i(funct3: u8, imm: i32) -> u32 {
+        Inst::build_i(0b0010011, 10, funct3, 11, imm).raw()
+    }
+
+    /// Byte instruction selected by low bits of funct7.
+    fn bs(funct7: u8, bs: usize) -> u32 {
+        r(0b000, (bs as u8) << 5 | funct7)
+    }
+
+    const ESI: u8 = 0b10001;
+    const ESMI: u8 = 0b10011;
+    const DSI: u8 = 0b10101;
+    const DSMI: u8 = 0b10111;
+    const SM4ED: u8 = 0b11000;
+    const SM4KS: u8 = 0b11010;
+
+    const AES_KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const AES_PLAIN: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    /// FIPS-197 appendix C.1
+    const AES_CIPHER: [u8; 16] = [
+        0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5,
+        0x5a,
+    ];
+    const AES_RCON: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36];
+
+    fn words(bytes: &[u8]) -> [u32; 4] {
+        array::from_fn(|i| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()))
+    }
+
+    fn bytes(words: [u32; 4]) -> [u8; 16] {
+        array::from_fn(|i| words[i / 4].to_le_bytes()[i % 4])
+    }
+
+    /// Column `col` of next AES round, taking byte `bs` from column shifted by
+    /// `bs` forward or backward.
+    fn aes32_column(funct7: u8, key: u32, state: &[u32; 4], col: usize, forward: bool) -> u32 {
+        (0..4).fold(key, |acc, b| {
+            let from = match forward {
+                true => (col + b) % 4,
+                false => (col + 4 - b) % 4,
+            };
+            rv32(bs(funct7, b), acc, state[from])
+        })
+    }
+
+    fn aes32_round(funct7: u8, keys: &[u32], state: [u32; 4], forward: bool) -> [u32; 4] {
+        array::from_fn(|c| aes32_column(funct7, keys[c], &state, c, forward))
+    }
+
+    #[test]
+    fn test_aes32() {
+        let mut w = [0u32; 44];
+        w[..4].copy_from_slice(&words(&AES_KEY));
+        for i in 4..44 {
+            w[i] = w[i - 4]
+                ^ match i % 4 {
+                    0 => {
+                        let t = w[i - 1].rotate_right(8);
+                        (0..4).fold(0, |acc, b| rv32(bs(ESI, b), acc, t)) ^ AES_RCON[i / 4 - 1]
+                    }
+                    _ => w[i - 1],
+                };
+        }
+
+        let mut state = words(&AES_PLAIN).map(|_| 0);
+        for (s, (p, k)) in state.iter_mut().zip(words(&AES_PLAIN).iter().zip(&w)) {
+            *s = p ^ k;
+        }
+        for round in 1..10 {
+            state = aes32_round(ESMI, &w[round * 4..], state, true);
+        }
+        state = aes32_round(ESI, &w[40..], state, true);
+        assert_eq!(bytes(state), AES_CIPHER);
+
+        // Equivalent inverse cipher, middle round keys are InvMixColumns of
+        // encryption keys.
+        let inv_mix =
+            |k: u32| (0..4).fold(0, |acc, b| rv32(bs(DSMI, b), acc, rv32(bs(ESI, b), 0, k)));
+        let mut state = array::from_fn(|c| words(&AES_CIPHER)[c] ^ w[40 + c]);
+        for round in (1..10).rev() {
+            let keys = array::from_fn::<_, 4, _>(|c| inv_mix(w[round * 4 + c]));
+            state = aes32_round(DSMI, &keys, state, false);
+        }
+        state = aes32_round(DSI, &w, state, false);
+        assert_eq!(bytes(state), AES_PLAIN);
+    }
+
+    #[test]
+    fn test_aes64() {
+        let (es, esm) = (r(0b000, 0b0011001), r(0b000, 0b0011011));
+        let (ds, dsm) = (r(0b000, 0b0011101), r(0b000, 0b0011111));
+        let im = i(0b001, 0x300);
+        let ks2 = r(0b000, 0b0111111);
+        let halves = |b: &[u8; 16]| {
+            let v = u128::from_le_bytes(*b);
+            (v as u64, (v >> 64) as u64)
+        };
+
+        let mut keys = [(0, 0); 11];
+        keys[0] = halves(&AES_KEY);
+        for rnum in 0..10 {
+            let (k0, k1) = keys[rnum];
+            let t = rv64(i(0b001, 0x310 | rnum as i32), k1, 0);
+            let k0 = rv64(ks2, t, k0);
+            keys[rnum + 1] = (k0, rv64(ks2, k0, k1));
+        }
+
+        let (p0, p1) = halves(&AES_PLAIN);
+        let (mut s0, mut s1) = (p0 ^ keys[0].0, p1 ^ keys[0].1);
+        for (round, (k0, k1)) in keys.iter().enumerate().skip(1) {
+            let op = match round {
+                10 => es,
+                _ => esm,
+            };
+            (s0, s1) = (rv64(op, s0, s1) ^ k0, rv64(op, s1, s0) ^ k1);
+        }
+        assert_eq!((s0, s1), halves(&AES_CIPHER));
+
+        let (c0, c1) = halves(&AES_CIPHER);
+        let (mut s0, mut s1) = (c0 ^ keys[10].0, c1 ^ keys[10].1);
+        for round in (0..10).rev() {
+            let (k0, k1) = keys[round];
+            (s0, s1) = match round {
+                0 => (rv64(ds, s0, s1) ^ k0, rv64(ds, s1, s0) ^ k1),
+                _ => (
+                    rv64(dsm, s0, s1) ^ rv64(im, k0, 0),
+                    rv64(dsm, s1, s0) ^ rv64(im, k1, 0),
+                ),
+            };
+        }
+        assert_eq!((s0, s1), (p0, p1));
+
+        // Reserved round number isn't decoded.
+        assert!(matches!(
+            RVCryptoInst::<Illegal<u64>>::new(&i(0b001, 0x31B).to_le_bytes()),
+            Ok(RVCryptoInst::Other(_))
+        ));
+    }
+
+    /// Round constants of SHA-512, upper halves of the first 64 are SHA-256
+    /// constants.
+    const SHA512_K: [u64; 80] = [
+        0x428A_2F98_D728_AE22,
+        0x7137_4491_23EF_65CD,
+        0xB5C0_FBCF_EC4D_3B2F,
+        0xE9B5_DBA5_8189_DBBC,
+        0x3956_C25B_F348_B538,
+        0x59F1_11F1_B605_D019,
+        0x923F_82A4_AF19_4F9B,
+        0xAB1C_5ED5_DA6D_8118,
+        0xD807_AA98_A303_0242,
+        0x1283_5B01_4570_6FBE,
+        0x2431_85BE_4EE4_B28C,
+        0x550C_7DC3_D5FF_B4E2,
+        0x72BE_5D74_F27B_896F,
+        0x80DE_B1FE_3B16_96B1,
+        0x9BDC_06A7_25C7_1235,
+        0xC19B_F174_CF69_2694,
+        0xE49B_69C1_9EF1_4AD2,
+        0xEFBE_4786_384F_25E3,
+        0x0FC1_9DC6_8B8C_D5B5,
+        0x240C_A1CC_77AC_9C65,
+        0x2DE9_2C6F_592B_0275,
+        0x4A74_84AA_6EA6_E483,
+        0x5CB0_A9DC_BD41_FBD4,
+        0x76F9_88DA_8311_53B5,
+        0x983E_5152_EE66_DFAB,
+        0xA831_C66D_2DB4_3210,
+        0xB003_27C8_98FB_213F,
+        0xBF59_7FC7_BEEF_0EE4,
+        0xC6E0_0BF3_3DA8_8FC2,
+        0xD5A7_9147_930A_A725,
+        0x06CA_6351_E003_826F,
+        0x1429_2967_0A0E_6E70,
+        0x27B7_0A85_46D2_2FFC,
+        0x2E1B_2138_5C26_C926,
+        0x4D2C_6DFC_5AC4_2AED,
+        0x5338_0D13_9D95_B3DF,
+        0x650A_7354_8BAF_63DE,
+        0x766A_0ABB_3C77_B2A8,
+        0x81C2_C92E_47ED_AEE6,
+        0x9272_2C85_1482_353B,
+        0xA2BF_E8A1_4CF1_0364,
+        0xA81A_664B_BC42_3001,
+        0xC24B_8B70_D0F8_9791,
+        0xC76C_51A3_0654_BE30,
+        0xD192_E819_D6EF_5218,
+        0xD699_0624_5565_A910,
+        0xF40E_3585_5771_202A,
+        0x106A_A070_32BB_D1B8,
+        0x19A4_C116_B8D2_D0C8,
+        0x1E37_6C08_5141_AB53,
+        0x2748_774C_DF8E_EB99,
+        0x34B0_BCB5_E19B_48A8,
+        0x391C_0CB3_C5C9_5A63,
+        0x4ED8_AA4A_E341_8ACB,
+        0x5B9C_CA4F_7763_E373,
+        0x682E_6FF3_D6B2_B8A3,
+        0x748F_82EE_5DEF_B2FC,
+        0x78A5_636F_4317_2F60,
+        0x84C8_7814_A1F0_AB72,
+        0x8CC7_0208_1A64_39EC,
+        0x90BE_FFFA_2363_1E28,
+        0xA450_6CEB_DE82_BDE9,
+        0xBEF9_A3F7_B2C6_7915,
+        0xC671_78F2_E372_532B,
+        0xCA27_3ECE_EA26_619C,
+        0xD186_B8C7_21C0_C207,
+        0xEADA_7DD6_CDE0_EB1E,
+        0xF57D_4F7F_EE6E_D178,
+        0x06F0_67AA_7217_6FBA,
+        0x0A63_7DC5_A2C8_98A6,
+        0x113F_9804_BEF9_0DAE,
+        0x1B71_0B35_131C_471B,
+        0x28DB_77F5_2304_7D84,
+        0x32CA_AB7B_40C7_2493,
+        0x3C9E_BE0A_15C9_BEBC,
+        0x431D_67C4_9C10_0D4C,
+        0x4CC5_D4BE_CB3E_42B6,
+        0x597F_299C_FC65_7E2A,
+        0x5FCB_6FAB_3AD6_FAEC,
+        0x6C44_198C_4A47_5817,
+    ];
+
+    const SHA512_IV: [u64; 8] = [
+        0x6A09_E667_F3BC_C908,
+        0xBB67_AE85_84CA_A73B,
+        0x3C6E_F372_FE94_F82B,
+        0xA54F_F53A_5F1D_36F1,
+        0x510E_527F_ADE6_82D1,
+        0x9B05_688C_2B3E_6C1F,
+        0x1F83_D9AB_FB41_BD6B,
+        0x5BE0_CD19_137E_2179,
+    ];
+
+    /// Single block of padded "abc" with words of `N` bytes.
+    fn abc_block<const N: usize>() -> [u8; 128] {
+        let mut block = [0; 128];
+        block[..4].copy_from_slice(b"abc\x80");
+        block[16 * N - 1] = 24;
+        block
+    }
+
+    #[test]
+    fn test_sha256() {
+        let (sig0, sig1) = (i(0b001, 0x102), i(0b001, 0x103));
+        let (sum0, sum1) = (i(0b001, 0x100), i(0b001, 0x101));
+
+        let block = abc_block::<4>();
+        let mut w = [0u32; 64];
+        for t in 0..64 {
+            w[t] = match t {
+                0..=15 => u32::from_be_bytes(block[t * 4..t * 4 + 4].try_into().unwrap()),
+                _ => rv32(sig1, w[t - 2], 0)
+                    .wrapping_add(w[t - 7])
+                    .wrapping_add(rv32(sig0, w[t - 15], 0))
+                    .wrapping_add(w[t - 16]),
+            };
+        }
+
+        let iv = SHA512_IV.map(|h| (h >> 32) as u32);
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = iv;
+        for t in 0..64 {
+            let t1 = h
+                .wrapping_add(rv32(sum1, e, 0))
+                .wrapping_add((e & f) ^ (!e & g))
+                .wrapping_add((SHA512_K[t] >> 32) as u32)
+                .wrapping_add(w[t]);
+            let t2 = rv32(sum0, a, 0).wrapping_add((a & b) ^ (a & c) ^ (b & c));
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+
+        let digest: [u32; 8] = array::from_fn(|n| iv[n].wrapping_add([a, b, c, d, e, f, g, h][n]));
+        assert_eq!(
+            digest,
+            [
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sha512() {
+        let (sig0, sig1) = (i(0b001, 0x106), i(0b001, 0x107));
+        let (sum0, sum1) = (i(0b001, 0x104), i(0b001, 0x105));
+
+        let block = abc_block::<8>();
+        let mut w = [0u64; 80];
+        for t in 0..80 {
+            w[t] = match t {
+                0..=15 => u64::from_be_bytes(block[t * 8..t * 8 + 8].try_into().unwrap()),
+                _ => rv64(sig1, w[t - 2], 0)
+                    .wrapping_add(w[t - 7])
+                    .wrapping_add(rv64(sig0, w[t - 15], 0))
+                    .wrapping_add(w[t - 16]),
+            };
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = SHA512_IV;
+        for t in 0..80 {
+            let t1 = h
+                .wrapping_add(rv64(sum1, e, 0))
+                .wrapping_add((e & f) ^ (!e & g))
+                .wrapping_add(SHA512_K[t])
+                .wrapping_add(w[t]);
+            let t2 = rv64(sum0, a, 0).wrapping_add((a & b) ^ (a & c) ^ (b & c));
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+
+        let digest: [u64; 8] =
+            array::from_fn(|n| SHA512_IV[n].wrapping_add([a, b, c, d, e, f, g, h][n]));
+        assert_eq!(
+            digest,
+            [
+                0xddaf35a193617aba,
+                0xcc417349ae204131,
+                0x12e6fa4e89a97ea2,
+                0x0a9eeee64b55d39a,
+                0x2192992a274fc1a8,
+                0x36ba3c23a3feebbd,
+                0x454d4423643ce80e,
+                0x2a9ac94fa54ca49f
+            ]
+        );
+
+        // RV32 halves match RV64 results.
+        let x = 0x0123_4567_89AB_CDEF_u64;
+        let (lo, hi) = (x as u32, (x >> 32) as u32);
+        let split = |v: u64| (v as u32, (v >> 32) as u32);
+        assert_eq!(
+            (rv32(r(0, 0b0101000), lo, hi), rv32(r(0, 0b0101000), hi, lo)),
+            split(rv64(sum0, x, 0))
+        );
+        assert_eq!(
+            (rv32(r(0, 0b0101001), lo, hi), rv32(r(0, 0b0101001), hi, lo)),
+            split(rv64(sum1, x, 0))
+        );
+        assert_eq!(
+            (rv32(r(0, 0b0101010), lo, hi), rv32(r(0, 0b0101110), hi, lo)),
+            split(rv64(sig0, x, 0))
+        );
+        assert_eq!(
+            (rv32(r(0, 0b0101011), lo, hi), rv32(r(0, 0b0101111), hi, lo)),
+            split(rv64(sig1, x, 0))
+        );
+    }
+
+    #[test]
+    fn test_sm3() {
+        let (p0, p1) = (i(0b001, 0x108), i(0b001, 0x109));
+
+        let block = abc_block::<4>();
+        let mut w = [0u32; 68];
+        for j in 0..68 {
+            w[j] = match j {
+                0..=15 => u32::from_be_bytes(block[j * 4..j * 4 + 4].try_into().unwrap()),
+                _ => {
+                    rv32(p1, w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15), 0)
+                        ^ w[j - 13].rotate_left(7)
+                        ^ w[j - 6]
+                }
+            };
+        }
+
+        let iv: [u32; 8] = [
+            0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d,
+            0xb0fb0e4e,
+        ];
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = iv;
+        for j in 0..64 {
+            let (t, ff, gg) = match j {
+                0..=15 => (0x79cc4519u32, a ^ b ^ c, e ^ f ^ g),
+                _ => (0x7a879d8a, (a & b) | (a & c) | (b & c), (e & f) | (!e & g)),
+            };
+            let ss1 = a
+                .rotate_left(12)
+                .wrapping_add(e)
+                .wrapping_add(t.rotate_left(j as u32 % 32))
+                .rotate_left(7);
+            let ss2 = ss1 ^ a.rotate_left(12);
+            let tt1 = ff
+                .wrapping_add(d)
+                .wrapping_add(ss2)
+                .wrapping_add(w[j] ^ w[j + 4]);
+            let tt2 = gg.wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+            (d, c, b, a) = (c, b.rotate_left(9), a, tt1);
+            (h, g, f, e) = (g, f.rotate_left(19), e, rv32(p0, tt2, 0));
+        }
+
+        let digest: [u32; 8] = array::from_fn(|n| iv[n] ^ [a, b, c, d, e, f, g, h][n]);
+        assert_eq!(
+            digest,
+            [
+                0x66c7f0f4, 0x62eeedd9, 0xd1f2d46b, 0xdc10e4e2, 0x4167c487, 0x5cf2f7a2, 0x297da02b,
+                0x8f4ba8e0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sm4() {
+        // GB/T 32907 example, key and plaintext are the same.
+        let input = [0x01234567, 0x89abcdef, 0xfedcba98, 0x76543210];
+        let fk = [0xa3b1bac6, 0x56aa3350, 0x677d9197, 0xb27022dc];
+        let ck = |n: usize| u32::from_be_bytes(array::from_fn(|j| ((4 * n + j) * 7) as u8));
+
+        // T applied byte by byte and xored into `acc`.
+        let t =
+            |funct7: u8, acc: u32, x: u32| (0..4).fold(acc, |acc, b| rv32(bs(funct7, b), acc, x));
+
+        let mut k = [0u32; 36];
+        for n in 0..36 {
+            k[n] = match n {
+                0..=3 => input[n] ^ fk[n],
+                _ => t(SM4KS, k[n - 4], k[n - 3] ^ k[n - 2] ^ k[n - 1] ^ ck(n - 4)),
+            };
+        }
+
+        let mut x = [0u32; 36];
+        for n in 0..36 {
+            x[n] = match n {
+                0..=3 => input[n],
+                _ => t(SM4ED, x[n - 4], x[n - 3] ^ x[n - 2] ^ x[n - 1] ^ k[n]),
+            };
+        }
+
+        assert_eq!(
+            [x[35], x[34], x[33], x[32]],
+            [0x681edf34, 0xd206965e, 0x86b3e94f, 0x536e4246]
+        );
+    }
+
+    #[test]
+    fn test_zbk() {
+        assert_eq!(
+            rv32(r(0b100, 0b0000100), 0xAAAA_1234, 0xBBBB_5678),
+            0x5678_1234
+        );
+        assert_eq!(rv32(r(0b111, 0b0000100), 0x1234, 0x5678), 0x7834);
+        assert_eq!(
+            rv64(r(0b100, 0b0000100), 0xAAAA_AAAA_1234_5678, 0x9ABC_DEF0),
+            0x9ABC_DEF0_1234_5678
+        );
+        assert_eq!(
+            rv64(
+                Inst::build_r(0b0111011, 10, 0b100, 11, 12, 0b0000100).raw(),
+                0x1234,
+                0x8765
+            ),
+            0xFFFF_FFFF_8765_1234
+        );
+        assert_eq!(rv32(i(0b101, 0x687), 0x0180_C001, 0), 0x8001_0380);
+
+        let (zip, unzip) = (i(0b001, 0x08F), i(0b101, 0x08F));
+        assert_eq!(rv32(zip, 0xFFFF_0000, 0), 0xAAAA_AAAA);
+        assert_eq!(rv32(unzip, 0xAAAA_AAAA, 0), 0xFFFF_0000);
+        assert_eq!(rv32(unzip, rv32(zip, 0x1234_5678, 0), 0), 0x1234_5678);
+
+        let (xperm4, xperm8) = (r(0b010, 0b0010100), r(0b100, 0b0010100));
+        assert_eq!(rv32(xperm8, 0x4433_2211, 0x0400_0103), 0x0011_2244);
+        assert_eq!(rv32(xperm4, 0x7654_3210, 0x0123_89AB), 0x0123_0000);
+        assert_eq!(
+            rv64(xperm8, 0x8877_6655_4433_2211, 0x0808_0808_0807_0400),
+            0x0000_0000_0088_5511
+        );
+    }
+}
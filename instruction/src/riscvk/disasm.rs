@@ -0,0 +1,103 @@
+use core::fmt;
+
+use crate::{
+    riscv::{abi_name, InstI, InstR},
+    Disassemble,
+};
+
+use super::RVCryptoInst;
+
+fn unary(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstI) -> fmt::Result {
+    write!(
+        f,
+        "{} {},{}",
+        name,
+        abi_name(inst.rd()),
+        abi_name(inst.rs1())
+    )
+}
+
+/// Byte instructions, with byte index as last operand.
+fn byte(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstR) -> fmt::Result {
+    write!(f, "{} {},{}", name, inst, inst.funct7() >> 5)
+}
+
+impl<I: Disassemble> Disassemble for RVCryptoInst<I> {
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pack(i) => write!(f, "pack {}", i),
+            Self::Packh(i) => write!(f, "packh {}", i),
+            Self::Packw(i) => write!(f, "packw {}", i),
+            Self::Brev8(i) => unary(f, "brev8", i),
+            Self::Zip(i) => unary(f, "zip", i),
+            Self::Unzip(i) => unary(f, "unzip", i),
+            Self::Xperm4(i) => write!(f, "xperm4 {}", i),
+            Self::Xperm8(i) => write!(f, "xperm8 {}", i),
+            Self::Aes32Esi(i) => byte(f, "aes32esi", i),
+            Self::Aes32Esmi(i) => byte(f, "aes32esmi", i),
+            Self::Aes32Dsi(i) => byte(f, "aes32dsi", i),
+            Self::Aes32Dsmi(i) => byte(f, "aes32dsmi", i),
+            Self::Aes64Es(i) => write!(f, "aes64es {}", i),
+            Self::Aes64Esm(i) => write!(f, "aes64esm {}", i),
+            Self::Aes64Ds(i) => write!(f, "aes64ds {}", i),
+            Self::Aes64Dsm(i) => write!(f, "aes64dsm {}", i),
+            Self::Aes64Im(i) => unary(f, "aes64im", i),
+            Self::Aes64Ks1i(i) => write!(
+                f,
+                "aes64ks1i {},{},{}",
+                abi_name(i.rd()),
+                abi_name(i.rs1()),
+                i.imm() & 0xF
+            ),
+            Self::Aes64Ks2(i) => write!(f, "aes64ks2 {}", i),
+            Self::Sha256Sig0(i) => unary(f, "sha256sig0", i),
+            Self::Sha256Sig1(i) => unary(f, "sha256sig1", i),
+            Self::Sha256Sum0(i) => unary(f, "sha256sum0", i),
+            Self::Sha256Sum1(i) => unary(f, "sha256sum1", i),
+            Self::Sha512Sum0r(i) => write!(f, "sha512sum0r {}", i),
+            Self::Sha512Sum1r(i) => write!(f, "sha512sum1r {}", i),
+            Self::Sha512Sig0l(i) => write!(f, "sha512sig0l {}", i),
+            Self::Sha512Sig0h(i) => write!(f, "sha512sig0h {}", i),
+            Self::Sha512Sig1l(i) => write!(f, "sha512sig1l {}", i),
+            Self::Sha512Sig1h(i) => write!(f, "sha512sig1h {}", i),
+            Self::Sha512Sig0(i) => unary(f, "sha512sig0", i),
+            Self::Sha512Sig1(i) => unary(f, "sha512sig1", i),
+            Self::Sha512Sum0(i) => unary(f, "sha512sum0", i),
+            Self::Sha512Sum1(i) => unary(f, "sha512sum1", i),
+            Self::Sm4Ed(i) => byte(f, "sm4ed", i),
+            Self::Sm4Ks(i) => byte(f, "sm4ks", i),
+            Self::Sm3P0(i) => unary(f, "sm3p0", i),
+            Self::Sm3P1(i) => unary(f, "sm3p1", i),
+            Self::Other(i) => i.disassemble(pc, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::{String, ToString};
+
+    use crate::{riscv32i::RV32iBaseInst, Instruction};
+
+    use super::RVCryptoInst;
+
+    fn disasm(raw: u32) -> String {
+        let inst = RV32iBaseInst::<RVCryptoInst<()>>::new(&raw.to_le_bytes()).unwrap();
+        inst.to_string()
+    }
+
+    #[test]
+    fn test_disasm_crypto() {
+        assert_eq!(disasm(0xe6c58533), "aes32esmi a0,a1,a2,3");
+        assert_eq!(disasm(0x62c58533), "aes32esi a0,a1,a2,1");
+        assert_eq!(disasm(0x10259513), "sha256sig0 a0,a1");
+        assert_eq!(disasm(0x50c58533), "sha512sum0r a0,a1,a2");
+        assert_eq!(disasm(0xf0c58533), "sm4ed a0,a1,a2,3");
+        assert_eq!(disasm(0x10859513), "sm3p0 a0,a1");
+        assert_eq!(disasm(0x08c5c533), "pack a0,a1,a2");
+        assert_eq!(disasm(0x6875d513), "brev8 a0,a1");
+        assert_eq!(disasm(0x00b50533), "add a0,a0,a1");
+    }
+}
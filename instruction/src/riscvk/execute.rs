@@ -0,0 +1,257 @@
+//! Cipher and hash primitives, on zero-extended values like
+//! [`crate::riscvb`] operations.
+
+pub use crate::riscvb::execute::{r, unary, word};
+
+/// Multiply in GF(2^8) with reduction polynomial `poly`.
+const fn gmul(mut a: u8, mut b: u8, poly: u16) -> u8 {
+    let mut r = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            r ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= poly as u8;
+        }
+        b >>= 1;
+    }
+    r
+}
+
+/// Multiplicative inverse in GF(2^8), 0 maps to 0.
+const fn ginv(a: u8, poly: u16) -> u8 {
+    // a^254
+    let (mut r, mut i) = (1, 0);
+    while i < 254 {
+        r = gmul(r, a, poly);
+        i += 1;
+    }
+    r
+}
+
+const fn aes_sbox() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let b = ginv(i as u8, 0x11B);
+        table[i] =
+            b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        i += 1;
+    }
+    table
+}
+
+const fn inverse(table: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[table[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+}
+
+/// Affine transform of SM4 S-box, rows are rotations of `0xA7`.
+const fn sm4_affine(x: u8) -> u8 {
+    let (mut r, mut i) = (0, 0);
+    while i < 8 {
+        let row = 0xA7u8.rotate_left(i);
+        r |= (((row & x).count_ones() & 1) as u8) << i;
+        i += 1;
+    }
+    r ^ 0xD3
+}
+
+const fn sm4_sbox() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = sm4_affine(ginv(sm4_affine(i as u8), 0x1F5));
+        i += 1;
+    }
+    table
+}
+
+pub const AES_SBOX: [u8; 256] = aes_sbox();
+pub const AES_INV_SBOX: [u8; 256] = inverse(&AES_SBOX);
+pub const SM4_SBOX: [u8; 256] = sm4_sbox();
+
+const AES_RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36];
+
+fn aes_mul(a: u8, b: u8) -> u8 {
+    gmul(a, b, 0x11B)
+}
+
+/// Apply `f` to byte `bs` of `rs2`, xor result rotated back into place with
+/// `rs1`. Shared by `aes32*` and `sm4*`.
+pub fn byte_select(rs1: u64, rs2: u64, bs: u32, f: impl Fn(u8) -> u32) -> u64 {
+    let shamt = bs * 8;
+    word(rs1 as u32 ^ f((rs2 >> shamt) as u8).rotate_left(shamt))
+}
+
+/// Column of forward MixColumns with a single non-zero byte.
+pub fn aes_mix_byte(x: u8) -> u32 {
+    u32::from_le_bytes([aes_mul(x, 2), x, x, aes_mul(x, 3)])
+}
+
+/// Column of inverse MixColumns with a single non-zero byte.
+pub fn aes_inv_mix_byte(x: u8) -> u32 {
+    u32::from_le_bytes([
+        aes_mul(x, 14),
+        aes_mul(x, 9),
+        aes_mul(x, 13),
+        aes_mul(x, 11),
+    ])
+}
+
+/// Linear transform L of SM4 round after S-box, rotations don't wrap a byte.
+pub fn sm4_ed(x: u8) -> u32 {
+    let x = SM4_SBOX[x as usize] as u32;
+    x ^ (x << 2) ^ (x << 10) ^ (x << 18) ^ (x << 24)
+}
+
+/// Linear transform L' of SM4 key schedule after S-box.
+pub fn sm4_ks(x: u8) -> u32 {
+    let x = SM4_SBOX[x as usize] as u32;
+    x ^ (x << 13) ^ (x << 23)
+}
+
+/// Low half of ShiftRows of state `rs2:rs1`, then S-box of each byte.
+pub fn aes64_rows(rs1: u64, rs2: u64, inverse: bool) -> [u8; 8] {
+    let state = ((rs1 as u128) | (rs2 as u128) << 64).to_le_bytes();
+    let sbox = match inverse {
+        true => &AES_INV_SBOX,
+        false => &AES_SBOX,
+    };
+
+    let mut out = [0; 8];
+    for (i, b) in out.iter_mut().enumerate() {
+        let (col, row) = (i / 4, i % 4);
+        let from = match inverse {
+            true => (col + 4 - row) % 4,
+            false => (col + row) % 4,
+        };
+        *b = sbox[state[from * 4 + row] as usize];
+    }
+    out
+}
+
+/// MixColumns, or its inverse, of both columns in `state`.
+pub fn aes64_mix(state: [u8; 8], inverse: bool) -> u64 {
+    let coeffs = match inverse {
+        true => [14, 11, 13, 9],
+        false => [2, 3, 1, 1],
+    };
+
+    let mut out = [0; 8];
+    for (i, b) in out.iter_mut().enumerate() {
+        let (col, row) = (i / 4, i % 4);
+        *b = (0..4).fold(0, |r, k| {
+            r ^ aes_mul(state[col * 4 + k], coeffs[(k + 4 - row) % 4])
+        });
+    }
+    u64::from_le_bytes(out)
+}
+
+/// Key schedule step of round `rnum`, `rnum` 10 only substitutes.
+pub fn aes64_ks1(rs1: u64, rnum: u32) -> u64 {
+    let word = (rs1 >> 32) as u32;
+    let (word, rcon) = match AES_RCON.get(rnum as usize) {
+        Some(rcon) => (word.rotate_right(8), *rcon as u32),
+        None => (word, 0),
+    };
+
+    let bytes = word.to_le_bytes().map(|b| AES_SBOX[b as usize]);
+    let word = (u32::from_le_bytes(bytes) ^ rcon) as u64;
+    word << 32 | word
+}
+
+pub fn aes64_ks2(rs1: u64, rs2: u64) -> u64 {
+    let w0 = (rs1 >> 32) as u32 ^ rs2 as u32;
+    let w1 = w0 ^ (rs2 >> 32) as u32;
+    (w1 as u64) << 32 | w0 as u64
+}
+
+/// `ror(a, x) ^ ror(a, y) ^ shift(a)` on a word, sign extended.
+pub fn sig32(a: u64, x: u32, y: u32, shift: impl Fn(u32) -> u32) -> u64 {
+    let a = a as u32;
+    word(a.rotate_right(x) ^ a.rotate_right(y) ^ shift(a))
+}
+
+pub fn sig64(a: u64, x: u32, y: u32, shift: impl Fn(u64) -> u64) -> u64 {
+    a.rotate_right(x) ^ a.rotate_right(y) ^ shift(a)
+}
+
+/// Halves of 64 bits SHA-512 functions on RV32, `a` is the half written.
+pub fn sha512_sum0r(a: u64, b: u64) -> u64 {
+    let (a, b) = (a as u32, b as u32);
+    word((a << 25) ^ (a << 30) ^ (a >> 28) ^ (b >> 7) ^ (b >> 2) ^ (b << 4))
+}
+
+pub fn sha512_sum1r(a: u64, b: u64) -> u64 {
+    let (a, b) = (a as u32, b as u32);
+    word((a << 23) ^ (a >> 14) ^ (a >> 18) ^ (b >> 9) ^ (b << 18) ^ (b << 14))
+}
+
+pub fn sha512_sig0(a: u64, b: u64, low: bool) -> u64 {
+    let (a, b) = (a as u32, b as u32);
+    let v = (a >> 1) ^ (a >> 7) ^ (a >> 8) ^ (b << 31) ^ (b << 24);
+    word(match low {
+        true => v ^ (b << 25),
+        false => v,
+    })
+}
+
+pub fn sha512_sig1(a: u64, b: u64, low: bool) -> u64 {
+    let (a, b) = (a as u32, b as u32);
+    let v = (a << 3) ^ (a >> 6) ^ (a >> 19) ^ (b >> 29) ^ (b << 13);
+    word(match low {
+        true => v ^ (b << 26),
+        false => v,
+    })
+}
+
+pub fn sm3_p(a: u64, x: u32, y: u32) -> u64 {
+    let a = a as u32;
+    word(a ^ a.rotate_left(x) ^ a.rotate_left(y))
+}
+
+/// Pack low halves of `a` and `b`, each of `half` bits.
+pub fn pack(a: u64, b: u64, half: u32) -> u64 {
+    let mask = u64::MAX >> (64 - half);
+    (a & mask) | (b & mask) << half
+}
+
+/// Reverse bits in each byte.
+pub fn brev8(a: u64) -> u64 {
+    u64::from_le_bytes(a.to_le_bytes().map(u8::reverse_bits))
+}
+
+/// Interleave low and high half of a word, `zip` on RV32.
+pub fn zip(a: u64) -> u64 {
+    (0..16).fold(0, |r, i| {
+        r | (a >> i & 1) << (2 * i) | (a >> (i + 16) & 1) << (2 * i + 1)
+    })
+}
+
+pub fn unzip(a: u64) -> u64 {
+    (0..16).fold(0, |r, i| {
+        r | (a >> (2 * i) & 1) << i | (a >> (2 * i + 1) & 1) << (i + 16)
+    })
+}
+
+/// Look up elements of `bits` bits in `a` by indices in `b`, 0 when out of
+/// range.
+pub fn xperm(a: u64, b: u64, bits: u32, xlen: u32) -> u64 {
+    let (mask, count) = ((1 << bits) - 1, (xlen / bits) as u64);
+
+    (0..count).fold(0, |r, i| {
+        let index = b >> (i * bits as u64) & mask;
+        match index < count {
+            true => r | (a >> (index * bits as u64) & mask) << (i * bits as u64),
+            false => r,
+        }
+    })
+}
@@ -0,0 +1,8 @@
+//! RISCV scalar cryptography extensions, Zkn and Zks
+
+mod base;
+pub use base::*;
+
+mod execute;
+
+mod disasm;
@@ -0,0 +1,1220 @@
+use crate::{
+    riscv::{Inst, InstV},
+    Context, Error, Instruction, Memory, MemoryMut, RegX, Result,
+};
+
+use super::{
+    execute::{self, VRegs},
+    vill, VType, VectorRegs,
+};
+
+/// Instruction of vector extension 1.0 for RV32 and RV64, with vector
+/// registers of `VLEN` bits
+///
+/// Covers configuration, unit-stride and strided loads and stores, integer
+/// arithmetic, reductions and mask instructions, with ELEN of 64. Vector
+/// registers are state of hart reached through [`Context::vector`], like
+/// [`VectorState`](super::VectorState), and must be `VLEN` bits. Tail and
+/// inactive elements are left undisturbed.
+pub enum RVVectorInst<I, const VLEN: usize = 128> {
+    /// Set `vl` and `vtype` from register and immediate
+    Vsetvli(InstV),
+    /// Set `vl` and `vtype` from immediates
+    Vsetivli(InstV),
+    /// Set `vl` and `vtype` from registers
+    Vsetvl(InstV),
+    /// Unit-stride load
+    Vle(InstV),
+    /// Strided load
+    Vlse(InstV),
+    /// Mask load
+    Vlm(InstV),
+    /// Unit-stride store
+    Vse(InstV),
+    /// Strided store
+    Vsse(InstV),
+    /// Mask store
+    Vsm(InstV),
+    /// Add
+    Vadd(InstV),
+    /// Subtract
+    Vsub(InstV),
+    /// Reverse subtract
+    Vrsub(InstV),
+    /// Minimum in Unsigned Int
+    Vminu(InstV),
+    /// Minimum
+    Vmin(InstV),
+    /// Maximum in Unsigned Int
+    Vmaxu(InstV),
+    /// Maximum
+    Vmax(InstV),
+    /// And
+    Vand(InstV),
+    /// Or
+    Vor(InstV),
+    /// Exclusive or
+    Vxor(InstV),
+    /// Shift left logical
+    Vsll(InstV),
+    /// Shift right logical
+    Vsrl(InstV),
+    /// Shift right arithmetic
+    Vsra(InstV),
+    /// Merge by mask, or move when unmasked
+    Vmerge(InstV),
+    /// Set mask if equal
+    Vmseq(InstV),
+    /// Set mask if not equal
+    Vmsne(InstV),
+    /// Set mask if less than, Unsigned
+    Vmsltu(InstV),
+    /// Set mask if less than
+    Vmslt(InstV),
+    /// Set mask if less than or equal, Unsigned
+    Vmsleu(InstV),
+    /// Set mask if less than or equal
+    Vmsle(InstV),
+    /// Set mask if greater than, Unsigned
+    Vmsgtu(InstV),
+    /// Set mask if greater than
+    Vmsgt(InstV),
+    /// Multiply, low half
+    Vmul(InstV),
+    /// Signed multiply, high half
+    Vmulh(InstV),
+    /// Unsigned multiply, high half
+    Vmulhu(InstV),
+    /// Signed and unsigned multiply, high half
+    Vmulhsu(InstV),
+    /// Unsigned divide
+    Vdivu(InstV),
+    /// Signed divide
+    Vdiv(InstV),
+    /// Unsigned remainder
+    Vremu(InstV),
+    /// Signed remainder
+    Vrem(InstV),
+    /// Multiply and add to destination
+    Vmacc(InstV),
+    /// Multiply and subtract from destination
+    Vnmsac(InstV),
+    /// Multiply destination and add
+    Vmadd(InstV),
+    /// Multiply destination and subtract
+    Vnmsub(InstV),
+    /// Sum reduction
+    Vredsum(InstV),
+    /// And reduction
+    Vredand(InstV),
+    /// Or reduction
+    Vredor(InstV),
+    /// Exclusive or reduction
+    Vredxor(InstV),
+    /// Unsigned minimum reduction
+    Vredminu(InstV),
+    /// Signed minimum reduction
+    Vredmin(InstV),
+    /// Unsigned maximum reduction
+    Vredmaxu(InstV),
+    /// Signed maximum reduction
+    Vredmax(InstV),
+    /// Mask and with inverted operand
+    Vmandn(InstV),
+    /// Mask and
+    Vmand(InstV),
+    /// Mask or
+    Vmor(InstV),
+    /// Mask exclusive or
+    Vmxor(InstV),
+    /// Mask or with inverted operand
+    Vmorn(InstV),
+    /// Mask nand
+    Vmnand(InstV),
+    /// Mask nor
+    Vmnor(InstV),
+    /// Mask exclusive nor
+    Vmxnor(InstV),
+    /// Count set mask bits
+    Vcpop(InstV),
+    /// Index of first set mask bit
+    Vfirst(InstV),
+    /// Prefix count of mask bits
+    Viota(InstV),
+    /// Element index
+    Vid(InstV),
+    /// Move element 0 to integer register
+    VmvXS(InstV),
+    /// Move integer register to element 0
+    VmvSX(InstV),
+    /// Other Instruction
+    Other(I),
+}
+
+/// Reserved or unsupported use of a decoded instruction, encoding and pc are
+/// filled by [`Instruction::execute`].
+const ILLEGAL: Error = Error::IllegalInstruction { raw: 0, pc: 0 };
+
+impl<I: Instruction, const VLEN: usize> RVVectorInst<I, VLEN> {
+    fn _new(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(Error::ErrBytecodeLengthNotEnough);
+        }
+
+        let inst = Inst::new([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let (funct3, funct6) = (inst.funct3(), inst.funct7() >> 1);
+        let vm = inst.funct7() & 1 == 1;
+        let (vs1, vs2) = (inst.rs1(), inst.rs2());
+
+        let r = match inst.opcode() {
+            0b1010111 => match (funct3, funct6) {
+                (0b111, _) => match inst.raw() >> 30 {
+                    0b00 | 0b01 => Self::Vsetvli(inst.into()),
+                    0b11 => Self::Vsetivli(inst.into()),
+                    _ if inst.funct7() == 0b1000000 => Self::Vsetvl(inst.into()),
+                    _ => Self::Other(I::new(bytes)?),
+                },
+                // OPIVV, OPIVI and OPIVX
+                (0b000 | 0b011 | 0b100, _) => {
+                    let (vv, vi) = (funct3 == 0b000, funct3 == 0b011);
+
+                    match funct6 {
+                        0b000000 => Self::Vadd(inst.into()),
+                        0b000010 if !vi => Self::Vsub(inst.into()),
+                        0b000011 if !vv => Self::Vrsub(inst.into()),
+                        0b000100 if !vi => Self::Vminu(inst.into()),
+                        0b000101 if !vi => Self::Vmin(inst.into()),
+                        0b000110 if !vi => Self::Vmaxu(inst.into()),
+                        0b000111 if !vi => Self::Vmax(inst.into()),
+                        0b001001 => Self::Vand(inst.into()),
+                        0b001010 => Self::Vor(inst.into()),
+                        0b001011 => Self::Vxor(inst.into()),
+                        0b010111 if !vm || vs2 == 0 => Self::Vmerge(inst.into()),
+                        0b011000 => Self::Vmseq(inst.into()),
+                        0b011001 => Self::Vmsne(inst.into()),
+                        0b011010 if !vi => Self::Vmsltu(inst.into()),
+                        0b011011 if !vi => Self::Vmslt(inst.into()),
+                        0b011100 => Self::Vmsleu(inst.into()),
+                        0b011101 => Self::Vmsle(inst.into()),
+                        0b011110 if !vv => Self::Vmsgtu(inst.into()),
+                        0b011111 if !vv => Self::Vmsgt(inst.into()),
+                        0b100101 => Self::Vsll(inst.into()),
+                        0b101000 => Self::Vsrl(inst.into()),
+                        0b101001 => Self::Vsra(inst.into()),
+                        _ => Self::Other(I::new(bytes)?),
+                    }
+                }
+                (0b010, 0b000000) => Self::Vredsum(inst.into()),
+                (0b010, 0b000001) => Self::Vredand(inst.into()),
+                (0b010, 0b000010) => Self::Vredor(inst.into()),
+                (0b010, 0b000011) => Self::Vredxor(inst.into()),
+                (0b010, 0b000100) => Self::Vredminu(inst.into()),
+                (0b010, 0b000101) => Self::Vredmin(inst.into()),
+                (0b010, 0b000110) => Self::Vredmaxu(inst.into()),
+                (0b010, 0b000111) => Self::Vredmax(inst.into()),
+                (0b010, 0b010000) => match vs1 {
+                    0b00000 if vm => Self::VmvXS(inst.into()),
+                    0b10000 => Self::Vcpop(inst.into()),
+                    0b10001 => Self::Vfirst(inst.into()),
+                    _ => Self::Other(I::new(bytes)?),
+                },
+                (0b110, 0b010000) if vm && vs2 == 0 => Self::VmvSX(inst.into()),
+                (0b010, 0b010100) => match vs1 {
+                    0b10000 => Self::Viota(inst.into()),
+                    0b10001 if vs2 == 0 => Self::Vid(inst.into()),
+                    _ => Self::Other(I::new(bytes)?),
+                },
+                (0b010, 0b011000) if vm => Self::Vmandn(inst.into()),
+                (0b010, 0b011001) if vm => Self::Vmand(inst.into()),
+                (0b010, 0b011010) if vm => Self::Vmor(inst.into()),
+                (0b010, 0b011011) if vm => Self::Vmxor(inst.into()),
+                (0b010, 0b011100) if vm => Self::Vmorn(inst.into()),
+                (0b010, 0b011101) if vm => Self::Vmnand(inst.into()),
+                (0b010, 0b011110) if vm => Self::Vmnor(inst.into()),
+                (0b010, 0b011111) if vm => Self::Vmxnor(inst.into()),
+                // OPMVV and OPMVX
+                (0b010 | 0b110, 0b100000) => Self::Vdivu(inst.into()),
+                (0b010 | 0b110, 0b100001) => Self::Vdiv(inst.into()),
+                (0b010 | 0b110, 0b100010) => Self::Vremu(inst.into()),
+                (0b010 | 0b110, 0b100011) => Self::Vrem(inst.into()),
+                (0b010 | 0b110, 0b100100) => Self::Vmulhu(inst.into()),
+                (0b010 | 0b110, 0b100101) => Self::Vmul(inst.into()),
+                (0b010 | 0b110, 0b100110) => Self::Vmulhsu(inst.into()),
+                (0b010 | 0b110, 0b100111) => Self::Vmulh(inst.into()),
+                (0b010 | 0b110, 0b101001) => Self::Vmadd(inst.into()),
+                (0b010 | 0b110, 0b101011) => Self::Vnmsub(inst.into()),
+                (0b010 | 0b110, 0b101101) => Self::Vmacc(inst.into()),
+                (0b010 | 0b110, 0b101111) => Self::Vnmsac(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            // Vector widths of LOAD-FP and STORE-FP, without segments and
+            // extended width.
+            opcode @ (0b0000111 | 0b0100111)
+                if matches!(funct3, 0b000 | 0b101 | 0b110 | 0b111) && inst.funct7() >> 3 == 0 =>
+            {
+                let mask = vm && funct3 == 0b000 && vs2 == 0b01011;
+
+                match (opcode == 0b0100111, (inst.funct7() >> 1) & 0b11) {
+                    (false, 0b00) if vs2 == 0 => Self::Vle(inst.into()),
+                    (false, 0b00) if mask => Self::Vlm(inst.into()),
+                    (false, 0b10) => Self::Vlse(inst.into()),
+                    (true, 0b00) if vs2 == 0 => Self::Vse(inst.into()),
+                    (true, 0b00) if mask => Self::Vsm(inst.into()),
+                    (true, 0b10) => Self::Vsse(inst.into()),
+                    _ => Self::Other(I::new(bytes)?),
+                }
+            }
+            _ => Self::Other(I::new(bytes)?),
+        };
+
+        Ok(r)
+    }
+}
+
+/// Source of second operand, vector register or scalar at SEW bits.
+enum Src {
+    Vector(usize),
+    Scalar(u64),
+}
+
+impl Src {
+    /// Operand of `inst`, immediate is zero-extended if `uimm`.
+    fn new<R: RegX>(inst: &InstV, regs: &[R], sew: u32, uimm: bool) -> Self {
+        match inst.funct3() {
+            0b000 | 0b010 => Self::Vector(inst.vs1()),
+            0b011 if uimm => Self::Scalar(inst.vs1() as u64),
+            0b011 => Self::Scalar(inst.simm5() as i64 as u64 & execute::mask(sew)),
+            _ => Self::Scalar(execute::scalar(&regs[inst.rs1()], sew)),
+        }
+    }
+
+    fn get<const VLEN: usize>(&self, v: &VRegs<VLEN>, i: usize, sew: u32) -> u64 {
+        match self {
+            Self::Vector(vs1) => v.get(*vs1, i, sew),
+            Self::Scalar(x) => *x,
+        }
+    }
+
+    /// Vector register, whose group must be aligned.
+    fn reg(&self) -> Option<usize> {
+        match self {
+            Self::Vector(vs1) => Some(*vs1),
+            Self::Scalar(_) => None,
+        }
+    }
+}
+
+/// Check registers are aligned to groups of `group` registers.
+fn aligned(group: usize, regs: &[Option<usize>]) -> Result<()> {
+    match regs.iter().flatten().all(|r| r % group == 0) {
+        true => Ok(()),
+        false => Err(ILLEGAL),
+    }
+}
+
+/// Kind of vector memory access.
+#[derive(PartialEq, Eq)]
+enum Access {
+    Unit,
+    Strided,
+    Mask,
+}
+
+impl<I, R, const VLEN: usize> RVVectorInst<I, VLEN>
+where
+    I: Instruction<Register = R>,
+    R: RegX + Copy,
+{
+    /// Encoding of vector instruction, `None` for other instructions.
+    fn inst(&self) -> Option<&InstV> {
+        match self {
+            Self::Vsetvli(i)
+            | Self::Vsetivli(i)
+            | Self::Vsetvl(i)
+            | Self::Vle(i)
+            | Self::Vlse(i)
+            | Self::Vlm(i)
+            | Self::Vse(i)
+            | Self::Vsse(i)
+            | Self::Vsm(i)
+            | Self::Vadd(i)
+            | Self::Vsub(i)
+            | Self::Vrsub(i)
+            | Self::Vminu(i)
+            | Self::Vmin(i)
+            | Self::Vmaxu(i)
+            | Self::Vmax(i)
+            | Self::Vand(i)
+            | Self::Vor(i)
+            | Self::Vxor(i)
+            | Self::Vsll(i)
+            | Self::Vsrl(i)
+            | Self::Vsra(i)
+            | Self::Vmerge(i)
+            | Self::Vmseq(i)
+            | Self::Vmsne(i)
+            | Self::Vmsltu(i)
+            | Self::Vmslt(i)
+            | Self::Vmsleu(i)
+            | Self::Vmsle(i)
+            | Self::Vmsgtu(i)
+            | Self::Vmsgt(i)
+            | Self::Vmul(i)
+            | Self::Vmulh(i)
+            | Self::Vmulhu(i)
+            | Self::Vmulhsu(i)
+            | Self::Vdivu(i)
+            | Self::Vdiv(i)
+            | Self::Vremu(i)
+            | Self::Vrem(i)
+            | Self::Vmacc(i)
+            | Self::Vnmsac(i)
+            | Self::Vmadd(i)
+            | Self::Vnmsub(i)
+            | Self::Vredsum(i)
+            | Self::Vredand(i)
+            | Self::Vredor(i)
+            | Self::Vredxor(i)
+            | Self::Vredminu(i)
+            | Self::Vredmin(i)
+            | Self::Vredmaxu(i)
+            | Self::Vredmax(i)
+            | Self::Vmandn(i)
+            | Self::Vmand(i)
+            | Self::Vmor(i)
+            | Self::Vmxor(i)
+            | Self::Vmorn(i)
+            | Self::Vmnand(i)
+            | Self::Vmnor(i)
+            | Self::Vmxnor(i)
+            | Self::Vcpop(i)
+            | Self::Vfirst(i)
+            | Self::Viota(i)
+            | Self::Vid(i)
+            | Self::VmvXS(i)
+            | Self::VmvSX(i) => Some(i),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Integer registers named by vector instruction.
+    fn scalar_regs(&self) -> [Option<usize>; 3] {
+        let Some(i) = self.inst() else {
+            return [None; 3];
+        };
+
+        match self {
+            Self::Vsetvli(_) => [Some(i.vd()), Some(i.rs1()), None],
+            Self::Vsetivli(_) => [Some(i.vd()), None, None],
+            Self::Vsetvl(_) => [Some(i.vd()), Some(i.rs1()), Some(i.vs2())],
+            Self::Vle(_) | Self::Vlm(_) | Self::Vse(_) | Self::Vsm(_) => {
+                [Some(i.rs1()), None, None]
+            }
+            Self::Vlse(_) | Self::Vsse(_) => [Some(i.rs1()), Some(i.vs2()), None],
+            Self::Vcpop(_) | Self::Vfirst(_) | Self::VmvXS(_) => [Some(i.vd()), None, None],
+            // OPIVX and OPMVX
+            _ if matches!(i.funct3(), 0b100 | 0b110) => [Some(i.rs1()), None, None],
+            _ => [None; 3],
+        }
+    }
+
+    /// Execute vector instruction, illegal ones get encoding and pc in `execute`.
+    fn execute_vector<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        use execute::*;
+
+        if self
+            .scalar_regs()
+            .iter()
+            .flatten()
+            .any(|r| *r >= regs.len())
+        {
+            return Err(ILLEGAL);
+        }
+
+        // Hart without vector registers, or with another VLEN.
+        let state = context
+            .vector()
+            .filter(|s| s.v.len() == 32 * VLEN / 8)
+            .ok_or(ILLEGAL)?;
+
+        if let Self::Vsetvli(_) | Self::Vsetivli(_) | Self::Vsetvl(_) = self {
+            self.vset(regs, state);
+            pc.set_regx(pc.regx().wrapping_add(4));
+            return Ok(());
+        }
+
+        let t = VType::new(*state.vtype).ok_or(ILLEGAL)?;
+        let vl = *state.vl as usize;
+        let v = &mut VRegs::<VLEN>::new(state.v);
+        let (sew, m) = (t.sew, mask(t.sew));
+        let s = |a: u64| signed(a, sew);
+
+        match &*self {
+            Self::Vle(i) => Self::access(i, regs, v, memory, t, vl, Access::Unit, false)?,
+            Self::Vlse(i) => Self::access(i, regs, v, memory, t, vl, Access::Strided, false)?,
+            Self::Vlm(i) => Self::access(i, regs, v, memory, t, vl, Access::Mask, false)?,
+            Self::Vse(i) => Self::access(i, regs, v, memory, t, vl, Access::Unit, true)?,
+            Self::Vsse(i) => Self::access(i, regs, v, memory, t, vl, Access::Strided, true)?,
+            Self::Vsm(i) => Self::access(i, regs, v, memory, t, vl, Access::Mask, true)?,
+            Self::Vadd(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a.wrapping_add(b))?
+            }
+            Self::Vsub(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a.wrapping_sub(b))?
+            }
+            Self::Vrsub(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| b.wrapping_sub(a))?
+            }
+            Self::Vminu(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a.min(b))?,
+            Self::Vmin(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| match s(a) < s(b) {
+                    true => a,
+                    false => b,
+                })?
+            }
+            Self::Vmaxu(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a.max(b))?,
+            Self::Vmax(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| match s(a) < s(b) {
+                    true => b,
+                    false => a,
+                })?
+            }
+            Self::Vand(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a & b)?,
+            Self::Vor(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a | b)?,
+            Self::Vxor(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a ^ b)?,
+            Self::Vsll(i) => Self::elementwise(i, regs, v, t, vl, true, |a, b, _| {
+                a << (b & (sew as u64 - 1))
+            })?,
+            Self::Vsrl(i) => Self::elementwise(i, regs, v, t, vl, true, |a, b, _| {
+                a >> (b & (sew as u64 - 1))
+            })?,
+            Self::Vsra(i) => Self::elementwise(i, regs, v, t, vl, true, |a, b, _| {
+                (s(a) >> (b & (sew as u64 - 1))) as u64 & m
+            })?,
+            Self::Vmerge(i) => Self::merge(i, regs, v, t, vl)?,
+            Self::Vmseq(i) => Self::compare(i, regs, v, t, vl, |a, b| a == b)?,
+            Self::Vmsne(i) => Self::compare(i, regs, v, t, vl, |a, b| a != b)?,
+            Self::Vmsltu(i) => Self::compare(i, regs, v, t, vl, |a, b| a < b)?,
+            Self::Vmslt(i) => Self::compare(i, regs, v, t, vl, |a, b| s(a) < s(b))?,
+            Self::Vmsleu(i) => Self::compare(i, regs, v, t, vl, |a, b| a <= b)?,
+            Self::Vmsle(i) => Self::compare(i, regs, v, t, vl, |a, b| s(a) <= s(b))?,
+            Self::Vmsgtu(i) => Self::compare(i, regs, v, t, vl, |a, b| a > b)?,
+            Self::Vmsgt(i) => Self::compare(i, regs, v, t, vl, |a, b| s(a) > s(b))?,
+            Self::Vmul(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| a.wrapping_mul(b))?
+            }
+            Self::Vmulh(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| mulh(a, b, sew))?
+            }
+            Self::Vmulhu(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| mulhu(a, b, sew))?
+            }
+            Self::Vmulhsu(i) => {
+                Self::elementwise(i, regs, v, t, vl, false, |a, b, _| mulhsu(a, b, sew))?
+            }
+            Self::Vdivu(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| divu(a, b))?,
+            Self::Vdiv(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| div(a, b, sew))?,
+            Self::Vremu(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| remu(a, b))?,
+            Self::Vrem(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, _| rem(a, b, sew))?,
+            Self::Vmacc(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, d| {
+                d.wrapping_add(a.wrapping_mul(b))
+            })?,
+            Self::Vnmsac(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, d| {
+                d.wrapping_sub(a.wrapping_mul(b))
+            })?,
+            Self::Vmadd(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, d| {
+                a.wrapping_add(d.wrapping_mul(b))
+            })?,
+            Self::Vnmsub(i) => Self::elementwise(i, regs, v, t, vl, false, |a, b, d| {
+                a.wrapping_sub(d.wrapping_mul(b))
+            })?,
+            Self::Vredsum(i) => Self::reduce(i, v, t, vl, u64::wrapping_add)?,
+            Self::Vredand(i) => Self::reduce(i, v, t, vl, |a, b| a & b)?,
+            Self::Vredor(i) => Self::reduce(i, v, t, vl, |a, b| a | b)?,
+            Self::Vredxor(i) => Self::reduce(i, v, t, vl, |a, b| a ^ b)?,
+            Self::Vredminu(i) => Self::reduce(i, v, t, vl, u64::min)?,
+            Self::Vredmin(i) => Self::reduce(i, v, t, vl, |a, b| match s(a) < s(b) {
+                true => a,
+                false => b,
+            })?,
+            Self::Vredmaxu(i) => Self::reduce(i, v, t, vl, u64::max)?,
+            Self::Vredmax(i) => Self::reduce(i, v, t, vl, |a, b| match s(a) < s(b) {
+                true => b,
+                false => a,
+            })?,
+            Self::Vmandn(i) => Self::logical(i, v, vl, |a, b| a & !b),
+            Self::Vmand(i) => Self::logical(i, v, vl, |a, b| a & b),
+            Self::Vmor(i) => Self::logical(i, v, vl, |a, b| a | b),
+            Self::Vmxor(i) => Self::logical(i, v, vl, |a, b| a ^ b),
+            Self::Vmorn(i) => Self::logical(i, v, vl, |a, b| a | !b),
+            Self::Vmnand(i) => Self::logical(i, v, vl, |a, b| !(a & b)),
+            Self::Vmnor(i) => Self::logical(i, v, vl, |a, b| !(a | b)),
+            Self::Vmxnor(i) => Self::logical(i, v, vl, |a, b| !(a ^ b)),
+            Self::Vcpop(i) | Self::Vfirst(i) => {
+                let mut set = (0..vl).filter(|n| v.active(i, *n) && v.bit(i.vs2(), *n));
+
+                let value = match self {
+                    Self::Vcpop(_) => set.count() as u64,
+                    _ => set.next().map_or(u64::MAX, |n| n as u64),
+                };
+                if i.vd() != 0 {
+                    regs[i.vd()].set_regx(value);
+                }
+            }
+            Self::Viota(i) | Self::Vid(i) => {
+                aligned(t.group(), &[Some(i.vd())])?;
+                if !i.vm() && i.vd() == 0 {
+                    return Err(ILLEGAL);
+                }
+
+                let iota = matches!(self, Self::Viota(_));
+                let mut count = 0;
+                for n in 0..vl {
+                    if !v.active(i, n) {
+                        continue;
+                    }
+
+                    let bit = v.bit(i.vs2(), n);
+                    let value = match iota {
+                        true => count,
+                        false => n as u64,
+                    };
+                    v.set(i.vd(), n, sew, value);
+                    count += bit as u64;
+                }
+            }
+            Self::VmvXS(i) => {
+                let value = signed(v.get(i.vs2(), 0, sew), sew);
+                if i.vd() != 0 {
+                    regs[i.vd()].set_regx(value as u64);
+                }
+            }
+            Self::VmvSX(i) => {
+                let value = scalar(&regs[i.rs1()], sew);
+                if vl > 0 {
+                    v.set(i.vd(), 0, sew, value);
+                }
+            }
+            Self::Vsetvli(_) | Self::Vsetivli(_) | Self::Vsetvl(_) | Self::Other(_) => {
+                unreachable!()
+            }
+        }
+
+        pc.set_regx(pc.regx().wrapping_add(4));
+
+        Ok(())
+    }
+
+    fn vset(&self, regs: &mut [R], state: VectorRegs) {
+        let (inst, vtype, avl) = match self {
+            Self::Vsetvli(i) => (i, (i.inst().imm_i() & 0x7FF) as u64, None),
+            Self::Vsetivli(i) => (i, (i.inst().imm_i() & 0x3FF) as u64, Some(i.rs1() as u64)),
+            Self::Vsetvl(i) => (i, regs[i.vs2()].regx(), None),
+            _ => unreachable!(),
+        };
+
+        // x0 as AVL keeps `vl` if rd is x0 too, else asks for VLMAX.
+        let avl = avl.unwrap_or_else(|| match (inst.rs1(), inst.vd()) {
+            (0, 0) => *state.vl,
+            (0, _) => u64::MAX,
+            (rs1, _) => regs[rs1].regx(),
+        });
+
+        let (vl, vtype) = match VType::new(vtype) {
+            Some(t) => (avl.min(t.vlmax(VLEN) as u64), vtype),
+            None => (0, vill(R::XLEN)),
+        };
+
+        *state.vl = vl;
+        *state.vtype = vtype;
+        if inst.vd() != 0 {
+            regs[inst.vd()].set_regx(vl);
+        }
+    }
+
+    /// Element-wise operation `f(vs2, operand, vd)` written to `vd`.
+    fn elementwise(
+        inst: &InstV,
+        regs: &[R],
+        v: &mut VRegs<VLEN>,
+        t: VType,
+        vl: usize,
+        uimm: bool,
+        f: impl Fn(u64, u64, u64) -> u64,
+    ) -> Result<()> {
+        let src = Src::new(inst, regs, t.sew, uimm);
+        aligned(t.group(), &[Some(inst.vd()), Some(inst.vs2()), src.reg()])?;
+        if !inst.vm() && inst.vd() == 0 {
+            return Err(ILLEGAL);
+        }
+
+        for i in 0..vl {
+            if !v.active(inst, i) {
+                continue;
+            }
+
+            let (a, b) = (v.get(inst.vs2(), i, t.sew), src.get(v, i, t.sew));
+            let d = v.get(inst.vd(), i, t.sew);
+            v.set(inst.vd(), i, t.sew, f(a, b, d));
+        }
+
+        Ok(())
+    }
+
+    /// Operand where mask is set, else `vs2`.
+    fn merge(inst: &InstV, regs: &[R], v: &mut VRegs<VLEN>, t: VType, vl: usize) -> Result<()> {
+        let src = Src::new(inst, regs, t.sew, false);
+        aligned(t.group(), &[Some(inst.vd()), Some(inst.vs2()), src.reg()])?;
+        if !inst.vm() && inst.vd() == 0 {
+            return Err(ILLEGAL);
+        }
+
+        for i in 0..vl {
+            let value = match v.active(inst, i) {
+                true => src.get(v, i, t.sew),
+                false => v.get(inst.vs2(), i, t.sew),
+            };
+            v.set(inst.vd(), i, t.sew, value);
+        }
+
+        Ok(())
+    }
+
+    /// Mask bits of `f(vs2, operand)` written to `vd`.
+    fn compare(
+        inst: &InstV,
+        regs: &[R],
+        v: &mut VRegs<VLEN>,
+        t: VType,
+        vl: usize,
+        f: impl Fn(u64, u64) -> bool,
+    ) -> Result<()> {
+        let src = Src::new(inst, regs, t.sew, false);
+        aligned(t.group(), &[Some(inst.vs2()), src.reg()])?;
+
+        for i in 0..vl {
+            if !v.active(inst, i) {
+                continue;
+            }
+
+            let bit = f(v.get(inst.vs2(), i, t.sew), src.get(v, i, t.sew));
+            v.set_bit(inst.vd(), i, bit);
+        }
+
+        Ok(())
+    }
+
+    /// Fold active elements of `vs2` into element 0 of `vs1`, written to `vd`.
+    fn reduce(
+        inst: &InstV,
+        v: &mut VRegs<VLEN>,
+        t: VType,
+        vl: usize,
+        f: impl Fn(u64, u64) -> u64,
+    ) -> Result<()> {
+        aligned(t.group(), &[Some(inst.vs2())])?;
+        if vl == 0 {
+            return Ok(());
+        }
+
+        let r = (0..vl)
+            .filter(|i| v.active(inst, *i))
+            .fold(v.get(inst.vs1(), 0, t.sew), |acc, i| {
+                f(acc, v.get(inst.vs2(), i, t.sew))
+            });
+        v.set(inst.vd(), 0, t.sew, r);
+
+        Ok(())
+    }
+
+    /// Mask bits of `f(vs2, vs1)` written to `vd`.
+    fn logical(inst: &InstV, v: &mut VRegs<VLEN>, vl: usize, f: impl Fn(bool, bool) -> bool) {
+        for i in 0..vl {
+            let bit = f(v.bit(inst.vs2(), i), v.bit(inst.vs1(), i));
+            v.set_bit(inst.vd(), i, bit);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn access<M>(
+        inst: &InstV,
+        regs: &[R],
+        v: &mut VRegs<VLEN>,
+        memory: &mut M,
+        t: VType,
+        vl: usize,
+        kind: Access,
+        store: bool,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+    {
+        let (eew, evl, emul) = match kind {
+            Access::Mask => (8, vl.div_ceil(8), 0),
+            _ => {
+                let eew = execute::eew(inst);
+                let emul = t.lmul + eew.trailing_zeros() as i32 - t.sew.trailing_zeros() as i32;
+                (eew, vl, emul)
+            }
+        };
+
+        if !(-3..=3).contains(&emul) {
+            return Err(ILLEGAL);
+        }
+        aligned(1 << emul.max(0), &[Some(inst.vd())])?;
+        if !store && !inst.vm() && inst.vd() == 0 {
+            return Err(ILLEGAL);
+        }
+
+        let base = regs[inst.rs1()].regx();
+        let stride = match kind {
+            Access::Strided => execute::signed(regs[inst.vs2()].regx(), R::XLEN) as u64,
+            _ => eew as u64 / 8,
+        };
+
+        let mut addr = regs[inst.rs1()];
+        for i in 0..evl {
+            if kind != Access::Mask && !v.active(inst, i) {
+                continue;
+            }
+
+            addr.set_regx(base.wrapping_add((i as u64).wrapping_mul(stride)));
+            if !memory.contains(addr, eew as u8 / 8) {
+                let addr = addr.regx();
+                return Err(match store {
+                    true => Error::StoreAccessFault { addr, pc: 0 },
+                    false => Error::LoadAccessFault { addr, pc: 0 },
+                });
+            }
+
+            match store {
+                true => {
+                    let bytes = v.get(inst.vd(), i, eew).to_le_bytes();
+                    memory.store(addr, &bytes[..eew as usize / 8]);
+                }
+                false => {
+                    let data = memory.load(addr, eew as u8 / 8);
+                    let value = data.iter().rev().fold(0, |r, b| r << 8 | *b as u64);
+                    v.set(inst.vd(), i, eew, value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I, R, const VLEN: usize> Instruction for RVVectorInst<I, VLEN>
+where
+    I: Instruction<Register = R>,
+    R: RegX + Copy,
+{
+    type Register = R;
+
+    fn new(bytes: &[u8]) -> Result<Self> {
+        Self::_new(bytes)
+    }
+
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        if let Self::Other(inst) = self {
+            return inst.execute(pc, regs, memory, context);
+        }
+
+        let (raw, at) = (self.inst().map_or(0, |i| i.inst().raw()), pc.regx());
+        self.execute_vector(pc, regs, memory, context)
+            .map_err(|e| match e {
+                Error::IllegalInstruction { .. } => Error::illegal(raw).with_pc(at),
+                Error::LoadAccessFault { addr, .. } => Error::LoadAccessFault { addr, pc: at },
+                Error::StoreAccessFault { addr, .. } => Error::StoreAccessFault { addr, pc: at },
+                e => e,
+            })
+    }
+
+    fn is_fence_i(&self) -> bool {
+        match self {
+            Self::Other(inst) => inst.is_fence_i(),
+            _ => false,
+        }
+    }
+
+    /// Only instructions writing a scalar result, vector registers aren't reported.
+    fn rd(&self) -> Option<usize> {
+        match self {
+            Self::Vsetvli(i)
+            | Self::Vsetivli(i)
+            | Self::Vsetvl(i)
+            | Self::Vcpop(i)
+            | Self::Vfirst(i)
+            | Self::VmvXS(i) => Some(i.vd()),
+            Self::Other(inst) => inst.rd(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        riscv::{Inst, InstV},
+        riscv32i::RV32iBaseInst,
+        riscvv::VectorState,
+        Instruction, Result,
+    };
+
+    use super::RVVectorInst;
+
+    type Vm = RV32iBaseInst<RVVectorInst<()>>;
+
+    struct Hart {
+        regs: [u32; 32],
+        state: VectorState,
+        memory: [u8; 256],
+    }
+
+    impl Hart {
+        fn new() -> Self {
+            Self {
+                regs: [0; 32],
+                state: VectorState::default(),
+                memory: [0; 256],
+            }
+        }
+
+        fn exec(&mut self, raw: u32) -> Result<()> {
+            let mut pc = 0;
+            let mut inst = Vm::new(&raw.to_le_bytes())?;
+            inst.execute(&mut pc, &mut self.regs, &mut self.memory, &mut self.state)
+        }
+
+        fn run(&mut self, program: &[u32]) {
+            for raw in program {
+                self.exec(*raw).unwrap();
+            }
+        }
+
+        fn words(&self, addr: usize, n: usize) -> [u32; 8] {
+            let mut r = [0; 8];
+            for (i, w) in r.iter_mut().enumerate().take(n) {
+                let at = addr + i * 4;
+                *w = u32::from_le_bytes(self.memory[at..at + 4].try_into().unwrap());
+            }
+            r
+        }
+    }
+
+    const E8: u32 = 0b000 << 3;
+    const E16: u32 = 0b001 << 3;
+    const E32: u32 = 0b010 << 3;
+    const E64: u32 = 0b011 << 3;
+    const M1: u32 = 0b000;
+    const M2: u32 = 0b001;
+    const MF8: u32 = 0b101;
+    const TA_MA: u32 = 0b11 << 6;
+
+    const OPIVV: u8 = 0b000;
+    const OPMVV: u8 = 0b010;
+    const OPIVI: u8 = 0b011;
+    const OPIVX: u8 = 0b100;
+    const OPMVX: u8 = 0b110;
+
+    fn vsetvli(rd: usize, rs1: usize, vtype: u32) -> u32 {
+        Inst::build_i(0b1010111, rd, 0b111, rs1, vtype as i32).raw()
+    }
+
+    fn vsetivli(rd: usize, avl: usize, vtype: u32) -> u32 {
+        0b11 << 30 | vtype << 20 | Inst::build_i(0b1010111, rd, 0b111, avl, 0).raw()
+    }
+
+    fn op(funct6: u8, funct3: u8, vd: usize, vs2: usize, vs1: usize) -> u32 {
+        InstV::build(funct6, true, vs2, vs1, funct3, vd)
+            .inst()
+            .raw()
+    }
+
+    /// Masked by `v0`.
+    fn op_m(funct6: u8, funct3: u8, vd: usize, vs2: usize, vs1: usize) -> u32 {
+        InstV::build(funct6, false, vs2, vs1, funct3, vd)
+            .inst()
+            .raw()
+    }
+
+    /// Memory instruction of `width`, `mop` and `vs2` field, unmasked.
+    fn mem(store: bool, width: u8, mop: u8, vd: usize, rs1: usize, vs2: usize) -> u32 {
+        let opcode = match store {
+            true => 0b0100111,
+            false => 0b0000111,
+        };
+        Inst::build_r(opcode, vd, width, rs1, vs2, mop << 1 | 1).raw()
+    }
+
+    #[test]
+    fn test_vsetvl() {
+        let mut hart = Hart::new();
+        hart.regs[11] = 10;
+
+        hart.run(&[vsetvli(10, 11, E32 | M1 | TA_MA)]);
+        assert_eq!(hart.regs[10], 4);
+        assert_eq!(hart.state.vl, 4);
+
+        hart.run(&[vsetvli(10, 0, E8 | M2)]);
+        assert_eq!(hart.regs[10], 32);
+
+        // x0 as both keeps vl.
+        hart.run(&[vsetivli(0, 3, E16 | M1), vsetvli(0, 0, E32 | M1)]);
+        assert_eq!(hart.state.vl, 3);
+
+        hart.regs[12] = E32 | M2;
+        hart.run(&[0b1000000 << 25 | Inst::build_r(0b1010111, 10, 0b111, 11, 12, 0).raw()]);
+        assert_eq!(hart.regs[10], 8);
+
+        // e64 with mf8 is unsupported, which sets vill.
+        hart.run(&[vsetvli(10, 11, E64 | MF8)]);
+        assert_eq!(hart.regs[10], 0);
+        assert_eq!(hart.state.vtype, 1 << 31);
+        assert!(hart.exec(op(0b000000, OPIVV, 1, 2, 3)).is_err());
+
+        // Hart without vector registers, or with VLEN of 256.
+        let mut regs = [0u32; 32];
+        let mut inst = Vm::new(&vsetvli(10, 11, E32).to_le_bytes()).unwrap();
+        assert!(inst
+            .execute(&mut 0, &mut regs, &mut [0u8; 0], &mut ())
+            .is_err());
+        let mut state = VectorState::<32>::default();
+        assert!(inst
+            .execute(&mut 0, &mut regs, &mut [0u8; 0], &mut state)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rv32e() {
+        let mut regs = [0u32; 16];
+        let mut state = VectorState::<16>::default();
+        let mut exec = |raw: u32, regs: &mut [u32]| {
+            let mut inst = Vm::new(&raw.to_le_bytes()).unwrap();
+            inst.execute(&mut 0, regs, &mut [0u8; 0], &mut state)
+        };
+
+        regs[11] = 2;
+        exec(vsetvli(10, 11, E32 | M1), &mut regs).unwrap();
+        assert_eq!(regs[10], 2);
+
+        // Scalar registers beyond x15 are illegal, vector ones aren't.
+        exec(op(0b010000, OPMVX, 20, 0, 11), &mut regs).unwrap();
+        assert!(exec(op(0b010000, OPMVX, 20, 0, 21), &mut regs).is_err());
+        assert!(exec(op(0b010000, OPMVV, 20, 20, 0), &mut regs).is_err());
+        exec(op(0b010000, OPMVV, 12, 20, 0), &mut regs).unwrap();
+        assert_eq!(regs[12], 2);
+    }
+
+    #[test]
+    fn test_vector_kernel() {
+        let mut hart = Hart::new();
+        for i in 0..6u32 {
+            let (a, b) = (i + 1, 10 * i);
+            hart.memory[i as usize * 4..][..4].copy_from_slice(&a.to_le_bytes());
+            hart.memory[64 + i as usize * 4..][..4].copy_from_slice(&b.to_le_bytes());
+        }
+        // a0 = a, a1 = b, a2 = n, a3 = c, a4 = 3
+        hart.regs[10..15].copy_from_slice(&[0, 64, 6, 128, 3]);
+
+        hart.run(&[
+            vsetvli(5, 12, E32 | M2 | TA_MA),
+            mem(false, 0b110, 0b00, 2, 10, 0),
+            mem(false, 0b110, 0b00, 4, 11, 0),
+            // c = a * 3 + b
+            op(0b100101, OPMVX, 6, 2, 14),
+            op(0b000000, OPIVV, 6, 6, 4),
+            mem(true, 0b110, 0b00, 6, 13, 0),
+            // Dot product of a and b
+            op(0b010000, OPMVX, 8, 0, 0),
+            op(0b100101, OPMVV, 10, 2, 4),
+            op(0b000000, OPMVV, 8, 10, 8),
+            op(0b010000, OPMVV, 15, 8, 0),
+        ]);
+
+        assert_eq!(hart.regs[5], 6);
+        assert_eq!(hart.words(128, 8), [3, 16, 29, 42, 55, 68, 0, 0]);
+        assert_eq!(hart.regs[15], 10 * (2 + 2 * 3 + 3 * 4 + 4 * 5 + 5 * 6));
+
+        // Multiply-add keeps accumulator in destination.
+        hart.run(&[
+            op(0b010111, OPIVI, 12, 0, 1),
+            op(0b101101, OPMVV, 12, 2, 2),
+            mem(true, 0b110, 0b00, 12, 13, 0),
+        ]);
+        assert_eq!(hart.words(128, 8), [2, 5, 10, 17, 26, 37, 0, 0]);
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut hart = Hart::new();
+        hart.regs[11] = 4;
+        hart.run(&[
+            vsetivli(0, 8, E8 | M1),
+            op(0b010100, OPMVV, 1, 0, 0b10001),
+            // v0 = v1 < 4, add 10 to those elements
+            op(0b011011, OPIVX, 0, 1, 11),
+            op_m(0b000000, OPIVI, 1, 1, 10),
+            mem(true, 0b000, 0b00, 1, 0, 0),
+        ]);
+        assert_eq!(hart.memory[..8], [10, 11, 12, 13, 4, 5, 6, 7]);
+
+        // Population count and first set bit of v0.
+        hart.run(&[
+            op(0b010000, OPMVV, 12, 0, 0b10000),
+            op(0b010000, OPMVV, 13, 2, 0b10001),
+        ]);
+        assert_eq!(hart.regs[12], 4);
+        assert_eq!(hart.regs[13], u32::MAX);
+
+        // v2 = !(v0 & v0), iota of v2.
+        hart.run(&[
+            op(0b011101, OPMVV, 2, 0, 0),
+            op(0b010000, OPMVV, 13, 2, 0b10001),
+            op(0b010100, OPMVV, 3, 2, 0b10000),
+            mem(true, 0b000, 0b00, 3, 0, 0),
+            mem(true, 0b000, 0b00, 2, 0, 0b01011),
+        ]);
+        assert_eq!(hart.regs[13], 4);
+        assert_eq!(hart.memory[..8], [0xF0, 0, 0, 0, 0, 1, 2, 3]);
+
+        // Merge 0x55 where v0 is set, else v1.
+        hart.regs[14] = 0x55;
+        hart.run(&[
+            op_m(0b010111, OPIVX, 4, 1, 14),
+            mem(true, 0b000, 0b00, 4, 0, 0),
+        ]);
+        assert_eq!(hart.memory[..8], [0x55, 0x55, 0x55, 0x55, 4, 5, 6, 7]);
+
+        // Mask load, then masked destination v0 is reserved.
+        hart.memory[32] = 0b1000_0001;
+        hart.regs[15] = 32;
+        hart.run(&[mem(false, 0b000, 0b00, 0, 15, 0b01011)]);
+        hart.run(&[op(0b010000, OPMVV, 12, 0, 0b10000)]);
+        assert_eq!(hart.regs[12], 2);
+        assert!(hart.exec(op_m(0b000000, OPIVV, 0, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn test_strided() {
+        let mut hart = Hart::new();
+        for (i, b) in hart.memory.iter_mut().enumerate().take(32) {
+            *b = i as u8;
+        }
+        hart.regs[10] = 0;
+        hart.regs[11] = 4;
+        hart.regs[12] = 64;
+        hart.regs[13] = (-2i32) as u32;
+
+        hart.run(&[
+            vsetivli(0, 4, E16 | M1),
+            mem(false, 0b101, 0b10, 1, 10, 11),
+            mem(true, 0b101, 0b00, 1, 12, 0),
+        ]);
+        assert_eq!(hart.memory[64..72], [0, 1, 4, 5, 8, 9, 12, 13]);
+
+        // Negative stride stores backwards.
+        hart.regs[12] = 96;
+        hart.run(&[mem(true, 0b101, 0b10, 1, 12, 13)]);
+        assert_eq!(hart.memory[90..98], [12, 13, 8, 9, 4, 5, 0, 1]);
+
+        // EEW of 64 with SEW 16 and LMUL 1 needs EMUL 4, v1 isn't aligned.
+        assert!(hart.exec(mem(false, 0b111, 0b00, 1, 10, 0)).is_err());
+    }
+
+    #[test]
+    fn test_integer() {
+        let mut hart = Hart::new();
+        let load = |hart: &mut Hart, v: usize, data: [u8; 4]| {
+            hart.memory[..4].copy_from_slice(&data);
+            hart.run(&[mem(false, 0b000, 0b00, v, 0, 0)]);
+        };
+
+        hart.run(&[vsetivli(0, 4, E8 | M1)]);
+        load(&mut hart, 1, [0x80, 7, 0xF9, 100]);
+        load(&mut hart, 2, [0xFF, 0, 2, 0xFD]);
+
+        let result = |hart: &mut Hart, raw: u32| {
+            hart.run(&[raw, mem(true, 0b000, 0b00, 3, 0, 0)]);
+            <[u8; 4]>::try_from(&hart.memory[..4]).unwrap()
+        };
+
+        // -128 / -1 overflows, division by zero.
+        assert_eq!(
+            result(&mut hart, op(0b100001, OPMVV, 3, 1, 2)),
+            [0x80, 0xFF, 0xFD, 0xDF]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b100011, OPMVV, 3, 1, 2)),
+            [0, 7, 0xFF, 1]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b100000, OPMVV, 3, 1, 2)),
+            [0, 0xFF, 0x7C, 0]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b100111, OPMVV, 3, 1, 2)),
+            [0, 0, 0xFF, 0xFE]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b100100, OPMVV, 3, 1, 2)),
+            [0x7F, 0, 1, 0x62]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b100110, OPMVV, 3, 1, 2)),
+            [0x80, 0, 0xFF, 0x62]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b101001, OPIVI, 3, 1, 1)),
+            [0xC0, 3, 0xFC, 50]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b000101, OPIVV, 3, 1, 2)),
+            [0x80, 0, 0xF9, 0xFD]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b000110, OPIVV, 3, 1, 2)),
+            [0xFF, 7, 0xF9, 0xFD]
+        );
+        assert_eq!(
+            result(&mut hart, op(0b000011, OPIVI, 3, 2, 1)),
+            [2, 1, 0xFF, 4]
+        );
+
+        // Signed maximum reduction into element 0.
+        load(&mut hart, 4, [5, 0, 0, 0]);
+        hart.run(&[op(0b000111, OPMVV, 5, 1, 4), op(0b010000, OPMVV, 10, 5, 0)]);
+        assert_eq!(hart.regs[10], 100);
+
+        // Scalar is sign extended to SEW of 64.
+        hart.regs[11] = u32::MAX;
+        hart.run(&[
+            vsetivli(0, 1, E64 | M1),
+            op(0b010000, OPMVX, 6, 0, 11),
+            op(0b000000, OPIVI, 6, 6, 2),
+            op(0b010000, OPMVV, 12, 6, 0),
+        ]);
+        assert_eq!(hart.regs[12], 1);
+        assert_eq!(hart.state.v[6][..8], [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,248 @@
+use core::fmt;
+
+use crate::{
+    riscv::{abi_name, InstV},
+    Disassemble,
+};
+
+use super::{RVVectorInst, VType};
+
+/// Name of vector register.
+struct V(usize);
+
+impl fmt::Display for V {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+fn masked(f: &mut fmt::Formatter<'_>, inst: &InstV) -> fmt::Result {
+    match inst.vm() {
+        true => Ok(()),
+        false => f.write_str(",v0.t"),
+    }
+}
+
+fn vtype(f: &mut fmt::Formatter<'_>, vtype: u64) -> fmt::Result {
+    match VType::new(vtype) {
+        Some(t) => write!(f, "{}", t),
+        None => write!(f, "{:#x}", vtype),
+    }
+}
+
+/// Arithmetic instruction, `uimm` for shifts taking unsigned immediate.
+fn arith(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstV, uimm: bool) -> fmt::Result {
+    let (vd, vs2) = (V(inst.vd()), V(inst.vs2()));
+    match inst.funct3() {
+        0b000 | 0b010 => write!(f, "{}.vv {},{},{}", name, vd, vs2, V(inst.vs1()))?,
+        0b011 if uimm => write!(f, "{}.vi {},{},{}", name, vd, vs2, inst.vs1())?,
+        0b011 => write!(f, "{}.vi {},{},{}", name, vd, vs2, inst.simm5())?,
+        _ => write!(f, "{}.vx {},{},{}", name, vd, vs2, abi_name(inst.rs1()))?,
+    }
+    masked(f, inst)
+}
+
+/// Multiply-add, with multiplier before `vs2`.
+fn fma(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstV) -> fmt::Result {
+    let (vd, vs2) = (V(inst.vd()), V(inst.vs2()));
+    match inst.funct3() {
+        0b010 => write!(f, "{}.vv {},{},{}", name, vd, V(inst.vs1()), vs2)?,
+        _ => write!(f, "{}.vx {},{},{}", name, vd, abi_name(inst.rs1()), vs2)?,
+    }
+    masked(f, inst)
+}
+
+fn reduce(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstV) -> fmt::Result {
+    write!(
+        f,
+        "{}.vs {},{},{}",
+        name,
+        V(inst.vd()),
+        V(inst.vs2()),
+        V(inst.vs1())
+    )?;
+    masked(f, inst)
+}
+
+fn logical(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstV) -> fmt::Result {
+    write!(
+        f,
+        "{}.mm {},{},{}",
+        name,
+        V(inst.vd()),
+        V(inst.vs2()),
+        V(inst.vs1())
+    )
+}
+
+/// Load or store, names of mask load and store end with `m`.
+fn access(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstV, stride: bool) -> fmt::Result {
+    let width = match inst.funct3() {
+        0b000 => 8,
+        0b101 => 16,
+        0b110 => 32,
+        _ => 64,
+    };
+
+    match name.ends_with('m') {
+        true => write!(f, "{}.v ", name)?,
+        false => write!(f, "{}{}.v ", name, width)?,
+    }
+    write!(f, "{},({})", V(inst.vd()), abi_name(inst.rs1()))?;
+    if stride {
+        write!(f, ",{}", abi_name(inst.vs2()))?;
+    }
+    masked(f, inst)
+}
+
+impl<I: Disassemble, const VLEN: usize> Disassemble for RVVectorInst<I, VLEN> {
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vsetvli(i) => {
+                let (rd, rs1) = (abi_name(i.vd()), abi_name(i.rs1()));
+                write!(f, "vsetvli {},{},", rd, rs1)?;
+                vtype(f, (i.inst().imm_i() & 0x7FF) as u64)
+            }
+            Self::Vsetivli(i) => {
+                write!(f, "vsetivli {},{},", abi_name(i.vd()), i.rs1())?;
+                vtype(f, (i.inst().imm_i() & 0x3FF) as u64)
+            }
+            Self::Vsetvl(i) => write!(
+                f,
+                "vsetvl {},{},{}",
+                abi_name(i.vd()),
+                abi_name(i.rs1()),
+                abi_name(i.vs2())
+            ),
+            Self::Vle(i) => access(f, "vle", i, false),
+            Self::Vlse(i) => access(f, "vlse", i, true),
+            Self::Vlm(i) => access(f, "vlm", i, false),
+            Self::Vse(i) => access(f, "vse", i, false),
+            Self::Vsse(i) => access(f, "vsse", i, true),
+            Self::Vsm(i) => access(f, "vsm", i, false),
+            Self::Vadd(i) => arith(f, "vadd", i, false),
+            Self::Vsub(i) => arith(f, "vsub", i, false),
+            Self::Vrsub(i) => arith(f, "vrsub", i, false),
+            Self::Vminu(i) => arith(f, "vminu", i, false),
+            Self::Vmin(i) => arith(f, "vmin", i, false),
+            Self::Vmaxu(i) => arith(f, "vmaxu", i, false),
+            Self::Vmax(i) => arith(f, "vmax", i, false),
+            Self::Vand(i) => arith(f, "vand", i, false),
+            Self::Vor(i) => arith(f, "vor", i, false),
+            Self::Vxor(i) => arith(f, "vxor", i, false),
+            Self::Vsll(i) => arith(f, "vsll", i, true),
+            Self::Vsrl(i) => arith(f, "vsrl", i, true),
+            Self::Vsra(i) => arith(f, "vsra", i, true),
+            Self::Vmerge(i) => {
+                let vd = V(i.vd());
+                match (i.vm(), i.funct3()) {
+                    (true, 0b000) => write!(f, "vmv.v.v {},{}", vd, V(i.vs1())),
+                    (true, 0b011) => write!(f, "vmv.v.i {},{}", vd, i.simm5()),
+                    (true, _) => write!(f, "vmv.v.x {},{}", vd, abi_name(i.rs1())),
+                    (false, 0b000) => {
+                        write!(f, "vmerge.vvm {},{},{},v0", vd, V(i.vs2()), V(i.vs1()))
+                    }
+                    (false, 0b011) => {
+                        write!(f, "vmerge.vim {},{},{},v0", vd, V(i.vs2()), i.simm5())
+                    }
+                    (false, _) => write!(
+                        f,
+                        "vmerge.vxm {},{},{},v0",
+                        vd,
+                        V(i.vs2()),
+                        abi_name(i.rs1())
+                    ),
+                }
+            }
+            Self::Vmseq(i) => arith(f, "vmseq", i, false),
+            Self::Vmsne(i) => arith(f, "vmsne", i, false),
+            Self::Vmsltu(i) => arith(f, "vmsltu", i, false),
+            Self::Vmslt(i) => arith(f, "vmslt", i, false),
+            Self::Vmsleu(i) => arith(f, "vmsleu", i, false),
+            Self::Vmsle(i) => arith(f, "vmsle", i, false),
+            Self::Vmsgtu(i) => arith(f, "vmsgtu", i, false),
+            Self::Vmsgt(i) => arith(f, "vmsgt", i, false),
+            Self::Vmul(i) => arith(f, "vmul", i, false),
+            Self::Vmulh(i) => arith(f, "vmulh", i, false),
+            Self::Vmulhu(i) => arith(f, "vmulhu", i, false),
+            Self::Vmulhsu(i) => arith(f, "vmulhsu", i, false),
+            Self::Vdivu(i) => arith(f, "vdivu", i, false),
+            Self::Vdiv(i) => arith(f, "vdiv", i, false),
+            Self::Vremu(i) => arith(f, "vremu", i, false),
+            Self::Vrem(i) => arith(f, "vrem", i, false),
+            Self::Vmacc(i) => fma(f, "vmacc", i),
+            Self::Vnmsac(i) => fma(f, "vnmsac", i),
+            Self::Vmadd(i) => fma(f, "vmadd", i),
+            Self::Vnmsub(i) => fma(f, "vnmsub", i),
+            Self::Vredsum(i) => reduce(f, "vredsum", i),
+            Self::Vredand(i) => reduce(f, "vredand", i),
+            Self::Vredor(i) => reduce(f, "vredor", i),
+            Self::Vredxor(i) => reduce(f, "vredxor", i),
+            Self::Vredminu(i) => reduce(f, "vredminu", i),
+            Self::Vredmin(i) => reduce(f, "vredmin", i),
+            Self::Vredmaxu(i) => reduce(f, "vredmaxu", i),
+            Self::Vredmax(i) => reduce(f, "vredmax", i),
+            Self::Vmandn(i) => logical(f, "vmandn", i),
+            Self::Vmand(i) => logical(f, "vmand", i),
+            Self::Vmor(i) => logical(f, "vmor", i),
+            Self::Vmxor(i) => logical(f, "vmxor", i),
+            Self::Vmorn(i) => logical(f, "vmorn", i),
+            Self::Vmnand(i) => logical(f, "vmnand", i),
+            Self::Vmnor(i) => logical(f, "vmnor", i),
+            Self::Vmxnor(i) => logical(f, "vmxnor", i),
+            Self::Vcpop(i) => {
+                write!(f, "vcpop.m {},{}", abi_name(i.vd()), V(i.vs2()))?;
+                masked(f, i)
+            }
+            Self::Vfirst(i) => {
+                write!(f, "vfirst.m {},{}", abi_name(i.vd()), V(i.vs2()))?;
+                masked(f, i)
+            }
+            Self::Viota(i) => {
+                write!(f, "viota.m {},{}", V(i.vd()), V(i.vs2()))?;
+                masked(f, i)
+            }
+            Self::Vid(i) => {
+                write!(f, "vid.v {}", V(i.vd()))?;
+                masked(f, i)
+            }
+            Self::VmvXS(i) => write!(f, "vmv.x.s {},{}", abi_name(i.vd()), V(i.vs2())),
+            Self::VmvSX(i) => write!(f, "vmv.s.x {},{}", V(i.vd()), abi_name(i.rs1())),
+            Self::Other(i) => i.disassemble(pc, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::{String, ToString};
+
+    use crate::{riscv32i::RV32iBaseInst, Instruction};
+
+    use super::RVVectorInst;
+
+    fn disasm(raw: u32) -> String {
+        let inst = RV32iBaseInst::<RVVectorInst<()>>::new(&raw.to_le_bytes()).unwrap();
+        inst.to_string()
+    }
+
+    #[test]
+    fn test_disasm_vector() {
+        assert_eq!(disasm(0x0d05f557), "vsetvli a0,a1,e32,m1,ta,ma");
+        assert_eq!(disasm(0xc0827557), "vsetivli a0,4,e16,m1,tu,mu");
+        assert_eq!(disasm(0x02056087), "vle32.v v1,(a0)");
+        assert_eq!(disasm(0x0ab55087), "vlse16.v v1,(a0),a1");
+        assert_eq!(disasm(0x02b50027), "vsm.v v0,(a0)");
+        assert_eq!(disasm(0x022180d7), "vadd.vv v1,v2,v3");
+        assert_eq!(disasm(0x022db0d7), "vadd.vi v1,v2,-5");
+        assert_eq!(disasm(0x9625e0d7), "vmul.vx v1,v2,a1");
+        assert_eq!(disasm(0xb62220d7), "vmacc.vv v1,v4,v2");
+        assert_eq!(disasm(0x6c25c057), "vmslt.vx v0,v2,a1,v0.t");
+        assert_eq!(disasm(0x5e0030d7), "vmv.v.i v1,0");
+        assert_eq!(disasm(0x022420d7), "vredsum.vs v1,v2,v8");
+        assert_eq!(disasm(0x42202557), "vmv.x.s a0,v2");
+        assert_eq!(disasm(0x00b50533), "add a0,a0,a1");
+    }
+}
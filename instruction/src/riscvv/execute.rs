@@ -0,0 +1,118 @@
+//! Element access of vector registers, and element operations on
+//! zero-extended values of SEW bits.
+
+use crate::{riscv::InstV, RegX};
+
+pub use crate::riscvb::execute::{mask, signed};
+
+/// Vector registers of `VLEN` bits, bytes of one register after another.
+pub struct VRegs<'a, const VLEN: usize> {
+    v: &'a mut [u8],
+}
+
+impl<'a, const VLEN: usize> VRegs<'a, VLEN> {
+    const VLENB: usize = VLEN / 8;
+
+    pub fn new(v: &'a mut [u8]) -> Self {
+        Self { v }
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        self.v[offset]
+    }
+
+    fn set_byte(&mut self, offset: usize, b: u8) {
+        self.v[offset] = b;
+    }
+
+    /// Element `i` of `eew` bits in group starting at `v`.
+    pub fn get(&self, v: usize, i: usize, eew: u32) -> u64 {
+        let bytes = eew as usize / 8;
+        let offset = v * Self::VLENB + i * bytes;
+
+        (0..bytes).fold(0, |r, b| r | (self.byte(offset + b) as u64) << (b * 8))
+    }
+
+    pub fn set(&mut self, v: usize, i: usize, eew: u32, value: u64) {
+        let bytes = eew as usize / 8;
+        let offset = v * Self::VLENB + i * bytes;
+
+        for b in 0..bytes {
+            self.set_byte(offset + b, (value >> (b * 8)) as u8);
+        }
+    }
+
+    /// Mask bit `i` of register `v`.
+    pub fn bit(&self, v: usize, i: usize) -> bool {
+        self.byte(v * Self::VLENB + i / 8) >> (i % 8) & 1 == 1
+    }
+
+    pub fn set_bit(&mut self, v: usize, i: usize, bit: bool) {
+        let offset = v * Self::VLENB + i / 8;
+        let b = self.byte(offset) & !(1 << (i % 8)) | (bit as u8) << (i % 8);
+        self.set_byte(offset, b);
+    }
+
+    /// Whether element `i` is active under mask of `inst`.
+    pub fn active(&self, inst: &InstV, i: usize) -> bool {
+        inst.vm() || self.bit(0, i)
+    }
+}
+
+/// Element width in bits of memory instruction.
+pub fn eew(inst: &InstV) -> u32 {
+    match inst.funct3() {
+        0b000 => 8,
+        0b101 => 16,
+        0b110 => 32,
+        _ => 64,
+    }
+}
+
+/// Scalar operand of `.vx` instruction at SEW bits, sign extended from XLEN.
+pub fn scalar<R: RegX>(x: &R, sew: u32) -> u64 {
+    signed(x.regx(), R::XLEN) as u64 & mask(sew)
+}
+
+pub fn mulh(a: u64, b: u64, sew: u32) -> u64 {
+    let r = signed(a, sew) as i128 * signed(b, sew) as i128;
+    (r >> sew) as u64 & mask(sew)
+}
+
+pub fn mulhu(a: u64, b: u64, sew: u32) -> u64 {
+    ((a as u128 * b as u128) >> sew) as u64
+}
+
+/// Signed `a` times unsigned `b`, high half.
+pub fn mulhsu(a: u64, b: u64, sew: u32) -> u64 {
+    let r = signed(a, sew) as i128 * b as i128;
+    (r >> sew) as u64 & mask(sew)
+}
+
+pub fn div(a: u64, b: u64, sew: u32) -> u64 {
+    match b {
+        0 => mask(sew),
+        _ => signed(a, sew).wrapping_div(signed(b, sew)) as u64 & mask(sew),
+    }
+}
+
+pub fn divu(a: u64, b: u64) -> u64 {
+    match b {
+        0 => u64::MAX,
+        _ => a / b,
+    }
+}
+
+pub fn rem(a: u64, b: u64, sew: u32) -> u64 {
+    match b {
+        0 => a,
+        _ => signed(a, sew).wrapping_rem(signed(b, sew)) as u64 & mask(sew),
+    }
+}
+
+pub fn remu(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => a % b,
+    }
+}
@@ -0,0 +1,11 @@
+//! RISCV vector extension 1.0, integer subset
+
+mod base;
+pub use base::*;
+
+mod state;
+pub use state::*;
+
+mod execute;
+
+mod disasm;
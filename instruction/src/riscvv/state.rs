@@ -0,0 +1,152 @@
+use core::fmt;
+
+use crate::Context;
+
+/// Widest element in bits.
+pub const ELEN: u32 = 64;
+
+/// CSR of vector length.
+pub const CSR_VL: u16 = 0xC20;
+
+/// CSR of vector type.
+pub const CSR_VTYPE: u16 = 0xC21;
+
+/// CSR of VLEN in bytes.
+pub const CSR_VLENB: u16 = 0xC22;
+
+/// Vector registers of `VLENB` bytes with `vl` and `vtype`, state of a hart
+/// apart from its integer registers.
+///
+/// Give it to instructions as [`Context`], like `VectorState<32>` for
+/// `RVVectorInst<_, 256>`. `vl`, `vtype` and `vlenb` are read-only CSRs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VectorState<const VLENB: usize = 16> {
+    pub vl: u64,
+    pub vtype: u64,
+    /// `v0` to `v31`, elements are little-endian.
+    pub v: [[u8; VLENB]; 32],
+}
+
+impl<const VLENB: usize> Default for VectorState<VLENB> {
+    fn default() -> Self {
+        Self {
+            vl: 0,
+            vtype: 0,
+            v: [[0; VLENB]; 32],
+        }
+    }
+}
+
+impl<const VLENB: usize> Context for VectorState<VLENB> {
+    fn read_csr(&self, csr: u16) -> Option<u64> {
+        match csr {
+            CSR_VL => Some(self.vl),
+            CSR_VTYPE => Some(self.vtype),
+            CSR_VLENB => Some(VLENB as u64),
+            _ => None,
+        }
+    }
+
+    fn vector(&mut self) -> Option<VectorRegs<'_>> {
+        Some(VectorRegs {
+            vl: &mut self.vl,
+            vtype: &mut self.vtype,
+            v: self.v.as_flattened_mut(),
+        })
+    }
+}
+
+/// Vector state of a hart as instructions see it, whatever its VLEN.
+pub struct VectorRegs<'a> {
+    pub vl: &'a mut u64,
+    pub vtype: &'a mut u64,
+    /// Bytes of `v0` to `v31`, one register after another.
+    pub v: &'a mut [u8],
+}
+
+/// Value of `vtype` with only `vill` set.
+pub const fn vill(xlen: u32) -> u64 {
+    1 << (xlen - 1)
+}
+
+/// Supported setting of `vtype`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VType {
+    /// Element width in bits
+    pub sew: u32,
+    /// Log2 of LMUL, from -3 to 3
+    pub lmul: i32,
+    /// Tail agnostic
+    pub ta: bool,
+    /// Mask agnostic
+    pub ma: bool,
+}
+
+impl VType {
+    /// Decode `vtype`, `None` if it is reserved or unsupported, which sets
+    /// `vill`.
+    pub fn new(vtype: u64) -> Option<Self> {
+        if vtype >> 8 != 0 {
+            return None;
+        }
+
+        let lmul = match vtype & 0b111 {
+            0b100 => return None,
+            v => ((v as i32) << 29) >> 29,
+        };
+
+        let sew = match (vtype >> 3) & 0b111 {
+            v @ 0..=3 => 8 << v,
+            _ => return None,
+        };
+
+        // Fractional LMUL needs SEW <= LMUL * ELEN.
+        if lmul < 0 && sew << -lmul > ELEN {
+            return None;
+        }
+
+        Some(Self {
+            sew,
+            lmul,
+            ta: vtype >> 6 & 1 == 1,
+            ma: vtype >> 7 & 1 == 1,
+        })
+    }
+
+    pub fn encode(&self) -> u64 {
+        let vsew = self.sew.trailing_zeros() - 3;
+        (self.ma as u64) << 7
+            | (self.ta as u64) << 6
+            | (vsew as u64) << 3
+            | (self.lmul & 0b111) as u64
+    }
+
+    /// Elements of a register group.
+    pub fn vlmax(&self, vlen: usize) -> usize {
+        match self.lmul < 0 {
+            true => (vlen >> -self.lmul) / self.sew as usize,
+            false => (vlen << self.lmul) / self.sew as usize,
+        }
+    }
+
+    /// Registers of a register group.
+    pub fn group(&self) -> usize {
+        1 << self.lmul.max(0)
+    }
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "e{},", self.sew)?;
+        match self.lmul < 0 {
+            true => write!(f, "mf{}", 1 << -self.lmul)?,
+            false => write!(f, "m{}", 1 << self.lmul)?,
+        }
+
+        let policy = |agnostic| match agnostic {
+            true => 'a',
+            false => 'u',
+        };
+        write!(f, ",t{},m{}", policy(self.ta), policy(self.ma))
+    }
+}
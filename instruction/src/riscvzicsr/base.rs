@@ -0,0 +1,345 @@
+use crate::{
+    riscv::{check_regs, Inst, InstI},
+    Context, Error, Instruction, Memory, MemoryMut, RegX, Result,
+};
+
+/// CSR of trap vector base address.
+pub const CSR_MTVEC: u16 = 0x305;
+
+/// CSR of scratch register for trap handlers.
+pub const CSR_MSCRATCH: u16 = 0x340;
+
+/// CSR of pc of instruction which trapped.
+pub const CSR_MEPC: u16 = 0x341;
+
+/// CSR of trap cause.
+pub const CSR_MCAUSE: u16 = 0x342;
+
+/// CSR of trap value, encoding of illegal instruction or misaligned address.
+pub const CSR_MTVAL: u16 = 0x343;
+
+/// CSR holding index of running hart.
+pub const CSR_MHARTID: u16 = 0xF14;
+
+/// Encoding of `mret`.
+const MRET: u32 = 0x3020_0073;
+
+/// CSR instructions and `mret` for RV32 and RV64
+///
+/// CSRs are read and written through [`Context::read_csr`] and
+/// [`Context::write_csr`], accessing a CSR which doesn't exist is illegal.
+/// Privilege modes and `mstatus` aren't modeled, `mret` only jumps to `mepc`.
+/// Layer it under base instructions, like `RV32iBaseInst<RVCsrInst<()>>`.
+pub enum RVCsrInst<I> {
+    /// Atomic read and write CSR
+    Csrrw(InstI),
+    /// Atomic read and set bits in CSR
+    Csrrs(InstI),
+    /// Atomic read and clear bits in CSR
+    Csrrc(InstI),
+    /// Atomic read and write CSR with immediate
+    Csrrwi(InstI),
+    /// Atomic read and set bits in CSR with immediate
+    Csrrsi(InstI),
+    /// Atomic read and clear bits in CSR with immediate
+    Csrrci(InstI),
+    /// Return from machine mode trap handler
+    Mret(InstI),
+    /// Other Instruction
+    Other(I),
+}
+
+impl<I: Instruction> RVCsrInst<I> {
+    fn _new(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(Error::ErrBytecodeLengthNotEnough);
+        }
+
+        let inst = Inst::new([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        let r = match inst.opcode() {
+            0b1110011 => match inst.funct3() {
+                0b000 if inst.raw() == MRET => Self::Mret(inst.into()),
+                0b001 => Self::Csrrw(inst.into()),
+                0b010 => Self::Csrrs(inst.into()),
+                0b011 => Self::Csrrc(inst.into()),
+                0b101 => Self::Csrrwi(inst.into()),
+                0b110 => Self::Csrrsi(inst.into()),
+                0b111 => Self::Csrrci(inst.into()),
+                _ => Self::Other(I::new(bytes)?),
+            },
+            _ => Self::Other(I::new(bytes)?),
+        };
+
+        Ok(r)
+    }
+}
+
+/// CSR number of CSR instruction.
+pub(crate) fn csr(inst: &InstI) -> u16 {
+    inst.imm() as u16 & 0xFFF
+}
+
+/// Whether `csr` is read-only, top 2 bits of number are set.
+fn is_read_only(csr: u16) -> bool {
+    csr >> 10 == 0b11
+}
+
+/// Run CSR instruction `inst` with `operand`, rs1 value or immediate, return
+/// old value to write to rd.
+///
+/// `csrrw` with rd `x0` doesn't read, `csrrs` and `csrrc` with rs1 `x0` or
+/// immediate 0 don't write.
+fn access<C: Context>(inst: &InstI, operand: u64, context: &mut C) -> Result<u64> {
+    let csr = csr(inst);
+    let illegal = || Error::illegal(inst.inst().raw());
+    let op = inst.funct3() & 0b11;
+
+    let old = match op == 0b01 && inst.rd() == 0 {
+        true => 0,
+        false => context.read_csr(csr).ok_or_else(illegal)?,
+    };
+
+    let new = match op {
+        0b01 => Some(operand),
+        _ if inst.rs1() == 0 => None,
+        0b10 => Some(old | operand),
+        _ => Some(old & !operand),
+    };
+
+    if let Some(new) = new {
+        if is_read_only(csr) || !context.write_csr(csr, new) {
+            return Err(illegal());
+        }
+    }
+
+    Ok(old)
+}
+
+impl<I, R> Instruction for RVCsrInst<I>
+where
+    I: Instruction<Register = R>,
+    R: RegX,
+{
+    type Register = R;
+
+    fn new(bytes: &[u8]) -> Result<Self> {
+        Self::_new(bytes)
+    }
+
+    fn execute<M, C>(
+        &mut self,
+        pc: &mut R,
+        regs: &mut [R],
+        memory: &mut M,
+        context: &mut C,
+    ) -> Result<()>
+    where
+        M: Memory<Register = R> + MemoryMut,
+        C: Context,
+    {
+        let (i, operand) = match self {
+            Self::Csrrw(i) | Self::Csrrs(i) | Self::Csrrc(i) => {
+                check_regs(i.inst(), regs.len(), &[i.rd(), i.rs1()])
+                    .map_err(|e| e.with_pc(pc.regx()))?;
+                (&*i, regs[i.rs1()].regx())
+            }
+            Self::Csrrwi(i) | Self::Csrrsi(i) | Self::Csrrci(i) => {
+                check_regs(i.inst(), regs.len(), &[i.rd()]).map_err(|e| e.with_pc(pc.regx()))?;
+                (&*i, i.rs1() as u64)
+            }
+            Self::Mret(i) => {
+                let epc = context
+                    .read_csr(CSR_MEPC)
+                    .ok_or_else(|| Error::illegal(i.inst().raw()).with_pc(pc.regx()))?;
+                pc.set_regx(epc);
+                return Ok(());
+            }
+            Self::Other(inst) => return inst.execute(pc, regs, memory, context),
+        };
+
+        let v = access(i, operand, context).map_err(|e| e.with_pc(pc.regx()))?;
+
+        if i.rd() != 0 {
+            regs[i.rd()].set_regx(v);
+        }
+        pc.set_regx(pc.regx().wrapping_add(4));
+
+        Ok(())
+    }
+
+    fn is_fence_i(&self) -> bool {
+        match self {
+            Self::Other(inst) => inst.is_fence_i(),
+            _ => false,
+        }
+    }
+
+    fn rd(&self) -> Option<usize> {
+        match self {
+            Self::Csrrw(i)
+            | Self::Csrrs(i)
+            | Self::Csrrc(i)
+            | Self::Csrrwi(i)
+            | Self::Csrrsi(i)
+            | Self::Csrrci(i) => Some(i.rd()),
+            Self::Mret(_) => None,
+            Self::Other(inst) => inst.rd(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        riscv::{Illegal, Inst},
+        riscv32i::RV32iBaseInst,
+        Context, Error, Instruction,
+    };
+
+    use super::{RVCsrInst, CSR_MEPC, CSR_MSCRATCH, MRET};
+
+    type Inst32 = RVCsrInst<RV32iBaseInst<Illegal>>;
+
+    const CYCLE: u16 = 0xC00;
+
+    /// Hart with `mscratch`, `mepc` and read-only `cycle` CSRs, counting
+    /// writes.
+    #[derive(Default)]
+    struct Csrs {
+        scratch: u64,
+        epc: u64,
+        writes: usize,
+    }
+
+    impl Context for Csrs {
+        fn read_csr(&self, csr: u16) -> Option<u64> {
+            match csr {
+                CSR_MSCRATCH => Some(self.scratch),
+                CSR_MEPC => Some(self.epc),
+                CYCLE => Some(42),
+                _ => None,
+            }
+        }
+
+        fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+            self.writes += 1;
+            match csr {
+                CSR_MSCRATCH => self.scratch = value,
+                CSR_MEPC => self.epc = value,
+                _ => return false,
+            }
+            true
+        }
+    }
+
+    fn csr(funct3: u8, rd: usize, rs1: usize, csr: u16) -> u32 {
+        Inst::build_i(0b1110011, rd, funct3, rs1, csr as i32).raw()
+    }
+
+    /// Execute `raw` at pc 4 with a1 set to `a1`, return a0 and pc.
+    fn run(raw: u32, a1: u32, csrs: &mut Csrs) -> Result<(u32, u32), Error> {
+        let mut regs = [0u32; 32];
+        regs[11] = a1;
+        let mut pc = 4;
+
+        let mut inst = Inst32::new(&raw.to_le_bytes()).unwrap();
+        inst.execute(&mut pc, &mut regs, &mut [0u8; 0], csrs)?;
+        Ok((regs[10], pc))
+    }
+
+    #[test]
+    fn test_csr() {
+        let mut csrs = Csrs {
+            scratch: 0b1100,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            run(csr(0b001, 10, 11, CSR_MSCRATCH), 5, &mut csrs),
+            Ok((12, 8))
+        );
+        assert_eq!(csrs.scratch, 5);
+        assert_eq!(
+            run(csr(0b010, 10, 11, CSR_MSCRATCH), 2, &mut csrs),
+            Ok((5, 8))
+        );
+        assert_eq!(csrs.scratch, 7);
+        assert_eq!(
+            run(csr(0b011, 10, 11, CSR_MSCRATCH), 3, &mut csrs),
+            Ok((7, 8))
+        );
+        assert_eq!(csrs.scratch, 4);
+
+        // Immediate is in rs1 field.
+        assert_eq!(
+            run(csr(0b101, 10, 9, CSR_MSCRATCH), 0, &mut csrs),
+            Ok((4, 8))
+        );
+        assert_eq!(csrs.scratch, 9);
+        assert_eq!(
+            run(csr(0b110, 10, 2, CSR_MSCRATCH), 0, &mut csrs),
+            Ok((9, 8))
+        );
+        assert_eq!(csrs.scratch, 11);
+        assert_eq!(
+            run(csr(0b111, 10, 1, CSR_MSCRATCH), 0, &mut csrs),
+            Ok((11, 8))
+        );
+        assert_eq!(csrs.scratch, 10);
+        assert_eq!(csrs.writes, 6);
+    }
+
+    #[test]
+    fn test_csr_read_only() {
+        let mut csrs = Csrs::default();
+
+        // `csrr` doesn't write, so read-only CSRs can be read.
+        assert_eq!(run(csr(0b010, 10, 0, CYCLE), 0, &mut csrs), Ok((42, 8)));
+        assert_eq!(run(csr(0b111, 10, 0, CYCLE), 0, &mut csrs), Ok((42, 8)));
+        assert_eq!(csrs.writes, 0);
+
+        let raw = csr(0b010, 10, 11, CYCLE);
+        let illegal = Error::IllegalInstruction { raw, pc: 4 };
+        assert_eq!(run(raw, 0, &mut csrs), Err(illegal));
+
+        // Missing CSRs are illegal, `csrw` doesn't read them.
+        let raw = csr(0b010, 10, 0, 0x7C0);
+        let illegal = Error::IllegalInstruction { raw, pc: 4 };
+        assert_eq!(run(raw, 0, &mut csrs), Err(illegal));
+        let raw = csr(0b001, 0, 11, 0x7C0);
+        let illegal = Error::IllegalInstruction { raw, pc: 4 };
+        assert_eq!(run(raw, 0, &mut csrs), Err(illegal));
+        assert_eq!(csrs.writes, 1);
+    }
+
+    #[test]
+    fn test_mret() {
+        let mut csrs = Csrs {
+            epc: 40,
+            ..Default::default()
+        };
+
+        assert_eq!(run(MRET, 0, &mut csrs), Ok((0, 40)));
+
+        // No CSRs without a hart.
+        let mut inst = Inst32::new(&MRET.to_le_bytes()).unwrap();
+        let (mut pc, mut regs) = (4, [0u32; 32]);
+        let r = inst.execute(&mut pc, &mut regs, &mut [0u8; 0], &mut ());
+        assert_eq!(r, Err(Error::IllegalInstruction { raw: MRET, pc: 4 }));
+
+        // Registers beyond RV32E are checked.
+        let raw = csr(0b001, 20, 11, CSR_MSCRATCH);
+        let r = Inst32::new(&raw.to_le_bytes()).unwrap().execute(
+            &mut pc,
+            &mut [0u32; 16],
+            &mut [0u8; 0],
+            &mut csrs,
+        );
+        assert_eq!(r, Err(Error::IllegalInstruction { raw, pc: 4 }));
+
+        // Other system instructions go to inner layer.
+        let ecall = Inst32::new(&0x73u32.to_le_bytes()).unwrap();
+        assert!(matches!(ecall, RVCsrInst::Other(RV32iBaseInst::ECall(_))));
+    }
+}
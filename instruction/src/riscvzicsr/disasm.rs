@@ -0,0 +1,58 @@
+use core::fmt;
+
+use crate::{
+    riscv::{abi_name, InstI},
+    Disassemble,
+};
+
+use super::{base::csr, RVCsrInst};
+
+/// CSR instruction with rs1 or immediate as last operand.
+fn csr_op(f: &mut fmt::Formatter<'_>, name: &str, inst: &InstI, imm: bool) -> fmt::Result {
+    write!(f, "{} {},{:#x},", name, abi_name(inst.rd()), csr(inst))?;
+
+    match imm {
+        true => write!(f, "{}", inst.rs1()),
+        false => f.write_str(abi_name(inst.rs1())),
+    }
+}
+
+impl<I: Disassemble> Disassemble for RVCsrInst<I> {
+    fn disassemble(&self, pc: Option<u64>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csrrw(i) => csr_op(f, "csrrw", i, false),
+            Self::Csrrs(i) => csr_op(f, "csrrs", i, false),
+            Self::Csrrc(i) => csr_op(f, "csrrc", i, false),
+            Self::Csrrwi(i) => csr_op(f, "csrrwi", i, true),
+            Self::Csrrsi(i) => csr_op(f, "csrrsi", i, true),
+            Self::Csrrci(i) => csr_op(f, "csrrci", i, true),
+            Self::Mret(_) => f.write_str("mret"),
+            Self::Other(i) => i.disassemble(pc, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::{String, ToString};
+
+    use crate::{riscv32i::RV32iBaseInst, Instruction};
+
+    use super::RVCsrInst;
+
+    fn disasm(raw: u32) -> String {
+        let inst = RV32iBaseInst::<RVCsrInst<()>>::new(&raw.to_le_bytes()).unwrap();
+        inst.to_string()
+    }
+
+    #[test]
+    fn test_disasm_csr() {
+        assert_eq!(disasm(0x34159573), "csrrw a0,0x341,a1");
+        assert_eq!(disasm(0x34202573), "csrrs a0,0x342,zero");
+        assert_eq!(disasm(0x3052f073), "csrrci zero,0x305,5");
+        assert_eq!(disasm(0x30200073), "mret");
+        assert_eq!(disasm(0x00000073), "ecall");
+    }
+}
@@ -0,0 +1,6 @@
+//! RISCV control and status register extension, Zicsr, and `mret`
+
+mod base;
+pub use base::*;
+
+mod disasm;
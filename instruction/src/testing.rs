@@ -0,0 +1,44 @@
+//! Helpers of instruction tests.
+
+use crate::{Instruction, Memory, MemoryMut};
+
+/// Memory of RV64 tests, which never access it.
+pub struct Mem64;
+
+impl Memory for Mem64 {
+    type Register = u64;
+
+    fn length(&self) -> u64 {
+        0
+    }
+
+    fn load(&self, _pos: u64, _length: u8) -> &[u8] {
+        &[]
+    }
+
+    fn contains(&self, _pos: u64, _length: u8) -> bool {
+        false
+    }
+}
+
+impl MemoryMut for Mem64 {
+    fn store(&mut self, _pos: u64, _data: &[u8]) {}
+}
+
+/// Execute `inst` with a1 and a2 set, return a0.
+pub fn run<T, M>(inst: u32, a1: T::Register, a2: T::Register, mut memory: M) -> T::Register
+where
+    T: Instruction,
+    T::Register: Default + Copy,
+    M: MemoryMut<Register = T::Register>,
+{
+    let mut regs = [T::Register::default(); 32];
+    regs[11] = a1;
+    regs[12] = a2;
+    let mut pc = T::Register::default();
+
+    let mut inst = T::new(&inst.to_le_bytes()).unwrap();
+    inst.execute(&mut pc, &mut regs, &mut memory, &mut ())
+        .unwrap();
+    regs[10]
+}